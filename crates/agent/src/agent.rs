@@ -1,12 +1,120 @@
 use futures::{Stream, StreamExt};
-use std::{fmt::Debug, pin::Pin};
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use ai_core::{Result, provider::ChatTextGeneration, tools::BuiltToolRouter, types::*};
+use ai_core::{
+    AgentError, AiError, Result, StorageError, ValidationError,
+    errors::ToolExecutionError,
+    platform::{self, Instant},
+    provider::ChatTextGeneration,
+    scratchpad::Scratchpad,
+    tools::BuiltToolRouter,
+    types::*,
+};
+
+use ai_memory::MessageStore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    audit::{AuditEvent, AuditLog, AuditRun, audit},
+    checkpoint::AgentCheckpoint,
+    compaction::Compaction,
+    compression::PromptCompression,
+    context::ContextStrategy,
+    guardrails::{Guardrail, Guardrails, guard_input, guard_output},
+    hooks::{AgentHooks, StepFinish},
+    memory::AgentMemory,
+    metrics::{MetricsSink, observe_request, observe_tool},
+    output::{
+        OutputValidator, output_request, record_answer, repair_message, schema_value,
+        validation_failed,
+    },
+    preflight::Preflight,
+    refusal::{RefusalAction, RefusalPolicy, refused, streamed_refusal},
+    retry::{RetryPolicy, with_retry},
+    telemetry::{GenAiSpan, traced_chat},
+};
 
 /// Trait for defining execution termination strategies
 pub trait RunUntil: Debug {
-    /// Check if execution should continue based on current step and finish reason
-    fn should_continue(&mut self, step: u32, reason: &FinishReason) -> bool;
+    /// Called once before the first step of a run
+    fn start(&mut self) {}
+
+    /// Check if execution should continue after the step described by `ctx`
+    fn should_continue(&mut self, ctx: &StepContext<'_>) -> bool;
+
+    /// Time by which the run has to be over; a model call or tool execution still running
+    /// then is cut short and the run returns what it has, see `AgentResponse::timed_out`
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+}
+
+/// State of a run at the end of a step
+#[derive(Debug, Clone, Copy)]
+pub struct StepContext<'a> {
+    pub step: u32,
+    pub finish_reason: &'a FinishReason,
+    /// Usage accumulated over all steps so far, if the provider reports it
+    pub usage: Option<&'a Usage>,
+    /// Assistant message produced in this step
+    pub last_message: &'a Message,
+    /// Tool calls the model made in this step
+    pub tool_calls: &'a [ToolCall],
+    pub scratchpad: &'a Scratchpad,
+}
+
+impl StepContext<'_> {
+    /// Text content of the step's assistant message
+    pub fn text(&self) -> String {
+        match self.last_message {
+            Message::Assistant { content, .. } => content
+                .iter()
+                .filter_map(|part| match part {
+                    AssistantContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Stop when a closure returns false
+#[derive(Clone)]
+pub struct RunUntilFn<F> {
+    f: F,
+}
+
+impl<F> RunUntilFn<F>
+where
+    F: FnMut(StepContext<'_>) -> bool,
+{
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F> Debug for RunUntilFn<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunUntilFn").finish_non_exhaustive()
+    }
+}
+
+impl<F> RunUntil for RunUntilFn<F>
+where
+    F: FnMut(StepContext<'_>) -> bool,
+{
+    fn should_continue(&mut self, ctx: &StepContext<'_>) -> bool {
+        (self.f)(*ctx)
+    }
 }
 
 /// Stop after a maximum number of steps
@@ -22,8 +130,8 @@ impl MaxSteps {
 }
 
 impl RunUntil for MaxSteps {
-    fn should_continue(&mut self, step: u32, _reason: &FinishReason) -> bool {
-        step < self.max
+    fn should_continue(&mut self, ctx: &StepContext<'_>) -> bool {
+        ctx.step < self.max
     }
 }
 
@@ -48,8 +156,78 @@ impl StopOnReason {
 }
 
 impl RunUntil for StopOnReason {
-    fn should_continue(&mut self, _step: u32, reason: &FinishReason) -> bool {
-        !self.reasons.contains(reason)
+    fn should_continue(&mut self, ctx: &StepContext<'_>) -> bool {
+        !self.reasons.contains(ctx.finish_reason)
+    }
+}
+
+/// Stop once a run has been going for longer than a maximum duration
+///
+/// The clock starts when the run starts. A model call or tool execution still running
+/// when time is up is cut short, and the run returns the steps finished so far.
+#[derive(Debug, Clone)]
+pub struct MaxDuration {
+    pub max: Duration,
+    started: Option<Instant>,
+}
+
+impl MaxDuration {
+    pub fn new(max: Duration) -> Self {
+        Self { max, started: None }
+    }
+}
+
+impl RunUntil for MaxDuration {
+    fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    fn should_continue(&mut self, _ctx: &StepContext<'_>) -> bool {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        started.elapsed() < self.max
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.started.map(|started| started + self.max)
+    }
+}
+
+/// Stop once a point in time has passed, cutting in-flight work short like [`MaxDuration`]
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    pub at: Instant,
+    /// Set by `after`: the deadline is moved to this long after each run starts
+    after: Option<Duration>,
+}
+
+impl Deadline {
+    /// Deadline at a fixed instant, shared by every run using this strategy
+    pub fn new(at: Instant) -> Self {
+        Self { at, after: None }
+    }
+
+    /// Deadline at `duration` after the run starts, so a reused config gets a fresh one
+    pub fn after(duration: Duration) -> Self {
+        Self {
+            at: Instant::now() + duration,
+            after: Some(duration),
+        }
+    }
+}
+
+impl RunUntil for Deadline {
+    fn start(&mut self) {
+        if let Some(after) = self.after {
+            self.at = Instant::now() + after;
+        }
+    }
+
+    fn should_continue(&mut self, _ctx: &StepContext<'_>) -> bool {
+        Instant::now() < self.at
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.at)
     }
 }
 
@@ -75,8 +253,20 @@ where
 }
 
 impl<A: RunUntil, B: RunUntil> RunUntil for RunUntilFirst<A, B> {
-    fn should_continue(&mut self, step: u32, reason: &FinishReason) -> bool {
-        self.first.should_continue(step, reason) && self.second.should_continue(step, reason)
+    fn start(&mut self) {
+        self.first.start();
+        self.second.start();
+    }
+
+    fn should_continue(&mut self, ctx: &StepContext<'_>) -> bool {
+        self.first.should_continue(ctx) && self.second.should_continue(ctx)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        match (self.first.deadline(), self.second.deadline()) {
+            (Some(first), Some(second)) => Some(first.min(second)),
+            (first, second) => first.or(second),
+        }
     }
 }
 
@@ -93,6 +283,31 @@ where
     pub tools: Option<Vec<ToolDefinition>>,
     pub tool_router: Option<BuiltToolRouter<S>>,
     pub run_until: Box<dyn RunUntil + Send>,
+    pub hooks: AgentHooks,
+    /// Trims the messages sent on each step; the full history is kept regardless
+    pub context: Option<Arc<dyn ContextStrategy>>,
+    /// Shrinks the messages sent on each step after the context strategy
+    pub compression: Option<PromptCompression>,
+    /// Summarizes older turns into the history once it grows past a threshold
+    pub compaction: Option<Compaction>,
+    /// Policy checks on user input and assistant output
+    pub guardrails: Guardrails,
+    /// Retries provider requests that fail with a retryable error
+    pub retry: Option<RetryPolicy>,
+    /// What to do when the provider refuses or filters a response
+    pub refusal: RefusalPolicy,
+    /// Checks each request fits the model's context window before sending it
+    pub preflight: Option<Preflight>,
+    /// Receives request and tool measurements
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+    /// Records prompts, completions, tool calls and tool results
+    pub audit: Option<AuditLog>,
+    /// Working memory shared by the run's steps, hooks and tools
+    pub scratchpad: Scratchpad,
+    /// When set, a final request asks for an answer matching this JSON schema
+    pub output_schema: Option<JsonValue>,
+    /// Checks the final answer, asking the model to repair it when the check fails
+    pub output_validator: Option<OutputValidator>,
 }
 
 impl<P, S> GenerateConfig<P, S>
@@ -124,6 +339,122 @@ where
         self.settings.max_tokens = Some(tokens);
         self
     }
+
+    pub fn hooks(mut self, hooks: AgentHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn context_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.context = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn compaction(mut self, compaction: Compaction) -> Self {
+        self.compaction = Some(compaction);
+        self
+    }
+
+    /// Compress each request's messages, see `PromptCompression`
+    pub fn compression(mut self, compression: PromptCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Add a guardrail; guardrails run in the order they were added
+    pub fn guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.push(Arc::new(guardrail));
+        self
+    }
+
+    /// Retry provider requests that fail with a rate limit, server error or timeout
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Stop, fail or retry when the provider refuses or filters a response
+    pub fn refusal_policy(mut self, policy: RefusalPolicy) -> Self {
+        self.refusal = policy;
+        self
+    }
+
+    /// Check requests against the model's context window before sending them
+    pub fn preflight(mut self, preflight: Preflight) -> Self {
+        self.preflight = Some(preflight);
+        self
+    }
+
+    /// Report request counts, latencies, token usage and tool durations to `sink`
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Record every prompt, completion, tool call and tool result to an audit log
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Share `scratchpad` with the run, e.g. to seed it or read it afterwards
+    pub fn scratchpad(mut self, scratchpad: Scratchpad) -> Self {
+        self.scratchpad = scratchpad;
+        self
+    }
+
+    /// Finish the run with an answer matching a JSON schema, see `AgentResponse::output_value`
+    pub fn output_schema(mut self, schema: JsonValue) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Finish the run with an answer matching the schema of `T`
+    pub fn output<T: JsonSchema>(self) -> Self {
+        self.output_schema(schema_value::<T>())
+    }
+
+    /// Check the final answer, feeding failures back to the model for repair
+    pub fn output_validator(mut self, validator: OutputValidator) -> Self {
+        self.output_validator = Some(validator);
+        self
+    }
+
+    pub fn on_step_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_step_start(hook);
+        self
+    }
+
+    pub fn on_step_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StepFinish) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_step_finish(hook);
+        self
+    }
+
+    pub fn on_tool_call<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), ToolExecutionError>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_tool_call(hook);
+        self
+    }
+
+    pub fn on_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(AgentResponse) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_finish(hook);
+        self
+    }
 }
 
 impl<P> GenerateConfig<P, ()>
@@ -138,6 +469,19 @@ where
             tools: None,
             tool_router: None,
             run_until: Box::new(MaxSteps::new(1)),
+            hooks: AgentHooks::default(),
+            context: None,
+            compression: None,
+            compaction: None,
+            guardrails: Vec::new(),
+            retry: None,
+            refusal: RefusalPolicy::default(),
+            preflight: None,
+            metrics: None,
+            audit: None,
+            scratchpad: Scratchpad::new(),
+            output_schema: None,
+            output_validator: None,
         }
     }
 
@@ -153,6 +497,19 @@ where
             tools: Some(tool_definitions),
             tool_router: Some(router),
             run_until: self.run_until,
+            hooks: self.hooks,
+            context: self.context,
+            compression: self.compression,
+            compaction: self.compaction,
+            guardrails: self.guardrails,
+            retry: self.retry,
+            refusal: self.refusal,
+            preflight: self.preflight,
+            metrics: self.metrics,
+            audit: self.audit,
+            scratchpad: self.scratchpad,
+            output_schema: self.output_schema,
+            output_validator: self.output_validator,
         }
     }
 }
@@ -170,6 +527,27 @@ where
     pub tools: Option<Vec<ToolDefinition>>,
     pub tool_router: Option<BuiltToolRouter<S>>,
     pub run_until: Box<dyn RunUntil + Send>,
+    pub hooks: AgentHooks,
+    /// Trims the messages sent on each step; the full history is kept regardless
+    pub context: Option<Arc<dyn ContextStrategy>>,
+    /// Shrinks the messages sent on each step after the context strategy
+    pub compression: Option<PromptCompression>,
+    /// Summarizes older turns into the history once it grows past a threshold
+    pub compaction: Option<Compaction>,
+    /// Policy checks on user input and assistant output
+    pub guardrails: Guardrails,
+    /// Retries starting a step's stream when it fails with a retryable error
+    pub retry: Option<RetryPolicy>,
+    /// What to do when the provider refuses or filters a step
+    pub refusal: RefusalPolicy,
+    /// Checks each request fits the model's context window before sending it
+    pub preflight: Option<Preflight>,
+    /// Receives request and tool measurements
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+    /// Records prompts, completions, tool calls and tool results
+    pub audit: Option<AuditLog>,
+    /// Working memory shared by the run's steps, hooks and tools
+    pub scratchpad: Scratchpad,
 }
 
 impl<P, S> StreamConfig<P, S>
@@ -208,6 +586,105 @@ where
         self.settings.max_tokens = Some(tokens);
         self
     }
+
+    pub fn hooks(mut self, hooks: AgentHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn context_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.context = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn compaction(mut self, compaction: Compaction) -> Self {
+        self.compaction = Some(compaction);
+        self
+    }
+
+    /// Compress each request's messages, see `PromptCompression`
+    pub fn compression(mut self, compression: PromptCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Add a guardrail; guardrails run in the order they were added
+    pub fn guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.push(Arc::new(guardrail));
+        self
+    }
+
+    /// Retry provider requests that fail with a rate limit, server error or timeout
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Stop, fail or retry when the provider refuses or filters a response
+    pub fn refusal_policy(mut self, policy: RefusalPolicy) -> Self {
+        self.refusal = policy;
+        self
+    }
+
+    /// Check requests against the model's context window before sending them
+    pub fn preflight(mut self, preflight: Preflight) -> Self {
+        self.preflight = Some(preflight);
+        self
+    }
+
+    /// Report request counts, latencies, token usage and tool durations to `sink`
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Record every prompt, completion, tool call and tool result to an audit log
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Share `scratchpad` with the run, e.g. to seed it or read it afterwards
+    pub fn scratchpad(mut self, scratchpad: Scratchpad) -> Self {
+        self.scratchpad = scratchpad;
+        self
+    }
+
+    pub fn on_step_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_step_start(hook);
+        self
+    }
+
+    pub fn on_step_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StepFinish) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_step_finish(hook);
+        self
+    }
+
+    pub fn on_tool_call<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), ToolExecutionError>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_tool_call(hook);
+        self
+    }
+
+    pub fn on_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(AgentResponse) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks = self.hooks.on_finish(hook);
+        self
+    }
 }
 
 impl<P> StreamConfig<P, ()>
@@ -222,18 +699,101 @@ where
             tools: None,
             tool_router: None,
             run_until: Box::new(MaxSteps::new(1)),
+            hooks: AgentHooks::default(),
+            context: None,
+            compression: None,
+            compaction: None,
+            guardrails: Vec::new(),
+            retry: None,
+            refusal: RefusalPolicy::default(),
+            preflight: None,
+            metrics: None,
+            audit: None,
+            scratchpad: Scratchpad::new(),
         }
     }
 }
 
 /// Response from agent execution
+///
+/// `T` is the structured final answer for runs with an output type, see `generate_output`.
 #[derive(Debug, Clone)]
-pub struct AgentResponse {
+pub struct AgentResponse<T = ()> {
     pub messages: Vec<Message>,
     pub final_message: Message,
-    pub steps: u32,
+    /// Every step of the run, including steps before a resumed checkpoint
+    pub steps: Vec<StepInfo>,
     pub finish_reason: FinishReason,
     pub total_usage: Option<Usage>,
+    /// Set when the run paused on tool calls without a handler
+    pub checkpoint: Option<AgentCheckpoint>,
+    /// Working memory as the run left it
+    pub scratchpad: Scratchpad,
+    /// Set when the run's deadline cut a model call or tool execution short
+    ///
+    /// `messages` then end with the last finished step, and `final_message` holds the
+    /// text streamed by an unfinished step, if any.
+    pub timed_out: bool,
+    pub output: T,
+}
+
+impl<T> AgentResponse<T> {
+    /// Text content of the final assistant message
+    pub fn text(&self) -> String {
+        match &self.final_message {
+            Message::Assistant { content, .. } => content
+                .iter()
+                .filter_map(|part| match part {
+                    AssistantContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => String::new(),
+        }
+    }
+
+    /// Same response with a different structured output
+    pub fn with_output<U>(self, output: U) -> AgentResponse<U> {
+        AgentResponse {
+            messages: self.messages,
+            final_message: self.final_message,
+            steps: self.steps,
+            finish_reason: self.finish_reason,
+            total_usage: self.total_usage,
+            checkpoint: self.checkpoint,
+            scratchpad: self.scratchpad,
+            timed_out: self.timed_out,
+            output,
+        }
+    }
+}
+
+/// What happened during one step of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepInfo {
+    pub step: u32,
+    /// ID of the provider response; empty when the provider gives none
+    pub response_id: String,
+    pub finish_reason: FinishReason,
+    pub usage: Option<Usage>,
+    pub tool_calls: Vec<ToolCall>,
+    /// Results of the calls executed by the tool router
+    pub tool_results: Vec<ToolResult>,
+    /// Time spent on the model call and tool executions
+    pub duration: Duration,
+}
+
+fn tool_calls_of(message: &Message) -> Vec<ToolCall> {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::ToolCall { tool_call } => Some(tool_call.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
 }
 
 /// Streaming chunk from agent execution
@@ -241,35 +801,192 @@ pub struct AgentResponse {
 pub struct AgentStreamChunk {
     pub step: u32,
     pub chunk: ChatStreamChunk,
+    /// Last chunk of this step's model response; later steps may follow
+    ///
+    /// Use `stream_events` for explicit `StepFinished` and `RunFinished` events.
     pub is_final: bool,
+    /// Usage accumulated over the run up to the end of this step; set on final chunks
+    pub total_usage: Option<Usage>,
 }
 
 /// Generate text using an agent with execution control
 pub async fn generate_text<P, S>(config: GenerateConfig<P, S>) -> Result<AgentResponse>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    let mut config = config;
+    let mut messages = std::mem::take(&mut config.messages);
+    guard_input(&config.guardrails, &mut messages).await?;
+    run_generate(config, messages, 0, None, Vec::new()).await
+}
+
+/// Resume a run paused on client-side tool calls
+///
+/// `tool_results` must answer every pending call of the checkpoint. The messages in
+/// `config` are ignored in favour of the checkpoint's conversation, and the checkpoint's
+/// scratchpad entries are copied into the config's scratchpad.
+pub async fn generate_text_from_checkpoint<P, S>(
+    config: GenerateConfig<P, S>,
+    checkpoint: AgentCheckpoint,
+    tool_results: Vec<ToolResult>,
+) -> Result<AgentResponse>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    config.scratchpad.merge(&checkpoint.scratchpad);
+    let start = RunStart::resume(checkpoint, tool_results)?;
+    run_generate(config, start.messages, start.step, start.usage, start.steps).await
+}
+
+/// Where a run picks up when resumed from a checkpoint
+struct RunStart {
+    messages: Vec<Message>,
+    step: u32,
+    usage: Option<Usage>,
+    steps: Vec<StepInfo>,
+}
+
+impl RunStart {
+    fn resume(checkpoint: AgentCheckpoint, tool_results: Vec<ToolResult>) -> Result<Self> {
+        if let Some(missing) = checkpoint.missing_results(&tool_results).first() {
+            return Err(AiError::Validation(ValidationError::MissingField {
+                field: format!("tool result for call '{}'", missing),
+            }));
+        }
+
+        let mut messages = checkpoint.messages;
+        let mut results = checkpoint.tool_results;
+        results.extend(tool_results);
+        messages.push(Message::Tool {
+            tool_results: results,
+            metadata: None,
+        });
+        Ok(Self {
+            messages,
+            step: checkpoint.step + 1,
+            usage: checkpoint.usage,
+            steps: checkpoint.steps,
+        })
+    }
+}
+
+async fn run_generate<P, S>(
+    config: GenerateConfig<P, S>,
+    messages: Vec<Message>,
+    step: u32,
+    usage: Option<Usage>,
+    steps: Vec<StepInfo>,
+) -> Result<AgentResponse>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    let span = GenAiSpan::agent(config.provider.name(), config.provider.model());
+    let result = span
+        .instrument(run_steps(config, messages, step, usage, steps, &span))
+        .await;
+    match &result {
+        Ok(response) => {
+            span.record_finish(&response.finish_reason, response.total_usage.as_ref());
+            span.record_steps(response.steps.len());
+        }
+        Err(e) => span.record_error(e),
+    }
+    result
+}
+
+async fn run_steps<P, S>(
+    config: GenerateConfig<P, S>,
+    mut messages: Vec<Message>,
+    mut step: u32,
+    usage: Option<Usage>,
+    mut steps: Vec<StepInfo>,
+    run_span: &GenAiSpan,
+) -> Result<AgentResponse>
 where
     P: ChatTextGeneration,
     S: Clone + Send + Sync + 'static,
 {
     let mut run_until = config.run_until;
-    let mut messages = config.messages;
-    let mut step = 0;
-    let mut total_usage = Usage {
+    run_until.start();
+    let deadline = run_until.deadline();
+    let mut has_usage = usage.is_some();
+    let mut total_usage = usage.unwrap_or(Usage {
         prompt_tokens: 0,
         completion_tokens: 0,
         total_tokens: 0,
-    };
-    let mut has_usage = false;
+    });
+    let audit_run = AuditRun::start(
+        config.audit.as_ref(),
+        config.provider.name(),
+        config.provider.model(),
+    );
+
+    let mut repairs = 0;
+    let mut refusals = 0;
+
+    'steps: loop {
+        config.hooks.step_start(step).await?;
+        let started = Instant::now();
+        let step_span = run_span.step(step);
+
+        if let Some(compaction) = &config.compaction
+            && compaction.needs_compaction(&messages)
+        {
+            messages = compaction.compact(&config.provider, messages).await?;
+        }
 
-    loop {
         // Create request from current messages
-        let request = ChatRequest {
-            messages: messages.clone(),
+        let prompt = apply_context(&config.context, &messages);
+        let mut request = ChatRequest {
+            messages: compress(&config.compression, &config.provider, prompt).await?,
             settings: config.settings.clone(),
             tools: config.tools.clone(),
+            headers: Vec::new(),
         };
+        preflight(&config.preflight, &config.provider, &mut request)?;
+        audit(audit_run.as_ref(), step, || AuditEvent::Prompt {
+            messages: request.messages.clone(),
+        })
+        .await?;
 
         // Generate response
-        let response = config.provider.generate(request).await?;
+        let chat_span = step_span.chat(
+            config.provider.name(),
+            config.provider.model(),
+            &config.settings,
+        );
+        let requested = Instant::now();
+        let chat = traced_chat(
+            &chat_span,
+            with_retry(config.retry.as_ref(), || {
+                config.provider.generate(request.clone())
+            }),
+        );
+        let Some(result) = before_deadline(deadline, chat).await else {
+            let usage = if has_usage { Some(total_usage) } else { None };
+            let response = timed_out_response(
+                messages,
+                Vec::new(),
+                steps,
+                usage,
+                config.scratchpad.clone(),
+            );
+            config.hooks.finish(&response).await?;
+            return Ok(response);
+        };
+        observe_request(
+            config.metrics.as_ref(),
+            config.provider.name(),
+            config.provider.model(),
+            requested,
+            result.as_ref().map(|response| response.usage.as_ref()),
+        );
+        let mut response = result?;
+        guard_output(&config.guardrails, &mut response.message).await?;
+        audit(audit_run.as_ref(), step, || completion_event(&response)).await?;
 
         // Update usage tracking
         if let Some(usage) = &response.usage {
@@ -279,66 +996,140 @@ where
             has_usage = true;
         }
 
-        // Handle tool calls if present
-        if let Message::Assistant { content, .. } = &response.message {
-            let mut tool_calls = Vec::new();
-            for content_part in content {
-                if let AssistantContent::ToolCall { tool_call } = content_part {
-                    tool_calls.push(tool_call.clone());
+        if let Some(refusal) = response.refusal() {
+            match config.refusal.decide(&refusal, refusals) {
+                RefusalAction::Stop => {}
+                RefusalAction::Fail => return Err(refused(step, refusal)),
+                RefusalAction::Retry { prompt } => {
+                    // The refused answer stays out of the history
+                    refusals += 1;
+                    steps.push(step_info(step, &response, Vec::new(), Vec::new(), started));
+                    messages.push(Message::user(prompt));
+                    step += 1;
+                    continue 'steps;
                 }
             }
+        }
 
+        let tool_calls = tool_calls_of(&response.message);
+        let mut step_results = Vec::new();
+        let mut timed_out = false;
+
+        // Handle tool calls if present
+        if let Message::Assistant { .. } = &response.message {
             if !tool_calls.is_empty() && config.tool_router.is_some() {
                 // Add assistant message with tool calls
                 messages.push(response.message.clone());
 
                 // Execute tool calls and collect results
                 let mut tool_results = Vec::new();
-                let mut should_end_loop = false;
+                let mut pending_tool_calls = Vec::new();
                 if let Some(router) = &config.tool_router {
-                    for tool_call in tool_calls {
-                        match router
-                            .execute_tool(&tool_call.name, tool_call.arguments.clone())
-                            .await
-                        {
-                            Some(Ok(result)) => {
-                                tool_results.push(ToolResult {
-                                    tool_call_id: tool_call.id,
-                                    result,
-                                    is_error: false,
-                                });
+                    for tool_call in tool_calls.iter().cloned() {
+                        audit(audit_run.as_ref(), step, || AuditEvent::ToolCall {
+                            tool_call: tool_call.clone(),
+                        })
+                        .await?;
+                        if let Err(e) = config.hooks.tool_call(&tool_call).await {
+                            tool_results.push(ToolResult {
+                                tool_call_id: tool_call.id,
+                                result: e.to_result_json(),
+                                is_error: true,
+                                content: Vec::new(),
+                            });
+                            continue;
+                        }
+
+                        let tool_span = step_span.tool(&tool_call);
+                        let tool_started = Instant::now();
+                        // Calls after one the deadline cut short are not started
+                        let output = if timed_out {
+                            Some(Err(deadline_exceeded()))
+                        } else {
+                            let execution = tool_span.instrument(
+                                config.scratchpad.scope(router.execute_call(&tool_call)),
+                            );
+                            let output = before_deadline(deadline, execution).await;
+                            timed_out = output.is_none();
+                            output.unwrap_or(Some(Err(deadline_exceeded())))
+                        };
+                        if let Some(result) = &output {
+                            observe_tool(
+                                config.metrics.as_ref(),
+                                config.provider.name(),
+                                config.provider.model(),
+                                &tool_call.name,
+                                tool_started,
+                                result.as_ref().err().map(|e| e.error_type()),
+                            );
+                        }
+                        match output {
+                            Some(Ok(output)) => {
+                                tool_results.push(output.into_tool_result(tool_call.id));
                             }
                             Some(Err(e)) => {
+                                tool_span.record_error_type(e.error_type());
                                 tool_results.push(ToolResult {
                                     tool_call_id: tool_call.id,
-                                    result: serde_json::json!({
-                                        "error": e.to_string()
-                                    }),
+                                    result: e.to_result_json(),
                                     is_error: true,
+                                    content: Vec::new(),
                                 });
                             }
                             None => {
-                                // Tool has no handler - end the loop to return control to client
-                                should_end_loop = true;
-                                break;
+                                // Tool has no handler - the client has to provide the result
+                                pending_tool_calls.push(tool_call);
                             }
                         }
                     }
                 }
+                for result in &tool_results {
+                    audit(audit_run.as_ref(), step, || AuditEvent::ToolResult {
+                        result: result.clone(),
+                    })
+                    .await?;
+                }
 
-                // If we should end the loop due to missing handler, return immediately
-                if should_end_loop {
-                    return Ok(AgentResponse {
+                // Pause the run and return control to the client
+                if !pending_tool_calls.is_empty() {
+                    config
+                        .hooks
+                        .step_finish(|| step_finish(step, &response, &config.scratchpad))
+                        .await?;
+                    steps.push(step_info(
+                        step,
+                        &response,
+                        tool_calls,
+                        tool_results.clone(),
+                        started,
+                    ));
+                    let usage = if has_usage { Some(total_usage) } else { None };
+                    let response = AgentResponse {
                         messages: messages.clone(),
                         final_message: response.message,
-                        steps: step + 1,
+                        steps: steps.clone(),
                         finish_reason: response.finish_reason,
-                        total_usage: if has_usage { Some(total_usage) } else { None },
-                    });
+                        total_usage: usage.clone(),
+                        checkpoint: Some(AgentCheckpoint {
+                            messages,
+                            step,
+                            usage,
+                            tool_results,
+                            pending_tool_calls,
+                            steps,
+                            scratchpad: config.scratchpad.snapshot(),
+                        }),
+                        scratchpad: config.scratchpad.clone(),
+                        timed_out: false,
+                        output: (),
+                    };
+                    config.hooks.finish(&response).await?;
+                    return Ok(response);
                 }
 
                 // Add tool results message
                 if !tool_results.is_empty() {
+                    step_results = tool_results.clone();
                     messages.push(Message::Tool {
                         tool_results,
                         metadata: None,
@@ -353,47 +1144,632 @@ where
             messages.push(response.message.clone());
         }
 
+        config
+            .hooks
+            .step_finish(|| step_finish(step, &response, &config.scratchpad))
+            .await?;
+        steps.push(step_info(
+            step,
+            &response,
+            tool_calls.clone(),
+            step_results,
+            started,
+        ));
+        if timed_out {
+            let usage = if has_usage { Some(total_usage) } else { None };
+            let response = timed_out_response(
+                messages,
+                Vec::new(),
+                steps,
+                usage,
+                config.scratchpad.clone(),
+            );
+            config.hooks.finish(&response).await?;
+            return Ok(response);
+        }
+
         // Check if we should continue
-        if !run_until.should_continue(step, &response.finish_reason) {
-            return Ok(AgentResponse {
+        let ctx = StepContext {
+            step,
+            finish_reason: &response.finish_reason,
+            usage: has_usage.then_some(&total_usage),
+            last_message: &response.message,
+            tool_calls: &tool_calls,
+            scratchpad: &config.scratchpad,
+        };
+        if !run_until.should_continue(&ctx) {
+            let mut response = response;
+            loop {
+                if let Some(schema) = &config.output_schema {
+                    // One more request for the structured final answer
+                    step += 1;
+                    let started = Instant::now();
+                    let chat_span = run_span.step(step).chat(
+                        config.provider.name(),
+                        config.provider.model(),
+                        &config.settings,
+                    );
+                    let prompt = apply_context(&config.context, &messages);
+                    let mut request = output_request(
+                        compress(&config.compression, &config.provider, prompt).await?,
+                        config.settings.clone(),
+                        config.tools.clone(),
+                        schema,
+                    );
+                    preflight(&config.preflight, &config.provider, &mut request)?;
+                    audit(audit_run.as_ref(), step, || AuditEvent::Prompt {
+                        messages: request.messages.clone(),
+                    })
+                    .await?;
+                    let requested = Instant::now();
+                    let result = traced_chat(
+                        &chat_span,
+                        with_retry(config.retry.as_ref(), || {
+                            config.provider.generate(request.clone())
+                        }),
+                    )
+                    .await;
+                    observe_request(
+                        config.metrics.as_ref(),
+                        config.provider.name(),
+                        config.provider.model(),
+                        requested,
+                        result.as_ref().map(|response| response.usage.as_ref()),
+                    );
+                    response = result?;
+                    audit(audit_run.as_ref(), step, || completion_event(&response)).await?;
+                    if let Some(usage) = &response.usage {
+                        total_usage.prompt_tokens += usage.prompt_tokens;
+                        total_usage.completion_tokens += usage.completion_tokens;
+                        total_usage.total_tokens += usage.total_tokens;
+                        has_usage = true;
+                    }
+                    messages.extend(record_answer(response.message.clone()));
+                    let calls = tool_calls_of(&response.message);
+                    steps.push(step_info(step, &response, calls, Vec::new(), started));
+                }
+                let Some(validator) = &config.output_validator else {
+                    break;
+                };
+                let Err(reason) = validator.check(&response.message) else {
+                    break;
+                };
+                if repairs == validator.max_repairs {
+                    return Err(validation_failed(repairs, reason));
+                }
+                repairs += 1;
+                messages.push(repair_message(&reason));
+                if config.output_schema.is_none() {
+                    // Plain answers are repaired by a regular step, tools included
+                    step += 1;
+                    continue 'steps;
+                }
+            }
+
+            let response = AgentResponse {
                 messages: messages.clone(),
                 final_message: response.message,
-                steps: step + 1,
+                steps,
                 finish_reason: response.finish_reason,
                 total_usage: if has_usage { Some(total_usage) } else { None },
-            });
+                checkpoint: None,
+                scratchpad: config.scratchpad.clone(),
+                timed_out: false,
+                output: (),
+            };
+            config.hooks.finish(&response).await?;
+            return Ok(response);
         }
 
         step += 1;
     }
 }
 
-/// Stream text using an agent with execution control
-pub async fn stream_text<P, S>(
-    config: StreamConfig<P, S>,
-) -> Result<Pin<Box<dyn Stream<Item = Result<AgentStreamChunk>> + Send + 'static>>>
-where
-    P: ChatTextGeneration + Send + 'static,
-    S: Clone + Send + Sync + 'static,
-{
-    let mut run_until = config.run_until;
-    let mut messages = config.messages;
-    let mut step = 0;
+/// Typed event from a streamed agent run
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// A new model call is starting
+    StepStarted { step: u32 },
+    /// Assistant text produced during a step
+    TextDelta { step: u32, text: String },
+    /// Reasoning text, for providers that stream it separately from the answer
+    ReasoningDelta { step: u32, text: String },
+    /// The model requested a tool call
+    ToolCallStarted { step: u32, tool_call: ToolCall },
+    /// A tool call was executed by the router
+    ToolCallCompleted { step: u32, result: ToolResult },
+    /// The step's model call and tool executions are done
+    StepFinished {
+        step: u32,
+        finish_reason: FinishReason,
+        usage: Option<Usage>,
+    },
+    /// The run paused on tool calls without a handler
+    ///
+    /// Collect results for `tool_calls`, then continue with `stream_events_from_checkpoint`
+    /// or `generate_text_from_checkpoint`. `RunFinished` follows with the same checkpoint.
+    PendingToolCalls {
+        step: u32,
+        tool_calls: Vec<ToolCall>,
+        checkpoint: AgentCheckpoint,
+    },
+    /// The run is over; carries the same response `generate_text` would return
+    RunFinished(AgentResponse),
+}
 
-    // Create async stream
-    let stream = async_stream::stream! {
-        loop {
-            // Create request from current messages
-            let request = ChatRequest {
-                messages: messages.clone(),
-                settings: config.settings.clone(),
-                tools: config.tools.clone(),
-            };
+/// Item produced by the shared streaming loop
+enum StreamItem {
+    Chunk(AgentStreamChunk),
+    Event(AgentEvent),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type StreamItems = Pin<Box<dyn Stream<Item = Result<StreamItem>> + Send + 'static>>;
+#[cfg(target_arch = "wasm32")]
+type StreamItems = Pin<Box<dyn Stream<Item = Result<StreamItem>> + 'static>>;
+
+/// Streamed agent text chunks; `Send` except on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type AgentChunkStream = Pin<Box<dyn Stream<Item = Result<AgentStreamChunk>> + Send + 'static>>;
+/// Streamed agent text chunks; `Send` except on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type AgentChunkStream = Pin<Box<dyn Stream<Item = Result<AgentStreamChunk>> + 'static>>;
+
+/// Streamed agent events; `Send` except on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type AgentEventStream = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + Send + 'static>>;
+/// Streamed agent events; `Send` except on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type AgentEventStream = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + 'static>>;
+
+fn apply_context(
+    strategy: &Option<Arc<dyn ContextStrategy>>,
+    messages: &[Message],
+) -> Vec<Message> {
+    match strategy {
+        Some(strategy) => strategy.apply(messages.to_vec()),
+        None => messages.to_vec(),
+    }
+}
+
+async fn compress<P: ChatTextGeneration>(
+    compression: &Option<PromptCompression>,
+    provider: &P,
+    messages: Vec<Message>,
+) -> Result<Vec<Message>> {
+    match compression {
+        Some(compression) => compression.compress(provider, messages).await,
+        None => Ok(messages),
+    }
+}
+
+fn preflight<P: ChatTextGeneration>(
+    preflight: &Option<Preflight>,
+    provider: &P,
+    request: &mut ChatRequest,
+) -> Result<()> {
+    match preflight {
+        Some(preflight) => preflight.check(provider, request),
+        None => Ok(()),
+    }
+}
+
+fn completion_event(response: &ChatResponse) -> AuditEvent {
+    AuditEvent::Completion {
+        message: response.message.clone(),
+        finish_reason: response.finish_reason.clone(),
+        usage: response.usage.clone(),
+    }
+}
+
+fn step_info(
+    step: u32,
+    response: &ChatResponse,
+    tool_calls: Vec<ToolCall>,
+    tool_results: Vec<ToolResult>,
+    started: Instant,
+) -> StepInfo {
+    StepInfo {
+        step,
+        response_id: response.id.clone(),
+        finish_reason: response.finish_reason.clone(),
+        usage: response.usage.clone(),
+        tool_calls,
+        tool_results,
+        duration: started.elapsed(),
+    }
+}
+
+fn step_finish(step: u32, response: &ChatResponse, scratchpad: &Scratchpad) -> StepFinish {
+    StepFinish {
+        step,
+        message: response.message.clone(),
+        finish_reason: response.finish_reason.clone(),
+        usage: response.usage.clone(),
+        scratchpad: scratchpad.clone(),
+    }
+}
+
+/// Stream text using an agent with execution control
+///
+/// A run can span several steps, each ending with an `is_final` chunk; the stream ends
+/// when the run does. Use `stream_events` to see tool calls the run paused on.
+pub async fn stream_text<P, S>(config: StreamConfig<P, S>) -> Result<AgentChunkStream>
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    Ok(only_chunks(stream_items(config, None)))
+}
+
+/// Stream typed events describing the structure of an agent run
+pub async fn stream_events<P, S>(config: StreamConfig<P, S>) -> Result<AgentEventStream>
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    Ok(only_events(stream_items(config, None)))
+}
+
+/// Stream the rest of a run paused on client-side tool calls
+///
+/// Streaming counterpart of `generate_text_from_checkpoint`, with the same checks.
+pub async fn stream_text_from_checkpoint<P, S>(
+    config: StreamConfig<P, S>,
+    checkpoint: AgentCheckpoint,
+    tool_results: Vec<ToolResult>,
+) -> Result<AgentChunkStream>
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    config.scratchpad.merge(&checkpoint.scratchpad);
+    let start = RunStart::resume(checkpoint, tool_results)?;
+    Ok(only_chunks(stream_items(config, Some(start))))
+}
+
+/// Stream events for the rest of a run paused on client-side tool calls
+pub async fn stream_events_from_checkpoint<P, S>(
+    config: StreamConfig<P, S>,
+    checkpoint: AgentCheckpoint,
+    tool_results: Vec<ToolResult>,
+) -> Result<AgentEventStream>
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    config.scratchpad.merge(&checkpoint.scratchpad);
+    let start = RunStart::resume(checkpoint, tool_results)?;
+    Ok(only_events(stream_items(config, Some(start))))
+}
+
+fn only_chunks(items: StreamItems) -> AgentChunkStream {
+    Box::pin(items.filter_map(|item| async move {
+        match item {
+            Ok(StreamItem::Chunk(chunk)) => Some(Ok(chunk)),
+            Ok(StreamItem::Event(_)) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }))
+}
+
+fn only_events(items: StreamItems) -> AgentEventStream {
+    Box::pin(items.filter_map(|item| async move {
+        match item {
+            Ok(StreamItem::Event(event)) => Some(Ok(event)),
+            Ok(StreamItem::Chunk(_)) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }))
+}
+
+/// Run `work` until `deadline`; `None` when the deadline passed first
+async fn before_deadline<F: Future>(deadline: Option<Instant>, work: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => {
+            platform::timeout(deadline.saturating_duration_since(Instant::now()), work).await
+        }
+        None => Some(work.await),
+    }
+}
+
+/// Error result of a tool call the run's deadline cut short or left unstarted
+fn deadline_exceeded() -> ToolExecutionError {
+    ToolExecutionError::ExecutionError("the run's deadline passed".to_string())
+}
+
+/// Response of a run its deadline cut short, made of the steps finished before it
+fn timed_out_response(
+    messages: Vec<Message>,
+    partial: Vec<AssistantContent>,
+    steps: Vec<StepInfo>,
+    total_usage: Option<Usage>,
+    scratchpad: Scratchpad,
+) -> AgentResponse {
+    let final_message = if partial.is_empty() {
+        messages
+            .iter()
+            .rev()
+            .find(|message| matches!(message, Message::Assistant { .. }))
+            .cloned()
+    } else {
+        None
+    };
+    let finish_reason = steps
+        .last()
+        .map_or(FinishReason::Stop, |step| step.finish_reason.clone());
+    AgentResponse {
+        messages,
+        final_message: final_message.unwrap_or(Message::Assistant {
+            content: partial,
+            metadata: None,
+        }),
+        steps,
+        finish_reason,
+        total_usage,
+        checkpoint: None,
+        scratchpad,
+        timed_out: true,
+        output: (),
+    }
+}
+
+/// End a streamed run its deadline cut short, unless the `on_finish` hook fails
+async fn finish_timed_out(
+    hooks: &AgentHooks,
+    run_span: &GenAiSpan,
+    response: AgentResponse,
+) -> Result<StreamItem> {
+    hooks.finish(&response).await?;
+    run_span.record_finish(&response.finish_reason, response.total_usage.as_ref());
+    run_span.record_steps(response.steps.len());
+    Ok(StreamItem::Event(AgentEvent::RunFinished(response)))
+}
+
+/// Keep what a failed streaming step produced, so callers can still show it
+fn partial_response(step: u32, content: Vec<AssistantContent>, error: AiError) -> AiError {
+    if content.is_empty() {
+        return error;
+    }
+    AiError::Agent(AgentError::PartialResponse {
+        step,
+        partial: Box::new(Message::Assistant {
+            content,
+            metadata: None,
+        }),
+        message: error.to_string(),
+    })
+}
+
+/// Concatenated text parts of assistant content
+fn content_text(content: &[AssistantContent]) -> String {
+    content
+        .iter()
+        .filter_map(|part| match part {
+            AssistantContent::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Run the output guardrails over a step's partial content before it leaves the run
+async fn guard_partial(
+    guardrails: &[Arc<dyn Guardrail>],
+    content: Vec<AssistantContent>,
+) -> Result<Vec<AssistantContent>> {
+    let mut message = Message::Assistant {
+        content,
+        metadata: None,
+    };
+    guard_output(guardrails, &mut message).await?;
+    let Message::Assistant { content, .. } = message else {
+        unreachable!("guardrails keep the message role");
+    };
+    Ok(content)
+}
+
+/// Items held back while the output guardrails checked a streamed step
+///
+/// When a guardrail rewrote the text, the streamed text deltas are replaced by one
+/// delta carrying the guarded text; everything else is released unchanged.
+fn release_held(held: Vec<StreamItem>, streamed: &str, message: &Message) -> Vec<StreamItem> {
+    let Message::Assistant { content, .. } = message else {
+        return held;
+    };
+    let guarded = content_text(content);
+    if guarded == streamed {
+        return held;
+    }
+
+    let mut released = Vec::new();
+    let mut replaced = guarded.is_empty();
+    for item in held {
+        match item {
+            StreamItem::Event(AgentEvent::TextDelta { .. }) => {}
+            StreamItem::Chunk(mut chunk)
+                if matches!(
+                    chunk.chunk.delta,
+                    MessageDelta::Assistant {
+                        content: Some(AssistantContent::Text { .. })
+                    }
+                ) =>
+            {
+                if !replaced {
+                    replaced = true;
+                    let mut text = chunk.clone();
+                    text.chunk.delta = MessageDelta::Assistant {
+                        content: Some(AssistantContent::Text {
+                            text: guarded.clone(),
+                        }),
+                    };
+                    text.chunk.finish_reason = None;
+                    text.chunk.usage = None;
+                    text.is_final = false;
+                    text.total_usage = None;
+                    released.push(StreamItem::Chunk(text));
+                    released.push(StreamItem::Event(AgentEvent::TextDelta {
+                        step: chunk.step,
+                        text: guarded.clone(),
+                    }));
+                }
+                // Keep chunks that also end the step or carry usage, minus their text
+                if chunk.is_final || chunk.chunk.usage.is_some() {
+                    chunk.chunk.delta = MessageDelta::Assistant { content: None };
+                    released.push(StreamItem::Chunk(chunk));
+                }
+            }
+            item => released.push(item),
+        }
+    }
+    released
+}
+
+/// Add a step's usage to the run total
+pub(crate) fn add_usage(total: Option<Usage>, step: Option<&Usage>) -> Option<Usage> {
+    match (total, step) {
+        (Some(total), Some(step)) => Some(Usage {
+            prompt_tokens: total.prompt_tokens + step.prompt_tokens,
+            completion_tokens: total.completion_tokens + step.completion_tokens,
+            total_tokens: total.total_tokens + step.total_tokens,
+        }),
+        (total, step) => total.or_else(|| step.cloned()),
+    }
+}
+
+/// Append a streamed content part, merging consecutive text deltas
+pub(crate) fn push_content(content: &mut Vec<AssistantContent>, part: &AssistantContent) {
+    match (content.last_mut(), part) {
+        (Some(AssistantContent::Text { text: accumulated }), AssistantContent::Text { text }) => {
+            accumulated.push_str(text)
+        }
+        _ => content.push(part.clone()),
+    }
+}
+
+/// Merge usage reported across a step's chunks, for providers whose streams don't
+/// already carry cumulative usage
+pub(crate) fn merge_stream_usage(current: Option<Usage>, reported: &Usage) -> Usage {
+    current.unwrap_or(Usage::new(0, 0)).merge_reported(reported)
+}
+
+/// Shared streaming loop; `start` resumes a paused run instead of starting from
+/// the config's messages
+fn stream_items<P, S>(config: StreamConfig<P, S>, start: Option<RunStart>) -> StreamItems
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    let mut run_until = config.run_until;
+    run_until.start();
+    let deadline = run_until.deadline();
+    let resumed = start.is_some();
+    let RunStart {
+        mut messages,
+        mut step,
+        usage: mut total_usage,
+        mut steps,
+    } = start.unwrap_or(RunStart {
+        messages: config.messages,
+        step: 0,
+        usage: None,
+        steps: Vec::new(),
+    });
+    let run_span = GenAiSpan::agent(config.provider.name(), config.provider.model());
+    let audit_run = AuditRun::start(
+        config.audit.as_ref(),
+        config.provider.name(),
+        config.provider.model(),
+    );
+    let mut refusals = 0;
+
+    // Create async stream
+    let stream = async_stream::stream! {
+        if !resumed && let Err(e) = guard_input(&config.guardrails, &mut messages).await {
+            yield Err(e);
+            return;
+        }
+
+        loop {
+            if let Err(e) = config.hooks.step_start(step).await {
+                yield Err(e);
+                return;
+            }
+            yield Ok(StreamItem::Event(AgentEvent::StepStarted { step }));
+            let started = Instant::now();
+            let step_span = run_span.step(step);
+
+            if let Some(compaction) = &config.compaction
+                && compaction.needs_compaction(&messages)
+            {
+                match compaction.compact(&config.provider, messages.clone()).await {
+                    Ok(compacted) => messages = compacted,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+
+            // Create request from current messages
+            let prompt = apply_context(&config.context, &messages);
+            let prompt = match compress(&config.compression, &config.provider, prompt).await {
+                Ok(prompt) => prompt,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut request = ChatRequest {
+                messages: prompt,
+                settings: config.settings.clone(),
+                headers: Vec::new(),
+                tools: config.tools.clone(),
+            };
+            if let Err(e) = preflight(&config.preflight, &config.provider, &mut request) {
+                yield Err(e);
+                return;
+            }
+            let prompt = || AuditEvent::Prompt {
+                messages: request.messages.clone(),
+            };
+            if let Err(e) = audit(audit_run.as_ref(), step, prompt).await {
+                yield Err(e);
+                return;
+            }
 
             // Generate streaming response
-            let mut response_stream = match config.provider.generate_stream(request).await {
+            let chat_span = step_span.chat(
+                config.provider.name(),
+                config.provider.model(),
+                &config.settings,
+            );
+            let requested = Instant::now();
+            let opening = chat_span.instrument(with_retry(config.retry.as_ref(), || {
+                config.provider.generate_stream(request.clone())
+            }));
+            let Some(opened) = before_deadline(deadline, opening).await else {
+                let response = timed_out_response(
+                    messages.clone(),
+                    Vec::new(),
+                    steps.clone(),
+                    total_usage.clone(),
+                    config.scratchpad.clone(),
+                );
+                yield finish_timed_out(&config.hooks, &run_span, response).await;
+                return;
+            };
+            let mut response_stream = match opened {
                 Ok(stream) => stream,
                 Err(e) => {
+                    chat_span.record_error(&e);
+                    run_span.record_error(&e);
+                    observe_request(
+                        config.metrics.as_ref(),
+                        config.provider.name(),
+                        config.provider.model(),
+                        requested,
+                        Err(&e),
+                    );
                     yield Err(e);
                     return;
                 }
@@ -402,92 +1778,271 @@ where
             let mut accumulated_content = Vec::new();
             let mut accumulated_tool_calls = Vec::new();
             let mut finish_reason = FinishReason::Stop;
+            let mut step_usage: Option<Usage> = None;
+            let mut response_id = String::new();
+            let mut step_results = Vec::new();
+            let mut timed_out = false;
+            // With output guardrails, the step's items are held until its text is checked
+            let mut held = Vec::new();
 
-            // Stream chunks for this step
-            while let Some(chunk_result) = response_stream.next().await {
+            // Stream chunks for this step, cutting the step short at the deadline
+            loop {
+                let Some(next) = before_deadline(deadline, response_stream.next()).await else {
+                    let partial = match guard_partial(&config.guardrails, accumulated_content).await {
+                        Ok(partial) => partial,
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    };
+                    let response = timed_out_response(
+                        messages.clone(),
+                        partial,
+                        steps.clone(),
+                        add_usage(total_usage.clone(), step_usage.as_ref()),
+                        config.scratchpad.clone(),
+                    );
+                    yield finish_timed_out(&config.hooks, &run_span, response).await;
+                    return;
+                };
+                let Some(chunk_result) = next else {
+                    break;
+                };
                 match chunk_result {
                     Ok(chunk) => {
                         let is_final = chunk.finish_reason.is_some();
+                        if response_id.is_empty() {
+                            response_id = chunk.id.clone();
+                        }
 
                         if let Some(reason) = &chunk.finish_reason {
                             finish_reason = reason.clone();
                         }
 
-                        // Accumulate content for conversation history
+                        if let Some(usage) = &chunk.usage {
+                            step_usage = Some(merge_stream_usage(step_usage, usage));
+                        }
+
+                        // Accumulate content for conversation history, merging text deltas
+                        let mut event = None;
+                        if let MessageDelta::Reasoning { text } = &chunk.delta
+                            && !text.is_empty()
+                        {
+                            event = Some(AgentEvent::ReasoningDelta { step, text: text.clone() });
+                        }
                         if let MessageDelta::Assistant { content: Some(content) } = &chunk.delta {
-                            accumulated_content.push(content.clone());
+                            push_content(&mut accumulated_content, content);
 
-                            // Check for tool calls in the content
-                            if let AssistantContent::ToolCall { tool_call } = content {
-                                accumulated_tool_calls.push(tool_call.clone());
+                            match content {
+                                AssistantContent::Text { text } if !text.is_empty() => {
+                                    event = Some(AgentEvent::TextDelta { step, text: text.clone() });
+                                }
+                                AssistantContent::ToolCall { tool_call } => {
+                                    accumulated_tool_calls.push(tool_call.clone());
+                                    event = Some(AgentEvent::ToolCallStarted {
+                                        step,
+                                        tool_call: tool_call.clone(),
+                                    });
+                                }
+                                _ => {}
                             }
                         }
 
                         // Yield the chunk
-                        yield Ok(AgentStreamChunk {
+                        let run_usage = if is_final {
+                            add_usage(total_usage.clone(), step_usage.as_ref())
+                        } else {
+                            None
+                        };
+                        let item = StreamItem::Chunk(AgentStreamChunk {
                             step,
                             chunk,
                             is_final,
+                            total_usage: run_usage,
                         });
+                        if config.guardrails.is_empty() {
+                            yield Ok(item);
+                            if let Some(event) = event {
+                                yield Ok(StreamItem::Event(event));
+                            }
+                        } else {
+                            held.push(item);
+                            held.extend(event.map(StreamItem::Event));
+                        }
 
                         if is_final {
                             break;
                         }
                     }
                     Err(e) => {
-                        yield Err(e);
+                        chat_span.record_error(&e);
+                        run_span.record_error(&e);
+                        observe_request(
+                            config.metrics.as_ref(),
+                            config.provider.name(),
+                            config.provider.model(),
+                            requested,
+                            Err(&e),
+                        );
+                        match guard_partial(&config.guardrails, accumulated_content).await {
+                            Ok(partial) => yield Err(partial_response(step, partial, e)),
+                            Err(blocked) => yield Err(blocked),
+                        }
+                        return;
+                    }
+                }
+            }
+
+            chat_span.record_response_id(&response_id);
+            chat_span.record_finish(&finish_reason, step_usage.as_ref());
+            observe_request(
+                config.metrics.as_ref(),
+                config.provider.name(),
+                config.provider.model(),
+                requested,
+                Ok(step_usage.as_ref()),
+            );
+            total_usage = add_usage(total_usage, step_usage.as_ref());
+
+            let streamed = content_text(&accumulated_content);
+            let mut final_message = Message::Assistant {
+                content: accumulated_content,
+                metadata: None,
+            };
+            if let Err(e) = guard_output(&config.guardrails, &mut final_message).await {
+                yield Err(e);
+                return;
+            }
+            for item in release_held(held, &streamed, &final_message) {
+                yield Ok(item);
+            }
+            let completion = || AuditEvent::Completion {
+                message: final_message.clone(),
+                finish_reason: finish_reason.clone(),
+                usage: step_usage.clone(),
+            };
+            if let Err(e) = audit(audit_run.as_ref(), step, completion).await {
+                yield Err(e);
+                return;
+            }
+            if let Some(refusal) = streamed_refusal(&finish_reason, &final_message) {
+                match config.refusal.decide(&refusal, refusals) {
+                    RefusalAction::Stop => {}
+                    RefusalAction::Fail => {
+                        yield Err(refused(step, refusal));
                         return;
                     }
+                    RefusalAction::Retry { prompt } => {
+                        // The refused answer stays out of the history
+                        refusals += 1;
+                        steps.push(StepInfo {
+                            step,
+                            response_id,
+                            finish_reason: finish_reason.clone(),
+                            usage: step_usage.clone(),
+                            tool_calls: Vec::new(),
+                            tool_results: Vec::new(),
+                            duration: started.elapsed(),
+                        });
+                        yield Ok(StreamItem::Event(AgentEvent::StepFinished {
+                            step,
+                            finish_reason,
+                            usage: step_usage,
+                        }));
+                        messages.push(Message::user(prompt));
+                        step += 1;
+                        continue;
+                    }
                 }
             }
+            let mut pending = None;
 
             // Add accumulated response to conversation
-            if !accumulated_content.is_empty() {
-                let assistant_message = Message::Assistant {
-                    content: accumulated_content,
-                    metadata: None,
-                };
-                messages.push(assistant_message);
+            if !matches!(&final_message, Message::Assistant { content, .. } if content.is_empty()) {
+                messages.push(final_message.clone());
 
                 // Handle tool calls if present
                 if !accumulated_tool_calls.is_empty() && config.tool_router.is_some() {
                     let mut tool_results = Vec::new();
-                    let mut should_end_loop = false;
+                    let mut pending_tool_calls = Vec::new();
                     if let Some(router) = &config.tool_router {
-                        for tool_call in accumulated_tool_calls {
-                            match router.execute_tool(&tool_call.name, tool_call.arguments.clone()).await {
-                                Some(Ok(result)) => {
-                                    tool_results.push(ToolResult {
-                                        tool_call_id: tool_call.id,
-                                        result,
-                                        is_error: false,
-                                    });
+                        for tool_call in accumulated_tool_calls.iter().cloned() {
+                            let requested_call = || AuditEvent::ToolCall {
+                                tool_call: tool_call.clone(),
+                            };
+                            if let Err(e) = audit(audit_run.as_ref(), step, requested_call).await {
+                                yield Err(e);
+                                return;
+                            }
+                            let result = if let Err(e) = config.hooks.tool_call(&tool_call).await {
+                                ToolResult {
+                                    tool_call_id: tool_call.id,
+                                    result: e.to_result_json(),
+                                    is_error: true,
+                                    content: Vec::new(),
                                 }
+                            } else {
+                                let tool_span = step_span.tool(&tool_call);
+                                let tool_started = Instant::now();
+                                // Calls after one the deadline cut short are not started
+                                let output = if timed_out {
+                                    Some(Err(deadline_exceeded()))
+                                } else {
+                                    let execution = tool_span.instrument(
+                                        config.scratchpad.scope(router.execute_call(&tool_call)),
+                                    );
+                                    let output = before_deadline(deadline, execution).await;
+                                    timed_out = output.is_none();
+                                    output.unwrap_or(Some(Err(deadline_exceeded())))
+                                };
+                                if let Some(result) = &output {
+                                    observe_tool(
+                                        config.metrics.as_ref(),
+                                        config.provider.name(),
+                                        config.provider.model(),
+                                        &tool_call.name,
+                                        tool_started,
+                                        result.as_ref().err().map(|e| e.error_type()),
+                                    );
+                                }
+                                match output {
+                                Some(Ok(output)) => output.into_tool_result(tool_call.id),
                                 Some(Err(e)) => {
-                                    tool_results.push(ToolResult {
+                                    tool_span.record_error_type(e.error_type());
+                                    ToolResult {
                                         tool_call_id: tool_call.id,
-                                        result: serde_json::json!({
-                                            "error": e.to_string()
-                                        }),
+                                        result: e.to_result_json(),
                                         is_error: true,
-                                    });
+                                        content: Vec::new(),
+                                    }
                                 }
                                 None => {
-                                    // Tool has no handler - end the loop to return control to client
-                                    should_end_loop = true;
-                                    break;
+                                    // Tool has no handler - the client has to provide the result
+                                    pending_tool_calls.push(tool_call);
+                                    continue;
                                 }
                             }
+                            };
+                            let completed = || AuditEvent::ToolResult {
+                                result: result.clone(),
+                            };
+                            if let Err(e) = audit(audit_run.as_ref(), step, completed).await {
+                                yield Err(e);
+                                return;
+                            }
+                            yield Ok(StreamItem::Event(AgentEvent::ToolCallCompleted {
+                                step,
+                                result: result.clone(),
+                            }));
+                            tool_results.push(result);
                         }
                     }
 
-                    // If we should end the loop due to missing handler, return immediately
-                    if should_end_loop {
-                        return;
-                    }
-
-                    // Add tool results message
-                    if !tool_results.is_empty() {
+                    // Add tool results message, or pause for the client's results
+                    step_results = tool_results.clone();
+                    if !pending_tool_calls.is_empty() {
+                        pending = Some((tool_results, pending_tool_calls));
+                    } else if !tool_results.is_empty() {
                         messages.push(Message::Tool {
                             tool_results,
                             metadata: None,
@@ -496,8 +2051,87 @@ where
                 }
             }
 
-            // Check if we should continue
-            if !run_until.should_continue(step, &finish_reason) {
+            let finished = config.hooks.step_finish(|| StepFinish {
+                step,
+                message: final_message.clone(),
+                finish_reason: finish_reason.clone(),
+                usage: step_usage.clone(),
+                scratchpad: config.scratchpad.clone(),
+            });
+            if let Err(e) = finished.await {
+                yield Err(e);
+                return;
+            }
+            steps.push(StepInfo {
+                step,
+                response_id,
+                finish_reason: finish_reason.clone(),
+                usage: step_usage.clone(),
+                tool_calls: accumulated_tool_calls.clone(),
+                tool_results: step_results,
+                duration: started.elapsed(),
+            });
+            yield Ok(StreamItem::Event(AgentEvent::StepFinished {
+                step,
+                finish_reason: finish_reason.clone(),
+                usage: step_usage,
+            }));
+            if timed_out && pending.is_none() {
+                let response = timed_out_response(
+                    messages.clone(),
+                    Vec::new(),
+                    steps.clone(),
+                    total_usage.clone(),
+                    config.scratchpad.clone(),
+                );
+                yield finish_timed_out(&config.hooks, &run_span, response).await;
+                return;
+            }
+
+            // End on a missing handler or when the strategy says so
+            let ctx = StepContext {
+                step,
+                finish_reason: &finish_reason,
+                usage: total_usage.as_ref(),
+                last_message: &final_message,
+                tool_calls: &accumulated_tool_calls,
+                scratchpad: &config.scratchpad,
+            };
+            if pending.is_some() || !run_until.should_continue(&ctx) {
+                let checkpoint = pending.map(|(tool_results, pending_tool_calls)| AgentCheckpoint {
+                    messages: messages.clone(),
+                    step,
+                    usage: total_usage.clone(),
+                    tool_results,
+                    pending_tool_calls,
+                    steps: steps.clone(),
+                    scratchpad: config.scratchpad.snapshot(),
+                });
+                if let Some(checkpoint) = &checkpoint {
+                    yield Ok(StreamItem::Event(AgentEvent::PendingToolCalls {
+                        step,
+                        tool_calls: checkpoint.pending_tool_calls.clone(),
+                        checkpoint: checkpoint.clone(),
+                    }));
+                }
+                let response = AgentResponse {
+                    messages: messages.clone(),
+                    final_message,
+                    steps: steps.clone(),
+                    finish_reason,
+                    total_usage: total_usage.clone(),
+                    checkpoint,
+                    scratchpad: config.scratchpad.clone(),
+                    timed_out: false,
+                    output: (),
+                };
+                if let Err(e) = config.hooks.finish(&response).await {
+                    yield Err(e);
+                    return;
+                }
+                run_span.record_finish(&response.finish_reason, response.total_usage.as_ref());
+                run_span.record_steps(response.steps.len());
+                yield Ok(StreamItem::Event(AgentEvent::RunFinished(response)));
                 return;
             }
 
@@ -506,5 +2140,1402 @@ where
         }
     };
 
-    Ok(Box::pin(stream))
+    Box::pin(stream)
+}
+
+/// Store and conversation an agent persists its history to
+#[derive(Clone)]
+struct Persistence {
+    store: Arc<dyn MessageStore>,
+    conversation_id: String,
+}
+
+/// Drop what a run adds in front of the history: the system message and any examples
+fn strip_run_prefix(messages: &mut Vec<Message>, strip_system: bool) {
+    if strip_system && matches!(messages.first(), Some(Message::System { .. })) {
+        messages.remove(0);
+    }
+    messages.retain(|message| !message.is_example());
+}
+
+/// Conversation kept by an agent
+#[derive(Debug, Default)]
+struct History {
+    messages: Vec<Message>,
+    /// Bumped on every change, so a run can tell whether the history moved under it
+    generation: u64,
+}
+
+impl History {
+    fn replace(&mut self, messages: Vec<Message>) {
+        self.messages = messages;
+        self.generation += 1;
+    }
+}
+
+/// Replace the history with a run's messages, unless it changed since the run started
+///
+/// The run passes the generation it started from, so overlapping runs, or a
+/// `clear_history` or `with_history` during a run, fail the run instead of being lost.
+fn commit_history(
+    history: &Mutex<History>,
+    persistence: Option<&Persistence>,
+    generation: u64,
+    mut messages: Vec<Message>,
+    strip_system: bool,
+) -> Result<Vec<Message>> {
+    strip_run_prefix(&mut messages, strip_system);
+    let mut current = history.lock().unwrap();
+    if current.generation != generation {
+        return Err(match persistence {
+            Some(persistence) => AiError::Storage(StorageError::Conflict {
+                conversation_id: persistence.conversation_id.clone(),
+            }),
+            None => AiError::Agent(AgentError::StateError {
+                message: "another run changed the history while this one was in progress"
+                    .to_string(),
+            }),
+        });
+    }
+    current.replace(messages.clone());
+    Ok(messages)
+}
+
+/// Messages a run added on top of the history it started from
+fn new_messages<'a>(before: &[Message], after: &'a [Message]) -> &'a [Message] {
+    if after.starts_with(before) {
+        &after[before.len()..]
+    } else {
+        after
+    }
+}
+
+impl Persistence {
+    /// Write the difference between two histories, appending when possible
+    async fn save(&self, before: &[Message], after: &[Message]) -> Result<()> {
+        if after.starts_with(before) {
+            if after.len() > before.len() {
+                self.store
+                    .append_if_len(&self.conversation_id, before.len(), &after[before.len()..])
+                    .await?;
+            }
+            Ok(())
+        } else {
+            // History was rewritten, e.g. by compaction
+            self.store
+                .replace_if_len(&self.conversation_id, before.len(), after)
+                .await
+        }
+    }
+}
+
+/// Builds a fresh termination strategy for each run
+type RunUntilFactory = Arc<dyn Fn() -> Box<dyn RunUntil + Send> + Send + Sync>;
+
+/// Reusable agent bundling a provider, system prompt, tools, settings and run policy
+///
+/// The agent keeps the conversation history between calls, so each `run` continues
+/// where the previous one stopped. Runs on one agent must not overlap: a run fails if
+/// another one changed the history after it started; use `fork` for independent runs.
+pub struct Agent<P, S = ()>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    provider: Arc<P>,
+    system_prompt: Option<String>,
+    examples: Vec<(String, String)>,
+    example_style: Option<ExampleStyle>,
+    settings: GenerationSettings,
+    tool_router: Option<BuiltToolRouter<S>>,
+    run_until: RunUntilFactory,
+    hooks: AgentHooks,
+    context: Option<Arc<dyn ContextStrategy>>,
+    compression: Option<PromptCompression>,
+    compaction: Option<Compaction>,
+    guardrails: Guardrails,
+    retry: Option<RetryPolicy>,
+    refusal: RefusalPolicy,
+    preflight: Option<Preflight>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    audit: Option<AuditLog>,
+    scratchpad: Scratchpad,
+    memory: Option<AgentMemory>,
+    persistence: Option<Persistence>,
+    history: Arc<Mutex<History>>,
+}
+
+impl<P, S> Debug for Agent<P, S>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Agent")
+            .field("provider", &self.provider.name())
+            .field("model", &self.provider.model())
+            .field("system_prompt", &self.system_prompt)
+            .field("examples", &self.examples.len())
+            .field("example_style", &self.example_style)
+            .field("settings", &self.settings)
+            .field("hooks", &self.hooks)
+            .field("context", &self.context)
+            .field("compression", &self.compression)
+            .field("compaction", &self.compaction)
+            .field("guardrails", &self.guardrails)
+            .field("retry", &self.retry)
+            .field("refusal", &self.refusal)
+            .field("preflight", &self.preflight)
+            .field("metrics", &self.metrics)
+            .field("audit", &self.audit)
+            .field("scratchpad", &self.scratchpad.keys())
+            .field("memory", &self.memory)
+            .field(
+                "conversation_id",
+                &self.persistence.as_ref().map(|p| &p.conversation_id),
+            )
+            .field(
+                "tools",
+                &self.tool_router.as_ref().map(|router| router.tool_names()),
+            )
+            .field("history", &self.history.lock().unwrap().messages.len())
+            .finish()
+    }
+}
+
+impl<P> Agent<P, ()>
+where
+    P: ChatTextGeneration,
+{
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            system_prompt: None,
+            examples: Vec::new(),
+            example_style: None,
+            settings: GenerationSettings::default(),
+            tool_router: None,
+            run_until: Arc::new(|| Box::new(MaxSteps::new(1))),
+            hooks: AgentHooks::default(),
+            context: None,
+            compression: None,
+            compaction: None,
+            guardrails: Vec::new(),
+            retry: None,
+            refusal: RefusalPolicy::default(),
+            preflight: None,
+            metrics: None,
+            audit: None,
+            scratchpad: Scratchpad::new(),
+            memory: None,
+            persistence: None,
+            history: Arc::default(),
+        }
+    }
+
+    pub fn tools<S: Clone + Send + Sync + 'static>(
+        self,
+        router: BuiltToolRouter<S>,
+    ) -> Agent<P, S> {
+        Agent {
+            provider: self.provider,
+            system_prompt: self.system_prompt,
+            examples: self.examples,
+            example_style: self.example_style,
+            settings: self.settings,
+            tool_router: Some(router),
+            run_until: self.run_until,
+            hooks: self.hooks,
+            context: self.context,
+            compression: self.compression,
+            compaction: self.compaction,
+            guardrails: self.guardrails,
+            retry: self.retry,
+            refusal: self.refusal,
+            preflight: self.preflight,
+            metrics: self.metrics,
+            audit: self.audit,
+            scratchpad: self.scratchpad,
+            memory: self.memory,
+            persistence: self.persistence,
+            history: self.history,
+        }
+    }
+}
+
+impl<P, S> Agent<P, S>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Few-shot `(user, assistant)` exchanges sent before the conversation on every run
+    ///
+    /// Examples are not kept in the history.
+    pub fn examples<U, A>(mut self, examples: Vec<(U, A)>) -> Self
+    where
+        U: Into<String>,
+        A: Into<String>,
+    {
+        self.examples = examples
+            .into_iter()
+            .map(|(user, assistant)| (user.into(), assistant.into()))
+            .collect();
+        self
+    }
+
+    /// Where examples are placed; defaults to the provider's preference
+    pub fn example_style(mut self, style: ExampleStyle) -> Self {
+        self.example_style = Some(style);
+        self
+    }
+
+    pub fn settings(mut self, settings: GenerationSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn temperature(mut self, temp: f32) -> Self {
+        self.settings.temperature = Some(temp);
+        self
+    }
+
+    pub fn max_tokens(mut self, tokens: u32) -> Self {
+        self.settings.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Set the termination strategy, cloned fresh for every run
+    pub fn run_until(mut self, run_until: impl RunUntil + Clone + Send + Sync + 'static) -> Self {
+        self.run_until = Arc::new(move || Box::new(run_until.clone()));
+        self
+    }
+
+    pub fn hooks(mut self, hooks: AgentHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn context_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.context = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn compaction(mut self, compaction: Compaction) -> Self {
+        self.compaction = Some(compaction);
+        self
+    }
+
+    /// Compress each request's messages, see `PromptCompression`
+    pub fn compression(mut self, compression: PromptCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Add a guardrail; guardrails run in the order they were added
+    pub fn guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.push(Arc::new(guardrail));
+        self
+    }
+
+    /// Retry provider requests that fail with a rate limit, server error or timeout
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Stop, fail or retry when the provider refuses or filters a response
+    pub fn refusal_policy(mut self, policy: RefusalPolicy) -> Self {
+        self.refusal = policy;
+        self
+    }
+
+    /// Check requests against the model's context window before sending them
+    pub fn preflight(mut self, preflight: Preflight) -> Self {
+        self.preflight = Some(preflight);
+        self
+    }
+
+    /// Report request counts, latencies, token usage and tool durations to `sink`
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Record every prompt, completion, tool call and tool result to an audit log
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Share `scratchpad` with the run, e.g. to seed it or read it afterwards
+    pub fn scratchpad(mut self, scratchpad: Scratchpad) -> Self {
+        self.scratchpad = scratchpad;
+        self
+    }
+
+    /// Recall relevant long-term memories before each run and learn new ones after it
+    pub fn memory(mut self, memory: AgentMemory) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Copy of this agent's configuration with an empty history that is not persisted
+    pub fn fork(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            system_prompt: self.system_prompt.clone(),
+            examples: self.examples.clone(),
+            example_style: self.example_style,
+            settings: self.settings.clone(),
+            tool_router: self.tool_router.clone(),
+            run_until: self.run_until.clone(),
+            hooks: self.hooks.clone(),
+            context: self.context.clone(),
+            compression: self.compression.clone(),
+            compaction: self.compaction.clone(),
+            guardrails: self.guardrails.clone(),
+            retry: self.retry.clone(),
+            refusal: self.refusal.clone(),
+            preflight: self.preflight.clone(),
+            metrics: self.metrics.clone(),
+            audit: self.audit.clone(),
+            scratchpad: self.scratchpad.snapshot(),
+            memory: self.memory.clone(),
+            persistence: None,
+            history: Arc::default(),
+        }
+    }
+
+    /// Persist the conversation to a store under the given ID
+    ///
+    /// Each run reloads the conversation from the store first, so several agents (or
+    /// processes) can continue the same conversation.
+    pub fn store(
+        mut self,
+        store: impl MessageStore + 'static,
+        conversation_id: impl Into<String>,
+    ) -> Self {
+        self.persistence = Some(Persistence {
+            store: Arc::new(store),
+            conversation_id: conversation_id.into(),
+        });
+        self
+    }
+
+    pub fn conversation_id(&self) -> Option<&str> {
+        self.persistence
+            .as_ref()
+            .map(|persistence| persistence.conversation_id.as_str())
+    }
+
+    /// Refresh the history from the store, returning the history the run starts from
+    /// and its generation
+    async fn load_history(&self) -> Result<(Vec<Message>, u64)> {
+        let stored = match &self.persistence {
+            Some(persistence) => Some(persistence.store.load(&persistence.conversation_id).await?),
+            None => None,
+        };
+        let mut history = self.history.lock().unwrap();
+        if let Some(stored) = stored
+            && stored != history.messages
+        {
+            history.replace(stored);
+        }
+        Ok((history.messages.clone(), history.generation))
+    }
+
+    /// Start from an existing conversation (without the system prompt)
+    pub fn with_history(self, messages: Vec<Message>) -> Self {
+        self.history.lock().unwrap().replace(messages);
+        self
+    }
+
+    /// Get the conversation so far (without the system prompt)
+    pub fn history(&self) -> Vec<Message> {
+        self.history.lock().unwrap().messages.clone()
+    }
+
+    /// Forget the conversation so far
+    pub fn clear_history(&self) {
+        self.history.lock().unwrap().replace(Vec::new());
+    }
+
+    /// System message for the next run: the system prompt plus memories relevant to the input
+    async fn system_message(&self, input: &UserContent) -> Result<Option<Message>> {
+        let memories = match (&self.memory, input) {
+            (Some(memory), UserContent::Text { text }) => memory.context(text).await?,
+            _ => None,
+        };
+        let text = match (&self.system_prompt, memories) {
+            (Some(prompt), Some(memories)) => format!("{}\n\n{}", prompt, memories),
+            (Some(prompt), None) => prompt.clone(),
+            (None, Some(memories)) => memories,
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(Message::system(text)))
+    }
+
+    /// Messages for the next run: system message, examples, history, then the new user input
+    ///
+    /// Also returns whether a system message was prepended, which is not kept in the history.
+    async fn next_messages(
+        &self,
+        history: &[Message],
+        input: impl Into<UserContent>,
+    ) -> Result<(Vec<Message>, bool)> {
+        let input = input.into();
+        let mut messages = Vec::new();
+        let system = self.system_message(&input).await?;
+        let has_system = system.is_some();
+        messages.extend(system);
+        let style = self
+            .example_style
+            .unwrap_or_else(|| self.provider.example_style());
+        messages.extend(style.messages(&self.examples));
+        messages.extend(history.iter().cloned());
+        messages.push(Message::user(input));
+        Ok((messages, has_system))
+    }
+
+    /// Send a user message and run the agent loop to completion
+    pub async fn run(&self, input: impl Into<UserContent>) -> Result<AgentResponse> {
+        let (before, generation) = self.load_history().await?;
+        let (messages, strip_system) = self.next_messages(&before, input).await?;
+        let response = generate_text(self.generate_config(messages)).await?;
+        self.finish_run(&before, generation, response, strip_system)
+            .await
+    }
+
+    /// Run one turn and return its final answer as `T`
+    pub async fn run_output<T>(&self, input: impl Into<UserContent>) -> Result<AgentResponse<T>>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        let (before, generation) = self.load_history().await?;
+        let (messages, strip_system) = self.next_messages(&before, input).await?;
+        let response = generate_text(self.generate_config(messages).output::<T>()).await?;
+        self.finish_run(&before, generation, response, strip_system)
+            .await?
+            .parse_output()
+    }
+
+    /// Resume a run that paused on client-side tool calls
+    pub async fn resume(
+        &self,
+        checkpoint: AgentCheckpoint,
+        tool_results: Vec<ToolResult>,
+    ) -> Result<AgentResponse> {
+        let (before, generation) = self.load_history().await?;
+        let config = self.generate_config(Vec::new());
+        let response = generate_text_from_checkpoint(config, checkpoint, tool_results).await?;
+        self.finish_run(&before, generation, response, self.system_prompt.is_some())
+            .await
+    }
+
+    fn generate_config(&self, messages: Vec<Message>) -> GenerateConfig<Arc<P>, S> {
+        GenerateConfig {
+            provider: self.provider.clone(),
+            messages,
+            settings: self.settings.clone(),
+            tools: self.tool_router.as_ref().map(|r| r.get_tool_definitions()),
+            tool_router: self.tool_router.clone(),
+            run_until: (self.run_until)(),
+            hooks: self.hooks.clone(),
+            context: self.context.clone(),
+            compression: self.compression.clone(),
+            compaction: self.compaction.clone(),
+            guardrails: self.guardrails.clone(),
+            retry: self.retry.clone(),
+            refusal: self.refusal.clone(),
+            preflight: self.preflight.clone(),
+            metrics: self.metrics.clone(),
+            audit: self.audit.clone(),
+            scratchpad: self.scratchpad.clone(),
+            output_schema: None,
+            output_validator: None,
+        }
+    }
+
+    /// Record a finished run in the history and the store
+    async fn finish_run(
+        &self,
+        before: &[Message],
+        generation: u64,
+        mut response: AgentResponse,
+        strip_system: bool,
+    ) -> Result<AgentResponse> {
+        let history = commit_history(
+            &self.history,
+            self.persistence.as_ref(),
+            generation,
+            response.messages.clone(),
+            strip_system,
+        )?;
+        if let Some(persistence) = &self.persistence {
+            persistence.save(before, &history).await?;
+        }
+        if let Some(memory) = &self.memory
+            && response.checkpoint.is_none()
+        {
+            memory
+                .learn(&*self.provider, new_messages(before, &history))
+                .await?;
+        }
+        response.messages = history;
+        Ok(response)
+    }
+
+    /// Stream a run, recording its conversation once `RunFinished` is produced
+    async fn run_items(&self, input: impl Into<UserContent>) -> Result<StreamItems>
+    where
+        P: 'static,
+    {
+        let (before, generation) = self.load_history().await?;
+        let (messages, strip_system) = self.next_messages(&before, input).await?;
+        let config = StreamConfig {
+            provider: self.provider.clone(),
+            messages,
+            settings: self.settings.clone(),
+            tools: self.tool_router.as_ref().map(|r| r.get_tool_definitions()),
+            tool_router: self.tool_router.clone(),
+            run_until: (self.run_until)(),
+            hooks: self.hooks.clone(),
+            context: self.context.clone(),
+            compression: self.compression.clone(),
+            compaction: self.compaction.clone(),
+            guardrails: self.guardrails.clone(),
+            retry: self.retry.clone(),
+            refusal: self.refusal.clone(),
+            preflight: self.preflight.clone(),
+            metrics: self.metrics.clone(),
+            audit: self.audit.clone(),
+            scratchpad: self.scratchpad.clone(),
+        };
+
+        let history = self.history.clone();
+        let persistence = self.persistence.clone();
+        let memory = self.memory.clone();
+        let provider = self.provider.clone();
+        let mut inner = stream_items(config, None);
+
+        Ok(Box::pin(async_stream::stream! {
+            while let Some(mut item) = inner.next().await {
+                if let Ok(StreamItem::Event(AgentEvent::RunFinished(response))) = &mut item {
+                    let messages = std::mem::take(&mut response.messages);
+                    match commit_history(
+                        &history,
+                        persistence.as_ref(),
+                        generation,
+                        messages,
+                        strip_system,
+                    ) {
+                        Ok(messages) => response.messages = messages,
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                    if let Some(persistence) = &persistence
+                        && let Err(e) = persistence.save(&before, &response.messages).await
+                    {
+                        yield Err(e);
+                        return;
+                    }
+                    // A paused turn is learned from once it has been resumed and finished
+                    if let Some(memory) = &memory
+                        && response.checkpoint.is_none()
+                        && let Err(e) = memory
+                            .learn(&*provider, new_messages(&before, &response.messages))
+                            .await
+                    {
+                        yield Err(e);
+                        return;
+                    }
+                }
+                yield item;
+            }
+        }))
+    }
+
+    /// Send a user message and stream the agent loop
+    ///
+    /// The history is updated once the stream has run to completion.
+    pub async fn run_stream(&self, input: impl Into<UserContent>) -> Result<AgentChunkStream>
+    where
+        P: 'static,
+    {
+        Ok(only_chunks(self.run_items(input).await?))
+    }
+
+    /// Send a user message and stream typed events for the agent loop
+    ///
+    /// The history is updated once the stream has run to completion.
+    pub async fn run_events(&self, input: impl Into<UserContent>) -> Result<AgentEventStream>
+    where
+        P: 'static,
+    {
+        Ok(only_events(self.run_items(input).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::response_metadata::RefusalKind;
+    use ai_core::{
+        ToolRouter,
+        provider::{ChatStream, EmbeddingGeneration},
+    };
+    use ai_memory::InMemoryVectorStore;
+    use ai_test_utils::MockProvider;
+
+    fn until_answer() -> RunUntilFirst<MaxSteps, StopOnReason> {
+        RunUntilFirst::new(MaxSteps::new(5), StopOnReason::stop_on_finish())
+    }
+
+    async fn collect_events(
+        config: StreamConfig<MockProvider>,
+    ) -> Vec<std::result::Result<AgentEvent, String>> {
+        stream_events(config)
+            .await
+            .unwrap()
+            .map(|event| event.map_err(|e| e.to_string()))
+            .collect()
+            .await
+    }
+
+    fn run_response(events: &[std::result::Result<AgentEvent, String>]) -> &AgentResponse {
+        match events.last() {
+            Some(Ok(AgentEvent::RunFinished(response))) => response,
+            other => panic!("expected RunFinished, got {:?}", other),
+        }
+    }
+
+    fn refused() -> ChatResponse {
+        ChatResponse {
+            id: "refused".to_string(),
+            message: Message::assistant("I can't help with that."),
+            finish_reason: FinishReason::ContentFilter,
+            usage: None,
+            metadata: None,
+        }
+    }
+
+    fn retry_refusals() -> RefusalPolicy {
+        RefusalPolicy::retry(1, "Please answer within policy.")
+    }
+
+    #[tokio::test]
+    async fn test_refusal_policy_in_generate_text() {
+        let provider = MockProvider::new().respond(refused()).text("Here you go.");
+        let config = GenerateConfig::new(provider.clone())
+            .messages(vec![Message::user("Hi")])
+            .run_until(until_answer())
+            .refusal_policy(retry_refusals());
+        let response = generate_text(config).await.unwrap();
+
+        assert_eq!(response.text(), "Here you go.");
+        assert_eq!(response.steps.len(), 2);
+        assert_eq!(
+            response.messages,
+            vec![
+                Message::user("Hi"),
+                Message::user("Please answer within policy."),
+                Message::assistant("Here you go."),
+            ]
+        );
+        assert_eq!(provider.requests()[1].messages, response.messages[..2]);
+
+        let config = GenerateConfig::new(MockProvider::new().respond(refused()))
+            .messages(vec![Message::user("Hi")])
+            .refusal_policy(RefusalPolicy::Fail);
+        match generate_text(config).await {
+            Err(AiError::Agent(AgentError::Refused { step: 0, refusal })) => {
+                assert_eq!(refusal.kind, RefusalKind::Filtered)
+            }
+            other => panic!("expected a refusal, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_until_fn_stops_when_the_closure_returns_false() {
+        let provider = MockProvider::new()
+            .text("Drafting")
+            .text("DONE")
+            .text("Never requested");
+        let config = GenerateConfig::new(provider.clone())
+            .messages(vec![Message::user("Write it")])
+            .run_until(RunUntilFn::new(|ctx: StepContext<'_>| {
+                !ctx.text().contains("DONE")
+            }));
+        let response = generate_text(config).await.unwrap();
+
+        assert_eq!(response.text(), "DONE");
+        assert_eq!(response.steps.len(), 2);
+        assert_eq!(provider.requests().len(), 2);
+        assert_eq!(provider.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_step_context_carries_usage_message_and_tool_calls() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let provider = MockProvider::new()
+            .with_usage(10, 5)
+            .tool_call("lookup", serde_json::json!({}))
+            .text("Found it");
+        let config = GenerateConfig::new(provider)
+            .messages(vec![Message::user("Look it up")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("lookup", None, lookup_tool)
+                    .with_state(()),
+            )
+            .run_until(RunUntilFn::new(move |ctx: StepContext<'_>| {
+                let tools: Vec<String> = ctx.tool_calls.iter().map(|c| c.name.clone()).collect();
+                record.lock().unwrap().push((
+                    ctx.step,
+                    ctx.finish_reason.clone(),
+                    ctx.usage.map(|usage| usage.total_tokens),
+                    tools,
+                    ctx.text(),
+                ));
+                !ctx.tool_calls.is_empty()
+            }));
+        generate_text(config).await.unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (
+                    0,
+                    FinishReason::ToolCalls,
+                    Some(15),
+                    vec!["lookup".to_string()],
+                    String::new(),
+                ),
+                (
+                    1,
+                    FinishReason::Stop,
+                    Some(30),
+                    Vec::new(),
+                    "Found it".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refusal_policy_in_streamed_runs() {
+        let provider = MockProvider::new().respond(refused()).text("Here you go.");
+        let config = StreamConfig::new(provider.clone())
+            .messages(vec![Message::user("Hi")])
+            .run_until(until_answer())
+            .refusal_policy(retry_refusals());
+        let events = collect_events(config).await;
+
+        let finished: Vec<&FinishReason> = events
+            .iter()
+            .filter_map(|event| match event {
+                Ok(AgentEvent::StepFinished { finish_reason, .. }) => Some(finish_reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            finished,
+            vec![&FinishReason::ContentFilter, &FinishReason::Stop]
+        );
+        let response = run_response(&events);
+        assert_eq!(response.text(), "Here you go.");
+        assert_eq!(
+            response.messages[1],
+            Message::user("Please answer within policy.")
+        );
+        assert_eq!(provider.requests().len(), 2);
+
+        let config = StreamConfig::new(MockProvider::new().respond(refused()))
+            .messages(vec![Message::user("Hi")])
+            .refusal_policy(RefusalPolicy::Fail);
+        let events = collect_events(config).await;
+        assert_eq!(
+            events.last().unwrap().as_ref().unwrap_err(),
+            "Agent error: Step 0 was blocked by a content filter: I can't help with that."
+        );
+
+        // Without a policy the refused answer finishes the run
+        let config = StreamConfig::new(MockProvider::new().respond(refused()))
+            .messages(vec![Message::user("Hi")])
+            .run_until(MaxSteps::new(0));
+        let events = collect_events(config).await;
+        assert_eq!(
+            run_response(&events).finish_reason,
+            FinishReason::ContentFilter
+        );
+    }
+
+    /// Embeds every input as the same vector, so any stored fact is recalled
+    struct UnitEmbedder;
+
+    #[async_trait::async_trait]
+    impl EmbeddingGeneration for UnitEmbedder {
+        fn name(&self) -> &str {
+            "unit"
+        }
+
+        fn model(&self) -> &str {
+            "unit-1"
+        }
+
+        async fn generate_embeddings(
+            &self,
+            request: EmbeddingRequest,
+        ) -> Result<EmbeddingResponse> {
+            Ok(EmbeddingResponse {
+                embeddings: request.inputs.iter().map(|_| vec![1.0]).collect(),
+                usage: None,
+                metadata: None,
+            })
+        }
+
+        fn embedding_dimension(&self) -> u32 {
+            1
+        }
+    }
+
+    fn chat_agent(provider: MockProvider) -> Agent<MockProvider> {
+        Agent::new(provider).run_until(StopOnReason::stop_on_finish())
+    }
+
+    #[tokio::test]
+    async fn test_agent_history_accumulates_without_system_or_examples() {
+        let provider = MockProvider::new().text("Hello").text("Welcome back");
+        let agent = chat_agent(provider.clone())
+            .system_prompt("Be brief")
+            .examples(vec![("2 + 2?", "4")])
+            .example_style(ExampleStyle::Messages);
+
+        agent.run("Hi").await.unwrap();
+        let response = agent.run("Again").await.unwrap();
+
+        let history = vec![
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+            Message::user("Again"),
+            Message::assistant("Welcome back"),
+        ];
+        assert_eq!(agent.history(), history);
+        assert_eq!(response.messages, history);
+
+        // Each request still carries the system prompt and examples in front of the history
+        let sent = &provider.requests()[1].messages;
+        assert!(matches!(sent[0], Message::System { .. }));
+        assert_eq!(
+            sent.iter().filter(|message| message.is_example()).count(),
+            2
+        );
+        assert_eq!(sent[3..], history[..3]);
+    }
+
+    #[tokio::test]
+    async fn test_fork_runs_apart_from_the_original_history() {
+        let agent = chat_agent(MockProvider::new().text("Hello").text("Hi there"));
+        agent.run("Hi").await.unwrap();
+
+        let fork = agent.fork();
+        assert!(fork.history().is_empty());
+        fork.run("Hello?").await.unwrap();
+        assert_eq!(
+            fork.history(),
+            vec![Message::user("Hello?"), Message::assistant("Hi there")]
+        );
+        assert_eq!(
+            agent.history(),
+            vec![Message::user("Hi"), Message::assistant("Hello")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persisted_history_is_reloaded_before_each_run() {
+        let store = Arc::new(ai_memory::InMemoryStore::new());
+        let first = chat_agent(MockProvider::new().text("Hello").text("Still here"))
+            .store(store.clone(), "chat");
+        let second =
+            chat_agent(MockProvider::new().text("Welcome back")).store(store.clone(), "chat");
+
+        first.run("Hi").await.unwrap();
+        second.run("Again").await.unwrap();
+        assert_eq!(second.history().len(), 4);
+
+        // The first agent picks up the turn the second one added
+        first.run("Third").await.unwrap();
+        let stored = store.load("chat").await.unwrap();
+        assert_eq!(stored.len(), 6);
+        assert_eq!(first.history(), stored);
+        assert_eq!(stored[2], Message::user("Again"));
+    }
+
+    #[tokio::test]
+    async fn test_new_agents_continue_a_stored_conversation() {
+        let store = ai_memory::InMemoryStore::new();
+        let earlier = vec![
+            Message::user("My name is Ada"),
+            Message::assistant("Hi Ada"),
+        ];
+        store.append("chat", &earlier).await.unwrap();
+        let store = Arc::new(store);
+
+        let provider = MockProvider::new().text("You are Ada");
+        let agent = chat_agent(provider.clone()).store(store.clone(), "chat");
+        assert_eq!(agent.conversation_id(), Some("chat"));
+        agent.run("Who am I?").await.unwrap();
+
+        let mut expected = earlier;
+        expected.push(Message::user("Who am I?"));
+        assert_eq!(provider.requests()[0].messages, expected);
+        expected.push(Message::assistant("You are Ada"));
+        assert_eq!(store.load("chat").await.unwrap(), expected);
+        assert_eq!(agent.history(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_runs_fail_instead_of_dropping_a_turn() {
+        let agent = chat_agent(
+            MockProvider::new()
+                .text("Second answer")
+                .text("First answer"),
+        );
+        let first = agent.run_events("First").await.unwrap();
+        agent.run("Second").await.unwrap();
+
+        let events: Vec<_> = first.collect().await;
+        let error = events.last().unwrap().as_ref().unwrap_err();
+        assert!(matches!(
+            error,
+            AiError::Agent(AgentError::StateError { .. })
+        ));
+        assert_eq!(
+            agent.history(),
+            vec![Message::user("Second"), Message::assistant("Second answer")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clearing_the_history_during_a_run_is_not_overwritten() {
+        let agent = chat_agent(MockProvider::new().text("Hello"));
+        let run = agent.run_events("Hi").await.unwrap();
+        // Same length as when the run started, but a different history
+        agent.clear_history();
+
+        let events: Vec<_> = run.collect().await;
+        assert!(matches!(
+            events.last(),
+            Some(Err(AiError::Agent(AgentError::StateError { .. })))
+        ));
+        assert!(agent.history().is_empty());
+    }
+
+    async fn finish_events(agent: &Agent<MockProvider>, input: &str) -> Vec<AgentEvent> {
+        agent
+            .run_events(input)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_streamed_runs_learn_only_from_finished_turns() {
+        let memory = AgentMemory::new(UnitEmbedder, InMemoryVectorStore::new());
+        let provider = MockProvider::new()
+            .tool_call(
+                "ask_user",
+                serde_json::json!({ "question": "Where do you live?" }),
+            )
+            .text("Noted.")
+            .text("- Lives in Lyon");
+        let agent = Agent::new(provider.clone())
+            .tools(
+                ToolRouter::new()
+                    .register_definition("ask_user", None, None)
+                    .with_state(()),
+            )
+            .run_until(StopOnReason::stop_on_finish())
+            .memory(memory.clone());
+
+        // The paused turn ends in an unanswered tool call, so nothing is learned yet
+        let events = finish_events(&agent, "Remember where I live").await;
+        let Some(AgentEvent::RunFinished(response)) = events.last() else {
+            panic!("expected RunFinished, got {:?}", events.last());
+        };
+        assert!(response.checkpoint.is_some());
+        assert_eq!(provider.requests().len(), 1);
+        assert!(memory.recall("home").await.unwrap().is_empty());
+
+        let events = finish_events(&agent.fork(), "I live in Lyon").await;
+        assert!(
+            matches!(events.last(), Some(AgentEvent::RunFinished(response)) if response.checkpoint.is_none())
+        );
+        let recalled = memory.recall("home").await.unwrap();
+        assert_eq!(recalled[0].record.text, "Lives in Lyon");
+    }
+
+    #[tokio::test]
+    async fn test_paused_runs_resume_from_a_serialized_checkpoint() {
+        let provider = MockProvider::new()
+            .tool_call("confirm", serde_json::json!({ "action": "delete" }))
+            .text("Deleted the file");
+        let agent = chat_agent(provider.clone()).tools(
+            ToolRouter::new()
+                .register_definition("confirm", None, None)
+                .with_state(()),
+        );
+
+        let paused = agent.run("Delete the file").await.unwrap();
+        let checkpoint = paused.checkpoint.expect("run should pause on 'confirm'");
+        assert_eq!(checkpoint.pending_tool_calls.len(), 1);
+        let call = checkpoint.pending_tool_calls[0].clone();
+        assert_eq!(call.name, "confirm");
+        assert_eq!(provider.requests().len(), 1);
+
+        // The checkpoint is stored and picked up again, e.g. by another process
+        let checkpoint = AgentCheckpoint::from_json(&checkpoint.to_json().unwrap()).unwrap();
+        let answer = ToolResult::success(&call.id, serde_json::json!({ "confirmed": true }));
+        let response = agent
+            .resume(checkpoint, vec![answer.clone()])
+            .await
+            .unwrap();
+
+        assert!(response.checkpoint.is_none());
+        assert_eq!(response.text(), "Deleted the file");
+        assert_eq!(response.steps.len(), 2);
+        let resumed = &provider.requests()[1];
+        assert_eq!(
+            resumed.messages.last(),
+            Some(&Message::tool_results(vec![answer.clone()]))
+        );
+        assert_eq!(
+            agent.history(),
+            vec![
+                Message::user("Delete the file"),
+                Message::assistant_with_tool_calls([call]),
+                Message::tool_results(vec![answer]),
+                Message::assistant("Deleted the file"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_text_is_held_until_output_guardrails_pass() {
+        let config = StreamConfig::new(MockProvider::new().text("Mail jane@example.com"))
+            .messages(vec![Message::user("Who do I contact?")])
+            .run_until(until_answer())
+            .guardrail(crate::pii::PiiGuardrail::new());
+        let events = collect_events(config).await;
+        let deltas: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                Ok(AgentEvent::TextDelta { text, .. }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deltas, ["Mail [REDACTED_EMAIL]"]);
+        assert_eq!(run_response(&events).text(), "Mail [REDACTED_EMAIL]");
+
+        let config = StreamConfig::new(MockProvider::new().text("Mail jane@example.com"))
+            .messages(vec![Message::user("Who do I contact?")])
+            .run_until(until_answer())
+            .guardrail(crate::pii::PiiGuardrail::new());
+        let chunks: Vec<AgentStreamChunk> = stream_text(config)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| match &chunk.chunk.delta {
+                MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text { text }),
+                } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Mail [REDACTED_EMAIL]");
+        assert!(chunks.last().unwrap().is_final);
+
+        let config = StreamConfig::new(MockProvider::new().text("Mail jane@example.com"))
+            .messages(vec![Message::user("Who do I contact?")])
+            .run_until(until_answer())
+            .guardrail(crate::pii::PiiGuardrail::new().mode(crate::pii::PiiMode::Block));
+        let events = collect_events(config).await;
+        assert!(
+            events
+                .iter()
+                .all(|event| !matches!(event, Ok(AgentEvent::TextDelta { .. })))
+        );
+        assert!(events.last().unwrap().as_ref().is_err());
+    }
+
+    /// Provider that never answers; its streams send "Partial" and then stall
+    struct StalledProvider;
+
+    #[async_trait::async_trait]
+    impl ChatTextGeneration for StalledProvider {
+        fn name(&self) -> &str {
+            "stalled"
+        }
+
+        fn model(&self) -> &str {
+            "stalled-1"
+        }
+
+        async fn generate(&self, _request: ChatRequest) -> Result<ChatResponse> {
+            futures::future::pending().await
+        }
+
+        async fn generate_stream(&self, _request: ChatRequest) -> Result<ChatStream> {
+            let chunk = ChatStreamChunk {
+                id: "stalled".to_string(),
+                delta: MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text {
+                        text: "Partial".to_string(),
+                    }),
+                },
+                finish_reason: None,
+                usage: None,
+            };
+            Ok(Box::pin(
+                futures::stream::iter([Ok(chunk)]).chain(futures::stream::pending()),
+            ))
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct NoInput {}
+
+    async fn slow_tool(_input: NoInput) -> String {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        "done".to_string()
+    }
+
+    async fn lookup_tool(_input: NoInput) -> String {
+        "found".to_string()
+    }
+
+    #[tokio::test]
+    async fn test_multi_step_events_are_framed_per_step() {
+        let provider = MockProvider::new()
+            .with_usage(10, 5)
+            .tool_call("lookup", serde_json::json!({}))
+            .text("Found it");
+        let config = StreamConfig::new(provider)
+            .messages(vec![Message::user("Look it up")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("lookup", None, lookup_tool)
+                    .with_state(()),
+            )
+            .run_until(until_answer());
+        let events = collect_events(config).await;
+
+        let step_usage = Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        };
+        let labels: Vec<String> = events
+            .iter()
+            .map(|event| match event.as_ref().unwrap() {
+                AgentEvent::StepStarted { step } => format!("started {step}"),
+                AgentEvent::TextDelta { step, text } => format!("text {step} {text}"),
+                AgentEvent::ToolCallStarted { step, tool_call } => {
+                    format!("tool {step} {}", tool_call.name)
+                }
+                AgentEvent::ToolCallCompleted { step, .. } => format!("result {step}"),
+                AgentEvent::StepFinished { step, usage, .. } => {
+                    assert_eq!(usage.as_ref(), Some(&step_usage));
+                    format!("finished {step}")
+                }
+                AgentEvent::RunFinished(_) => "run finished".to_string(),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "started 0",
+                "tool 0 lookup",
+                "result 0",
+                "finished 0",
+                "started 1",
+                "text 1 Found it",
+                "finished 1",
+                "run finished",
+            ]
+        );
+
+        let response = run_response(&events);
+        assert_eq!(response.steps.len(), 2);
+        assert_eq!(
+            response.total_usage,
+            Some(Usage {
+                prompt_tokens: 20,
+                completion_tokens: 10,
+                total_tokens: 30,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_final_stream_chunks_carry_the_run_usage() {
+        let provider = MockProvider::new()
+            .with_usage(10, 5)
+            .tool_call("lookup", serde_json::json!({}))
+            .text("Found it");
+        let config = StreamConfig::new(provider)
+            .messages(vec![Message::user("Look it up")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("lookup", None, lookup_tool)
+                    .with_state(()),
+            )
+            .run_until(until_answer());
+        let chunks: Vec<AgentStreamChunk> = stream_text(config)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let totals: Vec<(u32, Option<u32>)> = chunks
+            .iter()
+            .filter(|chunk| chunk.is_final)
+            .map(|chunk| {
+                let total = chunk.total_usage.as_ref().map(|usage| usage.total_tokens);
+                (chunk.step, total)
+            })
+            .collect();
+        assert_eq!(totals, [(0, Some(15)), (1, Some(30))]);
+        assert!(
+            chunks
+                .iter()
+                .filter(|chunk| !chunk.is_final)
+                .all(|chunk| chunk.total_usage.is_none())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_duration_cuts_slow_tools_short() {
+        let provider = MockProvider::new()
+            .tool_calls([
+                ("slow", serde_json::json!({})),
+                ("slow", serde_json::json!({})),
+            ])
+            .text("Too late");
+        let config = GenerateConfig::new(provider.clone())
+            .messages(vec![Message::user("Hi")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("slow", None, slow_tool)
+                    .with_state(()),
+            )
+            .run_until(RunUntilFirst::new(
+                until_answer(),
+                MaxDuration::new(Duration::from_millis(50)),
+            ));
+        let started = Instant::now();
+        let response = generate_text(config).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(response.timed_out);
+        assert_eq!(response.steps.len(), 1);
+        assert_eq!(provider.remaining(), 1);
+        // Both calls get an error result, so the history stays valid
+        let Some(Message::Tool { tool_results, .. }) = response.messages.last() else {
+            panic!("expected tool results, got {:?}", response.messages.last());
+        };
+        assert_eq!(tool_results.len(), 2);
+        assert!(tool_results.iter().all(|result| result.is_error));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_cuts_stalled_model_calls_short() {
+        let config = GenerateConfig::new(StalledProvider)
+            .messages(vec![Message::user("Hi")])
+            .run_until(Deadline::after(Duration::from_millis(50)));
+        let response = generate_text(config).await.unwrap();
+        assert!(response.timed_out);
+        assert!(response.steps.is_empty());
+        assert_eq!(response.messages, vec![Message::user("Hi")]);
+
+        let config = StreamConfig::new(StalledProvider)
+            .messages(vec![Message::user("Hi")])
+            .run_until(Deadline::after(Duration::from_millis(50)));
+        let events: Vec<AgentEvent> = stream_events(config)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let Some(AgentEvent::RunFinished(response)) = events.last() else {
+            panic!("expected RunFinished, got {:?}", events.last());
+        };
+        assert!(response.timed_out);
+        assert_eq!(response.text(), "Partial");
+        assert_eq!(response.messages, vec![Message::user("Hi")]);
+    }
+
+    #[test]
+    fn test_relative_deadlines_restart_with_each_run() {
+        let mut deadline = Deadline::after(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(deadline.deadline().unwrap() < Instant::now());
+        deadline.start();
+        assert!(deadline.deadline().unwrap() > Instant::now());
+
+        let mut fixed = Deadline::new(Instant::now());
+        let at = fixed.at;
+        fixed.start();
+        assert_eq!(fixed.deadline(), Some(at));
+
+        let mut duration = MaxDuration::new(Duration::from_secs(1));
+        assert_eq!(duration.deadline(), None);
+        duration.start();
+        let first = RunUntilFirst::new(duration, fixed);
+        assert_eq!(first.deadline(), Some(at));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_streamed_apart_from_the_answer() {
+        let provider = MockProvider::new().reasoning("The user greets me.", "Hello!");
+        let config = StreamConfig::new(provider)
+            .messages(vec![Message::user("Hi")])
+            .run_until(until_answer());
+        let events = collect_events(config).await;
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            Ok(AgentEvent::ReasoningDelta { step: 0, text }) if text == "The user greets me."
+        )));
+        let texts: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                Ok(AgentEvent::TextDelta { text, .. }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, vec!["Hello!"]);
+        assert_eq!(
+            run_response(&events).final_message,
+            Message::assistant(AssistantContent::Text {
+                text: "Hello!".to_string()
+            })
+        );
+    }
 }
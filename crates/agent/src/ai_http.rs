@@ -0,0 +1,233 @@
+use std::{convert::Infallible, fmt, time::Duration};
+
+use ai_core::Result;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+
+use crate::agent::{AgentEvent, AgentStreamChunk};
+
+pub mod ws;
+
+/// Interval between keep-alive comments of `events_sse`, `chunks_sse` and `sse_text`
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// One server-sent event: an event name and its JSON data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SseFrame {
+    pub event: String,
+    pub data: JsonValue,
+}
+
+impl SseFrame {
+    pub fn new(event: impl Into<String>, data: JsonValue) -> Self {
+        Self {
+            event: event.into(),
+            data,
+        }
+    }
+
+    /// `error` event carrying the error message
+    pub fn error(error: &ai_core::AiError) -> Self {
+        Self::new("error", json!({ "message": error.to_string() }))
+    }
+}
+
+/// Wire format, ending with the blank line that terminates an event
+impl fmt::Display for SseFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event: {}\ndata: {}\n\n", self.event, self.data)
+    }
+}
+
+impl From<SseFrame> for Event {
+    fn from(frame: SseFrame) -> Self {
+        Event::default()
+            .event(frame.event)
+            .data(frame.data.to_string())
+    }
+}
+
+/// Frame for an agent event
+///
+/// | Event | Name | Data |
+/// |---|---|---|
+/// | `StepStarted` | `step_started` | `step` |
+/// | `TextDelta` | `text` | `step`, `text` |
+/// | `ReasoningDelta` | `reasoning` | `step`, `text` |
+/// | `ToolCallStarted` | `tool_call` | `step`, `tool_call` |
+/// | `ToolCallCompleted` | `tool_result` | `step`, `result` |
+/// | `StepFinished` | `step_finished` | `step`, `finish_reason`, `usage` |
+/// | `PendingToolCalls` | `pending_tool_calls` | `step`, `tool_calls`, `checkpoint` |
+/// | `RunFinished` | `done` | `text`, `finish_reason`, `usage`, `checkpoint` |
+pub fn event_frame(event: &AgentEvent) -> SseFrame {
+    match event {
+        AgentEvent::StepStarted { step } => SseFrame::new("step_started", json!({ "step": step })),
+        AgentEvent::TextDelta { step, text } => {
+            SseFrame::new("text", json!({ "step": step, "text": text }))
+        }
+        AgentEvent::ReasoningDelta { step, text } => {
+            SseFrame::new("reasoning", json!({ "step": step, "text": text }))
+        }
+        AgentEvent::ToolCallStarted { step, tool_call } => {
+            SseFrame::new("tool_call", json!({ "step": step, "tool_call": tool_call }))
+        }
+        AgentEvent::ToolCallCompleted { step, result } => {
+            SseFrame::new("tool_result", json!({ "step": step, "result": result }))
+        }
+        AgentEvent::StepFinished {
+            step,
+            finish_reason,
+            usage,
+        } => SseFrame::new(
+            "step_finished",
+            json!({ "step": step, "finish_reason": finish_reason, "usage": usage }),
+        ),
+        AgentEvent::PendingToolCalls {
+            step,
+            tool_calls,
+            checkpoint,
+        } => SseFrame::new(
+            "pending_tool_calls",
+            json!({ "step": step, "tool_calls": tool_calls, "checkpoint": checkpoint }),
+        ),
+        AgentEvent::RunFinished(response) => SseFrame::new(
+            "done",
+            json!({
+                "text": response.text(),
+                "finish_reason": response.finish_reason,
+                "usage": response.total_usage,
+                "checkpoint": response.checkpoint,
+            }),
+        ),
+    }
+}
+
+/// `chunk` frame for a streamed chunk
+pub fn chunk_frame(chunk: &AgentStreamChunk) -> SseFrame {
+    SseFrame::new(
+        "chunk",
+        json!({
+            "step": chunk.step,
+            "chunk": chunk.chunk,
+            "is_final": chunk.is_final,
+            "total_usage": chunk.total_usage,
+        }),
+    )
+}
+
+/// Frames for `stream`, ending with an `error` frame at the first error
+pub fn sse_frames<St, T>(
+    stream: St,
+    frame: impl Fn(&T) -> SseFrame + Send + 'static,
+) -> impl Stream<Item = SseFrame> + Send + 'static
+where
+    St: Stream<Item = Result<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let mut stream = Box::pin(stream);
+    async_stream::stream! {
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(item) => yield frame(&item),
+                Err(e) => {
+                    yield SseFrame::error(&e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Wire-format frames for `frames`, with a keep-alive comment after `keep_alive` of silence
+pub fn sse_text<St>(frames: St, keep_alive: Duration) -> impl Stream<Item = String> + Send + 'static
+where
+    St: Stream<Item = SseFrame> + Send + 'static,
+{
+    let mut frames = Box::pin(frames);
+    async_stream::stream! {
+        loop {
+            match tokio::time::timeout(keep_alive, frames.next()).await {
+                Ok(Some(frame)) => yield frame.to_string(),
+                Ok(None) => return,
+                Err(_) => yield ":\n\n".to_string(),
+            }
+        }
+    }
+}
+
+fn sse(
+    frames: impl Stream<Item = SseFrame> + Send + 'static,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>> + Send + 'static> {
+    Sse::new(frames.map(|frame| Ok(Event::from(frame))))
+        .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL))
+}
+
+/// SSE response for `run_events` or `stream_events`
+pub fn events_sse<St>(
+    stream: St,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>> + Send + 'static>
+where
+    St: Stream<Item = Result<AgentEvent>> + Send + 'static,
+{
+    sse(sse_frames(stream, event_frame))
+}
+
+/// SSE response for `run_stream` or `stream_text`
+pub fn chunks_sse<St>(
+    stream: St,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>> + Send + 'static>
+where
+    St: Stream<Item = Result<AgentStreamChunk>> + Send + 'static,
+{
+    sse(sse_frames(stream, chunk_frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{AiError, NetworkError, types::FinishReason};
+
+    #[tokio::test]
+    async fn test_frames_end_with_error_event() {
+        let events = futures::stream::iter(vec![
+            Ok(AgentEvent::StepStarted { step: 0 }),
+            Ok(AgentEvent::TextDelta {
+                step: 0,
+                text: "Hi".to_string(),
+            }),
+            Err(AiError::Network(NetworkError::ConnectionFailed {
+                message: "reset".to_string(),
+            })),
+            Ok(AgentEvent::StepFinished {
+                step: 0,
+                finish_reason: FinishReason::Stop,
+                usage: None,
+            }),
+        ]);
+        let text: Vec<String> = sse_text(sse_frames(events, event_frame), KEEP_ALIVE_INTERVAL)
+            .collect()
+            .await;
+        assert_eq!(text.len(), 3);
+        assert_eq!(text[0], "event: step_started\ndata: {\"step\":0}\n\n");
+        assert_eq!(
+            text[1],
+            "event: text\ndata: {\"step\":0,\"text\":\"Hi\"}\n\n"
+        );
+        assert!(text[2].starts_with("event: error\n"));
+        assert!(text[2].contains("reset"));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_comments_fill_silence() {
+        let slow = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            SseFrame::new("done", json!({}))
+        });
+        let text: Vec<String> = sse_text(slow, Duration::from_millis(20)).collect().await;
+        assert!(text.len() >= 2);
+        assert_eq!(text[0], ":\n\n");
+        assert_eq!(text.last().unwrap(), "event: done\ndata: {}\n\n");
+    }
+}
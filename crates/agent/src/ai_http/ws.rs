@@ -0,0 +1,218 @@
+use std::{future::Future, pin::Pin};
+
+use ai_core::{AiError, NetworkError, Result, types::ToolResult};
+use axum::extract::ws::{Message, WebSocket};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{SseFrame, event_frame};
+use crate::{agent::AgentEvent, checkpoint::AgentCheckpoint};
+
+type Events = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + Send + 'static>>;
+
+/// Message a client sends during a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Results for the calls of the last `pending_tool_calls` event
+    ToolResults { tool_results: Vec<ToolResult> },
+    /// Stop the run and end the session
+    Cancel,
+}
+
+async fn send<Tx>(tx: &mut Tx, frame: SseFrame) -> Result<()>
+where
+    Tx: Sink<String> + Unpin,
+    Tx::Error: std::fmt::Display,
+{
+    let text = serde_json::to_string(&frame).expect("frames serialize to JSON");
+    tx.send(text).await.map_err(|e| {
+        AiError::Network(NetworkError::ConnectionFailed {
+            message: format!("websocket send failed: {}", e),
+        })
+    })
+}
+
+fn protocol_error(message: impl Into<String>) -> SseFrame {
+    SseFrame::new("error", json!({ "message": message.into() }))
+}
+
+/// Run a session over any transport carrying text messages
+///
+/// `events` is the initial run; `resume` continues it from a checkpoint with the tool
+/// results the client sent. Run errors are sent as an `error` event and end the
+/// session; only transport errors are returned.
+pub async fn serve_events<Tx, Rx, R, Fut>(
+    mut tx: Tx,
+    mut rx: Rx,
+    events: Events,
+    mut resume: R,
+) -> Result<()>
+where
+    Tx: Sink<String> + Unpin,
+    Tx::Error: std::fmt::Display,
+    Rx: Stream<Item = String> + Unpin,
+    R: FnMut(AgentCheckpoint, Vec<ToolResult>) -> Fut,
+    Fut: Future<Output = Result<Events>>,
+{
+    let mut events = events;
+    loop {
+        let mut checkpoint = None;
+        loop {
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(event)) => {
+                        if let AgentEvent::PendingToolCalls { checkpoint: pending, .. } = &event {
+                            checkpoint = Some(pending.clone());
+                        }
+                        send(&mut tx, event_frame(&event)).await?;
+                    }
+                    Some(Err(e)) => return send(&mut tx, SseFrame::error(&e)).await,
+                    None => break,
+                },
+                message = rx.next() => match message.map(|text| serde_json::from_str(&text)) {
+                    None | Some(Ok(ClientMessage::Cancel)) => return Ok(()),
+                    Some(Ok(ClientMessage::ToolResults { .. })) => {
+                        send(&mut tx, protocol_error("no tool calls are pending")).await?
+                    }
+                    Some(Err(e)) => send(&mut tx, protocol_error(e.to_string())).await?,
+                },
+            }
+        }
+
+        let Some(checkpoint) = checkpoint else {
+            return Ok(());
+        };
+        let tool_results = loop {
+            match rx.next().await.map(|text| serde_json::from_str(&text)) {
+                None | Some(Ok(ClientMessage::Cancel)) => return Ok(()),
+                Some(Ok(ClientMessage::ToolResults { tool_results })) => break tool_results,
+                Some(Err(e)) => send(&mut tx, protocol_error(e.to_string())).await?,
+            }
+        };
+        events = match resume(checkpoint, tool_results).await {
+            Ok(events) => events,
+            Err(e) => return send(&mut tx, SseFrame::error(&e)).await,
+        };
+    }
+}
+
+/// Run a session on an axum WebSocket, see `serve_events`
+pub async fn serve_events_ws<R, Fut>(socket: WebSocket, events: Events, resume: R) -> Result<()>
+where
+    R: FnMut(AgentCheckpoint, Vec<ToolResult>) -> Fut,
+    Fut: Future<Output = Result<Events>>,
+{
+    let (sink, stream) = socket.split();
+    let tx =
+        sink.with(|text: String| async move { Ok::<_, axum::Error>(Message::Text(text.into())) });
+    let rx = stream
+        .take_while(|message| {
+            std::future::ready(
+                matches!(message, Ok(message) if !matches!(message, Message::Close(_))),
+            )
+        })
+        .filter_map(|message| {
+            std::future::ready(match message {
+                Ok(Message::Text(text)) => Some(text.to_string()),
+                _ => None,
+            })
+        });
+    serve_events(Box::pin(tx), Box::pin(rx), events, resume).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentResponse;
+    use ai_core::{
+        Scratchpad,
+        types::{FinishReason, Message as ChatMessage, ToolCall},
+    };
+    use futures::channel::mpsc;
+
+    fn checkpoint() -> AgentCheckpoint {
+        AgentCheckpoint {
+            messages: Vec::new(),
+            step: 1,
+            usage: None,
+            tool_results: Vec::new(),
+            pending_tool_calls: Vec::new(),
+            steps: Vec::new(),
+            scratchpad: Scratchpad::new(),
+        }
+    }
+
+    fn finished(text: &str) -> AgentEvent {
+        let message = ChatMessage::assistant(text);
+        AgentEvent::RunFinished(AgentResponse {
+            messages: vec![message.clone()],
+            final_message: message,
+            steps: Vec::new(),
+            finish_reason: FinishReason::Stop,
+            total_usage: None,
+            checkpoint: None,
+            scratchpad: Scratchpad::new(),
+            timed_out: false,
+            output: (),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_round_trips_pending_tool_calls() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "confirm".to_string(),
+            arguments: json!({}),
+        };
+        let events: Events = Box::pin(futures::stream::iter(vec![Ok(
+            AgentEvent::PendingToolCalls {
+                step: 0,
+                tool_calls: vec![call],
+                checkpoint: checkpoint(),
+            },
+        )]));
+        let (server_tx, mut client_rx) = mpsc::unbounded::<String>();
+        let (client_tx, server_rx) = mpsc::unbounded::<String>();
+
+        let session = tokio::spawn(serve_events(
+            server_tx,
+            server_rx,
+            events,
+            |checkpoint: AgentCheckpoint, results: Vec<ToolResult>| async move {
+                assert_eq!(checkpoint.step, 1);
+                assert_eq!(results[0].result, json!("yes"));
+                let events: Events =
+                    Box::pin(futures::stream::iter(vec![Ok(finished("Confirmed"))]));
+                Ok(events)
+            },
+        ));
+
+        let frame: SseFrame = serde_json::from_str(&client_rx.next().await.unwrap()).unwrap();
+        assert_eq!(frame.event, "pending_tool_calls");
+        assert_eq!(frame.data["tool_calls"][0]["id"], "call_1");
+
+        client_tx.unbounded_send("not json".to_string()).unwrap();
+        let frame: SseFrame = serde_json::from_str(&client_rx.next().await.unwrap()).unwrap();
+        assert_eq!(frame.event, "error");
+
+        let answer = ClientMessage::ToolResults {
+            tool_results: vec![ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: json!("yes"),
+                is_error: false,
+                content: Vec::new(),
+            }],
+        };
+        client_tx
+            .unbounded_send(serde_json::to_string(&answer).unwrap())
+            .unwrap();
+        let frame: SseFrame = serde_json::from_str(&client_rx.next().await.unwrap()).unwrap();
+        assert_eq!(frame.event, "done");
+        assert_eq!(frame.data["text"], "Confirmed");
+
+        session.await.unwrap().unwrap();
+        assert!(client_rx.next().await.is_none());
+    }
+}
@@ -0,0 +1,499 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ai_core::{
+    AiError, Result, ValidationError,
+    types::{
+        AssistantContent, FinishReason, Message, SystemContent, ToolCall, ToolResult,
+        ToolResultContent, Usage, UserContent,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{fs, io::AsyncWriteExt};
+
+/// `previous_hash` of the first record in a chain
+pub const AUDIT_GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What happened in an audited run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Messages sent to the model, after any context strategy
+    Prompt {
+        messages: Vec<Message>,
+    },
+    /// The model's answer, after output guardrails
+    Completion {
+        message: Message,
+        finish_reason: FinishReason,
+        usage: Option<Usage>,
+    },
+    ToolCall {
+        tool_call: ToolCall,
+    },
+    ToolResult {
+        result: ToolResult,
+    },
+}
+
+/// One entry of the audit log
+///
+/// Records form a hash chain: `hash` covers every other field, including the previous
+/// record's hash, so editing, dropping or reordering records is caught by `verify_chain`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub run_id: String,
+    pub step: u32,
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// SHA-256 over the canonical JSON of every field except `hash`
+    pub fn compute_hash(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let JsonValue::Object(map) = &mut value {
+            map.remove("hash");
+        }
+        let mut canonical = String::new();
+        write_canonical(&value, &mut canonical);
+        Ok(Sha256::digest(canonical.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+}
+
+/// JSON with object keys sorted, so hashes survive a round trip through storage
+fn write_canonical(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&JsonValue::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Check that `records` form an unbroken chain starting at the genesis hash
+pub fn verify_chain(records: &[AuditRecord]) -> Result<()> {
+    let mut previous_hash = AUDIT_GENESIS_HASH.to_string();
+    for (index, record) in records.iter().enumerate() {
+        let problem = if record.sequence != index as u64 {
+            Some("is out of sequence")
+        } else if record.previous_hash != previous_hash {
+            Some("does not follow the previous record")
+        } else if record.hash != record.compute_hash()? {
+            Some("does not match its hash")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(AiError::Validation(ValidationError::InvalidValue {
+                field: "audit_log".to_string(),
+                message: format!("record {} {}", index, problem),
+            }));
+        }
+        previous_hash = record.hash.clone();
+    }
+    Ok(())
+}
+
+/// Destination for audit records, e.g. an append-only file or a WORM bucket
+///
+/// Records arrive in order and one at a time. An error aborts the run, so nothing
+/// happens that was not logged.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn record(&self, record: &AuditRecord) -> Result<()>;
+}
+
+/// Rewrites text before it reaches the audit log
+///
+/// Applied to message text, tool arguments and tool results; `PiiGuardrail` implements
+/// it, as does any `Fn(&str) -> String`.
+pub trait Redactor: Send + Sync {
+    fn redact(&self, text: &str) -> String;
+}
+
+impl<F> Redactor for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn redact(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Sink appending one JSON record per line to a file
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct JsonLinesAuditSink {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsonLinesAuditSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Read back every record in the file
+    pub async fn read(&self) -> Result<Vec<AuditRecord>> {
+        let contents = match fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn io_error(err: std::io::Error) -> AiError {
+    AiError::Storage(ai_core::StorageError::Backend {
+        backend: "audit file".to_string(),
+        message: err.to_string(),
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl EventSink for JsonLinesAuditSink {
+    async fn record(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let _guard = self.write_lock.lock().await;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(io_error)?;
+        file.write_all(line.as_bytes()).await.map_err(io_error)?;
+        file.flush().await.map_err(io_error)
+    }
+}
+
+/// Sink keeping records in memory, useful in tests
+#[derive(Debug, Default)]
+pub struct InMemoryAuditSink {
+    records: std::sync::Mutex<Vec<AuditRecord>>,
+}
+
+impl InMemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EventSink for InMemoryAuditSink {
+    async fn record(&self, record: &AuditRecord) -> Result<()> {
+        self.records.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Chain {
+    sequence: u64,
+    last_hash: String,
+}
+
+/// Hash-chained audit trail of prompts, completions, tool calls and tool results
+///
+/// Clones share the chain, so one log can be attached to several agents. To continue
+/// an existing log after a restart, use `resume` with the last stored record.
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<dyn EventSink>,
+    redactor: Option<Arc<dyn Redactor>>,
+    chain: Arc<Mutex<Chain>>,
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("redactor", &self.redactor.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuditLog {
+    pub fn new(sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            sink,
+            redactor: None,
+            chain: Arc::new(Mutex::new(Chain {
+                sequence: 0,
+                last_hash: AUDIT_GENESIS_HASH.to_string(),
+            })),
+        }
+    }
+
+    /// Continue the chain after `last`, the most recent record already in the sink
+    pub fn resume(self, last: &AuditRecord) -> Self {
+        Self {
+            chain: Arc::new(Mutex::new(Chain {
+                sequence: last.sequence + 1,
+                last_hash: last.hash.clone(),
+            })),
+            ..self
+        }
+    }
+
+    /// Redact text before it is recorded
+    pub fn redact(mut self, redactor: impl Redactor + 'static) -> Self {
+        self.redactor = Some(Arc::new(redactor));
+        self
+    }
+
+    async fn append(&self, run: &AuditRun, step: u32, mut event: AuditEvent) -> Result<()> {
+        if let Some(redactor) = &self.redactor {
+            redact_event(redactor.as_ref(), &mut event);
+        }
+        let mut chain = self.chain.lock().await;
+        let mut record = AuditRecord {
+            sequence: chain.sequence,
+            timestamp: Utc::now(),
+            run_id: run.run_id.clone(),
+            step,
+            provider: run.provider.clone(),
+            model: run.model.clone(),
+            event,
+            previous_hash: chain.last_hash.clone(),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash()?;
+        self.sink.record(&record).await?;
+        chain.sequence += 1;
+        chain.last_hash = record.hash;
+        Ok(())
+    }
+}
+
+/// An audit log bound to one run
+#[derive(Debug)]
+pub(crate) struct AuditRun {
+    log: AuditLog,
+    run_id: String,
+    provider: String,
+    model: String,
+}
+
+impl AuditRun {
+    pub(crate) fn start(log: Option<&AuditLog>, provider: &str, model: &str) -> Option<Self> {
+        log.map(|log| Self {
+            log: log.clone(),
+            run_id: uuid::Uuid::new_v4().to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+        })
+    }
+}
+
+/// Record the event built by `event` if the run is audited
+pub(crate) async fn audit(
+    run: Option<&AuditRun>,
+    step: u32,
+    event: impl FnOnce() -> AuditEvent,
+) -> Result<()> {
+    match run {
+        Some(run) => run.log.append(run, step, event()).await,
+        None => Ok(()),
+    }
+}
+
+fn redact_event(redactor: &dyn Redactor, event: &mut AuditEvent) {
+    match event {
+        AuditEvent::Prompt { messages } => {
+            for message in messages {
+                redact_message(redactor, message);
+            }
+        }
+        AuditEvent::Completion { message, .. } => redact_message(redactor, message),
+        AuditEvent::ToolCall { tool_call } => redact_json(redactor, &mut tool_call.arguments),
+        AuditEvent::ToolResult { result } => redact_tool_result(redactor, result),
+    }
+}
+
+fn redact_message(redactor: &dyn Redactor, message: &mut Message) {
+    match message {
+        Message::System { content, .. } => {
+            for part in content {
+                let SystemContent::Text { text } = part;
+                *text = redactor.redact(text);
+            }
+        }
+        Message::User { content, .. } => {
+            for part in content {
+                if let UserContent::Text { text } = part {
+                    *text = redactor.redact(text);
+                }
+            }
+        }
+        Message::Assistant { content, .. } => {
+            for part in content {
+                match part {
+                    AssistantContent::Text { text } => *text = redactor.redact(text),
+                    AssistantContent::ToolCall { tool_call } => {
+                        redact_json(redactor, &mut tool_call.arguments)
+                    }
+                }
+            }
+        }
+        Message::Tool { tool_results, .. } => {
+            for result in tool_results {
+                redact_tool_result(redactor, result);
+            }
+        }
+    }
+}
+
+fn redact_tool_result(redactor: &dyn Redactor, result: &mut ToolResult) {
+    redact_json(redactor, &mut result.result);
+    for part in &mut result.content {
+        match part {
+            ToolResultContent::Text { text } => *text = redactor.redact(text),
+            ToolResultContent::Json { value } => redact_json(redactor, value),
+            ToolResultContent::Image { .. } => {}
+        }
+    }
+}
+
+/// Redact every string in a JSON value; keys are left alone
+fn redact_json(redactor: &dyn Redactor, value: &mut JsonValue) {
+    match value {
+        JsonValue::String(text) => *text = redactor.redact(text),
+        JsonValue::Array(items) => {
+            for item in items {
+                redact_json(redactor, item);
+            }
+        }
+        JsonValue::Object(map) => {
+            for value in map.values_mut() {
+                redact_json(redactor, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(log: &AuditLog) -> AuditRun {
+        AuditRun::start(Some(log), "anthropic", "claude").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_records_form_verifiable_chain() {
+        let sink = Arc::new(InMemoryAuditSink::new());
+        let log = AuditLog::new(sink.clone());
+        let run = run(&log);
+        audit(Some(&run), 1, || AuditEvent::Prompt {
+            messages: vec![Message::user("Hi")],
+        })
+        .await
+        .unwrap();
+        audit(Some(&run), 1, || AuditEvent::ToolCall {
+            tool_call: ToolCall {
+                id: "call_1".to_string(),
+                name: "lookup".to_string(),
+                arguments: serde_json::json!({"user": "alice"}),
+            },
+        })
+        .await
+        .unwrap();
+
+        let mut records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].previous_hash, AUDIT_GENESIS_HASH);
+        assert!(verify_chain(&records).is_ok());
+
+        // A round trip through JSON keeps the chain valid
+        let reloaded: Vec<AuditRecord> = records
+            .iter()
+            .map(|r| serde_json::from_str(&serde_json::to_string(r).unwrap()).unwrap())
+            .collect();
+        assert!(verify_chain(&reloaded).is_ok());
+
+        records[1].step = 2;
+        assert!(verify_chain(&records).is_err());
+        assert!(verify_chain(&sink.records()[1..]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redacts_text_and_tool_arguments() {
+        let sink = Arc::new(InMemoryAuditSink::new());
+        let log = AuditLog::new(sink.clone())
+            .redact(|text: &str| text.replace("alice@example.com", "[EMAIL]"));
+        let run = run(&log);
+        audit(Some(&run), 1, || AuditEvent::Prompt {
+            messages: vec![Message::user("Mail alice@example.com")],
+        })
+        .await
+        .unwrap();
+        audit(Some(&run), 1, || AuditEvent::ToolCall {
+            tool_call: ToolCall {
+                id: "call_1".to_string(),
+                name: "send".to_string(),
+                arguments: serde_json::json!({"to": ["alice@example.com"]}),
+            },
+        })
+        .await
+        .unwrap();
+
+        let json = serde_json::to_string(&sink.records()).unwrap();
+        assert!(!json.contains("alice@example.com"));
+        assert_eq!(json.matches("[EMAIL]").count(), 2);
+    }
+}
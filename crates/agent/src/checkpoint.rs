@@ -0,0 +1,100 @@
+use ai_core::{
+    Result, SCHEMA_VERSION, Scratchpad, take_schema_version,
+    types::{Message, ToolCall, ToolResult, Usage},
+    upgrade_message_json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::agent::StepInfo;
+
+/// Snapshot of a run paused on tool calls that need client-side handling
+///
+/// Checkpoints serialize with serde, so a run can be resumed with
+/// `generate_text_from_checkpoint` later or in a different process once the pending
+/// calls have results. Store them with `to_json`, which records the `schema_version`,
+/// so `from_json` can upgrade them after the message format changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentCheckpoint {
+    /// Conversation so far, ending with the assistant message that made the calls
+    pub messages: Vec<Message>,
+    /// Step that was paused
+    pub step: u32,
+    /// Usage accumulated up to and including the paused step
+    pub usage: Option<Usage>,
+    /// Results of the step's tool calls that were already executed
+    pub tool_results: Vec<ToolResult>,
+    /// Tool calls without a handler, awaiting results from the client
+    pub pending_tool_calls: Vec<ToolCall>,
+    /// Steps run so far, including the paused step
+    #[serde(default)]
+    pub steps: Vec<StepInfo>,
+    /// Working memory at the pause, restored into the resumed run
+    #[serde(default)]
+    pub scratchpad: Scratchpad,
+}
+
+impl AgentCheckpoint {
+    /// IDs of pending calls that `results` does not answer
+    pub fn missing_results(&self, results: &[ToolResult]) -> Vec<&str> {
+        self.pending_tool_calls
+            .iter()
+            .filter(|call| !results.iter().any(|result| result.tool_call_id == call.id))
+            .map(|call| call.id.as_str())
+            .collect()
+    }
+
+    /// Serialize with the current `schema_version`
+    pub fn to_json(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+        }
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    /// Deserialize a checkpoint written by any version, upgrading its messages
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: JsonValue = serde_json::from_str(json)?;
+        let version = take_schema_version(&mut value)?;
+        if let Some(JsonValue::Array(messages)) = value.get_mut("messages") {
+            for message in messages {
+                upgrade_message_json(message, version)?;
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_checkpoint_json() {
+        let checkpoint = AgentCheckpoint {
+            messages: vec![
+                Message::user("Delete the file?"),
+                Message::assistant_with_tool_calls([ToolCall::new(
+                    "call_1",
+                    "confirm",
+                    serde_json::json!({}),
+                )]),
+            ],
+            step: 0,
+            usage: None,
+            tool_results: Vec::new(),
+            pending_tool_calls: vec![ToolCall::new("call_1", "confirm", serde_json::json!({}))],
+            steps: Vec::new(),
+            scratchpad: Scratchpad::new(),
+        };
+
+        let json = checkpoint.to_json().unwrap();
+        assert!(json.contains("\"schema_version\":1"));
+        assert_eq!(AgentCheckpoint::from_json(&json).unwrap(), checkpoint);
+
+        // Checkpoints stored before versioning still load
+        let legacy = serde_json::to_string(&checkpoint).unwrap();
+        assert_eq!(AgentCheckpoint::from_json(&legacy).unwrap(), checkpoint);
+    }
+}
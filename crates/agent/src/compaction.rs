@@ -0,0 +1,295 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use ai_core::{
+    Result,
+    provider::ChatTextGeneration,
+    tokenizer::{EstimatingTokenizer, Tokenizer, count_tokens},
+    types::*,
+};
+
+/// Metadata key marking a system message produced by compaction
+pub const COMPACTION_SUMMARY_KEY: &str = "compaction_summary";
+
+const DEFAULT_INSTRUCTIONS: &str = "Summarize the conversation transcript you are given. \
+Keep facts, decisions, open questions, tool results and anything the assistant promised to do. \
+Reply with the summary only.";
+
+/// Summarizes older turns once the conversation grows past a token threshold
+///
+/// Older messages are replaced by a single system message holding the summary. The cut
+/// always lands on a user message, so tool calls are never separated from their results.
+#[derive(Clone)]
+pub struct Compaction {
+    pub threshold_tokens: usize,
+    /// Number of most recent user turns kept verbatim; 0 is treated as 1
+    pub keep_recent_turns: usize,
+    pub instructions: String,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl Debug for Compaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Compaction")
+            .field("threshold_tokens", &self.threshold_tokens)
+            .field("keep_recent_turns", &self.keep_recent_turns)
+            .field("instructions", &self.instructions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Compaction {
+    pub fn new(threshold_tokens: usize) -> Self {
+        Self {
+            threshold_tokens,
+            keep_recent_turns: 2,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            tokenizer: Arc::new(EstimatingTokenizer),
+        }
+    }
+
+    /// Number of most recent user turns kept verbatim
+    pub fn keep_recent_turns(mut self, turns: usize) -> Self {
+        self.keep_recent_turns = turns.max(1);
+        self
+    }
+
+    /// Instructions given to the model when writing the summary
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    pub fn tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Check whether the conversation is over the threshold
+    pub fn needs_compaction(&self, messages: &[Message]) -> bool {
+        count_tokens(messages, self.tokenizer.as_ref()).total > self.threshold_tokens
+    }
+
+    /// Summarize older turns with the provider and return the compacted conversation
+    ///
+    /// Returns the messages unchanged when there is nothing old enough to summarize, or
+    /// when the model's summary is empty, so no history is dropped without a summary.
+    pub async fn compact<P>(&self, provider: &P, messages: Vec<Message>) -> Result<Vec<Message>>
+    where
+        P: ChatTextGeneration + ?Sized,
+    {
+        let Some((system, older, recent)) = self.split(messages.clone()) else {
+            return Ok(messages);
+        };
+
+        let request = ChatRequest::new()
+            .system(self.instructions.as_str())
+            .user(render_transcript(&older));
+        let response = provider.generate(request).await?;
+        let summary = match &response.message {
+            Message::Assistant { content, .. } => content
+                .iter()
+                .filter_map(|part| match part {
+                    AssistantContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>(),
+            _ => String::new(),
+        };
+        if summary.trim().is_empty() {
+            return Ok(messages);
+        }
+
+        let mut compacted = system;
+        compacted.push(summary_message(&summary));
+        compacted.extend(recent);
+        Ok(compacted)
+    }
+
+    /// Split into kept system messages, messages to summarize, and recent messages
+    fn split(&self, messages: Vec<Message>) -> Option<(Vec<Message>, Vec<Message>, Vec<Message>)> {
+        let mut system = Vec::new();
+        let mut older = Vec::new();
+        let mut conversation = Vec::new();
+        for message in messages {
+            match &message {
+                // Earlier summaries are folded into the new one
+                Message::System { metadata, .. } if is_summary(metadata) => older.push(message),
+                Message::System { .. } => system.push(message),
+                _ if message.is_example() => system.push(message),
+                _ => conversation.push(message),
+            }
+        }
+
+        let user_positions: Vec<usize> = conversation
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| matches!(message, Message::User { .. }))
+            .map(|(index, _)| index)
+            .collect();
+        // The latest turn is always kept, so there is something left to answer
+        let skip = user_positions
+            .len()
+            .checked_sub(self.keep_recent_turns.max(1))?;
+        let cut = user_positions[skip];
+        if cut == 0 {
+            return None;
+        }
+
+        let recent = conversation.split_off(cut);
+        older.extend(conversation);
+        Some((system, older, recent))
+    }
+}
+
+fn is_summary(metadata: &Option<HashMap<String, serde_json::Value>>) -> bool {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get(COMPACTION_SUMMARY_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+fn summary_message(summary: &str) -> Message {
+    Message::System {
+        content: vec![SystemContent::Text {
+            text: format!("Summary of the earlier conversation:\n{}", summary),
+        }],
+        metadata: Some(HashMap::from([(
+            COMPACTION_SUMMARY_KEY.to_string(),
+            serde_json::Value::Bool(true),
+        )])),
+    }
+}
+
+/// Render messages as a plain-text transcript for the summarizer
+pub(crate) fn render_transcript(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+    for message in messages {
+        match message {
+            Message::System { content, .. } => {
+                for SystemContent::Text { text } in content {
+                    lines.push(format!("[earlier summary] {}", text));
+                }
+            }
+            Message::User { content, .. } => {
+                for part in content {
+                    match part {
+                        UserContent::Text { text } => lines.push(format!("user: {}", text)),
+                        UserContent::Image { .. } => lines.push("user: [image]".to_string()),
+                    }
+                }
+            }
+            Message::Assistant { content, .. } => {
+                for part in content {
+                    match part {
+                        AssistantContent::Text { text } => {
+                            lines.push(format!("assistant: {}", text))
+                        }
+                        AssistantContent::ToolCall { tool_call } => lines.push(format!(
+                            "assistant called {}({})",
+                            tool_call.name, tool_call.arguments
+                        )),
+                    }
+                }
+            }
+            Message::Tool { tool_results, .. } => {
+                for result in tool_results {
+                    let text = match &result.result {
+                        serde_json::Value::String(text) => text.clone(),
+                        value => value.to_string(),
+                    };
+                    let label = if result.is_error {
+                        "tool error"
+                    } else {
+                        "tool"
+                    };
+                    lines.push(format!("{}: {}", label, text));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_test_utils::MockProvider;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::system("Be brief."),
+            Message::user("first question"),
+            Message::assistant(AssistantContent::ToolCall {
+                tool_call: ToolCall {
+                    id: "call_1".to_string(),
+                    name: "lookup".to_string(),
+                    arguments: serde_json::json!({"q": "first"}),
+                },
+            }),
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: serde_json::json!("found it"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant("first answer"),
+            Message::user("second question"),
+            Message::assistant("second answer"),
+            Message::user("third question"),
+        ]
+    }
+
+    #[test]
+    fn test_split_keeps_tool_calls_with_results() {
+        let (system, older, recent) = Compaction::new(0).split(conversation()).unwrap();
+        assert_eq!(system, vec![Message::system("Be brief.")]);
+        assert_eq!(older.len(), 4);
+        assert_eq!(recent[0], Message::user("second question"));
+
+        let transcript = render_transcript(&older);
+        assert!(transcript.contains("assistant called lookup({\"q\":\"first\"})"));
+        assert!(transcript.contains("tool: found it"));
+    }
+
+    #[test]
+    fn test_nothing_to_compact_with_few_turns() {
+        let compaction = Compaction::new(0).keep_recent_turns(3);
+        assert!(compaction.split(conversation()).is_none());
+        assert!(compaction.needs_compaction(&conversation()));
+        assert!(!Compaction::new(10_000).needs_compaction(&conversation()));
+    }
+
+    #[test]
+    fn test_zero_recent_turns_keeps_the_latest() {
+        let compaction = Compaction {
+            keep_recent_turns: 0,
+            ..Compaction::new(0)
+        };
+        let (_, older, recent) = compaction.split(conversation()).unwrap();
+        assert_eq!(older.len(), 6);
+        assert_eq!(recent, vec![Message::user("third question")]);
+    }
+
+    #[test]
+    fn test_previous_summary_is_folded_in() {
+        let mut messages = conversation();
+        messages.insert(1, summary_message("talked about the weather"));
+        let (system, older, _) = Compaction::new(0).split(messages).unwrap();
+        assert_eq!(system.len(), 1);
+        assert!(render_transcript(&older).contains("talked about the weather"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_summary_keeps_the_original_messages() {
+        let provider = MockProvider::new().text("  \n").text("asked about lookups");
+        let compaction = Compaction::new(0);
+
+        let kept = compaction.compact(&provider, conversation()).await.unwrap();
+        assert_eq!(kept, conversation());
+
+        let compacted = compaction.compact(&provider, conversation()).await.unwrap();
+        assert_eq!(compacted.len(), 5);
+        assert_eq!(compacted[1], summary_message("asked about lookups"));
+    }
+}
@@ -0,0 +1,314 @@
+use std::{fmt::Debug, sync::Arc};
+
+use ai_core::{
+    Result,
+    normalize::collapse_repeated_tool_results,
+    provider::ChatTextGeneration,
+    tokenizer::{EstimatingTokenizer, Tokenizer, count_tokens},
+    types::*,
+};
+
+const DEFAULT_INSTRUCTIONS: &str = "Compress the text you are given. Keep every fact, number, \
+name, identifier and instruction; drop repetition, filler and formatting. Reply with the \
+compressed text only.";
+
+/// Shrinks the messages sent on each step, leaving the stored history untouched
+///
+/// Cheap passes run on every request: redundant whitespace is stripped and tool results
+/// repeating an earlier result are replaced by a reference to it. When the prompt is
+/// still over `target_tokens`, text blocks longer than `summarize_blocks_over` are
+/// compressed by the model, longest first, until it fits. Cached messages and the latest
+/// user message are never rewritten.
+#[derive(Clone)]
+pub struct PromptCompression {
+    pub target_tokens: Option<usize>,
+    pub collapse_whitespace: bool,
+    pub dedupe_tool_results: bool,
+    /// Token length above which a block may be compressed by the model
+    pub summarize_blocks_over: Option<usize>,
+    pub instructions: String,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl Debug for PromptCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptCompression")
+            .field("target_tokens", &self.target_tokens)
+            .field("collapse_whitespace", &self.collapse_whitespace)
+            .field("dedupe_tool_results", &self.dedupe_tool_results)
+            .field("summarize_blocks_over", &self.summarize_blocks_over)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for PromptCompression {
+    fn default() -> Self {
+        Self {
+            target_tokens: None,
+            collapse_whitespace: true,
+            dedupe_tool_results: true,
+            summarize_blocks_over: None,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            tokenizer: Arc::new(EstimatingTokenizer),
+        }
+    }
+}
+
+impl PromptCompression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token budget the prompt should fit in
+    pub fn target_tokens(mut self, tokens: usize) -> Self {
+        self.target_tokens = Some(tokens);
+        self
+    }
+
+    pub fn collapse_whitespace(mut self, enabled: bool) -> Self {
+        self.collapse_whitespace = enabled;
+        self
+    }
+
+    pub fn dedupe_tool_results(mut self, enabled: bool) -> Self {
+        self.dedupe_tool_results = enabled;
+        self
+    }
+
+    /// Let the model compress blocks over `tokens` while the prompt exceeds the target
+    pub fn summarize_blocks_over(mut self, tokens: usize) -> Self {
+        self.summarize_blocks_over = Some(tokens);
+        self
+    }
+
+    /// Instructions given to the model when compressing a block
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    pub fn tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Apply the whitespace and duplicate passes
+    pub fn compress_locally(&self, mut messages: Vec<Message>) -> Vec<Message> {
+        if self.collapse_whitespace {
+            for message in messages.iter_mut().filter(|message| !message.is_cached()) {
+                for text in texts_mut(message) {
+                    *text = collapse_whitespace(text);
+                }
+            }
+        }
+        if self.dedupe_tool_results {
+            messages = collapse_repeated_tool_results(messages);
+        }
+        messages
+    }
+
+    /// Apply every pass, asking `provider` to compress long blocks if still over target
+    pub async fn compress<P>(&self, provider: &P, messages: Vec<Message>) -> Result<Vec<Message>>
+    where
+        P: ChatTextGeneration + ?Sized,
+    {
+        let mut messages = self.compress_locally(messages);
+        let (Some(target), Some(min_block)) = (self.target_tokens, self.summarize_blocks_over)
+        else {
+            return Ok(messages);
+        };
+
+        let mut total = count_tokens(&messages, self.tokenizer.as_ref()).total;
+        if total <= target {
+            return Ok(messages);
+        }
+        let latest_user = messages
+            .iter()
+            .rposition(|message| matches!(message, Message::User { .. }));
+        // (message, block, tokens), longest first
+        let mut blocks: Vec<(usize, usize, usize)> = Vec::new();
+        for (index, message) in messages.iter_mut().enumerate() {
+            if message.is_cached() || Some(index) == latest_user {
+                continue;
+            }
+            for (block, text) in texts_mut(message).into_iter().enumerate() {
+                let tokens = self.tokenizer.count_tokens(text);
+                if tokens > min_block {
+                    blocks.push((index, block, tokens));
+                }
+            }
+        }
+        blocks.sort_by_key(|&(_, _, tokens)| std::cmp::Reverse(tokens));
+
+        for (index, block, tokens) in blocks {
+            if total <= target {
+                break;
+            }
+            let Some(text) = texts_mut(&mut messages[index]).into_iter().nth(block) else {
+                continue;
+            };
+            let request = ChatRequest::new()
+                .system(self.instructions.as_str())
+                .user(text.as_str());
+            let response = provider.generate(request).await?;
+            let compressed = response_text(&response.message);
+            let compressed_tokens = self.tokenizer.count_tokens(&compressed);
+            if compressed_tokens < tokens {
+                *text = compressed;
+                total = total - tokens + compressed_tokens;
+            }
+        }
+        Ok(messages)
+    }
+}
+
+fn response_text(message: &Message) -> String {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Every text block of a message that can be rewritten
+fn texts_mut(message: &mut Message) -> Vec<&mut String> {
+    match message {
+        Message::System { content, .. } => content
+            .iter_mut()
+            .map(|part| match part {
+                SystemContent::Text { text } => text,
+            })
+            .collect(),
+        Message::User { content, .. } => content
+            .iter_mut()
+            .filter_map(|part| match part {
+                UserContent::Text { text } => Some(text),
+                UserContent::Image { .. } => None,
+            })
+            .collect(),
+        Message::Assistant { content, .. } => content
+            .iter_mut()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text),
+                AssistantContent::ToolCall { .. } => None,
+            })
+            .collect(),
+        Message::Tool { tool_results, .. } => tool_results
+            .iter_mut()
+            .filter_map(|result| match &mut result.result {
+                serde_json::Value::String(text) => Some(text),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Trim line ends, squeeze runs of spaces inside lines and blank lines between them
+///
+/// Leading indentation is kept so code and nested lists keep their shape.
+fn collapse_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.trim().lines() {
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_some_and(String::is_empty) {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let mut squeezed = line[..indent].to_string();
+        let mut previous_space = false;
+        for c in line[indent..].chars() {
+            let space = c == ' ' || c == '\t';
+            if !(space && previous_space) {
+                squeezed.push(if space { ' ' } else { c });
+            }
+            previous_space = space;
+        }
+        lines.push(squeezed);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_test_utils::MockProvider;
+    use serde_json::json;
+
+    fn lookup(id: &str, result: serde_json::Value) -> [Message; 2] {
+        [
+            Message::assistant(AssistantContent::ToolCall {
+                tool_call: ToolCall {
+                    id: id.to_string(),
+                    name: "lookup".to_string(),
+                    arguments: json!({}),
+                },
+            }),
+            Message::tool(ToolResult {
+                tool_call_id: id.to_string(),
+                result,
+                is_error: false,
+                content: Vec::new(),
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_local_passes() {
+        let report = json!({ "rows": ["alpha", "beta", "gamma", "delta"] });
+        let mut messages = vec![Message::user("Look   it up\n\n\n\n  twice  ")];
+        messages.extend(lookup("call_1", report.clone()));
+        messages.extend(lookup("call_2", report.clone()));
+        let compressed = PromptCompression::new().compress_locally(messages);
+
+        assert_eq!(compressed[0], Message::user("Look it up\n\n  twice"));
+        let Message::Tool { tool_results, .. } = &compressed[4] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(
+            tool_results[0].result,
+            json!("[same result as tool call call_1]")
+        );
+        let Message::Tool { tool_results, .. } = &compressed[2] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(tool_results[0].result, report);
+    }
+
+    #[tokio::test]
+    async fn test_long_blocks_are_compressed_until_under_target() {
+        let document = "report ".repeat(400);
+        let mut messages = vec![Message::user(document.as_str())];
+        messages.extend(lookup("call_1", json!(document)));
+        messages.push(Message::user(document.as_str()));
+
+        let compression = PromptCompression::new()
+            .target_tokens(1500)
+            .summarize_blocks_over(100);
+        let provider = MockProvider::new().text("report");
+        let compressed = compression.compress(&provider, messages).await.unwrap();
+
+        // One block was enough to get under the target; the latest question is kept
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 1);
+        let Some(Message::User { content, .. }) = requests[0].messages.last() else {
+            panic!("expected a block to compress");
+        };
+        assert_eq!(
+            content[0],
+            UserContent::Text {
+                text: document.trim().to_string()
+            }
+        );
+        assert_eq!(compressed[0], Message::user("report"));
+        let Message::Tool { tool_results, .. } = &compressed[2] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(tool_results[0].result, json!(document.trim()));
+        assert_eq!(compressed[3], Message::user(document.trim()));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{fmt::Debug, sync::Arc};
+
+use ai_core::{
+    tokenizer::{Tokenizer, count_tokens},
+    types::Message,
+};
+
+/// Decides which messages are sent to the provider on each step
+///
+/// Strategies only shape the request; the full conversation is still returned in
+/// the agent response.
+pub trait ContextStrategy: Debug + Send + Sync {
+    fn apply(&self, messages: Vec<Message>) -> Vec<Message>;
+}
+
+/// Split messages into system messages and few-shot examples, and the rest of the conversation
+fn split_system(messages: Vec<Message>) -> (Vec<Message>, Vec<Message>) {
+    messages
+        .into_iter()
+        .partition(|message| matches!(message, Message::System { .. }) || message.is_example())
+}
+
+/// Move a cut point forward to the next user message
+///
+/// Starting the kept window on a user message avoids sending tool results whose
+/// tool calls were trimmed away.
+fn align_to_user(conversation: &[Message], cut: usize) -> usize {
+    conversation[cut..]
+        .iter()
+        .position(|message| matches!(message, Message::User { .. }))
+        .map(|offset| cut + offset)
+        .unwrap_or(conversation.len())
+}
+
+fn keep_from(system: Vec<Message>, mut conversation: Vec<Message>, cut: usize) -> Vec<Message> {
+    let mut messages = system;
+    messages.extend(conversation.drain(cut..));
+    messages
+}
+
+/// Keep system messages and the last `max_turns` user turns
+///
+/// A turn is a user message together with everything the agent produced in reply.
+#[derive(Debug, Clone)]
+pub struct SlidingWindow {
+    pub max_turns: usize,
+}
+
+impl SlidingWindow {
+    pub fn new(max_turns: usize) -> Self {
+        Self { max_turns }
+    }
+}
+
+impl ContextStrategy for SlidingWindow {
+    fn apply(&self, messages: Vec<Message>) -> Vec<Message> {
+        let (system, conversation) = split_system(messages);
+        let user_positions: Vec<usize> = conversation
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| matches!(message, Message::User { .. }))
+            .map(|(index, _)| index)
+            .collect();
+
+        let cut = match user_positions.len().checked_sub(self.max_turns.max(1)) {
+            Some(skip) => user_positions[skip],
+            None => 0,
+        };
+        keep_from(system, conversation, cut)
+    }
+}
+
+/// Keep system messages and roughly the last `n` other messages
+///
+/// The window is shrunk to start on a user message so tool calls and their results
+/// stay together.
+#[derive(Debug, Clone)]
+pub struct KeepSystemAndLastN {
+    pub n: usize,
+}
+
+impl KeepSystemAndLastN {
+    pub fn new(n: usize) -> Self {
+        Self { n }
+    }
+}
+
+impl ContextStrategy for KeepSystemAndLastN {
+    fn apply(&self, messages: Vec<Message>) -> Vec<Message> {
+        let (system, conversation) = split_system(messages);
+        if conversation.len() <= self.n {
+            return keep_from(system, conversation, 0);
+        }
+
+        let cut = align_to_user(&conversation, conversation.len() - self.n);
+        // Never drop the latest user message, even if it does not fit the window
+        let cut = if cut == conversation.len() {
+            conversation
+                .iter()
+                .rposition(|message| matches!(message, Message::User { .. }))
+                .unwrap_or(cut)
+        } else {
+            cut
+        };
+        keep_from(system, conversation, cut)
+    }
+}
+
+/// Keep system messages and as many recent messages as fit in a token budget
+#[derive(Clone)]
+pub struct TokenBudget {
+    pub max_tokens: usize,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl TokenBudget {
+    pub fn new(max_tokens: usize, tokenizer: impl Tokenizer + 'static) -> Self {
+        Self {
+            max_tokens,
+            tokenizer: Arc::new(tokenizer),
+        }
+    }
+}
+
+impl Debug for TokenBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenBudget")
+            .field("max_tokens", &self.max_tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ContextStrategy for TokenBudget {
+    fn apply(&self, messages: Vec<Message>) -> Vec<Message> {
+        let (system, conversation) = split_system(messages);
+        let mut used = count_tokens(&system, self.tokenizer.as_ref()).total;
+
+        // Walk backwards until the budget is exhausted
+        let mut cut = conversation.len();
+        for (index, message) in conversation.iter().enumerate().rev() {
+            used += self.tokenizer.count_message_tokens(message);
+            if used > self.max_tokens {
+                break;
+            }
+            cut = index;
+        }
+
+        let aligned = align_to_user(&conversation, cut);
+        // Always keep the latest user message so the model has something to answer
+        let cut = if aligned == conversation.len() {
+            conversation
+                .iter()
+                .rposition(|message| matches!(message, Message::User { .. }))
+                .unwrap_or(aligned)
+        } else {
+            aligned
+        };
+        keep_from(system, conversation, cut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{tokenizer::EstimatingTokenizer, types::*};
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::system("Be brief."),
+            Message::user("first question"),
+            Message::assistant(AssistantContent::ToolCall {
+                tool_call: ToolCall {
+                    id: "call_1".to_string(),
+                    name: "lookup".to_string(),
+                    arguments: serde_json::json!({}),
+                },
+            }),
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: serde_json::json!("found it"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant("first answer"),
+            Message::user("second question"),
+            Message::assistant("second answer"),
+            Message::user("third question"),
+        ]
+    }
+
+    #[test]
+    fn test_sliding_window_keeps_last_turns() {
+        let trimmed = SlidingWindow::new(2).apply(conversation());
+        assert_eq!(trimmed.len(), 4);
+        assert_eq!(trimmed[0].role(), "system");
+        assert_eq!(trimmed[1], Message::user("second question"));
+    }
+
+    #[test]
+    fn test_sliding_window_keeps_examples() {
+        let request = ChatRequest {
+            messages: conversation(),
+            ..ChatRequest::new()
+        }
+        .examples(vec![("example question", "example answer")]);
+        let trimmed = SlidingWindow::new(1).apply(request.messages);
+        let roles: Vec<&str> = trimmed.iter().map(Message::role).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert!(trimmed[1].is_example());
+        assert_eq!(trimmed[3], Message::user("third question"));
+    }
+
+    #[test]
+    fn test_last_n_does_not_orphan_tool_results() {
+        // The last five messages start at the tool result, so the window moves up to
+        // the next user message
+        let trimmed = KeepSystemAndLastN::new(5).apply(conversation());
+        assert_eq!(trimmed.len(), 4);
+        assert!(trimmed.iter().all(|message| message.role() != "tool"));
+        assert_eq!(trimmed[1], Message::user("second question"));
+    }
+
+    #[test]
+    fn test_token_budget_keeps_latest_user_message() {
+        let trimmed = TokenBudget::new(1, EstimatingTokenizer).apply(conversation());
+        assert_eq!(
+            trimmed,
+            vec![
+                Message::system("Be brief."),
+                Message::user("third question")
+            ]
+        );
+
+        let everything = TokenBudget::new(10_000, EstimatingTokenizer).apply(conversation());
+        assert_eq!(everything, conversation());
+    }
+}
@@ -0,0 +1,231 @@
+use ai_core::{
+    AiError, Result, ValidationError,
+    provider::{EmbeddingGeneration, embed_with_dimensions},
+    types::{EmbeddingRequest, EmbeddingResponse, Usage},
+};
+use futures::{StreamExt, TryStreamExt, stream};
+
+use crate::retry::{RetryPolicy, with_retry};
+
+/// How `embed_many` splits and sends its inputs
+#[derive(Debug, Clone)]
+pub struct EmbedOptions {
+    /// Inputs per request, at most the provider's limit
+    pub batch_size: usize,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    pub retry: Option<RetryPolicy>,
+    pub model: Option<String>,
+    pub dimensions: Option<u32>,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 96,
+            concurrency: 4,
+            retry: None,
+            model: None,
+            dimensions: None,
+        }
+    }
+}
+
+impl EmbedOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Retry batches that fail with a retryable error
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Embedding length, requested from the provider or cut down client-side
+    pub fn dimensions(mut self, dimensions: u32) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+}
+
+/// Embed any number of inputs in batches of `options.batch_size`
+///
+/// Up to `options.concurrency` batches run at once. Embeddings come back in input order
+/// and usage is summed over the batches that reported it. The first batch that still
+/// fails after its retries fails the whole call.
+pub async fn embed_many(
+    embedder: &dyn EmbeddingGeneration,
+    inputs: Vec<String>,
+    options: &EmbedOptions,
+) -> Result<EmbeddingResponse> {
+    let batches: Vec<Vec<String>> = inputs
+        .chunks(options.batch_size.max(1))
+        .map(<[String]>::to_vec)
+        .collect();
+
+    let responses: Vec<EmbeddingResponse> = stream::iter(batches)
+        .map(|batch| embed_batch(embedder, batch, options))
+        .buffered(options.concurrency.max(1))
+        .try_collect()
+        .await?;
+
+    let mut embeddings = Vec::with_capacity(inputs.len());
+    let mut usage: Option<Usage> = None;
+    for response in responses {
+        embeddings.extend(response.embeddings);
+        if let Some(batch) = response.usage {
+            let total = usage.get_or_insert(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            });
+            total.prompt_tokens += batch.prompt_tokens;
+            total.completion_tokens += batch.completion_tokens;
+            total.total_tokens += batch.total_tokens;
+        }
+    }
+    Ok(EmbeddingResponse {
+        embeddings,
+        usage,
+        metadata: None,
+    })
+}
+
+async fn embed_batch(
+    embedder: &dyn EmbeddingGeneration,
+    batch: Vec<String>,
+    options: &EmbedOptions,
+) -> Result<EmbeddingResponse> {
+    let expected = batch.len();
+    let response = with_retry(options.retry.as_ref(), || {
+        embed_with_dimensions(
+            embedder,
+            EmbeddingRequest {
+                inputs: batch.clone(),
+                model: options.model.clone(),
+                encoding_format: None,
+                dimensions: options.dimensions,
+            },
+        )
+    })
+    .await?;
+    if response.embeddings.len() != expected {
+        return Err(AiError::Validation(ValidationError::InvalidValue {
+            field: "embeddings".to_string(),
+            message: format!(
+                "expected {} embeddings, got {}",
+                expected,
+                response.embeddings.len()
+            ),
+        }));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::ProviderError;
+    use async_trait::async_trait;
+    use std::{
+        sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    /// Embeds each input as its length; the first request is rate limited
+    #[derive(Default)]
+    struct LengthEmbedder {
+        calls: AtomicUsize,
+        batch_sizes: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingGeneration for LengthEmbedder {
+        fn name(&self) -> &str {
+            "length"
+        }
+
+        fn model(&self) -> &str {
+            "length-1"
+        }
+
+        async fn generate_embeddings(
+            &self,
+            request: EmbeddingRequest,
+        ) -> Result<EmbeddingResponse> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(AiError::Provider(ProviderError::RateLimit {
+                    provider: "length".to_string(),
+                    retry_after: None,
+                    message: "slow down".to_string(),
+                    request_id: None,
+                }));
+            }
+            self.batch_sizes.lock().unwrap().push(request.inputs.len());
+            let tokens = request.inputs.len() as u32;
+            Ok(EmbeddingResponse {
+                embeddings: request
+                    .inputs
+                    .iter()
+                    .map(|input| vec![input.len() as f32])
+                    .collect(),
+                usage: Some(Usage {
+                    prompt_tokens: tokens,
+                    completion_tokens: 0,
+                    total_tokens: tokens,
+                }),
+                metadata: None,
+            })
+        }
+
+        fn embedding_dimension(&self) -> u32 {
+            1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_many_batches_in_order() {
+        let embedder = LengthEmbedder::default();
+        let inputs: Vec<String> = (1..=7).map(|n| "x".repeat(n)).collect();
+        let options = EmbedOptions::new()
+            .batch_size(3)
+            .concurrency(2)
+            .retry(RetryPolicy::new(2).initial_delay(Duration::from_millis(1)));
+
+        let response = embed_many(&embedder, inputs, &options).await.unwrap();
+
+        let lengths: Vec<f32> = response.embeddings.iter().map(|e| e[0]).collect();
+        assert_eq!(lengths, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(response.usage.unwrap().total_tokens, 7);
+        let mut sizes = embedder.batch_sizes.lock().unwrap().clone();
+        sizes.sort();
+        assert_eq!(sizes, [1, 3, 3]);
+
+        let failing = LengthEmbedder::default();
+        let no_retry = EmbedOptions::new().batch_size(3);
+        assert!(
+            embed_many(&failing, vec!["a".to_string()], &no_retry)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use ai_core::{
+    AgentError, AiError, Result,
+    types::{AssistantContent, Message, UserContent},
+};
+use async_trait::async_trait;
+use serde_json::Value as JsonValue;
+
+/// Metadata key under which guardrail annotations are stored on a message
+pub const GUARDRAILS_METADATA_KEY: &str = "guardrails";
+
+/// What a guardrail decided about a piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardrailAction {
+    /// Let the text through unchanged
+    Allow,
+    /// Stop the run with `AgentError::GuardrailBlocked`
+    Block { reason: String },
+    /// Replace the text
+    Rewrite(String),
+    /// Let the text through and record a note in the message metadata
+    Annotate(JsonValue),
+}
+
+/// Policy check on user input and assistant output
+///
+/// Input checks run on the text of the latest user message before the first request of a
+/// run. Output checks run on the assistant text of every step before it is added to the
+/// conversation; when streaming, a step's chunks and events are held back until its text
+/// has passed them, and a rewrite replaces the streamed text deltas.
+#[async_trait]
+pub trait Guardrail: Debug + Send + Sync {
+    /// Name used in errors and annotations
+    fn name(&self) -> &str;
+
+    async fn check_input(&self, _text: &str) -> GuardrailAction {
+        GuardrailAction::Allow
+    }
+
+    async fn check_output(&self, _text: &str) -> GuardrailAction {
+        GuardrailAction::Allow
+    }
+}
+
+/// Shared list of guardrails applied in order
+pub type Guardrails = Vec<Arc<dyn Guardrail>>;
+
+enum Direction {
+    Input,
+    Output,
+}
+
+/// Run every guardrail over one text part, applying rewrites and collecting annotations
+async fn check_text(
+    guardrails: &[Arc<dyn Guardrail>],
+    direction: &Direction,
+    text: &mut String,
+    annotations: &mut HashMap<String, JsonValue>,
+) -> Result<()> {
+    for guardrail in guardrails {
+        let action = match direction {
+            Direction::Input => guardrail.check_input(text).await,
+            Direction::Output => guardrail.check_output(text).await,
+        };
+        match action {
+            GuardrailAction::Allow => {}
+            GuardrailAction::Block { reason } => {
+                return Err(AiError::Agent(AgentError::GuardrailBlocked {
+                    guardrail: guardrail.name().to_string(),
+                    reason,
+                }));
+            }
+            GuardrailAction::Rewrite(rewritten) => *text = rewritten,
+            GuardrailAction::Annotate(note) => {
+                annotations.insert(guardrail.name().to_string(), note);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn annotate(
+    metadata: &mut Option<HashMap<String, JsonValue>>,
+    annotations: HashMap<String, JsonValue>,
+) {
+    if annotations.is_empty() {
+        return;
+    }
+    let entry = metadata
+        .get_or_insert_with(HashMap::new)
+        .entry(GUARDRAILS_METADATA_KEY.to_string())
+        .or_insert_with(|| JsonValue::Object(Default::default()));
+    if let JsonValue::Object(map) = entry {
+        map.extend(annotations);
+    }
+}
+
+/// Check the text of the latest user message
+pub(crate) async fn guard_input(
+    guardrails: &[Arc<dyn Guardrail>],
+    messages: &mut [Message],
+) -> Result<()> {
+    if guardrails.is_empty() {
+        return Ok(());
+    }
+    let Some(Message::User { content, metadata }) = messages
+        .iter_mut()
+        .rev()
+        .find(|message| matches!(message, Message::User { .. }))
+    else {
+        return Ok(());
+    };
+
+    let mut annotations = HashMap::new();
+    for part in content.iter_mut() {
+        if let UserContent::Text { text } = part {
+            check_text(guardrails, &Direction::Input, text, &mut annotations).await?;
+        }
+    }
+    annotate(metadata, annotations);
+    Ok(())
+}
+
+/// Check the text of an assistant message
+pub(crate) async fn guard_output(
+    guardrails: &[Arc<dyn Guardrail>],
+    message: &mut Message,
+) -> Result<()> {
+    if guardrails.is_empty() {
+        return Ok(());
+    }
+    let Message::Assistant { content, metadata } = message else {
+        return Ok(());
+    };
+
+    let mut annotations = HashMap::new();
+    for part in content.iter_mut() {
+        if let AssistantContent::Text { text } = part {
+            check_text(guardrails, &Direction::Output, text, &mut annotations).await?;
+        }
+    }
+    annotate(metadata, annotations);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct NoSecrets;
+
+    #[async_trait]
+    impl Guardrail for NoSecrets {
+        fn name(&self) -> &str {
+            "no_secrets"
+        }
+
+        async fn check_input(&self, text: &str) -> GuardrailAction {
+            if text.contains("password") {
+                GuardrailAction::Block {
+                    reason: "input mentions a password".to_string(),
+                }
+            } else {
+                GuardrailAction::Allow
+            }
+        }
+
+        async fn check_output(&self, text: &str) -> GuardrailAction {
+            if text.contains("hunter2") {
+                GuardrailAction::Rewrite(text.replace("hunter2", "*******"))
+            } else {
+                GuardrailAction::Annotate(JsonValue::Bool(true))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_actions() {
+        let guardrails: Guardrails = vec![Arc::new(NoSecrets)];
+
+        let mut messages = vec![Message::user("what is my password?")];
+        let blocked = guard_input(&guardrails, &mut messages).await;
+        assert!(matches!(
+            blocked,
+            Err(AiError::Agent(AgentError::GuardrailBlocked { .. }))
+        ));
+
+        let mut message = Message::assistant("it is hunter2");
+        guard_output(&guardrails, &mut message).await.unwrap();
+        assert_eq!(message, Message::assistant("it is *******"));
+
+        let mut message = Message::assistant("all clear");
+        guard_output(&guardrails, &mut message).await.unwrap();
+        let Message::Assistant { metadata, .. } = message else {
+            unreachable!()
+        };
+        assert_eq!(
+            metadata.unwrap()[GUARDRAILS_METADATA_KEY]["no_secrets"],
+            JsonValue::Bool(true)
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc};
+
+use ai_core::{
+    Result, Scratchpad,
+    errors::ToolExecutionError,
+    types::{FinishReason, Message, ToolCall, Usage},
+};
+
+use crate::agent::AgentResponse;
+
+/// Boxed future returned by lifecycle hooks
+pub type HookFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+type StepStartHook = Arc<dyn Fn(u32) -> HookFuture<Result<()>> + Send + Sync>;
+type StepFinishHook = Arc<dyn Fn(StepFinish) -> HookFuture<Result<()>> + Send + Sync>;
+type ToolCallHook =
+    Arc<dyn Fn(ToolCall) -> HookFuture<std::result::Result<(), ToolExecutionError>> + Send + Sync>;
+type FinishHook = Arc<dyn Fn(AgentResponse) -> HookFuture<Result<()>> + Send + Sync>;
+
+/// Summary of a completed step passed to `on_step_finish`
+#[derive(Debug, Clone)]
+pub struct StepFinish {
+    pub step: u32,
+    /// Assistant message produced by the model in this step
+    pub message: Message,
+    pub finish_reason: FinishReason,
+    pub usage: Option<Usage>,
+    /// The run's working memory
+    pub scratchpad: Scratchpad,
+}
+
+/// Async callbacks invoked at each stage of an agent run
+///
+/// Returning an error from `on_step_start`, `on_step_finish` or `on_finish` aborts the
+/// run with that error. Returning an error from `on_tool_call` vetoes that single call:
+/// the handler is skipped and the error is sent back to the model as the tool result.
+#[derive(Clone, Default)]
+pub struct AgentHooks {
+    on_step_start: Option<StepStartHook>,
+    on_step_finish: Option<StepFinishHook>,
+    on_tool_call: Option<ToolCallHook>,
+    on_finish: Option<FinishHook>,
+}
+
+impl Debug for AgentHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentHooks")
+            .field("on_step_start", &self.on_step_start.is_some())
+            .field("on_step_finish", &self.on_step_finish.is_some())
+            .field("on_tool_call", &self.on_tool_call.is_some())
+            .field("on_finish", &self.on_finish.is_some())
+            .finish()
+    }
+}
+
+impl AgentHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called before each model request with the step number
+    pub fn on_step_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_step_start = Some(Arc::new(move |step| Box::pin(hook(step))));
+        self
+    }
+
+    /// Called after the step's model response and tool executions
+    pub fn on_step_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StepFinish) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_step_finish = Some(Arc::new(move |finish| Box::pin(hook(finish))));
+        self
+    }
+
+    /// Called before a tool call is executed; an error vetoes the call
+    pub fn on_tool_call<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), ToolExecutionError>> + Send + 'static,
+    {
+        self.on_tool_call = Some(Arc::new(move |tool_call| Box::pin(hook(tool_call))));
+        self
+    }
+
+    /// Called once with the final response before the run returns
+    pub fn on_finish<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(AgentResponse) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_finish = Some(Arc::new(move |response| Box::pin(hook(response))));
+        self
+    }
+
+    pub(crate) async fn step_start(&self, step: u32) -> Result<()> {
+        match &self.on_step_start {
+            Some(hook) => hook(step).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn step_finish(&self, finish: impl FnOnce() -> StepFinish) -> Result<()> {
+        match &self.on_step_finish {
+            Some(hook) => hook(finish()).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn tool_call(
+        &self,
+        tool_call: &ToolCall,
+    ) -> std::result::Result<(), ToolExecutionError> {
+        match &self.on_tool_call {
+            Some(hook) => hook(tool_call.clone()).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn finish(&self, response: &AgentResponse) -> Result<()> {
+        match &self.on_finish {
+            Some(hook) => hook(response.clone()).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use ai_core::{AgentError, AiError, ToolRouter, types::ToolResult};
+    use ai_test_utils::MockProvider;
+    use futures::StreamExt;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::agent::{
+        AgentEvent, GenerateConfig, MaxSteps, RunUntilFirst, StopOnReason, StreamConfig,
+        generate_text, stream_events,
+    };
+
+    #[derive(Deserialize, JsonSchema)]
+    struct NoInput {}
+
+    async fn lookup(_input: NoInput) -> String {
+        "found".to_string()
+    }
+
+    fn provider() -> MockProvider {
+        MockProvider::new()
+            .tool_call("lookup", serde_json::json!({}))
+            .text("Done")
+    }
+
+    /// Hooks recording each call, in order, into `log`
+    fn recording(log: &Arc<Mutex<Vec<String>>>) -> AgentHooks {
+        let (start, step, tool, finish) = (log.clone(), log.clone(), log.clone(), log.clone());
+        AgentHooks::new()
+            .on_step_start(move |n| {
+                start.lock().unwrap().push(format!("step_start {n}"));
+                async { Ok(()) }
+            })
+            .on_step_finish(move |finished| {
+                step.lock()
+                    .unwrap()
+                    .push(format!("step_finish {}", finished.step));
+                async { Ok(()) }
+            })
+            .on_tool_call(move |tool_call| {
+                tool.lock()
+                    .unwrap()
+                    .push(format!("tool_call {}", tool_call.name));
+                async { Ok(()) }
+            })
+            .on_finish(move |response| {
+                finish
+                    .lock()
+                    .unwrap()
+                    .push(format!("finish {}", response.text()));
+                async { Ok(()) }
+            })
+    }
+
+    fn veto() -> AgentHooks {
+        AgentHooks::new().on_tool_call(|tool_call| async move {
+            Err(ToolExecutionError::Unauthorized(format!(
+                "{} is not allowed",
+                tool_call.name
+            )))
+        })
+    }
+
+    fn failing_finish() -> AgentHooks {
+        AgentHooks::new().on_finish(|_| async {
+            Err(AiError::Agent(AgentError::StateError {
+                message: "audit sink unavailable".to_string(),
+            }))
+        })
+    }
+
+    fn generate_config(hooks: AgentHooks) -> GenerateConfig<MockProvider> {
+        GenerateConfig::new(provider())
+            .messages(vec![Message::user("Look it up")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("lookup", None, lookup)
+                    .with_state(()),
+            )
+            .run_until(RunUntilFirst::new(
+                MaxSteps::new(5),
+                StopOnReason::stop_on_finish(),
+            ))
+            .hooks(hooks)
+    }
+
+    fn stream_config(hooks: AgentHooks) -> StreamConfig<MockProvider> {
+        StreamConfig::new(provider())
+            .messages(vec![Message::user("Look it up")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("lookup", None, lookup)
+                    .with_state(()),
+            )
+            .run_until(RunUntilFirst::new(
+                MaxSteps::new(5),
+                StopOnReason::stop_on_finish(),
+            ))
+            .hooks(hooks)
+    }
+
+    async fn stream(config: StreamConfig<MockProvider>) -> Vec<Result<AgentEvent>> {
+        stream_events(config).await.unwrap().collect().await
+    }
+
+    fn tool_results(messages: &[Message]) -> Vec<ToolResult> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Tool { tool_results, .. } => Some(tool_results.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    const ORDER: [&str; 6] = [
+        "step_start 0",
+        "tool_call lookup",
+        "step_finish 0",
+        "step_start 1",
+        "step_finish 1",
+        "finish Done",
+    ];
+
+    #[tokio::test]
+    async fn test_hooks_run_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        generate_text(generate_config(recording(&log)))
+            .await
+            .unwrap();
+        assert_eq!(*log.lock().unwrap(), ORDER);
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let events = stream(stream_config(recording(&log))).await;
+        assert!(matches!(
+            events.last(),
+            Some(Ok(AgentEvent::RunFinished(_)))
+        ));
+        assert_eq!(*log.lock().unwrap(), ORDER);
+    }
+
+    #[tokio::test]
+    async fn test_vetoed_tool_calls_become_error_results() {
+        let response = generate_text(generate_config(veto())).await.unwrap();
+        let results = tool_results(&response.messages);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_error);
+        assert!(
+            results[0]
+                .result
+                .to_string()
+                .contains("lookup is not allowed")
+        );
+        assert_eq!(response.text(), "Done");
+
+        let events = stream(stream_config(veto())).await;
+        let Some(Ok(AgentEvent::RunFinished(response))) = events.last() else {
+            panic!("expected RunFinished, got {:?}", events.last());
+        };
+        let results = tool_results(&response.messages);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_error);
+        assert!(
+            results[0]
+                .result
+                .to_string()
+                .contains("lookup is not allowed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_finish_errors_abort_the_run() {
+        let error = generate_text(generate_config(failing_finish()))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("audit sink unavailable"));
+
+        let events = stream(stream_config(failing_finish())).await;
+        assert!(
+            events
+                .iter()
+                .all(|event| !matches!(event, Ok(AgentEvent::RunFinished(_))))
+        );
+        let error = events.last().unwrap().as_ref().unwrap_err();
+        assert!(error.to_string().contains("audit sink unavailable"));
+    }
+}
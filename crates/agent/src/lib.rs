@@ -1,3 +1,56 @@
 pub mod agent;
+#[cfg(feature = "ai_http")]
+pub mod ai_http;
+pub mod audit;
+pub mod checkpoint;
+pub mod compaction;
+pub mod compression;
+pub mod context;
+pub mod embedding;
+pub mod guardrails;
+pub mod hooks;
+pub mod memory;
+pub mod metrics;
+pub mod output;
+pub mod parallel;
+pub mod pii;
+pub mod preflight;
+pub mod rag;
+pub mod refusal;
+pub mod report;
+pub mod retry;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod runner;
+pub mod smooth;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sub_agent;
+mod telemetry;
+pub mod transcript;
+pub mod workflow;
 
 pub use agent::*;
+pub use audit::*;
+pub use checkpoint::*;
+pub use compaction::*;
+pub use compression::*;
+pub use context::*;
+pub use embedding::*;
+pub use guardrails::*;
+pub use hooks::*;
+pub use memory::*;
+pub use metrics::*;
+pub use output::*;
+pub use parallel::*;
+pub use pii::*;
+pub use preflight::*;
+pub use rag::*;
+pub use refusal::*;
+pub use report::*;
+pub use retry::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use runner::*;
+pub use smooth::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use sub_agent::*;
+pub use transcript::*;
+pub use workflow::*;
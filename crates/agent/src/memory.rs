@@ -0,0 +1,260 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use ai_core::{
+    AiError, Result, ValidationError,
+    provider::{ChatTextGeneration, EmbeddingGeneration},
+    types::*,
+};
+use ai_memory::{MetadataFilter, ScoredRecord, VectorRecord, VectorStore};
+
+use crate::compaction::render_transcript;
+
+/// Metadata key holding the namespace of a stored memory
+pub const NAMESPACE_KEY: &str = "namespace";
+
+const DEFAULT_INSTRUCTIONS: &str = "Extract facts worth remembering in future conversations \
+from the transcript you are given: stable preferences, personal details, decisions and \
+commitments. Reply with one short, self-contained fact per line, or NONE if there is nothing \
+worth remembering.";
+
+/// Long-term memory for an agent, kept as embedded facts in a vector store
+///
+/// Before each run the memories closest to the user input are added to the system prompt;
+/// after each run the model is asked to extract new facts from the turn. Give each user a
+/// `namespace` when several users share one store.
+#[derive(Clone)]
+pub struct AgentMemory {
+    embedder: Arc<dyn EmbeddingGeneration>,
+    store: Arc<dyn VectorStore>,
+    top_k: usize,
+    min_score: f32,
+    extract: bool,
+    instructions: String,
+    namespace: Option<String>,
+}
+
+impl Debug for AgentMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentMemory")
+            .field("embedder", &self.embedder.model())
+            .field("top_k", &self.top_k)
+            .field("min_score", &self.min_score)
+            .field("extract", &self.extract)
+            .field("namespace", &self.namespace)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentMemory {
+    pub fn new(
+        embedder: impl EmbeddingGeneration + 'static,
+        store: impl VectorStore + 'static,
+    ) -> Self {
+        Self {
+            embedder: Arc::new(embedder),
+            store: Arc::new(store),
+            top_k: 5,
+            min_score: 0.0,
+            extract: true,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            namespace: None,
+        }
+    }
+
+    /// Maximum number of memories added to a run
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Ignore memories less similar to the input than this
+    pub fn min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Whether to extract new facts after each run (default true)
+    pub fn extract(mut self, enabled: bool) -> Self {
+        self.extract = enabled;
+        self
+    }
+
+    /// Instructions given to the model when extracting facts
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    /// Keep memories apart from other namespaces in the same store, e.g. one per user
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Store facts; storing the same fact again replaces it
+    pub async fn remember(&self, facts: &[String]) -> Result<()> {
+        if facts.is_empty() {
+            return Ok(());
+        }
+        let vectors = embed_texts(&*self.embedder, facts.to_vec()).await?;
+        let records = facts
+            .iter()
+            .zip(vectors)
+            .map(|(fact, vector)| {
+                let record = VectorRecord::new(
+                    fact_id(self.namespace.as_deref(), fact),
+                    vector,
+                    fact.as_str(),
+                );
+                match &self.namespace {
+                    Some(namespace) => record.metadata(NAMESPACE_KEY, namespace.as_str().into()),
+                    None => record,
+                }
+            })
+            .collect();
+        self.store.upsert(records).await
+    }
+
+    /// Remove previously stored facts
+    pub async fn forget(&self, facts: &[String]) -> Result<()> {
+        let ids: Vec<String> = facts
+            .iter()
+            .map(|fact| fact_id(self.namespace.as_deref(), fact))
+            .collect();
+        self.store.delete(&ids).await
+    }
+
+    /// Memories most relevant to `query`, best first
+    pub async fn recall(&self, query: &str) -> Result<Vec<ScoredRecord>> {
+        if query.trim().is_empty() || self.top_k == 0 {
+            return Ok(Vec::new());
+        }
+        let vector = embed_texts(&*self.embedder, vec![query.to_string()])
+            .await?
+            .remove(0);
+        let filter = self
+            .namespace
+            .as_ref()
+            .map(|namespace| MetadataFilter::eq(NAMESPACE_KEY, namespace.as_str()));
+        let mut memories = self
+            .store
+            .query(&vector, self.top_k, filter.as_ref())
+            .await?;
+        memories.retain(|memory| memory.score >= self.min_score);
+        Ok(memories)
+    }
+
+    /// System prompt section listing the memories relevant to `query`
+    pub async fn context(&self, query: &str) -> Result<Option<String>> {
+        let memories = self.recall(query).await?;
+        if memories.is_empty() {
+            return Ok(None);
+        }
+        let lines: Vec<String> = memories
+            .iter()
+            .map(|memory| format!("- {}", memory.record.text))
+            .collect();
+        Ok(Some(format!(
+            "Things you remember about the user from earlier conversations:\n{}",
+            lines.join("\n")
+        )))
+    }
+
+    /// Ask the model for facts worth remembering in `messages` and store them
+    pub async fn learn<P>(&self, provider: &P, messages: &[Message]) -> Result<Vec<String>>
+    where
+        P: ChatTextGeneration + ?Sized,
+    {
+        if !self.extract || messages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = ChatRequest::new()
+            .system(self.instructions.as_str())
+            .user(render_transcript(messages));
+        let response = provider.generate(request).await?;
+        let text: String = match &response.message {
+            Message::Assistant { content, .. } => content
+                .iter()
+                .filter_map(|part| match part {
+                    AssistantContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => String::new(),
+        };
+
+        let facts = parse_facts(&text);
+        self.remember(&facts).await?;
+        Ok(facts)
+    }
+}
+
+/// Embed texts, checking that the provider returned one embedding per input
+pub(crate) async fn embed_texts(
+    embedder: &dyn EmbeddingGeneration,
+    inputs: Vec<String>,
+) -> Result<Vec<Vec<f32>>> {
+    let expected = inputs.len();
+    let response = embedder
+        .generate_embeddings(EmbeddingRequest {
+            inputs,
+            model: None,
+            encoding_format: None,
+            dimensions: None,
+        })
+        .await?;
+    if response.embeddings.len() != expected {
+        return Err(AiError::Validation(ValidationError::InvalidValue {
+            field: "embeddings".to_string(),
+            message: format!(
+                "expected {} embeddings, got {}",
+                expected,
+                response.embeddings.len()
+            ),
+        }));
+    }
+    Ok(response.embeddings)
+}
+
+/// Stable ID so that storing a fact twice does not duplicate it
+fn fact_id(namespace: Option<&str>, fact: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    namespace.hash(&mut hasher);
+    fact.trim().to_lowercase().hash(&mut hasher);
+    format!("memory-{:016x}", hasher.finish())
+}
+
+fn parse_facts(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', '•']).trim())
+        .filter(|line| !line.is_empty() && !line.eq_ignore_ascii_case("none"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_facts() {
+        assert_eq!(
+            parse_facts("- Prefers metric units\n\n* Lives in Lyon\n"),
+            vec!["Prefers metric units", "Lives in Lyon"]
+        );
+        assert!(parse_facts("NONE").is_empty());
+        assert_eq!(
+            fact_id(None, "Lives in Lyon"),
+            fact_id(None, " lives in lyon")
+        );
+        assert_ne!(
+            fact_id(Some("alice"), "Lives in Lyon"),
+            fact_id(Some("bob"), "Lives in Lyon")
+        );
+    }
+}
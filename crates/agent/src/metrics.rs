@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ai_core::{AiError, platform::Instant, types::Usage};
+
+/// A finished model request, after any retries
+#[derive(Debug, Clone)]
+pub struct RequestMetric<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    /// Time until the response, or the whole stream, was received
+    pub latency: Duration,
+    pub usage: Option<&'a Usage>,
+    /// `AiError::error_type` when the request failed
+    pub error: Option<&'static str>,
+}
+
+/// A finished tool execution
+#[derive(Debug, Clone)]
+pub struct ToolMetric<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub tool: &'a str,
+    pub duration: Duration,
+    /// `ToolExecutionError::error_type` when the tool failed
+    pub error: Option<&'static str>,
+}
+
+/// Receives measurements from agent runs
+///
+/// Implement this to forward request counts, latencies, token usage and tool durations
+/// to Prometheus, StatsD or the `metrics` crate. Methods are called inline on the run's
+/// task, so they should not block.
+pub trait MetricsSink: Debug + Send + Sync {
+    fn record_request(&self, request: &RequestMetric<'_>);
+
+    fn record_tool(&self, _tool: &ToolMetric<'_>) {}
+}
+
+/// Totals for one provider and model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub total_latency: Duration,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl RequestStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+
+    pub fn mean_latency(&self) -> Duration {
+        match u32::try_from(self.requests) {
+            Ok(0) => Duration::ZERO,
+            Ok(requests) => self.total_latency / requests,
+            Err(_) => self.total_latency.div_f64(self.requests as f64),
+        }
+    }
+}
+
+/// Totals for one tool
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_duration: Duration,
+}
+
+/// Sink that aggregates measurements in memory, useful for tests and simple status pages
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    requests: Mutex<HashMap<(String, String), RequestStats>>,
+    tools: Mutex<HashMap<String, ToolStats>>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Totals for requests to `model` on `provider`
+    pub fn requests(&self, provider: &str, model: &str) -> RequestStats {
+        self.requests
+            .lock()
+            .unwrap()
+            .get(&(provider.to_string(), model.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Totals for executions of the tool `name`
+    pub fn tool(&self, name: &str) -> ToolStats {
+        self.tools
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn record_request(&self, request: &RequestMetric<'_>) {
+        let mut requests = self.requests.lock().unwrap();
+        let stats = requests
+            .entry((request.provider.to_string(), request.model.to_string()))
+            .or_default();
+        stats.requests += 1;
+        stats.errors += u64::from(request.error.is_some());
+        stats.total_latency += request.latency;
+        if let Some(usage) = request.usage {
+            stats.prompt_tokens += u64::from(usage.prompt_tokens);
+            stats.completion_tokens += u64::from(usage.completion_tokens);
+        }
+    }
+
+    fn record_tool(&self, tool: &ToolMetric<'_>) {
+        let mut tools = self.tools.lock().unwrap();
+        let stats = tools.entry(tool.tool.to_string()).or_default();
+        stats.calls += 1;
+        stats.errors += u64::from(tool.error.is_some());
+        stats.total_duration += tool.duration;
+    }
+}
+
+/// Report a model request started at `started` to `sink`, if any
+pub(crate) fn observe_request(
+    sink: Option<&Arc<dyn MetricsSink>>,
+    provider: &str,
+    model: &str,
+    started: Instant,
+    outcome: std::result::Result<Option<&Usage>, &AiError>,
+) {
+    if let Some(sink) = sink {
+        sink.record_request(&RequestMetric {
+            provider,
+            model,
+            latency: started.elapsed(),
+            usage: outcome.ok().flatten(),
+            error: outcome.err().map(AiError::error_type),
+        });
+    }
+}
+
+/// Report a tool execution started at `started` to `sink`, if any
+pub(crate) fn observe_tool(
+    sink: Option<&Arc<dyn MetricsSink>>,
+    provider: &str,
+    model: &str,
+    tool: &str,
+    started: Instant,
+    error: Option<&'static str>,
+) {
+    if let Some(sink) = sink {
+        sink.record_tool(&ToolMetric {
+            provider,
+            model,
+            tool,
+            duration: started.elapsed(),
+            error,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_metrics_aggregates_per_model_and_tool() {
+        let metrics = InMemoryMetrics::new();
+        let usage = Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        };
+        for error in [None, None, Some("provider_error"), None] {
+            metrics.record_request(&RequestMetric {
+                provider: "anthropic",
+                model: "claude",
+                latency: Duration::from_millis(100),
+                usage: error.is_none().then_some(&usage),
+                error,
+            });
+        }
+        metrics.record_tool(&ToolMetric {
+            provider: "anthropic",
+            model: "claude",
+            tool: "search",
+            duration: Duration::from_millis(30),
+            error: Some("not_found"),
+        });
+
+        let stats = metrics.requests("anthropic", "claude");
+        assert_eq!(stats.requests, 4);
+        assert_eq!(stats.prompt_tokens, 30);
+        assert_eq!(stats.completion_tokens, 15);
+        assert_eq!(stats.error_rate(), 0.25);
+        assert_eq!(stats.mean_latency(), Duration::from_millis(100));
+        assert_eq!(
+            metrics.requests("anthropic", "other"),
+            RequestStats::default()
+        );
+
+        let tool = metrics.tool("search");
+        assert_eq!((tool.calls, tool.errors), (1, 1));
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt, sync::Arc};
+
+use ai_core::{AgentError, AiError, Result, provider::ChatTextGeneration, types::*};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use crate::agent::{AgentResponse, GenerateConfig, generate_text};
+
+/// Name of the tool the model calls to hand in a structured final answer
+pub const FINAL_ANSWER_TOOL: &str = "final_answer";
+
+const OUTPUT_INSTRUCTIONS: &str = "Give your final answer now by calling the final_answer tool \
+with the answer as its input. Do not call any other tool.";
+
+/// Request for the final answer once the tool loop is done
+///
+/// The run's own tools stay declared so providers accept earlier tool calls in the
+/// conversation; the instructions restrict the model to `final_answer`.
+pub(crate) fn output_request(
+    messages: Vec<Message>,
+    settings: GenerationSettings,
+    tools: Option<Vec<ToolDefinition>>,
+    schema: &JsonValue,
+) -> ChatRequest {
+    let mut tools = tools.unwrap_or_default();
+    tools.push(ToolDefinition::new(
+        FINAL_ANSWER_TOOL,
+        "Return the final answer to the user",
+        schema.clone(),
+    ));
+    ChatRequest {
+        messages,
+        settings,
+        tools: Some(tools),
+        headers: Vec::new(),
+    }
+    .system(OUTPUT_INSTRUCTIONS)
+}
+
+fn final_answer_call(message: &Message) -> Option<&ToolCall> {
+    let Message::Assistant { content, .. } = message else {
+        return None;
+    };
+    content.iter().find_map(|part| match part {
+        AssistantContent::ToolCall { tool_call } if tool_call.name == FINAL_ANSWER_TOOL => {
+            Some(tool_call)
+        }
+        _ => None,
+    })
+}
+
+/// Messages recording the final answer, keeping the conversation valid for later turns
+pub(crate) fn record_answer(message: Message) -> Vec<Message> {
+    let Some(call_id) = final_answer_call(&message).map(|call| call.id.clone()) else {
+        return vec![message];
+    };
+    vec![
+        message,
+        Message::tool(ToolResult {
+            tool_call_id: call_id,
+            result: JsonValue::String("Answer recorded".to_string()),
+            is_error: false,
+            content: Vec::new(),
+        }),
+    ]
+}
+
+/// Parse JSON from assistant text, allowing a surrounding Markdown code fence
+pub fn parse_json_text(text: &str) -> serde_json::Result<JsonValue> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim())
+}
+
+/// Validate `value` against a JSON schema; `Err` lists every violation and where it is
+pub fn validate_json_schema(
+    schema: &JsonValue,
+    value: &JsonValue,
+) -> std::result::Result<(), String> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| format!("invalid schema: {}", e))?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|error| format!("{} at '{}'", error, error.instance_path))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn message_text(message: &Message) -> String {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Structured answer of a message: the `final_answer` input, or its text parsed as JSON
+fn message_output_value(message: &Message) -> Option<JsonValue> {
+    if let Some(call) = final_answer_call(message) {
+        return Some(call.arguments.clone());
+    }
+    parse_json_text(&message_text(message)).ok()
+}
+
+type Check = dyn Fn(&Message) -> std::result::Result<(), String> + Send + Sync;
+
+/// Check on a run's final answer, with repair retries
+///
+/// When the check fails, the reason is sent back to the model as a user message and the
+/// answer is requested again, up to `max_repairs` times; after that the run fails with
+/// `AgentError::InvalidOutput`.
+#[derive(Clone)]
+pub struct OutputValidator {
+    check: Arc<Check>,
+    pub max_repairs: u32,
+}
+
+impl fmt::Debug for OutputValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputValidator")
+            .field("max_repairs", &self.max_repairs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OutputValidator {
+    /// Validate the final message; `Err` holds the reason shown to the model
+    pub fn new(
+        check: impl Fn(&Message) -> std::result::Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            check: Arc::new(check),
+            max_repairs: 2,
+        }
+    }
+
+    /// Validate the text of the final message
+    pub fn text(
+        check: impl Fn(&str) -> std::result::Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(move |message| check(&message_text(message)))
+    }
+
+    /// Validate the structured answer against a JSON schema
+    pub fn schema(schema: JsonValue) -> Self {
+        Self::new(move |message| {
+            let value = message_output_value(message)
+                .ok_or_else(|| "the answer is not valid JSON".to_string())?;
+            validate_json_schema(&schema, &value)
+        })
+    }
+
+    /// Validate that the structured answer deserializes into `T`
+    pub fn parses<T: DeserializeOwned>() -> Self {
+        Self::new(|message| {
+            let value = message_output_value(message)
+                .ok_or_else(|| "the answer is not valid JSON".to_string())?;
+            serde_json::from_value::<T>(value)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn max_repairs(mut self, max_repairs: u32) -> Self {
+        self.max_repairs = max_repairs;
+        self
+    }
+
+    pub(crate) fn check(&self, message: &Message) -> std::result::Result<(), String> {
+        (self.check)(message)
+    }
+}
+
+/// User message asking the model to fix an answer that failed validation
+pub(crate) fn repair_message(reason: &str) -> Message {
+    Message::user(format!(
+        "Your output failed validation because {}. Correct it and give your answer again.",
+        reason
+    ))
+}
+
+pub(crate) fn validation_failed(repairs: u32, reason: String) -> AiError {
+    AiError::Agent(AgentError::InvalidOutput {
+        message: format!("failed validation after {} repairs: {}", repairs, reason),
+    })
+}
+
+impl<T> AgentResponse<T> {
+    /// Structured answer of the final message
+    ///
+    /// This is the input of the `final_answer` tool call, or the message text when the
+    /// model answered with plain JSON instead.
+    pub fn output_value(&self) -> Option<JsonValue> {
+        message_output_value(&self.final_message)
+    }
+
+    /// Deserialize the structured answer into `U`
+    pub fn parse_output<U: DeserializeOwned>(self) -> Result<AgentResponse<U>> {
+        if self.checkpoint.is_some() {
+            return Err(AiError::Agent(AgentError::StateError {
+                message: "run paused on tool calls before producing a final answer".to_string(),
+            }));
+        }
+        let value = self.output_value().ok_or_else(|| {
+            AiError::Agent(AgentError::InvalidOutput {
+                message: format!("no structured answer in the final message: {}", self.text()),
+            })
+        })?;
+        let output = serde_json::from_value(value).map_err(|e| {
+            AiError::Agent(AgentError::InvalidOutput {
+                message: e.to_string(),
+            })
+        })?;
+        Ok(self.with_output(output))
+    }
+}
+
+pub(crate) fn schema_value<T: JsonSchema>() -> JsonValue {
+    serde_json::to_value(schemars::schema_for!(T)).unwrap_or(JsonValue::Null)
+}
+
+/// Run the tool loop, then ask for a final answer matching `T`
+pub async fn generate_output<T, P, S>(config: GenerateConfig<P, S>) -> Result<AgentResponse<T>>
+where
+    T: DeserializeOwned + JsonSchema,
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    generate_text(config.output::<T>()).await?.parse_output()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_value_sources() {
+        let call = Message::assistant(AssistantContent::ToolCall {
+            tool_call: ToolCall {
+                id: "call_1".to_string(),
+                name: FINAL_ANSWER_TOOL.to_string(),
+                arguments: serde_json::json!({"city": "Paris"}),
+            },
+        });
+        let recorded = record_answer(call.clone());
+        assert_eq!(recorded.len(), 2);
+        assert!(matches!(recorded[1], Message::Tool { .. }));
+        assert_eq!(final_answer_call(&recorded[0]).unwrap().id, "call_1");
+
+        assert_eq!(
+            parse_json_text("```json\n{\"city\": \"Rome\"}\n```").ok(),
+            Some(serde_json::json!({"city": "Rome"}))
+        );
+        assert!(parse_json_text("Rome").is_err());
+    }
+}
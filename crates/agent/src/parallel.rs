@@ -0,0 +1,321 @@
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use ai_core::{
+    AgentError, AiError, Result,
+    provider::ChatTextGeneration,
+    types::{Message, Usage},
+};
+
+use crate::{
+    agent::{AgentResponse, GenerateConfig, generate_text},
+    output::generate_output,
+};
+
+/// Results of running several agent configurations concurrently
+#[derive(Debug, Clone)]
+pub struct FanOutResponse {
+    /// One result per configuration, in the order they were given
+    pub responses: Vec<Result<AgentResponse>>,
+    /// Usage summed over all successful runs
+    pub total_usage: Option<Usage>,
+}
+
+impl FanOutResponse {
+    /// Successful responses with their original index
+    pub fn successful(&self) -> impl Iterator<Item = (usize, &AgentResponse)> {
+        self.responses
+            .iter()
+            .enumerate()
+            .filter_map(|(index, response)| response.as_ref().ok().map(|r| (index, r)))
+    }
+
+    /// Final text of every successful run with its original index
+    pub fn texts(&self) -> Vec<(usize, String)> {
+        self.successful()
+            .map(|(index, response)| (index, response.text()))
+            .collect()
+    }
+
+    /// Fail with the first error if no run succeeded
+    fn ensure_any_success(&self) -> Result<()> {
+        if self.successful().next().is_some() {
+            return Ok(());
+        }
+        match self.responses.iter().find_map(|r| r.as_ref().err()) {
+            Some(e) => Err(e.clone()),
+            None => Err(AiError::Agent(AgentError::StateError {
+                message: "fan-out needs at least one configuration".to_string(),
+            })),
+        }
+    }
+}
+
+/// Aggregated result of a fan-out followed by a reducing or judging run
+#[derive(Debug, Clone)]
+pub struct AggregateResponse {
+    pub candidates: FanOutResponse,
+    /// Reducer output for `map_reduce`, or the chosen candidate for `best_of_n`
+    pub result: AgentResponse,
+    /// Index into `candidates.responses` of the chosen candidate for `best_of_n`
+    pub selected: Option<usize>,
+    /// Usage of all candidate runs plus the aggregating run
+    pub total_usage: Option<Usage>,
+}
+
+fn add_usage(total: Option<Usage>, usage: Option<&Usage>) -> Option<Usage> {
+    match (total, usage) {
+        (Some(total), Some(usage)) => Some(Usage {
+            prompt_tokens: total.prompt_tokens + usage.prompt_tokens,
+            completion_tokens: total.completion_tokens + usage.completion_tokens,
+            total_tokens: total.total_tokens + usage.total_tokens,
+        }),
+        (total, None) => total,
+        (None, Some(usage)) => Some(usage.clone()),
+    }
+}
+
+/// Run several configurations concurrently, at most `concurrency` at a time
+pub async fn generate_all<P, S>(
+    configs: Vec<GenerateConfig<P, S>>,
+    concurrency: usize,
+) -> FanOutResponse
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    let responses: Vec<Result<AgentResponse>> = futures::stream::iter(configs)
+        .map(generate_text)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let total_usage = responses
+        .iter()
+        .filter_map(|response| response.as_ref().ok())
+        .fold(None, |total, response| {
+            add_usage(total, response.total_usage.as_ref())
+        });
+
+    FanOutResponse {
+        responses,
+        total_usage,
+    }
+}
+
+/// Render candidate answers for an aggregating prompt, numbered from 1
+fn render_candidates(candidates: &FanOutResponse) -> String {
+    candidates
+        .texts()
+        .iter()
+        .map(|(index, text)| format!("[{}]\n{}", index + 1, text))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Fan out, then combine all successful answers with a reducer run
+///
+/// The reducer receives `instructions` followed by the numbered answers as a user message.
+pub async fn map_reduce<P, S, RP, RS>(
+    configs: Vec<GenerateConfig<P, S>>,
+    concurrency: usize,
+    reducer: GenerateConfig<RP, RS>,
+    instructions: &str,
+) -> Result<AggregateResponse>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+    RP: ChatTextGeneration,
+    RS: Clone + Send + Sync + 'static,
+{
+    let candidates = generate_all(configs, concurrency).await;
+    candidates.ensure_any_success()?;
+
+    let mut messages = reducer.messages.clone();
+    messages.push(Message::user(format!(
+        "{}\n\n{}",
+        instructions,
+        render_candidates(&candidates)
+    )));
+    let result = generate_text(reducer.messages(messages)).await?;
+
+    let total_usage = add_usage(candidates.total_usage.clone(), result.total_usage.as_ref());
+    Ok(AggregateResponse {
+        candidates,
+        result,
+        selected: None,
+        total_usage,
+    })
+}
+
+/// The judge's structured reply in `best_of_n`
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Verdict {
+    /// Number of the best candidate, as shown in brackets
+    candidate: usize,
+}
+
+/// Fan out, then let a judge run pick the best answer
+///
+/// The judge hands in the number of the best candidate as a structured answer, see
+/// `generate_output`; a number that does not match a successful candidate is an error.
+pub async fn best_of_n<P, S, JP, JS>(
+    configs: Vec<GenerateConfig<P, S>>,
+    concurrency: usize,
+    judge: GenerateConfig<JP, JS>,
+    criteria: &str,
+) -> Result<AggregateResponse>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+    JP: ChatTextGeneration,
+    JS: Clone + Send + Sync + 'static,
+{
+    let candidates = generate_all(configs, concurrency).await;
+    candidates.ensure_any_success()?;
+
+    let mut messages = judge.messages.clone();
+    messages.push(Message::user(format!(
+        "{}\n\nPick the number of the best candidate.\n\n{}",
+        criteria,
+        render_candidates(&candidates)
+    )));
+    let verdict = generate_output::<Verdict, _, _>(judge.messages(messages)).await?;
+
+    let number = verdict.output.candidate;
+    let result = match number
+        .checked_sub(1)
+        .and_then(|index| candidates.responses.get(index))
+    {
+        Some(Ok(response)) => response.clone(),
+        _ => {
+            return Err(AiError::Agent(AgentError::InvalidOutput {
+                message: format!(
+                    "judge selected {}, which is not a successful candidate",
+                    number
+                ),
+            }));
+        }
+    };
+    let selected = number - 1;
+    let total_usage = add_usage(candidates.total_usage.clone(), verdict.total_usage.as_ref());
+    Ok(AggregateResponse {
+        candidates,
+        result,
+        selected: Some(selected),
+        total_usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ai_core::types::{FinishReason, Message, UserContent};
+    use ai_test_utils::MockProvider;
+
+    use super::*;
+    use crate::agent::MaxSteps;
+
+    fn candidate(provider: MockProvider) -> GenerateConfig<MockProvider> {
+        GenerateConfig::new(provider)
+            .messages(vec![Message::user("Name a colour")])
+            .run_until(MaxSteps::new(0))
+    }
+
+    fn unavailable() -> AiError {
+        AiError::Agent(AgentError::StateError {
+            message: "candidate unavailable".to_string(),
+        })
+    }
+
+    fn prompt_text(provider: &MockProvider) -> String {
+        match provider.requests()[0].messages.last() {
+            Some(Message::User { content, .. }) => content
+                .iter()
+                .filter_map(|part| match part {
+                    UserContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            other => panic!("expected a user prompt, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_combines_successful_answers() {
+        let configs = vec![
+            candidate(MockProvider::new().with_usage(3, 1).text("Red")),
+            candidate(MockProvider::new().error(unavailable())),
+            candidate(MockProvider::new().with_usage(3, 1).text("Blue")),
+        ];
+        let reducer = MockProvider::new().with_usage(10, 2).text("Red and blue");
+        let aggregate = map_reduce(configs, 2, candidate(reducer.clone()), "Combine these")
+            .await
+            .unwrap();
+
+        assert_eq!(aggregate.result.text(), "Red and blue");
+        assert_eq!(aggregate.selected, None);
+        assert_eq!(aggregate.candidates.texts().len(), 2);
+        let prompt = prompt_text(&reducer);
+        assert!(prompt.contains("Combine these"));
+        assert!(prompt.contains("[1]\nRed"));
+        assert!(prompt.contains("[3]\nBlue"));
+        assert_eq!(aggregate.total_usage.unwrap().total_tokens, 20);
+    }
+
+    #[tokio::test]
+    async fn test_best_of_n_returns_the_judged_candidate() {
+        let configs = || {
+            vec![
+                candidate(MockProvider::new().text("Red")),
+                candidate(MockProvider::new().error(unavailable())),
+                candidate(MockProvider::new().text("Blue")),
+            ]
+        };
+        let judge = MockProvider::new()
+            .text("Blue is the better answer")
+            .tool_call("final_answer", serde_json::json!({ "candidate": 3 }));
+        let aggregate = best_of_n(configs(), 3, candidate(judge), "Pick the calmest")
+            .await
+            .unwrap();
+        assert_eq!(aggregate.selected, Some(2));
+        assert_eq!(aggregate.result.text(), "Blue");
+        assert_eq!(aggregate.result.finish_reason, FinishReason::Stop);
+
+        // Numbers of failed or missing candidates are rejected, not searched past
+        for number in [0, 2, 4] {
+            let judge = MockProvider::new()
+                .text("Hmm")
+                .tool_call("final_answer", serde_json::json!({ "candidate": number }));
+            let error = best_of_n(configs(), 3, candidate(judge), "Pick the calmest")
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                AiError::Agent(AgentError::InvalidOutput { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_fails_when_every_candidate_failed() {
+        let configs = || {
+            vec![
+                candidate(MockProvider::new().error(unavailable())),
+                candidate(MockProvider::new().error(unavailable())),
+            ]
+        };
+        let judge = MockProvider::new().text("unused");
+        let error = best_of_n(configs(), 2, candidate(judge.clone()), "Pick one")
+            .await
+            .unwrap_err();
+        assert_eq!(error, unavailable());
+        assert_eq!(judge.remaining(), 1);
+
+        let error = map_reduce(configs(), 2, candidate(judge.clone()), "Combine")
+            .await
+            .unwrap_err();
+        assert_eq!(error, unavailable());
+        assert_eq!(judge.remaining(), 1);
+    }
+}
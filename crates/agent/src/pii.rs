@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use ai_core::{AiError, Result, ValidationError};
+use async_trait::async_trait;
+use regex::Regex;
+
+use crate::{
+    audit::Redactor,
+    guardrails::{Guardrail, GuardrailAction},
+};
+
+const EMAIL_PATTERN: &str = r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b";
+const CREDIT_CARD_PATTERN: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\b\d{3})[ .-]?\d{3}[ .-]?\d{4}\b";
+
+/// What a `PiiGuardrail` does when it finds PII
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiMode {
+    /// Replace each match with a `[REDACTED_<LABEL>]` placeholder
+    Redact,
+    /// Leave the text alone and record match counts per label in the message metadata
+    Flag,
+    /// Stop the run
+    Block,
+}
+
+#[derive(Debug, Clone)]
+struct PiiPattern {
+    label: String,
+    regex: Regex,
+    /// Only count matches passing a Luhn checksum
+    luhn: bool,
+}
+
+/// Guardrail detecting emails, phone numbers, credit card numbers and custom patterns
+///
+/// Credit card candidates must pass a Luhn check, which keeps order numbers and the
+/// like from being redacted. Patterns are applied in order, so a custom pattern added
+/// with `pattern` runs after the built-in ones.
+#[derive(Debug, Clone)]
+pub struct PiiGuardrail {
+    patterns: Vec<PiiPattern>,
+    mode: PiiMode,
+    inputs: bool,
+    outputs: bool,
+}
+
+impl Default for PiiGuardrail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PiiGuardrail {
+    /// Redact emails, credit card numbers and phone numbers in input and output
+    pub fn new() -> Self {
+        Self::empty()
+            .builtin("email", EMAIL_PATTERN, false)
+            .builtin("credit_card", CREDIT_CARD_PATTERN, true)
+            .builtin("phone", PHONE_PATTERN, false)
+    }
+
+    /// Guardrail without built-in patterns, for use with custom patterns only
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+            mode: PiiMode::Redact,
+            inputs: true,
+            outputs: true,
+        }
+    }
+
+    fn builtin(mut self, label: &str, pattern: &str, luhn: bool) -> Self {
+        self.patterns.push(PiiPattern {
+            label: label.to_string(),
+            regex: Regex::new(pattern).expect("built-in PII pattern is valid"),
+            luhn,
+        });
+        self
+    }
+
+    /// Detect matches of a custom regular expression, reported under `label`
+    pub fn pattern(mut self, label: impl Into<String>, pattern: &str) -> Result<Self> {
+        let label = label.into();
+        let regex = Regex::new(pattern).map_err(|e| {
+            AiError::Validation(ValidationError::InvalidValue {
+                field: format!("pii pattern '{}'", label),
+                message: e.to_string(),
+            })
+        })?;
+        self.patterns.push(PiiPattern {
+            label,
+            regex,
+            luhn: false,
+        });
+        Ok(self)
+    }
+
+    pub fn mode(mut self, mode: PiiMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Whether to check user input (default true)
+    pub fn inputs(mut self, enabled: bool) -> Self {
+        self.inputs = enabled;
+        self
+    }
+
+    /// Whether to check assistant output (default true)
+    pub fn outputs(mut self, enabled: bool) -> Self {
+        self.outputs = enabled;
+        self
+    }
+
+    /// Count matches per label and produce the redacted text
+    fn scan(&self, text: &str) -> (String, BTreeMap<String, usize>) {
+        let mut redacted = text.to_string();
+        let mut counts = BTreeMap::new();
+        for pattern in &self.patterns {
+            let placeholder = format!("[REDACTED_{}]", pattern.label.to_uppercase());
+            redacted = pattern
+                .regex
+                .replace_all(&redacted, |captures: &regex::Captures<'_>| {
+                    let found = &captures[0];
+                    if pattern.luhn && !luhn_valid(found) {
+                        return found.to_string();
+                    }
+                    *counts.entry(pattern.label.clone()).or_insert(0) += 1;
+                    placeholder.clone()
+                })
+                .into_owned();
+        }
+        (redacted, counts)
+    }
+
+    fn check(&self, text: &str) -> GuardrailAction {
+        let (redacted, counts) = self.scan(text);
+        if counts.is_empty() {
+            return GuardrailAction::Allow;
+        }
+        match self.mode {
+            PiiMode::Redact => GuardrailAction::Rewrite(redacted),
+            PiiMode::Flag => GuardrailAction::Annotate(serde_json::json!(counts)),
+            PiiMode::Block => GuardrailAction::Block {
+                reason: format!(
+                    "detected {}",
+                    counts.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            },
+        }
+    }
+}
+
+/// Luhn checksum over the digits of a candidate card number
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, &digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[async_trait]
+impl Guardrail for PiiGuardrail {
+    fn name(&self) -> &str {
+        "pii"
+    }
+
+    async fn check_input(&self, text: &str) -> GuardrailAction {
+        if !self.inputs {
+            return GuardrailAction::Allow;
+        }
+        self.check(text)
+    }
+
+    async fn check_output(&self, text: &str) -> GuardrailAction {
+        if !self.outputs {
+            return GuardrailAction::Allow;
+        }
+        self.check(text)
+    }
+}
+
+/// Replaces matches with placeholders regardless of `mode`
+impl Redactor for PiiGuardrail {
+    fn redact(&self, text: &str) -> String {
+        self.scan(text).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redacts_builtin_patterns() {
+        let guardrail = PiiGuardrail::new();
+        let action = guardrail
+            .check_input(
+                "Mail jane.doe@example.com or call +1 (555) 123-4567, card 4111 1111 1111 1111, order 1234567890123",
+            )
+            .await;
+        assert_eq!(
+            action,
+            GuardrailAction::Rewrite(
+                "Mail [REDACTED_EMAIL] or call [REDACTED_PHONE], card [REDACTED_CREDIT_CARD], order 1234567890123"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            guardrail.check_output("nothing to see").await,
+            GuardrailAction::Allow
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_pattern_flag_and_block() {
+        let guardrail = PiiGuardrail::empty()
+            .pattern("ssn", r"\b\d{3}-\d{2}-\d{4}\b")
+            .unwrap()
+            .mode(PiiMode::Flag);
+        assert_eq!(
+            guardrail.check_input("ssn 123-45-6789").await,
+            GuardrailAction::Annotate(serde_json::json!({"ssn": 1}))
+        );
+
+        let guardrail = guardrail.mode(PiiMode::Block).outputs(false);
+        assert!(matches!(
+            guardrail.check_input("ssn 123-45-6789").await,
+            GuardrailAction::Block { .. }
+        ));
+        assert_eq!(
+            guardrail.check_output("ssn 123-45-6789").await,
+            GuardrailAction::Allow
+        );
+
+        assert!(PiiGuardrail::empty().pattern("bad", "(").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fmt::Debug, sync::Arc};
+
+use ai_core::{
+    AiError, Result, ValidationError, provider::ChatTextGeneration, tokenizer::Tokenizer,
+    types::ChatRequest,
+};
+
+use crate::context::{ContextStrategy, TokenBudget};
+
+/// What to do with a request that does not fit the context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Fail with `ValidationError::PromptTooLarge` before calling the provider
+    Error,
+    /// Drop the oldest messages, as `TokenBudget` does, until the request fits
+    Truncate,
+}
+
+/// Checks each request fits the model's context window before it is sent
+///
+/// Prompt tokens are estimated with `tokenizer`, and the request's `max_tokens` is
+/// reserved for the answer. Models without a known context window are not checked
+/// unless one is set with `context_window`.
+#[derive(Clone)]
+pub struct Preflight {
+    tokenizer: Arc<dyn Tokenizer>,
+    overflow: Overflow,
+    context_window: Option<u32>,
+}
+
+impl Debug for Preflight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Preflight")
+            .field("overflow", &self.overflow)
+            .field("context_window", &self.context_window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Preflight {
+    /// Fail requests that do not fit
+    pub fn new(tokenizer: impl Tokenizer + 'static) -> Self {
+        Self {
+            tokenizer: Arc::new(tokenizer),
+            overflow: Overflow::Error,
+            context_window: None,
+        }
+    }
+
+    /// Trim the oldest messages from requests that do not fit instead of failing
+    pub fn truncate(mut self) -> Self {
+        self.overflow = Overflow::Truncate;
+        self
+    }
+
+    /// Check against `tokens` instead of the provider's reported context window
+    pub fn context_window(mut self, tokens: u32) -> Self {
+        self.context_window = Some(tokens);
+        self
+    }
+
+    /// Check `request` for `provider`, trimming it when configured to
+    pub fn check<P: ChatTextGeneration + ?Sized>(
+        &self,
+        provider: &P,
+        request: &mut ChatRequest,
+    ) -> Result<()> {
+        let Some(context_window) = self.context_window.or_else(|| provider.context_window()) else {
+            return Ok(());
+        };
+        let max_output_tokens = request.settings.max_tokens.unwrap_or(0);
+        let available = context_window.saturating_sub(max_output_tokens) as usize;
+
+        let mut prompt_tokens = self.tokenizer.count_request_tokens(request);
+        if prompt_tokens > available && self.overflow == Overflow::Truncate {
+            let tools = request.tools.as_deref().unwrap_or_default();
+            let budget = available.saturating_sub(self.tokenizer.count_tool_tokens(tools));
+            let messages = std::mem::take(&mut request.messages);
+            request.messages = TokenBudget::new(budget, self.tokenizer.clone()).apply(messages);
+            prompt_tokens = self.tokenizer.count_request_tokens(request);
+        }
+        if prompt_tokens > available {
+            return Err(AiError::Validation(ValidationError::PromptTooLarge {
+                prompt_tokens: prompt_tokens as u32,
+                max_output_tokens,
+                context_window,
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{tokenizer::EstimatingTokenizer, types::*};
+    use ai_test_utils::MockProvider;
+
+    fn request() -> ChatRequest {
+        ChatRequest::new()
+            .system("Be brief.")
+            .user("x".repeat(200))
+            .assistant("ok")
+            .user("What now?")
+            .max_tokens(50)
+    }
+
+    #[test]
+    fn test_oversized_prompts_fail_or_are_truncated() {
+        let small = MockProvider::new().with_context_window(100);
+        let mut oversized = request();
+        match Preflight::new(EstimatingTokenizer).check(&small, &mut oversized) {
+            Err(AiError::Validation(ValidationError::PromptTooLarge {
+                max_output_tokens,
+                context_window,
+                ..
+            })) => assert_eq!((max_output_tokens, context_window), (50, 100)),
+            other => panic!("expected the prompt to be rejected, got {:?}", other),
+        }
+
+        let mut truncated = request();
+        Preflight::new(EstimatingTokenizer)
+            .truncate()
+            .check(&small, &mut truncated)
+            .unwrap();
+        assert_eq!(
+            truncated.messages,
+            vec![Message::system("Be brief."), Message::user("What now?")]
+        );
+
+        let mut unchecked = request();
+        Preflight::new(EstimatingTokenizer)
+            .context_window(10_000)
+            .check(&small, &mut unchecked)
+            .unwrap();
+        assert_eq!(unchecked.messages.len(), 4);
+    }
+}
@@ -0,0 +1,287 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use ai_core::{
+    Result,
+    provider::{ChatTextGeneration, EmbeddingGeneration},
+    types::*,
+};
+use ai_memory::{MetadataFilter, ScoredRecord, VectorRecord, VectorStore};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::memory::embed_texts;
+
+/// Metadata key under which citations are stored on the answer message
+pub const CITATIONS_METADATA_KEY: &str = "citations";
+
+const DEFAULT_INSTRUCTIONS: &str = "Answer the question using only the numbered sources \
+below. Cite the sources you use as [n]. If the sources do not contain the answer, say so.";
+
+/// Reorders retrieved documents by relevance to the query, e.g. with a cross-encoder
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    async fn rerank(&self, query: &str, documents: Vec<ScoredRecord>) -> Result<Vec<ScoredRecord>>;
+}
+
+/// A document to index for retrieval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, JsonValue>,
+}
+
+impl Document {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: JsonValue) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+}
+
+/// A source given to the model, numbered as it appeared in the prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    /// 1-based number the model cites as `[n]`
+    pub index: usize,
+    pub id: String,
+    pub text: String,
+    pub score: f32,
+    pub metadata: HashMap<String, JsonValue>,
+    /// Whether the answer cites this source
+    pub cited: bool,
+}
+
+/// Answer produced by `rag_generate`
+#[derive(Debug, Clone)]
+pub struct RagResponse {
+    pub response: ChatResponse,
+    /// Every retrieved source, in prompt order
+    pub citations: Vec<Citation>,
+}
+
+impl RagResponse {
+    pub fn text(&self) -> String {
+        message_text(&self.response.message)
+    }
+
+    /// Sources the answer actually cites
+    pub fn cited(&self) -> impl Iterator<Item = &Citation> {
+        self.citations.iter().filter(|citation| citation.cited)
+    }
+}
+
+/// Configuration for retrieve-then-generate
+#[derive(Clone)]
+pub struct RagConfig<P>
+where
+    P: ChatTextGeneration,
+{
+    pub provider: P,
+    pub settings: GenerationSettings,
+    pub instructions: String,
+    /// Number of sources given to the model
+    pub top_k: usize,
+    /// Number of documents retrieved before reranking; defaults to `top_k`
+    pub candidates: Option<usize>,
+    /// Only retrieve documents whose metadata matches
+    pub filter: Option<MetadataFilter>,
+    embedder: Arc<dyn EmbeddingGeneration>,
+    store: Arc<dyn VectorStore>,
+    reranker: Option<Arc<dyn Reranker>>,
+}
+
+impl<P> Debug for RagConfig<P>
+where
+    P: ChatTextGeneration,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RagConfig")
+            .field("provider", &self.provider.name())
+            .field("embedder", &self.embedder.model())
+            .field("settings", &self.settings)
+            .field("top_k", &self.top_k)
+            .field("candidates", &self.candidates)
+            .field("filter", &self.filter)
+            .field("reranker", &self.reranker.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> RagConfig<P>
+where
+    P: ChatTextGeneration,
+{
+    pub fn new(
+        provider: P,
+        embedder: impl EmbeddingGeneration + 'static,
+        store: impl VectorStore + 'static,
+    ) -> Self {
+        Self {
+            provider,
+            settings: GenerationSettings::default(),
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            top_k: 4,
+            candidates: None,
+            filter: None,
+            embedder: Arc::new(embedder),
+            store: Arc::new(store),
+            reranker: None,
+        }
+    }
+
+    pub fn settings(mut self, settings: GenerationSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn filter(mut self, filter: MetadataFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Retrieve `candidates` documents and let the reranker pick the best `top_k`
+    pub fn reranker(mut self, reranker: impl Reranker + 'static, candidates: usize) -> Self {
+        self.reranker = Some(Arc::new(reranker));
+        self.candidates = Some(candidates);
+        self
+    }
+
+    /// Embed documents and add them to the store
+    pub async fn index(&self, documents: Vec<Document>) -> Result<()> {
+        if documents.is_empty() {
+            return Ok(());
+        }
+        let texts = documents.iter().map(|doc| doc.text.clone()).collect();
+        let vectors = embed_texts(&*self.embedder, texts).await?;
+        let records = documents
+            .into_iter()
+            .zip(vectors)
+            .map(|(doc, vector)| VectorRecord {
+                id: doc.id,
+                vector,
+                text: doc.text,
+                metadata: doc.metadata,
+            })
+            .collect();
+        self.store.upsert(records).await
+    }
+
+    /// Sources for `query`, best first
+    pub async fn retrieve(&self, query: &str) -> Result<Vec<ScoredRecord>> {
+        let vector = embed_texts(&*self.embedder, vec![query.to_string()])
+            .await?
+            .remove(0);
+        let candidates = self.candidates.unwrap_or(self.top_k).max(self.top_k);
+        let mut documents = self
+            .store
+            .query(&vector, candidates, self.filter.as_ref())
+            .await?;
+        if let Some(reranker) = &self.reranker {
+            documents = reranker.rerank(query, documents).await?;
+        }
+        documents.truncate(self.top_k);
+        Ok(documents)
+    }
+}
+
+fn message_text(message: &Message) -> String {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+fn render_sources(instructions: &str, sources: &[ScoredRecord]) -> String {
+    let mut prompt = instructions.to_string();
+    for (index, source) in sources.iter().enumerate() {
+        prompt.push_str(&format!("\n\n[{}] {}", index + 1, source.record.text));
+    }
+    prompt
+}
+
+/// Retrieve sources for `query`, answer from them and report which sources were cited
+pub async fn rag_generate<P>(query: &str, config: &RagConfig<P>) -> Result<RagResponse>
+where
+    P: ChatTextGeneration,
+{
+    let sources = config.retrieve(query).await?;
+    let mut request = ChatRequest::new()
+        .system(render_sources(&config.instructions, &sources))
+        .user(query);
+    request.settings = config.settings.clone();
+    let mut response = config.provider.generate(request).await?;
+
+    let answer = message_text(&response.message);
+    let citations: Vec<Citation> = sources
+        .into_iter()
+        .enumerate()
+        .map(|(index, source)| Citation {
+            index: index + 1,
+            cited: answer.contains(&format!("[{}]", index + 1)),
+            id: source.record.id,
+            text: source.record.text,
+            score: source.score,
+            metadata: source.record.metadata,
+        })
+        .collect();
+
+    if let Message::Assistant { metadata, .. } = &mut response.message {
+        metadata.get_or_insert_with(HashMap::new).insert(
+            CITATIONS_METADATA_KEY.to_string(),
+            serde_json::to_value(&citations)?,
+        );
+    }
+    Ok(RagResponse {
+        response,
+        citations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sources_numbers_from_one() {
+        let sources = vec![
+            ScoredRecord {
+                record: VectorRecord::new("a", vec![], "Paris is in France."),
+                score: 0.9,
+            },
+            ScoredRecord {
+                record: VectorRecord::new("b", vec![], "Rome is in Italy."),
+                score: 0.5,
+            },
+        ];
+        assert_eq!(
+            render_sources("Use the sources.", &sources),
+            "Use the sources.\n\n[1] Paris is in France.\n\n[2] Rome is in Italy."
+        );
+    }
+}
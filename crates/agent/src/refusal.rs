@@ -0,0 +1,144 @@
+use std::{fmt::Debug, sync::Arc};
+
+use ai_core::{
+    AgentError, AiError,
+    response_metadata::{Refusal, RefusalKind},
+    types::{AssistantContent, FinishReason, Message},
+};
+
+/// What to do with a step that finished with `FinishReason::ContentFilter`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefusalAction {
+    /// Finish the run with the refused response
+    Stop,
+    /// Drop the refused response and ask again with `prompt` added as a user message
+    Retry { prompt: String },
+    /// Fail the run with `AgentError::Refused`
+    Fail,
+}
+
+/// Decides about a refusal, given the number of refusals already retried in the run
+pub type RefusalCallback = Arc<dyn Fn(&Refusal, u32) -> RefusalAction + Send + Sync>;
+
+/// How a run handles responses the provider refused or filtered
+///
+/// The refusal comes from `ChatResponse::refusal`, or from the finish reason of a streamed
+/// step. Streamed text has already been yielded when the policy runs, so a retry only
+/// keeps the refused answer out of the history.
+#[derive(Clone, Default)]
+pub enum RefusalPolicy {
+    /// Finish the run with the refused response, as for any other final answer
+    #[default]
+    Stop,
+    /// Fail the run with `AgentError::Refused`
+    Fail,
+    /// Ask again with `prompt` added as a user message, up to `max_retries` times, then
+    /// stop
+    Retry { max_retries: u32, prompt: String },
+    /// Let a callback decide each time
+    Callback(RefusalCallback),
+}
+
+impl Debug for RefusalPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stop => f.write_str("Stop"),
+            Self::Fail => f.write_str("Fail"),
+            Self::Retry {
+                max_retries,
+                prompt,
+            } => f
+                .debug_struct("Retry")
+                .field("max_retries", max_retries)
+                .field("prompt", prompt)
+                .finish(),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+impl RefusalPolicy {
+    pub fn retry(max_retries: u32, prompt: impl Into<String>) -> Self {
+        Self::Retry {
+            max_retries,
+            prompt: prompt.into(),
+        }
+    }
+
+    pub fn callback(
+        callback: impl Fn(&Refusal, u32) -> RefusalAction + Send + Sync + 'static,
+    ) -> Self {
+        Self::Callback(Arc::new(callback))
+    }
+
+    /// The action for `refusal` after `retries` earlier refusals were retried
+    pub fn decide(&self, refusal: &Refusal, retries: u32) -> RefusalAction {
+        match self {
+            Self::Stop => RefusalAction::Stop,
+            Self::Fail => RefusalAction::Fail,
+            Self::Retry {
+                max_retries,
+                prompt,
+            } if retries < *max_retries => RefusalAction::Retry {
+                prompt: prompt.clone(),
+            },
+            Self::Retry { .. } => RefusalAction::Stop,
+            Self::Callback(callback) => callback(refusal, retries),
+        }
+    }
+}
+
+pub(crate) fn refused(step: u32, refusal: Refusal) -> AiError {
+    AiError::Agent(AgentError::Refused { step, refusal })
+}
+
+/// Refusal of a streamed step; streams carry no metadata, so any text is the message
+pub(crate) fn streamed_refusal(finish_reason: &FinishReason, message: &Message) -> Option<Refusal> {
+    if *finish_reason != FinishReason::ContentFilter {
+        return None;
+    }
+    let text: String = match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    };
+    let refusal = Refusal::new(RefusalKind::Filtered);
+    Some(if text.is_empty() {
+        refusal
+    } else {
+        refusal.message(text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions() {
+        let refusal = Refusal::new(RefusalKind::Filtered);
+        let retry = RefusalPolicy::retry(1, "Please answer within policy.");
+        assert_eq!(
+            retry.decide(&refusal, 0),
+            RefusalAction::Retry {
+                prompt: "Please answer within policy.".to_string()
+            }
+        );
+        assert_eq!(retry.decide(&refusal, 1), RefusalAction::Stop);
+
+        let callback = RefusalPolicy::callback(|refusal, _| match refusal.kind {
+            RefusalKind::Filtered => RefusalAction::Fail,
+            RefusalKind::Declined => RefusalAction::Stop,
+        });
+        assert_eq!(callback.decide(&refusal, 0), RefusalAction::Fail);
+        assert_eq!(
+            RefusalPolicy::default().decide(&refusal, 0),
+            RefusalAction::Stop
+        );
+    }
+}
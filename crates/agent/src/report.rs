@@ -0,0 +1,225 @@
+use ai_core::{
+    Result,
+    models::ModelInfo,
+    types::{FinishReason, ToolCall, ToolResult, Usage},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{AgentResponse, StepInfo};
+
+/// A tool call made during a step, with its outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallReport {
+    pub id: String,
+    pub name: String,
+    pub arguments: JsonValue,
+    /// `None` when the call was not executed, e.g. the run paused on it
+    pub result: Option<JsonValue>,
+    pub is_error: bool,
+}
+
+/// One step of a run report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepReport {
+    pub step: u32,
+    pub response_id: String,
+    pub finish_reason: FinishReason,
+    /// Time since the start of the run, summed from the steps before this one
+    pub offset_ms: u64,
+    pub latency_ms: u64,
+    pub usage: Option<Usage>,
+    /// USD, when the model's pricing is known
+    pub cost: Option<f64>,
+    pub tool_calls: Vec<ToolCallReport>,
+    /// Results of failed tool calls
+    pub errors: Vec<JsonValue>,
+}
+
+/// Summary of an agent run that can be archived as JSON
+///
+/// Built from an `AgentResponse` with `from_response`; pass the model's `ModelInfo` to
+/// `priced` to fill in costs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRunReport {
+    pub generated_at: DateTime<Utc>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub finish_reason: FinishReason,
+    /// Whether the run paused on tool calls without a handler
+    pub paused: bool,
+    pub duration_ms: u64,
+    pub usage: Option<Usage>,
+    pub cost: Option<f64>,
+    pub tool_calls: usize,
+    pub errors: usize,
+    pub steps: Vec<StepReport>,
+}
+
+impl AgentRunReport {
+    pub fn from_response<T>(response: &AgentResponse<T>) -> Self {
+        let mut offset_ms = 0;
+        let steps: Vec<StepReport> = response
+            .steps
+            .iter()
+            .map(|info| {
+                let step = step_report(info, offset_ms);
+                offset_ms += step.latency_ms;
+                step
+            })
+            .collect();
+        Self {
+            generated_at: Utc::now(),
+            provider: None,
+            model: None,
+            finish_reason: response.finish_reason.clone(),
+            paused: response.checkpoint.is_some(),
+            duration_ms: offset_ms,
+            usage: response.total_usage.clone(),
+            cost: None,
+            tool_calls: steps.iter().map(|step| step.tool_calls.len()).sum(),
+            errors: steps.iter().map(|step| step.errors.len()).sum(),
+            steps,
+        }
+    }
+
+    /// Record the model and price every step with its pricing
+    pub fn priced(mut self, model: &ModelInfo) -> Self {
+        self.provider = Some(model.provider.clone());
+        self.model = Some(model.model.clone());
+        for step in &mut self.steps {
+            step.cost = step.usage.as_ref().and_then(|usage| model.cost(usage));
+        }
+        self.cost = self.usage.as_ref().and_then(|usage| model.cost(usage));
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+fn step_report(info: &StepInfo, offset_ms: u64) -> StepReport {
+    let result_of = |call: &ToolCall| -> Option<&ToolResult> {
+        info.tool_results
+            .iter()
+            .find(|result| result.tool_call_id == call.id)
+    };
+    StepReport {
+        step: info.step,
+        response_id: info.response_id.clone(),
+        finish_reason: info.finish_reason.clone(),
+        offset_ms,
+        latency_ms: info.duration.as_millis() as u64,
+        usage: info.usage.clone(),
+        cost: None,
+        tool_calls: info
+            .tool_calls
+            .iter()
+            .map(|call| {
+                let result = result_of(call);
+                ToolCallReport {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                    result: result.map(|result| result.result.clone()),
+                    is_error: result.is_some_and(|result| result.is_error),
+                }
+            })
+            .collect(),
+        errors: info
+            .tool_results
+            .iter()
+            .filter(|result| result.is_error)
+            .map(|result| result.result.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{scratchpad::Scratchpad, types::Message};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn usage(prompt: u32, completion: u32) -> Usage {
+        Usage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt + completion,
+        }
+    }
+
+    #[test]
+    fn test_report_from_response() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "lookup".to_string(),
+            arguments: json!({ "q": "rust" }),
+        };
+        let response = AgentResponse {
+            messages: Vec::new(),
+            final_message: Message::assistant("done"),
+            steps: vec![
+                StepInfo {
+                    step: 0,
+                    response_id: "resp_1".to_string(),
+                    finish_reason: FinishReason::ToolCalls,
+                    usage: Some(usage(1_000, 100)),
+                    tool_calls: vec![call],
+                    tool_results: vec![ToolResult {
+                        tool_call_id: "call_1".to_string(),
+                        result: json!("not found"),
+                        is_error: true,
+                        content: Vec::new(),
+                    }],
+                    duration: Duration::from_millis(250),
+                },
+                StepInfo {
+                    step: 1,
+                    response_id: "resp_2".to_string(),
+                    finish_reason: FinishReason::Stop,
+                    usage: Some(usage(2_000, 200)),
+                    tool_calls: Vec::new(),
+                    tool_results: Vec::new(),
+                    duration: Duration::from_millis(100),
+                },
+            ],
+            finish_reason: FinishReason::Stop,
+            total_usage: Some(usage(3_000, 300)),
+            checkpoint: None,
+            scratchpad: Scratchpad::new(),
+            timed_out: false,
+            output: (),
+        };
+        let model = ModelInfo {
+            provider: "test".to_string(),
+            model: "test-1".to_string(),
+            context_window: 100_000,
+            max_output_tokens: 4_096,
+            supports_tools: true,
+            supports_vision: false,
+            supports_caching: false,
+            input_price: Some(1.0),
+            output_price: Some(10.0),
+        };
+        let report = AgentRunReport::from_response(&response).priced(&model);
+
+        assert_eq!(report.duration_ms, 350);
+        assert_eq!(report.steps[1].offset_ms, 250);
+        assert_eq!((report.tool_calls, report.errors), (1, 1));
+        assert!(report.steps[0].tool_calls[0].is_error);
+        assert_eq!(report.steps[0].errors, vec![json!("not found")]);
+        assert!((report.cost.unwrap() - 0.006).abs() < 1e-9);
+        assert!((report.steps[0].cost.unwrap() - 0.002).abs() < 1e-9);
+
+        let json = report.to_json().unwrap();
+        assert_eq!(AgentRunReport::from_json(&json).unwrap(), report);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{future::Future, time::Duration};
+
+use ai_core::{AiError, Result, platform};
+
+/// Backoff for provider requests that fail with a retryable error
+///
+/// Rate limits, 408, 429 and 5xx responses, connection failures and timeouts are retried
+/// (see `AiError::is_retryable`). The wait grows by `multiplier` after each attempt, and a
+/// longer `retry_after` from the provider is honoured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Wait before retry number `retry` (starting at 0) after `error`
+    pub fn delay(&self, retry: u32, error: &AiError) -> Duration {
+        // Grow in f64 so that long retry sequences saturate at `max_delay` instead of
+        // overflowing `Duration`
+        let factor = self.multiplier.max(1.0).powf(f64::from(retry));
+        let backoff = Duration::try_from_secs_f64(self.initial_delay.as_secs_f64() * factor)
+            .map_or(self.max_delay, |backoff| backoff.min(self.max_delay));
+        match error.retry_after() {
+            Some(retry_after) => backoff.max(retry_after),
+            None => backoff,
+        }
+    }
+}
+
+/// Run `request`, retrying retryable errors according to `policy`
+pub(crate) async fn with_retry<T, F, Fut>(policy: Option<&RetryPolicy>, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match request().await {
+            Err(e) if e.is_retryable() && policy.is_some_and(|p| retry < p.max_retries) => {
+                if let Some(policy) = policy {
+                    platform::sleep(policy.delay(retry, &e)).await;
+                }
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{NetworkError, ProviderError};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn overloaded() -> AiError {
+        AiError::Provider(ProviderError::Overloaded {
+            provider: "test".to_string(),
+            message: "Overloaded".to_string(),
+            request_id: None,
+        })
+    }
+
+    #[test]
+    fn test_delay_backs_off_and_honours_retry_after() {
+        let policy = RetryPolicy::new(5)
+            .initial_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(350));
+        assert_eq!(policy.delay(0, &overloaded()), Duration::from_millis(100));
+        assert_eq!(policy.delay(1, &overloaded()), Duration::from_millis(200));
+        assert_eq!(policy.delay(2, &overloaded()), Duration::from_millis(350));
+
+        let rate_limited = AiError::Provider(ProviderError::RateLimit {
+            provider: "test".to_string(),
+            retry_after: Some(Duration::from_secs(2)),
+            message: "Slow down".to_string(),
+            request_id: None,
+        });
+        assert_eq!(policy.delay(0, &rate_limited), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_delay_saturates_at_max_delay_for_long_retry_sequences() {
+        let policy = RetryPolicy::new(100).multiplier(10.0);
+        for retry in 0..policy.max_retries {
+            assert!(policy.delay(retry, &overloaded()) <= policy.max_delay);
+        }
+        assert_eq!(policy.delay(99, &overloaded()), policy.max_delay);
+        assert_eq!(policy.delay(u32::MAX, &overloaded()), policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_stops_on_success_or_permanent_error() {
+        let policy = RetryPolicy::new(2).initial_delay(Duration::ZERO);
+        let attempts = AtomicU32::new(0);
+        let result = with_retry(Some(&policy), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(overloaded()),
+                1 => Err(AiError::Network(NetworkError::Timeout {
+                    duration: Duration::from_secs(60),
+                })),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = with_retry(Some(&policy), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(AiError::Provider(ProviderError::Authentication {
+                provider: "test".to_string(),
+                message: "Bad key".to_string(),
+                request_id: None,
+            }))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = with_retry(None, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(overloaded())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ai_core::{AgentError, AiError, Result, ValidationError, provider::ChatTextGeneration};
+use tokio::{
+    sync::{Semaphore, mpsc},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+
+use crate::{
+    agent::{AgentResponse, GenerateConfig, generate_text},
+    hooks::HookFuture,
+};
+
+type JobFn = Arc<dyn Fn() -> HookFuture<Result<AgentResponse>> + Send + Sync>;
+type ResultCallback = Arc<dyn Fn(JobResult) -> HookFuture<()> + Send + Sync>;
+
+/// Named unit of work for an `AgentRunner`
+///
+/// A job builds a fresh run every time it executes, so the same job can be scheduled
+/// repeatedly.
+#[derive(Clone)]
+pub struct AgentJob {
+    name: String,
+    run: JobFn,
+}
+
+impl Debug for AgentJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentJob")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl AgentJob {
+    /// Job running `generate_text` on the configuration built by `config`
+    pub fn new<P, S, F>(name: impl Into<String>, config: F) -> Self
+    where
+        P: ChatTextGeneration + 'static,
+        S: Clone + Send + Sync + 'static,
+        F: Fn() -> GenerateConfig<P, S> + Send + Sync + 'static,
+    {
+        Self::from_fn(name, move || generate_text(config()))
+    }
+
+    /// Job running any future producing an agent response, e.g. `Agent::run`
+    pub fn from_fn<F, Fut>(name: impl Into<String>, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<AgentResponse>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            run: Arc::new(move || Box::pin(run())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Outcome of one job execution, passed to `on_result`
+#[derive(Debug, Clone)]
+pub struct JobResult {
+    pub job: String,
+    /// Time from acquiring a concurrency slot to the end of the run
+    pub duration: Duration,
+    pub result: Result<AgentResponse>,
+}
+
+/// Hosts agent jobs on tokio tasks, run once from a queue or repeatedly on a schedule
+///
+/// At most `concurrency` jobs run at a time; further jobs wait for a free slot. Every
+/// execution, including failures and timeouts, is reported to the `on_result` callback.
+/// Clones share the concurrency limit.
+#[derive(Clone)]
+pub struct AgentRunner {
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    timeout: Option<Duration>,
+    on_result: Option<ResultCallback>,
+}
+
+impl Debug for AgentRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentRunner")
+            .field("concurrency", &self.concurrency)
+            .field("timeout", &self.timeout)
+            .field("on_result", &self.on_result.is_some())
+            .finish()
+    }
+}
+
+impl AgentRunner {
+    pub fn new(concurrency: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            timeout: None,
+            on_result: None,
+        }
+    }
+
+    /// Abort runs taking longer than `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Called with the result of every job execution
+    pub fn on_result<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(JobResult) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_result = Some(Arc::new(move |result| Box::pin(callback(result))));
+        self
+    }
+
+    /// Run `job` once, waiting for a free slot first
+    pub async fn run(&self, job: &AgentJob) -> JobResult {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("runner semaphore is never closed");
+        self.execute(job).await
+    }
+
+    async fn execute(&self, job: &AgentJob) -> JobResult {
+        let started = Instant::now();
+        let result = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, (job.run)()).await {
+                Ok(result) => result,
+                Err(_) => Err(AiError::Agent(AgentError::Timeout {
+                    operation: format!("job '{}'", job.name),
+                    duration: timeout,
+                })),
+            },
+            None => (job.run)().await,
+        };
+        let result = JobResult {
+            job: job.name.clone(),
+            duration: started.elapsed(),
+            result,
+        };
+        if let Some(callback) = &self.on_result {
+            callback(result.clone()).await;
+        }
+        result
+    }
+
+    /// Run `job` once on a background task
+    pub fn spawn(&self, job: AgentJob) -> JoinHandle<JobResult> {
+        let runner = self.clone();
+        tokio::spawn(async move { runner.run(&job).await })
+    }
+
+    /// Run `job` every `period`, starting now
+    ///
+    /// A tick that comes due while the previous execution is still running is skipped,
+    /// so a job never overlaps with itself. Fails if `period` is zero.
+    pub fn schedule(&self, job: AgentJob, period: Duration) -> Result<ScheduleHandle> {
+        if period.is_zero() {
+            return Err(AiError::Validation(ValidationError::InvalidValue {
+                field: "period".to_string(),
+                message: "schedule period must be greater than zero".to_string(),
+            }));
+        }
+        let runner = self.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                runner.run(&job).await;
+            }
+        });
+        Ok(ScheduleHandle { task })
+    }
+
+    /// Start a queue whose jobs are each run once, in the order they are pushed
+    ///
+    /// Jobs start as soon as a slot is free, so up to `concurrency` of them run at once.
+    /// The queue stops when every `JobQueue` handle has been dropped.
+    pub fn queue(&self, capacity: usize) -> JobQueue {
+        let (sender, mut receiver) = mpsc::channel::<AgentJob>(capacity.max(1));
+        let runner = self.clone();
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let permit = runner
+                    .permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("runner semaphore is never closed");
+                // Take the slot before spawning, so jobs start in queue order
+                let runner = runner.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    runner.execute(&job).await
+                });
+            }
+        });
+        JobQueue { sender }
+    }
+
+    /// Wait until no job holds a slot; queued jobs may still start afterwards
+    pub async fn idle(&self) {
+        let _all = self
+            .permits
+            .acquire_many(self.concurrency as u32)
+            .await
+            .expect("runner semaphore is never closed");
+    }
+}
+
+/// Handle to a scheduled job; the schedule runs until `cancel` is called
+#[derive(Debug)]
+pub struct ScheduleHandle {
+    task: JoinHandle<()>,
+}
+
+impl ScheduleHandle {
+    /// Stop the schedule, aborting an execution in progress
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+}
+
+/// Sending side of an `AgentRunner::queue`
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    sender: mpsc::Sender<AgentJob>,
+}
+
+impl JobQueue {
+    /// Add a job, waiting while the queue is full
+    pub async fn push(&self, job: AgentJob) -> Result<()> {
+        self.sender.send(job).await.map_err(|_| {
+            AiError::Agent(AgentError::StateError {
+                message: "job queue has stopped".to_string(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::types::{FinishReason, Message};
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    fn answer(text: &str) -> AgentResponse {
+        let message = Message::assistant(text);
+        AgentResponse {
+            messages: vec![message.clone()],
+            final_message: message,
+            steps: Vec::new(),
+            finish_reason: FinishReason::Stop,
+            total_usage: None,
+            checkpoint: None,
+            scratchpad: Default::default(),
+            timed_out: false,
+            output: (),
+        }
+    }
+
+    fn sleeper(
+        name: &str,
+        millis: u64,
+        running: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    ) -> AgentJob {
+        AgentJob::from_fn(name, move || {
+            let running = running.clone();
+            let peak = peak.clone();
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(answer("done"))
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_queue_respects_concurrency_and_timeout() {
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let collected = results.clone();
+        let runner = AgentRunner::new(2)
+            .timeout(Duration::from_millis(200))
+            .on_result(move |result| {
+                let collected = collected.clone();
+                async move { collected.lock().unwrap().push(result) }
+            });
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let queue = runner.queue(8);
+        for name in ["a", "b", "c", "d"] {
+            queue
+                .push(sleeper(name, 20, running.clone(), peak.clone()))
+                .await
+                .unwrap();
+        }
+        queue
+            .push(sleeper("slow", 5_000, running.clone(), peak.clone()))
+            .await
+            .unwrap();
+        drop(queue);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        runner.idle().await;
+        let results = results.lock().unwrap();
+        assert_eq!(results.len(), 5);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let slow = results.iter().find(|r| r.job == "slow").unwrap();
+        assert!(matches!(
+            slow.result,
+            Err(AiError::Agent(AgentError::Timeout { duration, .. }))
+                if duration == Duration::from_millis(200)
+        ));
+        assert_eq!(results.iter().filter(|r| r.result.is_ok()).count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_runs_until_cancelled() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let runner = AgentRunner::new(1);
+        let job = AgentJob::from_fn("tick", move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Ok(answer("tick")) }
+        });
+
+        let handle = runner.schedule(job, Duration::from_millis(10)).unwrap();
+        tokio::time::sleep(Duration::from_millis(45)).await;
+        handle.cancel();
+        let seen = count.load(Ordering::SeqCst);
+        assert!(seen >= 2);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(count.load(Ordering::SeqCst), seen);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_rejects_a_zero_period() {
+        let runner = AgentRunner::new(1);
+        let job = AgentJob::from_fn("tick", || async { Ok(answer("tick")) });
+        assert!(matches!(
+            runner.schedule(job, Duration::ZERO),
+            Err(AiError::Validation(ValidationError::InvalidValue { .. }))
+        ));
+    }
+}
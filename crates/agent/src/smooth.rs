@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use ai_core::{
+    MaybeSend, Result,
+    platform::{self, Instant},
+    types::{AssistantContent, MessageDelta},
+};
+use futures::{Stream, StreamExt};
+
+use crate::agent::{AgentChunkStream, AgentStreamChunk};
+
+/// Where coalesced text may be cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// After whitespace
+    Word,
+    /// After `.`, `!` or `?` followed by whitespace, or after a newline
+    Sentence,
+    /// After a newline
+    Line,
+}
+
+impl Boundary {
+    /// Byte offset just past the last boundary in `text`
+    fn last_split(self, text: &str) -> Option<usize> {
+        match self {
+            Boundary::Word => text
+                .char_indices()
+                .rev()
+                .find(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| i + c.len_utf8()),
+            Boundary::Line => text.rfind('\n').map(|i| i + 1),
+            Boundary::Sentence => {
+                let mut previous: Option<char> = None;
+                let mut split = None;
+                for (i, c) in text.char_indices() {
+                    let after_stop = matches!(previous, Some('.' | '!' | '?'));
+                    if c == '\n' || (c.is_whitespace() && after_stop) {
+                        split = Some(i + c.len_utf8());
+                    }
+                    previous = Some(c);
+                }
+                split
+            }
+        }
+    }
+}
+
+/// How `smooth_stream` regroups text deltas
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smoothing {
+    pub boundary: Boundary,
+    /// Minimum time between text chunks; text arriving meanwhile joins the next chunk
+    pub interval: Option<Duration>,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self {
+            boundary: Boundary::Word,
+            interval: None,
+        }
+    }
+}
+
+impl Smoothing {
+    pub fn new(boundary: Boundary) -> Self {
+        Self {
+            boundary,
+            interval: None,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+}
+
+fn text_of(chunk: &AgentStreamChunk) -> Option<&str> {
+    match &chunk.chunk.delta {
+        MessageDelta::Assistant {
+            content: Some(AssistantContent::Text { text }),
+        } if chunk.chunk.finish_reason.is_none() && chunk.chunk.usage.is_none() => {
+            Some(text.as_str())
+        }
+        _ => None,
+    }
+}
+
+/// Chunk carrying `text`, based on the last text chunk seen
+fn text_chunk(template: &AgentStreamChunk, text: String) -> AgentStreamChunk {
+    let mut chunk = template.clone();
+    chunk.chunk.delta = MessageDelta::Assistant {
+        content: Some(AssistantContent::Text { text }),
+    };
+    chunk.is_final = false;
+    chunk.total_usage = None;
+    chunk
+}
+
+/// Coalesce small text deltas into chunks ending at word, sentence or line boundaries
+///
+/// Only plain text chunks are regrouped. Buffered text is flushed before any other
+/// chunk, such as tool calls, usage or a step's final chunk, which pass through
+/// unchanged, so the concatenated text and chunk order are preserved.
+pub fn smooth_stream<St>(stream: St, smoothing: Smoothing) -> AgentChunkStream
+where
+    St: Stream<Item = Result<AgentStreamChunk>> + MaybeSend + 'static,
+{
+    Box::pin(async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        let mut buffer = String::new();
+        let mut template: Option<AgentStreamChunk> = None;
+        let mut next_emit = Instant::now();
+
+        loop {
+            let ready = smoothing.boundary.last_split(&buffer);
+            let item = if ready.is_some() && Instant::now() >= next_emit {
+                None
+            } else {
+                tokio::select! {
+                    biased;
+                    _ = platform::sleep(next_emit.saturating_duration_since(Instant::now())), if ready.is_some() => None,
+                    item = stream.next() => Some(item),
+                }
+            };
+            let Some(item) = item else {
+                // A boundary is buffered and the interval has passed
+                let rest = buffer.split_off(ready.unwrap_or_default());
+                let text = std::mem::replace(&mut buffer, rest);
+                if let Some(template) = &template {
+                    yield Ok(text_chunk(template, text));
+                }
+                if let Some(interval) = smoothing.interval {
+                    next_emit = Instant::now() + interval;
+                }
+                continue;
+            };
+
+            let passthrough = match item {
+                Some(Ok(chunk)) => match text_of(&chunk) {
+                    Some(text) => {
+                        buffer.push_str(text);
+                        template = Some(chunk);
+                        continue;
+                    }
+                    None => Some(Ok(chunk)),
+                },
+                other => other,
+            };
+            if let Some(template) = &template
+                && !buffer.is_empty()
+            {
+                yield Ok(text_chunk(template, std::mem::take(&mut buffer)));
+            }
+            match passthrough {
+                Some(item) => yield item,
+                None => return,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::types::{ChatStreamChunk, FinishReason};
+
+    fn chunk(text: &str) -> AgentStreamChunk {
+        AgentStreamChunk {
+            step: 0,
+            chunk: ChatStreamChunk {
+                id: "msg_1".to_string(),
+                delta: MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text {
+                        text: text.to_string(),
+                    }),
+                },
+                finish_reason: None,
+                usage: None,
+            },
+            is_final: false,
+            total_usage: None,
+        }
+    }
+
+    fn deltas() -> impl Stream<Item = Result<AgentStreamChunk>> + Send + 'static {
+        let mut last = chunk("");
+        last.chunk.delta = MessageDelta::Assistant { content: None };
+        last.chunk.finish_reason = Some(FinishReason::Stop);
+        last.is_final = true;
+        let chunks = ["Hel", "lo wo", "rld. How", " are you?"]
+            .into_iter()
+            .map(chunk)
+            .chain([last]);
+        futures::stream::iter(chunks.map(Ok))
+    }
+
+    async fn texts(smoothing: Smoothing) -> Vec<String> {
+        let chunks: Vec<AgentStreamChunk> = smooth_stream(deltas(), smoothing)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert!(chunks.last().unwrap().is_final);
+        chunks
+            .iter()
+            .filter_map(|chunk| text_of(chunk).map(str::to_string))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_coalesces_on_boundaries() {
+        assert_eq!(
+            texts(Smoothing::new(Boundary::Word)).await,
+            vec!["Hello ", "world. ", "How are ", "you?"]
+        );
+        assert_eq!(
+            texts(Smoothing::new(Boundary::Sentence)).await,
+            vec!["Hello world. ", "How are you?"]
+        );
+        assert_eq!(
+            texts(Smoothing::new(Boundary::Word).interval(Duration::from_secs(60))).await,
+            vec!["Hello ", "world. How are you?"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use ai_core::{
+    ToolExecutionError,
+    provider::ChatTextGeneration,
+    tools::{ToolOutput, ToolRouter},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::agent::Agent;
+
+/// Input passed by the calling model to a sub-agent tool
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SubAgentInput {
+    /// Task or question for the sub-agent, with all the context it needs
+    pub input: String,
+}
+
+/// An agent exposed as a single tool, for planner/worker setups
+///
+/// Every call runs on a fresh fork of the agent, so calls neither share history nor
+/// interfere when run concurrently.
+pub struct AgentTool<P, S = ()>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    name: String,
+    description: String,
+    agent: Arc<Agent<P, S>>,
+}
+
+/// Wrap an agent so that a whole run can be registered as one tool
+pub fn agent_as_tool<P, S>(
+    name: impl Into<String>,
+    description: impl Into<String>,
+    agent: Agent<P, S>,
+) -> AgentTool<P, S>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    AgentTool {
+        name: name.into(),
+        description: description.into(),
+        agent: Arc::new(agent),
+    }
+}
+
+impl<P, S> AgentTool<P, S>
+where
+    P: ChatTextGeneration + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    /// Register the sub-agent as a tool on a router
+    pub fn register<T: Clone + Send + Sync + 'static>(
+        self,
+        router: ToolRouter<T>,
+    ) -> ToolRouter<T> {
+        let agent = self.agent;
+        let name = self.name.clone();
+        router.register(
+            self.name,
+            Some(self.description),
+            move |input: SubAgentInput| {
+                let agent = agent.clone();
+                let name = name.clone();
+                async move {
+                    let response = agent.fork().run(input.input).await.map_err(|e| {
+                        ToolExecutionError::ExecutionError(format!(
+                            "Sub-agent '{}' failed: {}",
+                            name, e
+                        ))
+                    })?;
+
+                    if let Some(checkpoint) = &response.checkpoint {
+                        let pending: Vec<&str> = checkpoint
+                            .pending_tool_calls
+                            .iter()
+                            .map(|call| call.name.as_str())
+                            .collect();
+                        return Err(ToolExecutionError::ExecutionError(format!(
+                            "Sub-agent '{}' needs client-side tools: {}",
+                            name,
+                            pending.join(", ")
+                        )));
+                    }
+
+                    Ok(ToolOutput::text(response.text()))
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::StopOnReason;
+    use ai_core::types::{Message, ToolResult};
+    use ai_test_utils::MockProvider;
+
+    #[tokio::test]
+    async fn test_parent_agent_gets_the_sub_agent_answer_as_tool_result() {
+        let worker = MockProvider::new().text("Paris");
+        let researcher = Agent::new(worker.clone())
+            .system_prompt("Answer in one word")
+            .run_until(StopOnReason::stop_on_finish());
+        let parent = MockProvider::new()
+            .tool_call(
+                "researcher",
+                serde_json::json!({ "input": "What is the capital of France?" }),
+            )
+            .text("The capital is Paris");
+        let planner = Agent::new(parent.clone())
+            .run_until(StopOnReason::stop_on_finish())
+            .tools(
+                agent_as_tool("researcher", "Looks facts up", researcher)
+                    .register(ToolRouter::new())
+                    .with_state(()),
+            );
+
+        let response = planner.run("Where is the Eiffel Tower?").await.unwrap();
+        assert_eq!(response.text(), "The capital is Paris");
+
+        let delegated = &worker.requests()[0];
+        assert_eq!(
+            delegated.messages.last(),
+            Some(&Message::user("What is the capital of France?"))
+        );
+        let call_id = &response.steps[0].tool_calls[0].id;
+        assert_eq!(
+            parent.requests()[1].messages.last(),
+            Some(&Message::tool_results(vec![ToolResult::success(
+                call_id,
+                serde_json::json!("Paris")
+            )]))
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::future::Future;
+
+use ai_core::{AiError, types::*};
+
+#[cfg(feature = "tracing")]
+use tracing::{Instrument, field::Empty};
+
+/// A span that is a no-op unless the `tracing` feature is enabled
+#[derive(Debug, Clone)]
+pub(crate) struct GenAiSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+fn finish_reason_name(reason: &FinishReason) -> &'static str {
+    match reason {
+        FinishReason::Stop => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::ContentFilter => "content_filter",
+        FinishReason::Error => "error",
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl GenAiSpan {
+    /// Span around a whole agent run
+    pub(crate) fn agent(provider: &str, model: &str) -> Self {
+        let span = tracing::info_span!(
+            "invoke_agent",
+            otel.name = "invoke_agent",
+            otel.status_code = Empty,
+            gen_ai.operation.name = "invoke_agent",
+            gen_ai.provider.name = provider,
+            gen_ai.request.model = model,
+            gen_ai.response.finish_reasons = Empty,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            ai.agent.steps = Empty,
+            error.type = Empty,
+        );
+        Self { span }
+    }
+
+    /// Span around one step of a run: the model call and its tool executions
+    pub(crate) fn step(&self, step: u32) -> Self {
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "agent_step",
+            otel.name = format!("agent_step {}", step),
+            ai.agent.step = step,
+        );
+        Self { span }
+    }
+
+    /// Span around a model call
+    pub(crate) fn chat(&self, provider: &str, model: &str, settings: &GenerationSettings) -> Self {
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "chat",
+            otel.name = format!("chat {}", model),
+            otel.status_code = Empty,
+            gen_ai.operation.name = "chat",
+            gen_ai.provider.name = provider,
+            gen_ai.request.model = model,
+            gen_ai.request.temperature = settings.temperature,
+            gen_ai.request.max_tokens = settings.max_tokens,
+            gen_ai.request.top_p = settings.top_p,
+            gen_ai.response.id = Empty,
+            gen_ai.response.finish_reasons = Empty,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            error.type = Empty,
+        );
+        Self { span }
+    }
+
+    /// Span around a tool execution
+    pub(crate) fn tool(&self, tool_call: &ToolCall) -> Self {
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "execute_tool",
+            otel.name = format!("execute_tool {}", tool_call.name),
+            otel.status_code = Empty,
+            gen_ai.operation.name = "execute_tool",
+            gen_ai.tool.name = tool_call.name.as_str(),
+            gen_ai.tool.call.id = tool_call.id.as_str(),
+            error.type = Empty,
+        );
+        Self { span }
+    }
+
+    fn record_usage(&self, usage: Option<&Usage>) {
+        if let Some(usage) = usage {
+            self.span
+                .record("gen_ai.usage.input_tokens", usage.prompt_tokens)
+                .record("gen_ai.usage.output_tokens", usage.completion_tokens);
+        }
+    }
+
+    pub(crate) fn record_finish(&self, finish_reason: &FinishReason, usage: Option<&Usage>) {
+        self.span.record(
+            "gen_ai.response.finish_reasons",
+            finish_reason_name(finish_reason),
+        );
+        self.record_usage(usage);
+    }
+
+    pub(crate) fn record_response(&self, response: &ChatResponse) {
+        if !response.id.is_empty() {
+            self.span.record("gen_ai.response.id", response.id.as_str());
+        }
+        self.record_finish(&response.finish_reason, response.usage.as_ref());
+    }
+
+    pub(crate) fn record_response_id(&self, id: &str) {
+        if !id.is_empty() {
+            self.span.record("gen_ai.response.id", id);
+        }
+    }
+
+    pub(crate) fn record_steps(&self, steps: usize) {
+        self.span.record("ai.agent.steps", steps);
+    }
+
+    pub(crate) fn record_error_type(&self, error_type: &str) {
+        self.span
+            .record("otel.status_code", "ERROR")
+            .record("error.type", error_type);
+    }
+
+    pub(crate) fn record_error(&self, error: &AiError) {
+        self.record_error_type(error.error_type());
+    }
+
+    /// Run `future` inside the span
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        future.instrument(self.span.clone()).await
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl GenAiSpan {
+    pub(crate) fn agent(_provider: &str, _model: &str) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn step(&self, _step: u32) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn chat(
+        &self,
+        _provider: &str,
+        _model: &str,
+        _settings: &GenerationSettings,
+    ) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn tool(&self, _tool_call: &ToolCall) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn record_finish(&self, _finish_reason: &FinishReason, _usage: Option<&Usage>) {}
+
+    pub(crate) fn record_response(&self, _response: &ChatResponse) {}
+
+    pub(crate) fn record_response_id(&self, _id: &str) {}
+
+    pub(crate) fn record_steps(&self, _steps: usize) {}
+
+    pub(crate) fn record_error_type(&self, _error_type: &str) {}
+
+    pub(crate) fn record_error(&self, _error: &AiError) {}
+
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        future.await
+    }
+}
+
+/// Run a model call inside `span`, recording the response or error
+pub(crate) async fn traced_chat<F>(span: &GenAiSpan, call: F) -> ai_core::Result<ChatResponse>
+where
+    F: Future<Output = ai_core::Result<ChatResponse>>,
+{
+    let result = span.instrument(call).await;
+    match &result {
+        Ok(response) => span.record_response(response),
+        Err(e) => span.record_error(e),
+    }
+    result
+}
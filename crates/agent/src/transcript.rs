@@ -0,0 +1,314 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ai_core::{
+    MaybeSend, Result, Scratchpad,
+    platform::Instant,
+    types::{AssistantContent, FinishReason, Message, MessageDelta, ToolCall, ToolResult, Usage},
+};
+use futures::{Stream, StreamExt};
+
+use crate::agent::{
+    AgentChunkStream, AgentEvent, AgentEventStream, AgentResponse, AgentStreamChunk, StepInfo,
+    add_usage, merge_stream_usage, push_content,
+};
+
+#[derive(Debug, Default)]
+struct Recording {
+    messages: Vec<Message>,
+    /// Content of the step in progress
+    content: Vec<AssistantContent>,
+    tool_calls: Vec<ToolCall>,
+    tool_results: Vec<ToolResult>,
+    response_id: String,
+    step_usage: Option<Usage>,
+    step_started: Option<Instant>,
+    steps: Vec<StepInfo>,
+    total_usage: Option<Usage>,
+    response: Option<AgentResponse>,
+}
+
+impl Recording {
+    fn push_part(&mut self, part: &AssistantContent) {
+        if let AssistantContent::ToolCall { tool_call } = part {
+            self.tool_calls.push(tool_call.clone());
+        }
+        push_content(&mut self.content, part);
+    }
+
+    fn finish_step(&mut self, step: u32, finish_reason: FinishReason, usage: Option<Usage>) {
+        let duration = self
+            .step_started
+            .take()
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        if !self.content.is_empty() {
+            self.messages.push(Message::Assistant {
+                content: std::mem::take(&mut self.content),
+                metadata: None,
+            });
+        }
+        if !self.tool_results.is_empty() {
+            self.messages.push(Message::Tool {
+                tool_results: self.tool_results.clone(),
+                metadata: None,
+            });
+        }
+        self.steps.push(StepInfo {
+            step,
+            response_id: std::mem::take(&mut self.response_id),
+            finish_reason,
+            usage,
+            tool_calls: std::mem::take(&mut self.tool_calls),
+            tool_results: std::mem::take(&mut self.tool_results),
+            duration,
+        });
+        self.step_usage = None;
+    }
+
+    fn record_chunk(&mut self, chunk: &AgentStreamChunk) {
+        self.step_started.get_or_insert_with(Instant::now);
+        if self.response_id.is_empty() {
+            self.response_id = chunk.chunk.id.clone();
+        }
+        if let Some(usage) = &chunk.chunk.usage {
+            self.step_usage = Some(merge_stream_usage(self.step_usage.take(), usage));
+        }
+        if let MessageDelta::Assistant {
+            content: Some(part),
+        } = &chunk.chunk.delta
+        {
+            self.push_part(part);
+        }
+        if chunk.is_final {
+            let finish_reason = chunk
+                .chunk
+                .finish_reason
+                .clone()
+                .unwrap_or(FinishReason::Stop);
+            let usage = self.step_usage.clone();
+            self.total_usage = match &chunk.total_usage {
+                Some(total) => Some(total.clone()),
+                None => add_usage(self.total_usage.take(), usage.as_ref()),
+            };
+            self.finish_step(chunk.step, finish_reason, usage);
+        }
+    }
+
+    fn record_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::StepStarted { .. } => self.step_started = Some(Instant::now()),
+            AgentEvent::TextDelta { text, .. } => {
+                self.push_part(&AssistantContent::Text { text: text.clone() })
+            }
+            AgentEvent::ToolCallStarted { tool_call, .. } => {
+                self.push_part(&AssistantContent::ToolCall {
+                    tool_call: tool_call.clone(),
+                })
+            }
+            AgentEvent::ToolCallCompleted { result, .. } => self.tool_results.push(result.clone()),
+            AgentEvent::StepFinished {
+                step,
+                finish_reason,
+                usage,
+            } => {
+                self.total_usage = add_usage(self.total_usage.take(), usage.as_ref());
+                self.finish_step(*step, finish_reason.clone(), usage.clone());
+            }
+            AgentEvent::RunFinished(response) => {
+                self.messages = response.messages.clone();
+                self.response = Some(response.clone());
+            }
+            AgentEvent::ReasoningDelta { .. } | AgentEvent::PendingToolCalls { .. } => {}
+        }
+    }
+
+    /// Response assembled from recorded chunks once their stream has ended
+    fn finish_chunks(&mut self) {
+        if self.response.is_some() || self.steps.is_empty() {
+            return;
+        }
+        let final_message = self
+            .messages
+            .iter()
+            .rev()
+            .find(|message| matches!(message, Message::Assistant { .. }))
+            .cloned()
+            .unwrap_or_else(|| Message::assistant(""));
+        let finish_reason = self
+            .steps
+            .last()
+            .map_or(FinishReason::Stop, |step| step.finish_reason.clone());
+        self.response = Some(AgentResponse {
+            messages: self.messages.clone(),
+            final_message,
+            steps: self.steps.clone(),
+            finish_reason,
+            total_usage: self.total_usage.clone(),
+            checkpoint: None,
+            scratchpad: Scratchpad::new(),
+            timed_out: false,
+            output: (),
+        });
+    }
+}
+
+/// Conversation accumulated while an agent stream is forwarded elsewhere
+///
+/// `record_chunks` and `record_events` pass every item through unchanged while
+/// rebuilding the messages the run produces. Clones share the recording, so the
+/// transcript can be read while the stream is still being consumed, e.g. by a UI.
+///
+/// Chunk streams carry no tool results, so a transcript of `stream_text` holds the
+/// assistant messages only; record `stream_events` for the full conversation and the
+/// response `generate_text` would return.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl Transcript {
+    /// Transcript starting from the messages sent to the run
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self {
+            recording: Arc::new(Mutex::new(Recording {
+                messages,
+                ..Recording::default()
+            })),
+        }
+    }
+
+    /// Forward `stream` while recording its chunks
+    pub fn record_chunks<St>(&self, stream: St) -> AgentChunkStream
+    where
+        St: Stream<Item = Result<AgentStreamChunk>> + MaybeSend + 'static,
+    {
+        let recording = self.recording.clone();
+        let mut stream = Box::pin(stream);
+        Box::pin(async_stream::stream! {
+            while let Some(item) = stream.next().await {
+                if let Ok(chunk) = &item {
+                    recording.lock().unwrap().record_chunk(chunk);
+                }
+                yield item;
+            }
+            recording.lock().unwrap().finish_chunks();
+        })
+    }
+
+    /// Forward `stream` while recording its events
+    pub fn record_events<St>(&self, stream: St) -> AgentEventStream
+    where
+        St: Stream<Item = Result<AgentEvent>> + MaybeSend + 'static,
+    {
+        let recording = self.recording.clone();
+        Box::pin(stream.inspect(move |item| {
+            if let Ok(event) = item {
+                recording.lock().unwrap().record_event(event);
+            }
+        }))
+    }
+
+    /// Messages so far, including the step in progress
+    pub fn messages(&self) -> Vec<Message> {
+        let recording = self.recording.lock().unwrap();
+        let mut messages = recording.messages.clone();
+        if !recording.content.is_empty() {
+            messages.push(Message::Assistant {
+                content: recording.content.clone(),
+                metadata: None,
+            });
+        }
+        messages
+    }
+
+    /// Steps finished so far
+    pub fn steps(&self) -> Vec<StepInfo> {
+        self.recording.lock().unwrap().steps.clone()
+    }
+
+    /// Final response, once the run has finished
+    pub fn response(&self) -> Option<AgentResponse> {
+        self.recording.lock().unwrap().response.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::types::ChatStreamChunk;
+
+    fn chunk(
+        step: u32,
+        part: Option<AssistantContent>,
+        finish: Option<FinishReason>,
+    ) -> AgentStreamChunk {
+        let is_final = finish.is_some();
+        AgentStreamChunk {
+            step,
+            chunk: ChatStreamChunk {
+                id: format!("msg_{}", step),
+                delta: MessageDelta::Assistant { content: part },
+                finish_reason: finish,
+                usage: is_final.then_some(Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 5,
+                    total_tokens: 15,
+                }),
+            },
+            is_final,
+            total_usage: None,
+        }
+    }
+
+    fn text(text: &str) -> Option<AssistantContent> {
+        Some(AssistantContent::Text {
+            text: text.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_records_chunks_while_forwarding() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"q": "rust"}),
+        };
+        let chunks = vec![
+            chunk(
+                0,
+                Some(AssistantContent::ToolCall {
+                    tool_call: call.clone(),
+                }),
+                None,
+            ),
+            chunk(0, None, Some(FinishReason::ToolCalls)),
+            chunk(1, text("Rust is "), None),
+            chunk(1, text("a language."), None),
+            chunk(1, None, Some(FinishReason::Stop)),
+        ];
+        let transcript = Transcript::new(vec![Message::user("What is Rust?")]);
+        let mut stream =
+            transcript.record_chunks(futures::stream::iter(chunks.into_iter().map(Ok)));
+
+        let mut forwarded = 0;
+        while let Some(item) = stream.next().await {
+            item.unwrap();
+            forwarded += 1;
+            if forwarded == 4 {
+                assert_eq!(transcript.messages().len(), 3);
+                assert!(transcript.response().is_none());
+            }
+        }
+        assert_eq!(forwarded, 5);
+
+        let response = transcript.response().unwrap();
+        assert_eq!(response.messages.len(), 3);
+        assert_eq!(response.text(), "Rust is a language.");
+        assert_eq!(response.steps.len(), 2);
+        assert_eq!(response.steps[0].tool_calls, vec![call]);
+        assert_eq!(response.steps[1].response_id, "msg_1");
+        assert_eq!(response.total_usage.unwrap().total_tokens, 30);
+    }
+}
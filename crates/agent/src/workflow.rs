@@ -0,0 +1,349 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+#[cfg(not(target_arch = "wasm32"))]
+use ai_core::provider::ChatTextGeneration;
+use ai_core::{AgentError, AiError, BuiltToolRouter, Result, ToolError};
+use futures::{FutureExt, future::BoxFuture};
+use serde_json::Value as JsonValue;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::agent::{Agent, AgentResponse};
+use crate::retry::{RetryPolicy, with_retry};
+
+type NodeFn<T> = Arc<dyn Fn(T) -> BoxFuture<'static, Result<T>> + Send + Sync>;
+type Condition<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+struct Node<T> {
+    run: NodeFn<T>,
+    retry: Option<RetryPolicy>,
+}
+
+struct Edge<T> {
+    to: String,
+    condition: Option<Condition<T>>,
+}
+
+/// Deterministic pipeline of nodes passing a state value along conditional edges
+///
+/// Each node takes the state and returns the next one. After a node finishes, the
+/// first outgoing edge whose condition holds picks the next node; the run ends when
+/// none does. Edges are tried in the order they were added.
+///
+/// ```
+/// # use ai_agent::Workflow;
+/// # async fn example() -> ai_core::Result<()> {
+/// let workflow = Workflow::new()
+///     .node("double", |n: i64| async move { Ok(n * 2) })
+///     .node("done", |n: i64| async move { Ok(n) })
+///     .edge_if("double", "double", |n| *n < 100)
+///     .edge("double", "done");
+/// assert_eq!(workflow.run(3).await?.state, 192);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Workflow<T> {
+    start: Option<String>,
+    nodes: HashMap<String, Node<T>>,
+    edges: HashMap<String, Vec<Edge<T>>>,
+    max_transitions: usize,
+}
+
+impl<T> std::fmt::Debug for Workflow<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut nodes: Vec<&String> = self.nodes.keys().collect();
+        nodes.sort();
+        f.debug_struct("Workflow")
+            .field("start", &self.start)
+            .field("nodes", &nodes)
+            .field("max_transitions", &self.max_transitions)
+            .finish()
+    }
+}
+
+/// Final state of a workflow run with the nodes it went through
+#[derive(Debug, Clone)]
+pub struct WorkflowRun<T> {
+    pub state: T,
+    /// Names of the executed nodes, in order
+    pub path: Vec<String>,
+}
+
+impl<T> Default for Workflow<T>
+where
+    T: Clone + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Workflow<T>
+where
+    T: Clone + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            max_transitions: 100,
+        }
+    }
+
+    /// Add a function node; the first node added is the start node
+    pub fn node<F, Fut>(mut self, name: impl Into<String>, node: F) -> Self
+    where
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let name = name.into();
+        self.start.get_or_insert_with(|| name.clone());
+        let run: NodeFn<T> = Arc::new(move |state| node(state).boxed());
+        self.nodes.insert(name, Node { run, retry: None });
+        self
+    }
+
+    /// Add a node running one turn of `agent` on a fresh fork
+    ///
+    /// `prompt` builds the user message from the state and `apply` folds the response
+    /// back into it. Tool and workflow futures are `Send`, so this is native-only.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn agent_node<P, S>(
+        self,
+        name: impl Into<String>,
+        agent: Agent<P, S>,
+        prompt: impl Fn(&T) -> String + Send + Sync + 'static,
+        apply: impl Fn(T, AgentResponse) -> T + Send + Sync + 'static,
+    ) -> Self
+    where
+        P: ChatTextGeneration + 'static,
+        S: Clone + Send + Sync + 'static,
+    {
+        let agent = Arc::new(agent);
+        let apply = Arc::new(apply);
+        self.node(name, move |state: T| {
+            let agent = agent.clone();
+            let apply = apply.clone();
+            let input = prompt(&state);
+            async move {
+                let response = agent.fork().run(input).await?;
+                Ok(apply(state, response))
+            }
+        })
+    }
+
+    /// Add a node calling the router's tool `tool` with arguments built from the state
+    pub fn tool_node<S>(
+        self,
+        name: impl Into<String>,
+        router: BuiltToolRouter<S>,
+        tool: impl Into<String>,
+        arguments: impl Fn(&T) -> JsonValue + Send + Sync + 'static,
+        apply: impl Fn(T, JsonValue) -> T + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let tool = tool.into();
+        let apply = Arc::new(apply);
+        self.node(name, move |state: T| {
+            let router = router.clone();
+            let tool = tool.clone();
+            let apply = apply.clone();
+            let input = arguments(&state);
+            async move {
+                match router.execute_tool(&tool, input).await {
+                    Some(Ok(output)) => Ok(apply(state, output)),
+                    Some(Err(e)) => Err(AiError::Tool(ToolError::ExecutionFailed {
+                        name: tool,
+                        error: e.to_string(),
+                    })),
+                    None => Err(AiError::Tool(ToolError::NoHandler { name: tool })),
+                }
+            }
+        })
+    }
+
+    /// Retry a node's retryable errors, see `RetryPolicy`
+    ///
+    /// Each attempt starts from the state the node was given.
+    pub fn retry(mut self, node: &str, policy: RetryPolicy) -> Self {
+        if let Some(node) = self.nodes.get_mut(node) {
+            node.retry = Some(policy);
+        }
+        self
+    }
+
+    /// Start at `node` instead of the first node added
+    pub fn start(mut self, node: impl Into<String>) -> Self {
+        self.start = Some(node.into());
+        self
+    }
+
+    /// Always continue from `from` to `to`
+    pub fn edge(self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.add_edge(from.into(), to.into(), None)
+    }
+
+    /// Continue from `from` to `to` when `condition` holds for the state
+    pub fn edge_if(
+        self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        condition: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.add_edge(from.into(), to.into(), Some(Arc::new(condition)))
+    }
+
+    /// Fail runs executing more than `max` nodes, guarding against endless cycles
+    pub fn max_transitions(mut self, max: usize) -> Self {
+        self.max_transitions = max;
+        self
+    }
+
+    fn add_edge(mut self, from: String, to: String, condition: Option<Condition<T>>) -> Self {
+        self.edges
+            .entry(from)
+            .or_default()
+            .push(Edge { to, condition });
+        self
+    }
+
+    fn validate(&self) -> Result<&str> {
+        let start = self
+            .start
+            .as_deref()
+            .ok_or_else(|| workflow_error("has no nodes"))?;
+        if !self.nodes.contains_key(start) {
+            return Err(workflow_error(format!(
+                "start node '{}' does not exist",
+                start
+            )));
+        }
+        for (from, edges) in &self.edges {
+            for name in std::iter::once(from).chain(edges.iter().map(|edge| &edge.to)) {
+                if !self.nodes.contains_key(name) {
+                    return Err(workflow_error(format!(
+                        "edge refers to unknown node '{}'",
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(start)
+    }
+
+    /// Run from the start node until no edge matches
+    pub async fn run(&self, state: T) -> Result<WorkflowRun<T>> {
+        let mut current = self.validate()?.to_string();
+        let mut state = state;
+        let mut path = Vec::new();
+
+        loop {
+            if path.len() == self.max_transitions {
+                return Err(workflow_error(format!(
+                    "exceeded {} transitions at node '{}'",
+                    self.max_transitions, current
+                )));
+            }
+            let node = &self.nodes[&current];
+            let input = state;
+            state = with_retry(node.retry.as_ref(), || (node.run)(input.clone())).await?;
+            path.push(current.clone());
+
+            let next = self.edges.get(&current).and_then(|edges| {
+                edges
+                    .iter()
+                    .find(|edge| edge.condition.as_ref().is_none_or(|holds| holds(&state)))
+            });
+            match next {
+                Some(edge) => current = edge.to.clone(),
+                None => return Ok(WorkflowRun { state, path }),
+            }
+        }
+    }
+}
+
+fn workflow_error(message: impl std::fmt::Display) -> AiError {
+    AiError::Agent(AgentError::StateError {
+        message: format!("workflow {}", message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::{ToolRouter, errors::ToolExecutionError};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug, Clone, Default)]
+    struct Draft {
+        text: String,
+        revisions: u32,
+    }
+
+    async fn shout(input: JsonValue) -> std::result::Result<String, ToolExecutionError> {
+        Ok(input["text"].as_str().unwrap_or_default().to_uppercase())
+    }
+
+    #[tokio::test]
+    async fn test_runs_nodes_along_conditional_edges() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let flaky = attempts.clone();
+        let router = ToolRouter::new()
+            .register("shout", None, shout)
+            .with_state(());
+
+        let workflow = Workflow::new()
+            .node("write", move |mut draft: Draft| {
+                let attempts = flaky.clone();
+                async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err(AiError::Network(ai_core::NetworkError::ConnectionFailed {
+                            message: "reset".to_string(),
+                        }));
+                    }
+                    draft.text.push_str("draft ");
+                    Ok(draft)
+                }
+            })
+            .node("revise", |mut draft: Draft| async move {
+                draft.revisions += 1;
+                Ok(draft)
+            })
+            .tool_node(
+                "publish",
+                router,
+                "shout",
+                |draft: &Draft| serde_json::json!({ "text": draft.text.trim() }),
+                |mut draft, output| {
+                    draft.text = output.as_str().unwrap_or_default().to_string();
+                    draft
+                },
+            )
+            .retry(
+                "write",
+                RetryPolicy::new(1).initial_delay(std::time::Duration::ZERO),
+            )
+            .edge("write", "revise")
+            .edge_if("revise", "revise", |draft| draft.revisions < 2)
+            .edge("revise", "publish");
+
+        let run = workflow.run(Draft::default()).await.unwrap();
+        assert_eq!(run.state.text, "DRAFT");
+        assert_eq!(run.state.revisions, 2);
+        assert_eq!(run.path, vec!["write", "revise", "revise", "publish"]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let endless = Workflow::new()
+            .node("loop", |n: u32| async move { Ok(n + 1) })
+            .edge("loop", "loop")
+            .max_transitions(5);
+        assert!(endless.run(0).await.is_err());
+
+        let dangling = Workflow::new()
+            .node("a", |n: u32| async move { Ok(n) })
+            .edge("a", "b");
+        assert!(dangling.run(0).await.is_err());
+    }
+}
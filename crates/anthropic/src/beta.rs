@@ -0,0 +1,133 @@
+use std::{fmt, str::FromStr};
+
+use ai_core::errors::{AiError, ValidationError};
+
+/// Header listing the beta features a request opts into
+pub const BETA_HEADER: &str = "anthropic-beta";
+
+/// Anthropic beta feature, sent in the `anthropic-beta` header
+///
+/// Enable betas for every request with `AnthropicConfig::with_beta`, or for one request
+/// with `ChatRequest::headers(AnthropicBeta::request_header(..))`. Names parsed with
+/// `FromStr`, including those in a request's `anthropic-beta` header, must be known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnthropicBeta {
+    TokenCounting,
+    FilesApi,
+    PromptCaching,
+    /// One-hour cache TTL for prompt caching
+    ExtendedCacheTtl,
+    MessageBatches,
+    Pdfs,
+    ComputerUse,
+    /// Up to 128k output tokens on Claude 3.7 Sonnet
+    Output128k,
+    TokenEfficientTools,
+    InterleavedThinking,
+    FineGrainedToolStreaming,
+    CodeExecution,
+    McpClient,
+    /// One-million-token context window
+    Context1m,
+}
+
+impl AnthropicBeta {
+    /// Every beta this crate knows
+    pub const ALL: &[AnthropicBeta] = &[
+        Self::TokenCounting,
+        Self::FilesApi,
+        Self::PromptCaching,
+        Self::ExtendedCacheTtl,
+        Self::MessageBatches,
+        Self::Pdfs,
+        Self::ComputerUse,
+        Self::Output128k,
+        Self::TokenEfficientTools,
+        Self::InterleavedThinking,
+        Self::FineGrainedToolStreaming,
+        Self::CodeExecution,
+        Self::McpClient,
+        Self::Context1m,
+    ];
+
+    /// Header value naming this beta
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TokenCounting => "token-counting-2024-11-01",
+            Self::FilesApi => "files-api-2025-04-14",
+            Self::PromptCaching => "prompt-caching-2024-07-31",
+            Self::ExtendedCacheTtl => "extended-cache-ttl-2025-04-11",
+            Self::MessageBatches => "message-batches-2024-09-24",
+            Self::Pdfs => "pdfs-2024-09-25",
+            Self::ComputerUse => "computer-use-2025-01-24",
+            Self::Output128k => "output-128k-2025-02-19",
+            Self::TokenEfficientTools => "token-efficient-tools-2025-02-19",
+            Self::InterleavedThinking => "interleaved-thinking-2025-05-14",
+            Self::FineGrainedToolStreaming => "fine-grained-tool-streaming-2025-05-14",
+            Self::CodeExecution => "code-execution-2025-05-22",
+            Self::McpClient => "mcp-client-2025-04-04",
+            Self::Context1m => "context-1m-2025-08-07",
+        }
+    }
+
+    /// `anthropic-beta` header enabling `betas` for one request
+    pub fn request_header(betas: &[AnthropicBeta]) -> (String, String) {
+        (BETA_HEADER.to_string(), join(betas))
+    }
+}
+
+impl fmt::Display for AnthropicBeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AnthropicBeta {
+    type Err = AiError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|beta| beta.as_str() == name)
+            .ok_or_else(|| {
+                AiError::Validation(ValidationError::InvalidValue {
+                    field: BETA_HEADER.to_string(),
+                    message: format!("unknown Anthropic beta `{}`", name),
+                })
+            })
+    }
+}
+
+/// Comma-separated header value for `betas`
+pub(crate) fn join(betas: &[AnthropicBeta]) -> String {
+    betas
+        .iter()
+        .map(|beta| beta.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_roundtrip_and_unknown_names_fail() {
+        for beta in AnthropicBeta::ALL {
+            assert_eq!(beta.as_str().parse::<AnthropicBeta>().unwrap(), *beta);
+        }
+        assert!(matches!(
+            "made-up-2099-01-01".parse::<AnthropicBeta>(),
+            Err(AiError::Validation(ValidationError::InvalidValue { .. }))
+        ));
+        assert_eq!(
+            AnthropicBeta::request_header(&[AnthropicBeta::TokenCounting, AnthropicBeta::Pdfs]),
+            (
+                "anthropic-beta".to_string(),
+                "token-counting-2024-11-01,pdfs-2024-09-25".to_string()
+            )
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod beta;
 pub mod provider;
 
+pub use beta::*;
 pub use provider::*;
@@ -1,12 +1,33 @@
 use async_trait::async_trait;
 use eventsource_stream::Eventsource;
-use futures::{Stream, StreamExt as FuturesStreamExt};
-use reqwest::Client;
+use futures::StreamExt as FuturesStreamExt;
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
 
-use ai_core::errors::{AiError, NetworkError, ProviderError, SerializationError, ValidationError};
-use ai_core::{Result, provider::ChatTextGeneration, types::*};
+use crate::beta::{self, AnthropicBeta, BETA_HEADER};
+use ai_core::errors::{
+    AiError, HttpDiagnostics, NetworkError, ProviderError, SerializationError, ValidationError,
+};
+use ai_core::{
+    Result,
+    gateway::Gateway,
+    http::{
+        ConcurrencyLimit, HttpClientOptions, HttpRequest, HttpResponse, HttpTransport, SseCapture,
+        SseFrame,
+    },
+    models::{ModelInfo, ModelRegistry, builtin_models},
+    normalize::{normalize_messages, validate_alternation},
+    provider::{ChatStream, ChatTextGeneration, SettingsRules, cumulative_usage},
+    response_metadata::{
+        LATENCY_MS_METADATA_KEY, MODEL_METADATA_KEY, RATE_LIMITS_METADATA_KEY,
+        REQUEST_ID_METADATA_KEY, RateLimits, Refusal, RefusalKind, Stopwatch,
+    },
+    schema::{SchemaOptions, SchemaTarget},
+    types::*,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Model used when `ANTHROPIC_MODEL` is not set
+pub const DEFAULT_MODEL: &str = "claude-3-5-sonnet-20241022";
 
 /// Configuration for Anthropic provider
 #[derive(Debug, Clone)]
@@ -16,6 +37,26 @@ pub struct AnthropicConfig {
     pub model: String,
     pub max_retries: u32,
     pub timeout_seconds: u64,
+    /// Attach response headers and a body snapshot to API errors
+    pub debug_errors: bool,
+    /// Connection settings for the client `AnthropicProvider::new` creates
+    pub http: HttpClientOptions,
+    /// Requests one provider (and its clones) may have in flight at once
+    pub max_concurrent_requests: Option<usize>,
+    /// Times a broken stream is re-requested and resumed within one call
+    pub stream_reconnects: u32,
+    /// Capabilities and pricing of known models
+    pub models: Arc<ModelRegistry>,
+    /// Clamp out-of-range settings and drop unsupported ones instead of failing
+    pub clamp_settings: bool,
+    /// Receives every raw SSE frame of streamed responses
+    pub sse_capture: Option<SseCapture>,
+    /// Post-processing of tool parameter schemas, targeting Anthropic by default
+    pub schema_options: SchemaOptions,
+    /// Extra HTTP headers sent with every request, e.g. a gateway's auth
+    pub headers: Vec<(String, String)>,
+    /// Beta features enabled for every request
+    pub betas: Vec<AnthropicBeta>,
 }
 
 impl AnthropicConfig {
@@ -26,6 +67,16 @@ impl AnthropicConfig {
             model: model.into(),
             max_retries: 3,
             timeout_seconds: 60,
+            debug_errors: false,
+            http: HttpClientOptions::default(),
+            max_concurrent_requests: None,
+            stream_reconnects: 0,
+            models: builtin_models(),
+            clamp_settings: false,
+            sse_capture: None,
+            schema_options: SchemaOptions::for_target(SchemaTarget::Anthropic),
+            headers: Vec::new(),
+            betas: Vec::new(),
         }
     }
 
@@ -43,39 +94,165 @@ impl AnthropicConfig {
         self.max_retries = retries;
         self
     }
+
+    pub fn with_debug_errors(mut self, debug_errors: bool) -> Self {
+        self.debug_errors = debug_errors;
+        self
+    }
+
+    pub fn with_http_options(mut self, http: HttpClientOptions) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Queue requests beyond `limit` until earlier ones finish, streams included
+    pub fn with_max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = Some(limit);
+        self
+    }
+
+    pub fn with_clamped_settings(mut self, clamp_settings: bool) -> Self {
+        self.clamp_settings = clamp_settings;
+        self
+    }
+
+    /// Look models up in `models` instead of the built-in table
+    pub fn with_models(mut self, models: impl Into<Arc<ModelRegistry>>) -> Self {
+        self.models = models.into();
+        self
+    }
+
+    /// Re-send a stream that breaks off with a retryable error, up to `reconnects` times,
+    /// skipping the text the caller has already received
+    pub fn with_stream_reconnects(mut self, reconnects: u32) -> Self {
+        self.stream_reconnects = reconnects;
+        self
+    }
+
+    /// Pass the raw SSE frames of streamed responses to `capture`, alongside the parsed
+    /// chunks, to see events that were ignored or failed to parse
+    pub fn with_sse_capture(mut self, capture: SseCapture) -> Self {
+        self.sse_capture = Some(capture);
+        self
+    }
+
+    /// Post-process tool parameter schemas before sending them, on top of the router's
+    pub fn with_schema_options(mut self, options: SchemaOptions) -> Self {
+        self.schema_options = options;
+        self
+    }
+
+    /// Send an extra HTTP header with every request
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Enable a beta feature for every request
+    pub fn with_beta(mut self, beta: AnthropicBeta) -> Self {
+        if !self.betas.contains(&beta) {
+            self.betas.push(beta);
+        }
+        self
+    }
+
+    /// Send requests through `gateway`, replacing `base_url` and adding its headers;
+    /// tag single requests with `ChatRequest::headers(gateway.tag_headers(..))`
+    pub fn with_gateway(mut self, gateway: &Gateway) -> Self {
+        let (base_url, headers) = gateway.route("anthropic", &self.base_url);
+        self.base_url = base_url;
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Configuration from `ANTHROPIC_API_KEY` and the optional `ANTHROPIC_BASE_URL` and
+    /// `ANTHROPIC_MODEL` (default `DEFAULT_MODEL`)
+    ///
+    /// When `AI_MODELS_FILE` names a JSON file, its models and aliases override the
+    /// built-in ones, see `ModelRegistry::extend_from_json`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let api_key = var("ANTHROPIC_API_KEY").ok_or_else(|| {
+            AiError::Validation(ValidationError::MissingField {
+                field: "ANTHROPIC_API_KEY".to_string(),
+            })
+        })?;
+        let model = var("ANTHROPIC_MODEL").unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        let mut config = Self::new(api_key, model);
+        if let Some(base_url) = var("ANTHROPIC_BASE_URL") {
+            config = config.with_base_url(base_url);
+        }
+        if let Some(path) = var("AI_MODELS_FILE") {
+            config = config.with_models(ModelRegistry::builtin().extend_from_file(path)?);
+        }
+        Ok(config)
+    }
 }
 
 /// Anthropic provider implementation
 #[derive(Clone)]
 pub struct AnthropicProvider {
     config: AnthropicConfig,
-    client: Client,
+    transport: Arc<dyn HttpTransport>,
 }
 
 impl AnthropicProvider {
+    /// Provider sending requests with reqwest
+    #[cfg(feature = "reqwest")]
     pub fn new(config: AnthropicConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(config.timeout_seconds))
-            .build()
-            .map_err(|e| {
-                AiError::Network(NetworkError::ConnectionFailed {
-                    message: format!("Failed to create HTTP client: {}", e),
-                })
-            })?;
+        let transport = ai_core::http::ReqwestTransport::with_options(
+            Duration::from_secs(config.timeout_seconds),
+            &config.http,
+        )?;
+        Ok(Self::with_transport(config, transport))
+    }
 
-        Ok(Self { config, client })
+    /// Provider sending requests through an existing client, sharing its connection pool;
+    /// the client's own timeout and connection settings apply
+    #[cfg(feature = "reqwest")]
+    pub fn with_client(config: AnthropicConfig, client: ai_core::http::reqwest::Client) -> Self {
+        Self::with_transport(config, ai_core::http::ReqwestTransport::new(client))
+    }
+
+    /// Provider configured by `AnthropicConfig::from_env`
+    #[cfg(feature = "reqwest")]
+    pub fn from_env() -> Result<Self> {
+        Self::new(AnthropicConfig::from_env()?)
+    }
+
+    /// Provider sending requests through `transport`; `config.timeout_seconds` is up to it
+    ///
+    /// A model alias in `config.model` is resolved through `config.models` here.
+    pub fn with_transport(
+        mut config: AnthropicConfig,
+        transport: impl HttpTransport + 'static,
+    ) -> Self {
+        config.model = config.models.resolve("anthropic", &config.model);
+        let transport: Arc<dyn HttpTransport> = match config.max_concurrent_requests {
+            Some(limit) => Arc::new(ConcurrencyLimit::new(transport, limit)),
+            None => Arc::new(transport),
+        };
+        Self { config, transport }
+    }
+
+    fn registered_model(&self) -> Option<&ModelInfo> {
+        self.config.models.get("anthropic", &self.config.model)
     }
 
     /// Convert our Message enum to Anthropic's message format
     fn convert_messages(
         &self,
         messages: &[Message],
-    ) -> Result<(Option<String>, Vec<AnthropicMessage>)> {
-        let mut system_prompt = None;
-        let mut anthropic_messages = Vec::new();
+    ) -> Result<(Option<AnthropicSystem>, Vec<AnthropicMessage>)> {
+        let mut system_prompt: Option<String> = None;
+        let mut system_cached = false;
+        let mut anthropic_messages: Vec<AnthropicMessage> = Vec::new();
 
         for message in messages {
-            match message {
+            let (role, mut content) = match message {
                 Message::System { content, .. } => {
                     // Anthropic uses a separate system parameter
                     let text = content
@@ -86,41 +263,67 @@ impl AnthropicProvider {
                         .collect::<Vec<_>>()
                         .join(" ");
 
+                    // Multiple system messages (e.g. a prompt plus a summary) are joined
                     if !text.is_empty() {
-                        system_prompt = Some(text);
+                        system_prompt = Some(match system_prompt.take() {
+                            Some(existing) => format!("{}\n\n{}", existing, text),
+                            None => text,
+                        });
                     }
+                    system_cached |= message.is_cached();
+                    continue;
                 }
-                Message::User { content, .. } => {
-                    let anthropic_content = self.convert_text_content(content)?;
-                    anthropic_messages.push(AnthropicMessage {
-                        role: "user".to_string(),
-                        content: anthropic_content,
-                    });
-                }
+                Message::User { content, .. } => ("user", self.convert_text_content(content)?),
                 Message::Assistant { content, .. } => {
-                    let anthropic_content = self.convert_assistant_content(content)?;
-                    anthropic_messages.push(AnthropicMessage {
-                        role: "assistant".to_string(),
-                        content: anthropic_content,
-                    });
+                    ("assistant", self.convert_assistant_content(content)?)
                 }
                 Message::Tool { tool_results, .. } => {
-                    // Convert tool results to user messages in Anthropic format
-                    for result in tool_results {
-                        anthropic_messages.push(AnthropicMessage {
-                            role: "user".to_string(),
-                            content: vec![AnthropicContent::ToolResult {
+                    let results = tool_results
+                        .iter()
+                        .map(|result| {
+                            Ok(AnthropicContent::ToolResult {
                                 tool_use_id: result.tool_call_id.clone(),
-                                content: result.result.to_string(),
+                                content: self.convert_tool_result(result)?,
                                 is_error: Some(result.is_error),
-                            }],
-                        });
-                    }
+                                cache_control: None,
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    ("user", results)
                 }
+            };
+
+            // The breakpoint goes on the last block sent for a cached message
+            if message.is_cached()
+                && let Some(block) = content.last_mut()
+            {
+                block.cache();
+            }
+
+            // Tool results and the user text after them are sent as one user turn
+            match anthropic_messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(content),
+                _ if content.is_empty() => {}
+                _ => anthropic_messages.push(AnthropicMessage {
+                    role: role.to_string(),
+                    content,
+                }),
             }
         }
 
-        Ok((system_prompt, anthropic_messages))
+        let system = system_prompt.map(|text| {
+            if system_cached {
+                let mut block = AnthropicContent::Text {
+                    text,
+                    cache_control: None,
+                };
+                block.cache();
+                AnthropicSystem::Blocks(vec![block])
+            } else {
+                AnthropicSystem::Text(text)
+            }
+        });
+        Ok((system, anthropic_messages))
     }
 
     fn convert_text_content(&self, content: &[UserContent]) -> Result<Vec<AnthropicContent>> {
@@ -129,26 +332,13 @@ impl AnthropicProvider {
         for item in content {
             match item {
                 UserContent::Text { text } => {
-                    anthropic_content.push(AnthropicContent::Text { text: text.clone() });
+                    anthropic_content.push(AnthropicContent::Text {
+                        text: text.clone(),
+                        cache_control: None,
+                    });
                 }
                 UserContent::Image { image } => {
-                    if let Some(base64) = &image.base64 {
-                        anthropic_content.push(AnthropicContent::Image {
-                            source: AnthropicImageSource {
-                                r#type: "base64".to_string(),
-                                media_type: image
-                                    .mime_type
-                                    .clone()
-                                    .unwrap_or("image/jpeg".to_string()),
-                                data: base64.clone(),
-                            },
-                        });
-                    } else {
-                        return Err(AiError::Validation(ValidationError::InvalidValue {
-                            field: "image".to_string(),
-                            message: "Anthropic requires base64 encoded images".to_string(),
-                        }));
-                    }
+                    anthropic_content.push(self.convert_image(image)?);
                 }
             }
         }
@@ -156,6 +346,64 @@ impl AnthropicProvider {
         Ok(anthropic_content)
     }
 
+    fn convert_image(&self, image: &ImageContent) -> Result<AnthropicContent> {
+        if let Some(base64) = &image.base64 {
+            Ok(AnthropicContent::Image {
+                source: AnthropicImageSource {
+                    r#type: "base64".to_string(),
+                    media_type: image.mime_type.clone().unwrap_or("image/jpeg".to_string()),
+                    data: base64.clone(),
+                },
+                cache_control: None,
+            })
+        } else {
+            Err(AiError::Validation(ValidationError::InvalidValue {
+                field: "image".to_string(),
+                message: "Anthropic requires base64 encoded images".to_string(),
+            }))
+        }
+    }
+
+    /// Convert a tool result into a plain string, or content blocks when it carries extra parts
+    fn convert_tool_result(&self, result: &ToolResult) -> Result<AnthropicToolResultContent> {
+        let text = match &result.result {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        if result.content.is_empty() {
+            return Ok(AnthropicToolResultContent::Text(text));
+        }
+
+        let mut blocks = Vec::new();
+        if !result.result.is_null() {
+            blocks.push(AnthropicContent::Text {
+                text,
+                cache_control: None,
+            });
+        }
+        for part in &result.content {
+            match part {
+                ToolResultContent::Text { text } => {
+                    blocks.push(AnthropicContent::Text {
+                        text: text.clone(),
+                        cache_control: None,
+                    });
+                }
+                ToolResultContent::Image { image } => {
+                    blocks.push(self.convert_image(image)?);
+                }
+                ToolResultContent::Json { value } => {
+                    blocks.push(AnthropicContent::Text {
+                        text: value.to_string(),
+                        cache_control: None,
+                    });
+                }
+            }
+        }
+
+        Ok(AnthropicToolResultContent::Blocks(blocks))
+    }
+
     fn convert_assistant_content(
         &self,
         content: &[AssistantContent],
@@ -166,7 +414,10 @@ impl AnthropicProvider {
             match item {
                 AssistantContent::Text { text } => {
                     if !text.is_empty() {
-                        anthropic_content.push(AnthropicContent::Text { text: text.clone() });
+                        anthropic_content.push(AnthropicContent::Text {
+                            text: text.clone(),
+                            cache_control: None,
+                        });
                     }
                 }
                 AssistantContent::ToolCall { tool_call } => {
@@ -174,6 +425,7 @@ impl AnthropicProvider {
                         id: tool_call.id.clone(),
                         name: tool_call.name.clone(),
                         input: tool_call.arguments.clone(),
+                        cache_control: None,
                     });
                 }
             }
@@ -182,68 +434,223 @@ impl AnthropicProvider {
         Ok(anthropic_content)
     }
 
+    /// Validate or clamp the settings, then build the Messages API request
+    fn build_request(&self, request: &ChatRequest, stream: bool) -> Result<AnthropicRequest> {
+        let settings = self.settings_rules().apply(
+            "anthropic",
+            &request.settings,
+            self.config.clamp_settings,
+        )?;
+        let messages = normalize_messages(request.messages.clone());
+        validate_alternation(&messages)?;
+        let (system, messages) = self.convert_messages(&messages)?;
+        Ok(AnthropicRequest {
+            model: self.config.model.clone(),
+            max_tokens: settings.max_tokens.unwrap_or(1000),
+            temperature: settings.temperature,
+            top_p: settings.top_p,
+            top_k: settings.top_k,
+            stop_sequences: settings.stop_sequences,
+            system,
+            messages,
+            tools: request.tools.as_ref().map(|t| self.convert_tools(t)),
+            stream,
+            headers: self.request_headers(&request.headers)?,
+        })
+    }
+
+    /// A request's headers with its `anthropic-beta` values checked and merged with the
+    /// configured betas into a single header
+    fn request_headers(&self, headers: &[(String, String)]) -> Result<Vec<(String, String)>> {
+        let mut betas = self.config.betas.clone();
+        let mut merged = Vec::new();
+        for (name, value) in headers {
+            if !name.eq_ignore_ascii_case(BETA_HEADER) {
+                merged.push((name.clone(), value.clone()));
+                continue;
+            }
+            for beta in value.split(',').filter(|beta| !beta.trim().is_empty()) {
+                let beta: AnthropicBeta = beta.parse()?;
+                if !betas.contains(&beta) {
+                    betas.push(beta);
+                }
+            }
+        }
+        if !betas.is_empty() {
+            merged.push((BETA_HEADER.to_string(), beta::join(&betas)));
+        }
+        Ok(merged)
+    }
+
     fn convert_tools(&self, tools: &[ToolDefinition]) -> Vec<AnthropicTool> {
         tools
             .iter()
-            .map(|tool| AnthropicTool {
-                name: tool.name.clone(),
-                description: tool.description.clone(),
-                input_schema: tool.parameters.clone(),
+            .map(|tool| {
+                let mut input_schema = tool.parameters.clone();
+                self.config.schema_options.apply(&mut input_schema);
+                // Extensions can't replace the fields the tool is built from
+                let extra = tool
+                    .extensions_for("anthropic")
+                    .into_iter()
+                    .flatten()
+                    .filter(|(key, _)| {
+                        !matches!(key.as_str(), "name" | "description" | "input_schema")
+                    })
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                AnthropicTool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    input_schema,
+                    extra,
+                }
             })
             .collect()
     }
 
-    async fn make_request(&self, request: AnthropicRequest) -> Result<AnthropicResponse> {
-        let response = self
-            .client
-            .post(format!("{}/v1/messages", self.config.base_url))
+    /// Send a messages request, turning error statuses into provider errors
+    async fn send(&self, request: &AnthropicRequest) -> Result<HttpResponse> {
+        let mut http_request = HttpRequest::post(format!("{}/v1/messages", self.config.base_url))
             .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
-                AiError::Network(NetworkError::ConnectionFailed {
-                    message: format!("Request failed: {}", e),
-                })
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-
-            // Check for specific error types
-            if status == 401 {
-                return Err(AiError::Provider(ProviderError::Authentication {
-                    provider: "anthropic".to_string(),
-                    message: error_text,
-                }));
-            } else if status == 429 {
-                // TODO: Parse retry-after header if available
-                return Err(AiError::Provider(ProviderError::RateLimit {
-                    provider: "anthropic".to_string(),
-                    retry_after: None,
-                    message: error_text,
-                }));
-            } else {
-                return Err(AiError::Provider(ProviderError::ApiError {
-                    provider: "anthropic".to_string(),
-                    status: status.as_u16(),
-                    message: error_text,
-                }));
+            .header("anthropic-version", "2023-06-01");
+        for (name, value) in self.config.headers.iter().chain(&request.headers) {
+            http_request = http_request.header(name, value);
+        }
+        let request = http_request.json(request)?;
+        let response = self.transport.send(request).await?;
+        if response.is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status;
+        let retry_after = response
+            .header_value("retry-after")
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let header_request_id = response.header_value("request-id").map(str::to_string);
+        let headers = response.headers.clone();
+        let error_text = response.text().await.unwrap_or_default();
+        let diagnostics = self
+            .config
+            .debug_errors
+            .then(|| Box::new(HttpDiagnostics::capture(&headers, &error_text)));
+
+        let mut error = match serde_json::from_str::<AnthropicErrorBody>(&error_text) {
+            Ok(body) => api_error(
+                status,
+                &body.error.r#type,
+                body.error.message,
+                body.request_id.or(header_request_id),
+                retry_after,
+            ),
+            Err(_) => {
+                let error_type = match status {
+                    400 | 413 => "invalid_request_error",
+                    401 => "authentication_error",
+                    429 => "rate_limit_error",
+                    529 => "overloaded_error",
+                    _ => "api_error",
+                };
+                api_error(
+                    status,
+                    error_type,
+                    error_text,
+                    header_request_id,
+                    retry_after,
+                )
+            }
+        };
+        if let AiError::Provider(ProviderError::ApiError {
+            diagnostics: slot, ..
+        }) = &mut error
+        {
+            *slot = diagnostics;
+        }
+        Err(error)
+    }
+
+    /// Send a streaming request and parse its events into chunks
+    async fn open_stream(&self, request: &AnthropicRequest) -> Result<ChatStream> {
+        let response = self.send(request).await?;
+
+        let capture = self.config.sse_capture.clone();
+        let stream = response.body.eventsource().filter_map(move |event_result| {
+            let item = match event_result {
+                Ok(event) => Self::parse_stream_event(event, capture.as_ref()),
+                Err(e) => Some(Err(AiError::Network(NetworkError::ConnectionFailed {
+                    message: format!("Stream error: {}", e),
+                }))),
+            };
+            futures::future::ready(item)
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Parse one SSE event, dropping events that carry nothing for the caller
+    fn parse_stream_event(
+        event: eventsource_stream::Event,
+        capture: Option<&SseCapture>,
+    ) -> Option<Result<ChatStreamChunk>> {
+        let parsed = serde_json::from_str::<AnthropicStreamEvent>(&event.data);
+        if let Some(capture) = capture {
+            capture.record(&SseFrame {
+                provider: "anthropic".to_string(),
+                event: event.event,
+                data: event.data,
+                parse_error: parsed.as_ref().err().map(ToString::to_string),
+            });
+        }
+        // Unknown or malformed events are skipped, as the API asks clients to do
+        let result = AnthropicProvider::handle_stream_event_static(parsed.ok()?);
+        match &result {
+            Ok(chunk) => {
+                let empty_delta = matches!(chunk.delta, MessageDelta::Assistant { content: None });
+                (!empty_delta || chunk.finish_reason.is_some() || chunk.usage.is_some())
+                    .then_some(result)
             }
+            Err(_) => Some(result),
         }
+    }
 
-        response.json().await.map_err(|e| {
+    /// Send a request and parse the response, along with its `ChatResponse` metadata
+    async fn make_request(
+        &self,
+        request: AnthropicRequest,
+    ) -> Result<(AnthropicResponse, HashMap<String, serde_json::Value>)> {
+        let stopwatch = Stopwatch::start();
+        let response = self.send(&request).await?;
+        let mut metadata = HashMap::new();
+        if let Some(request_id) = response.header_value("request-id") {
+            metadata.insert(REQUEST_ID_METADATA_KEY.to_string(), request_id.into());
+        }
+        let limits = rate_limits(&response);
+        if !limits.is_empty() {
+            metadata.insert(
+                RATE_LIMITS_METADATA_KEY.to_string(),
+                serde_json::to_value(limits)?,
+            );
+        }
+
+        let body = response.bytes().await?;
+        let parsed: AnthropicResponse = serde_json::from_slice(&body).map_err(|e| {
             AiError::Serialization(SerializationError::JsonError {
                 message: format!("Failed to parse response: {}", e),
             })
-        })
+        })?;
+        metadata.insert(
+            LATENCY_MS_METADATA_KEY.to_string(),
+            (stopwatch.elapsed().as_millis() as u64).into(),
+        );
+        if let Some(model) = &parsed.model {
+            metadata.insert(MODEL_METADATA_KEY.to_string(), model.as_str().into());
+        }
+        Ok((parsed, metadata))
     }
 }
 
-#[async_trait]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 impl ChatTextGeneration for AnthropicProvider {
     fn name(&self) -> &str {
         "anthropic"
@@ -258,7 +665,9 @@ impl ChatTextGeneration for AnthropicProvider {
     }
 
     fn supports_vision(&self) -> bool {
-        self.config.model.contains("claude-3")
+        // Every Claude model since Claude 3 accepts images
+        self.registered_model()
+            .is_none_or(|info| info.supports_vision)
     }
 
     fn supports_system_messages(&self) -> bool {
@@ -266,33 +675,53 @@ impl ChatTextGeneration for AnthropicProvider {
     }
 
     fn max_tokens(&self) -> Option<u32> {
-        // Claude models have different limits, but 4096 is a safe default
-        Some(4096)
+        // 4096 is a safe default for models missing from the registry
+        Some(
+            self.registered_model()
+                .map_or(4096, |info| info.max_output_tokens),
+        )
     }
 
-    async fn generate(&self, request: ChatRequest) -> Result<ChatResponse> {
-        let (system, messages) = self.convert_messages(&request.messages)?;
+    fn context_window(&self) -> Option<u32> {
+        Some(
+            self.registered_model()
+                .map_or(200_000, |info| info.context_window),
+        )
+    }
 
-        let anthropic_request = AnthropicRequest {
-            model: self.config.model.clone(),
-            max_tokens: request.settings.max_tokens.unwrap_or(1000),
-            temperature: request.settings.temperature,
-            system,
-            messages,
-            tools: request.tools.as_ref().map(|t| self.convert_tools(t)),
-            stream: false,
-        };
+    fn model_info(&self) -> Option<ModelInfo> {
+        self.registered_model().cloned()
+    }
+
+    fn settings_rules(&self) -> SettingsRules {
+        SettingsRules {
+            temperature: Some((0.0, 1.0)),
+            top_p: Some((0.0, 1.0)),
+            top_k: true,
+            penalties: None,
+            seed: false,
+            max_tokens: self.max_tokens(),
+            max_stop_sequences: None,
+            logit_bias: None,
+            constraints: Vec::new(),
+        }
+    }
 
-        let response = self.make_request(anthropic_request).await?;
+    async fn generate(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let anthropic_request = self.build_request(&request, false)?;
+
+        let (response, metadata) = self.make_request(anthropic_request).await?;
 
         // Convert Anthropic response back to our format
         let mut content = Vec::new();
         for item in response.content {
             match item {
-                AnthropicContent::Text { text } => {
+                AnthropicContent::Text { text, .. } => {
                     content.push(AssistantContent::Text { text });
                 }
-                AnthropicContent::ToolUse { id, name, input } => {
+                AnthropicContent::ToolUse {
+                    id, name, input, ..
+                } => {
                     content.push(AssistantContent::ToolCall {
                         tool_call: ToolCall {
                             id,
@@ -305,17 +734,22 @@ impl ChatTextGeneration for AnthropicProvider {
             }
         }
 
+        let text: String = content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
         let message = Message::Assistant {
             content,
             metadata: None,
         };
 
-        let finish_reason = match response.stop_reason.as_deref() {
-            Some("end_turn") => FinishReason::Stop,
-            Some("max_tokens") => FinishReason::Length,
-            Some("tool_use") => FinishReason::ToolCalls,
-            _ => FinishReason::Stop,
-        };
+        let finish_reason = response
+            .stop_reason
+            .as_deref()
+            .map_or(FinishReason::Stop, finish_reason);
 
         let usage = response.usage.map(|u| Usage {
             prompt_tokens: u.input_tokens,
@@ -323,114 +757,35 @@ impl ChatTextGeneration for AnthropicProvider {
             total_tokens: u.input_tokens + u.output_tokens,
         });
 
-        Ok(ChatResponse {
+        let mut response = ChatResponse {
             id: response.id,
             message,
             finish_reason,
             usage,
-            metadata: None,
-        })
-    }
-
-    async fn generate_stream(
-        &self,
-        request: ChatRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<ChatStreamChunk>> + Send>>> {
-        let (system, messages) = self.convert_messages(&request.messages)?;
-
-        let anthropic_request = AnthropicRequest {
-            model: self.config.model.clone(),
-            max_tokens: request.settings.max_tokens.unwrap_or(1000),
-            temperature: request.settings.temperature,
-            system,
-            messages,
-            tools: request.tools.as_ref().map(|t| self.convert_tools(t)),
-            stream: true,
+            metadata: Some(metadata),
         };
-
-        let response = self
-            .client
-            .post(format!("{}/v1/messages", self.config.base_url))
-            .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&anthropic_request)
-            .send()
-            .await
-            .map_err(|e| {
-                AiError::Network(NetworkError::ConnectionFailed {
-                    message: format!("Stream request failed: {}", e),
-                })
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-
-            if status == 401 {
-                return Err(AiError::Provider(ProviderError::Authentication {
-                    provider: "anthropic".to_string(),
-                    message: error_text,
-                }));
-            } else if status == 429 {
-                return Err(AiError::Provider(ProviderError::RateLimit {
-                    provider: "anthropic".to_string(),
-                    retry_after: None,
-                    message: error_text,
-                }));
-            } else {
-                return Err(AiError::Provider(ProviderError::ApiError {
-                    provider: "anthropic".to_string(),
-                    status: status.as_u16(),
-                    message: error_text,
-                }));
+        if response.finish_reason == FinishReason::ContentFilter {
+            // Claude's safety classifiers stopped the response; any text is the model's
+            let mut refusal = Refusal::new(RefusalKind::Filtered).provider_reason("refusal");
+            if !text.is_empty() {
+                refusal = refusal.message(text);
             }
+            response = response.with_refusal(refusal);
         }
+        Ok(response)
+    }
 
-        // Use proper SSE parsing
-        let stream = response
-            .bytes_stream()
-            .eventsource()
-            .filter_map(|event_result| async move {
-                match event_result {
-                    Ok(event) => {
-                        // Parse the SSE event data
-                        match serde_json::from_str::<AnthropicStreamEvent>(&event.data) {
-                            Ok(stream_event) => {
-                                let result =
-                                    AnthropicProvider::handle_stream_event_static(stream_event);
-                                // Only return Some if it's an error or has meaningful content
-                                match &result {
-                                    Ok(chunk) => {
-                                        let empty_delta = matches!(
-                                            chunk.delta,
-                                            MessageDelta::Assistant { content: None }
-                                        );
-                                        if !empty_delta
-                                            || chunk.finish_reason.is_some()
-                                            || chunk.usage.is_some()
-                                        {
-                                            Some(result)
-                                        } else {
-                                            None
-                                        }
-                                    }
-                                    Err(_) => Some(result),
-                                }
-                            }
-                            Err(_) => {
-                                // Ignore parsing errors for unknown/ping events
-                                None
-                            }
-                        }
-                    }
-                    Err(e) => Some(Err(AiError::Network(NetworkError::ConnectionFailed {
-                        message: format!("Stream error: {}", e),
-                    }))),
-                }
-            });
+    async fn generate_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        let anthropic_request = self.build_request(&request, true)?;
 
-        Ok(Box::pin(stream))
+        let request = Arc::new(anthropic_request);
+        let stream = self.open_stream(&request).await?;
+        Ok(cumulative_usage(Box::pin(resume_stream(
+            self.clone(),
+            request,
+            stream,
+            self.config.stream_reconnects,
+        ))))
     }
 }
 
@@ -478,18 +833,19 @@ impl AnthropicProvider {
                             // For now, just ignore these incremental JSON updates
                             None
                         }
-                        "thinking_delta" => {
-                            // For extended thinking - could be handled separately
-                            Some(AssistantContent::Text {
-                                text: delta.thinking.unwrap_or_default(),
-                            })
-                        }
                         _ => None,
                     };
+                    let delta = match delta.thinking {
+                        // Extended thinking stays out of the answer
+                        Some(text) if delta.r#type == "thinking_delta" => {
+                            MessageDelta::Reasoning { text }
+                        }
+                        _ => MessageDelta::Assistant { content },
+                    };
 
                     Ok(ChatStreamChunk {
                         id: "stream".to_string(),
-                        delta: MessageDelta::Assistant { content },
+                        delta,
                         finish_reason: None,
                         usage: None,
                     })
@@ -513,12 +869,7 @@ impl AnthropicProvider {
             }
             "message_delta" => {
                 if let AnthropicStreamEventData::MessageDelta { delta, usage } = event.data {
-                    let finish_reason = delta.stop_reason.map(|reason| match reason.as_str() {
-                        "end_turn" => FinishReason::Stop,
-                        "max_tokens" => FinishReason::Length,
-                        "tool_use" => FinishReason::ToolCalls,
-                        _ => FinishReason::Stop,
-                    });
+                    let finish_reason = delta.stop_reason.as_deref().map(finish_reason);
 
                     let usage = usage.map(|u| Usage {
                         prompt_tokens: u.input_tokens,
@@ -561,17 +912,15 @@ impl AnthropicProvider {
             }
             "error" => {
                 if let AnthropicStreamEventData::Error { error } = event.data {
-                    Err(AiError::Provider(ProviderError::ApiError {
-                        provider: "anthropic".to_string(),
-                        status: 500,
-                        message: error.message,
-                    }))
+                    Err(api_error(500, &error.r#type, error.message, None, None))
                 } else {
-                    Err(AiError::Provider(ProviderError::ApiError {
-                        provider: "anthropic".to_string(),
-                        status: 500,
-                        message: "Unknown error".to_string(),
-                    }))
+                    Err(api_error(
+                        500,
+                        "api_error",
+                        "Unknown error".to_string(),
+                        None,
+                        None,
+                    ))
                 }
             }
             _ => {
@@ -595,11 +944,20 @@ struct AnthropicRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<AnthropicSystem>,
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<AnthropicTool>>,
     stream: bool,
+    /// Headers of the originating `ChatRequest`, sent after the configured ones
+    #[serde(skip)]
+    headers: Vec<(String, String)>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -613,23 +971,64 @@ struct AnthropicMessage {
 enum AnthropicContent {
     Text {
         text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
     Image {
         source: AnthropicImageSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
     ToolUse {
         id: String,
         name: String,
         input: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
     ToolResult {
         tool_use_id: String,
-        content: String,
+        content: AnthropicToolResultContent,
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
 }
 
+impl AnthropicContent {
+    /// Place a cache breakpoint after this block
+    fn cache(&mut self) {
+        let (Self::Text { cache_control, .. }
+        | Self::Image { cache_control, .. }
+        | Self::ToolUse { cache_control, .. }
+        | Self::ToolResult { cache_control, .. }) = self;
+        *cache_control = Some(AnthropicCacheControl {
+            r#type: "ephemeral".to_string(),
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnthropicCacheControl {
+    r#type: String,
+}
+
+/// System prompt: plain text, or one block when it carries a cache breakpoint
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum AnthropicSystem {
+    Text(String),
+    Blocks(Vec<AnthropicContent>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum AnthropicToolResultContent {
+    Text(String),
+    Blocks(Vec<AnthropicContent>),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct AnthropicImageSource {
     r#type: String,
@@ -642,11 +1041,15 @@ struct AnthropicTool {
     name: String,
     description: String,
     input_schema: serde_json::Value,
+    /// `ToolDefinition::extensions` for Anthropic, e.g. `cache_control`
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
 struct AnthropicResponse {
     id: String,
+    model: Option<String>,
     content: Vec<AnthropicContent>,
     stop_reason: Option<String>,
     usage: Option<AnthropicUsage>,
@@ -654,6 +1057,8 @@ struct AnthropicResponse {
 
 #[derive(Debug, Deserialize)]
 struct AnthropicUsage {
+    /// Missing from `message_delta`, which only reports output tokens
+    #[serde(default)]
     input_tokens: u32,
     output_tokens: u32,
 }
@@ -745,7 +1150,945 @@ struct AnthropicMessageDelta {
 
 #[derive(Debug, Deserialize)]
 struct AnthropicStreamError {
-    #[allow(dead_code)]
     r#type: String,
     message: String,
 }
+
+/// Body of an error response: `{"type": "error", "error": {...}, "request_id": ...}`
+#[derive(Debug, Deserialize)]
+struct AnthropicErrorBody {
+    error: AnthropicStreamError,
+    request_id: Option<String>,
+}
+
+/// `FinishReason` of an Anthropic `stop_reason`
+fn finish_reason(stop_reason: &str) -> FinishReason {
+    match stop_reason {
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
+/// Rate limits from the `anthropic-ratelimit-*` response headers
+fn rate_limits(response: &HttpResponse) -> RateLimits {
+    let header = |name: &str| response.header_value(&format!("anthropic-ratelimit-{}", name));
+    let number = |name: &str| header(name).and_then(|value| value.trim().parse().ok());
+    let text = |name: &str| header(name).map(str::to_string);
+    RateLimits {
+        requests_limit: number("requests-limit"),
+        requests_remaining: number("requests-remaining"),
+        requests_reset: text("requests-reset"),
+        tokens_limit: number("tokens-limit"),
+        tokens_remaining: number("tokens-remaining"),
+        tokens_reset: text("tokens-reset"),
+        input_tokens_remaining: number("input-tokens-remaining"),
+        output_tokens_remaining: number("output-tokens-remaining"),
+    }
+}
+
+/// Stream `stream`, re-sending `request` when it breaks off with a retryable error
+///
+/// The regenerated response has to repeat the text and reasoning already yielded; that
+/// prefix is skipped, along with the new message's start and block markers, and
+/// streaming continues where it left off. When reconnects run out, or the new response
+/// diverges, the stream ends with `ProviderError::StreamInterrupted` carrying the text
+/// yielded so far.
+fn resume_stream(
+    provider: AnthropicProvider,
+    request: Arc<AnthropicRequest>,
+    mut stream: ChatStream,
+    reconnects: u32,
+) -> impl futures::Stream<Item = Result<ChatStreamChunk>> {
+    async_stream::stream! {
+        let mut attempts = 0;
+        let mut sent = String::new();
+        let mut sent_reasoning = String::new();
+        // Text and reasoning of the interrupted attempt the new one still has to repeat
+        let mut replay = String::new();
+        let mut replay_reasoning = String::new();
+        while let Some(item) = stream.next().await {
+            let error = match item {
+                Ok(mut chunk) => {
+                    let (delta, yielded, pending) = match &mut chunk.delta {
+                        MessageDelta::Assistant {
+                            content: Some(AssistantContent::Text { text }),
+                        } => (text, &mut sent, &mut replay),
+                        MessageDelta::Reasoning { text } => {
+                            (text, &mut sent_reasoning, &mut replay_reasoning)
+                        }
+                        // Markers of a resumed attempt repeat ones already yielded
+                        MessageDelta::Assistant { content: None }
+                            if attempts > 0 && chunk.finish_reason.is_none() =>
+                        {
+                            continue;
+                        }
+                        _ => {
+                            yield Ok(chunk);
+                            continue;
+                        }
+                    };
+                    if !pending.is_empty() {
+                        if let Some(rest) = pending.strip_prefix(delta.as_str()) {
+                            *pending = rest.to_string();
+                            continue;
+                        } else if let Some(rest) = delta.strip_prefix(pending.as_str()) {
+                            *delta = rest.to_string();
+                            pending.clear();
+                        } else {
+                            yield Err(interrupted(&sent, "the resumed response diverged"));
+                            return;
+                        }
+                    }
+                    yielded.push_str(delta);
+                    yield Ok(chunk);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            if (sent.is_empty() && sent_reasoning.is_empty()) || !error.is_retryable() {
+                yield Err(error);
+                return;
+            }
+            if attempts == reconnects {
+                yield Err(interrupted(&sent, &error.to_string()));
+                return;
+            }
+            attempts += 1;
+            match provider.open_stream(&request).await {
+                Ok(resumed) => {
+                    stream = resumed;
+                    replay = sent.clone();
+                    replay_reasoning = sent_reasoning.clone();
+                }
+                Err(error) => {
+                    yield Err(interrupted(&sent, &error.to_string()));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn interrupted(partial_text: &str, message: &str) -> AiError {
+    AiError::Provider(ProviderError::StreamInterrupted {
+        provider: "anthropic".to_string(),
+        message: message.to_string(),
+        partial_text: partial_text.to_string(),
+    })
+}
+
+/// Map an Anthropic error type onto the matching provider error
+fn api_error(
+    status: u16,
+    error_type: &str,
+    message: String,
+    request_id: Option<String>,
+    retry_after: Option<Duration>,
+) -> AiError {
+    let provider = "anthropic".to_string();
+    AiError::Provider(match error_type {
+        "authentication_error" | "permission_error" => ProviderError::Authentication {
+            provider,
+            message,
+            request_id,
+        },
+        "rate_limit_error" => ProviderError::RateLimit {
+            provider,
+            retry_after,
+            message,
+            request_id,
+        },
+        "overloaded_error" => ProviderError::Overloaded {
+            provider,
+            message,
+            request_id,
+        },
+        "invalid_request_error" | "request_too_large" => ProviderError::InvalidRequest {
+            provider,
+            message,
+            request_id,
+        },
+        _ => ProviderError::ApiError {
+            provider,
+            status,
+            message,
+            request_id,
+            diagnostics: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_core::gateway::GatewayTags;
+
+    fn provider() -> AnthropicProvider {
+        with_config(AnthropicConfig::new(
+            "test-key",
+            "claude-3-5-haiku-20241022",
+        ))
+    }
+
+    /// Provider that is never sent a request, so it needs no HTTP client
+    fn with_config(config: AnthropicConfig) -> AnthropicProvider {
+        AnthropicProvider::with_transport(config, CannedTransport::default())
+    }
+
+    #[test]
+    fn test_config_from_env_vars() {
+        let vars = |name: &str| match name {
+            "ANTHROPIC_API_KEY" => Some("env-key".to_string()),
+            "ANTHROPIC_BASE_URL" => Some("http://localhost:8080".to_string()),
+            _ => None,
+        };
+        let config = AnthropicConfig::from_lookup(vars).unwrap();
+        assert_eq!(config.api_key, "env-key");
+        assert_eq!(config.base_url, "http://localhost:8080");
+        assert_eq!(config.model, DEFAULT_MODEL);
+
+        assert!(matches!(
+            AnthropicConfig::from_lookup(|_| None),
+            Err(AiError::Validation(ValidationError::MissingField { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_capabilities_come_from_the_model_registry() {
+        let provider = provider();
+        assert!(provider.supports_vision());
+        assert_eq!(provider.max_tokens(), Some(8192));
+        assert_eq!(provider.context_window(), Some(200_000));
+        assert_eq!(provider.model_info().unwrap().model, "claude-3-5-haiku");
+
+        let registry = ModelRegistry::new().register(ModelInfo {
+            provider: "anthropic".to_string(),
+            model: "claude-3-5-haiku".to_string(),
+            context_window: 100_000,
+            max_output_tokens: 1024,
+            supports_tools: true,
+            supports_vision: false,
+            supports_caching: false,
+            input_price: None,
+            output_price: None,
+        });
+        let provider = with_config(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022").with_models(registry),
+        );
+        assert!(!provider.supports_vision());
+        assert_eq!(provider.max_tokens(), Some(1024));
+        assert_eq!(provider.context_window(), Some(100_000));
+    }
+
+    #[test]
+    fn test_model_aliases_are_resolved() {
+        let registry =
+            ModelRegistry::builtin().alias("anthropic", "house-model", "claude-3-5-haiku-20241022");
+        let provider =
+            with_config(AnthropicConfig::new("test-key", "house-model").with_models(registry));
+        assert_eq!(provider.model(), "claude-3-5-haiku-20241022");
+        assert_eq!(provider.max_tokens(), Some(8192));
+    }
+
+    #[test]
+    fn test_settings_are_validated_or_clamped() {
+        let request = ChatRequest::new()
+            .user("Hi")
+            .temperature(1.5)
+            .max_tokens(100_000);
+        match provider().build_request(&request, false) {
+            Err(AiError::Validation(ValidationError::InvalidValue { field, .. })) => {
+                assert_eq!(field, "temperature")
+            }
+            other => panic!("expected an invalid temperature, got {:?}", other),
+        }
+
+        let mut seeded = ChatRequest::new().user("Hi");
+        seeded.settings.seed = Some(7);
+        assert!(provider().build_request(&seeded, false).is_err());
+        let mut penalized = ChatRequest::new().user("Hi");
+        penalized.settings.frequency_penalty = Some(0.5);
+        match provider().build_request(&penalized, false) {
+            Err(AiError::Provider(ProviderError::UnsupportedFeature { feature, .. })) => {
+                assert_eq!(feature, "frequency_penalty")
+            }
+            other => panic!("expected an unsupported setting, got {:?}", other),
+        }
+        let constrained = ChatRequest::new()
+            .user("Hi")
+            .constraint(GenerationConstraint::regex("[a-z]+"));
+        assert!(provider().build_request(&constrained, false).is_err());
+        let biased = ChatRequest::new().user("Hi").logit_bias("1234", -100.0);
+        assert!(provider().build_request(&biased, false).is_err());
+
+        let clamping = with_config(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022")
+                .with_clamped_settings(true),
+        );
+        let clamped = clamping.build_request(&request, false).unwrap();
+        assert_eq!(clamped.temperature, Some(1.0));
+        assert_eq!(clamped.max_tokens, 8192);
+        assert!(clamping.build_request(&seeded, false).is_ok());
+        assert!(clamping.build_request(&penalized, false).is_ok());
+        assert!(clamping.build_request(&constrained, false).is_ok());
+    }
+
+    #[test]
+    fn test_cached_messages_get_cache_breakpoints() {
+        let request = ChatRequest::new()
+            .message(Message::system("Long instructions").cached())
+            .message(Message::user("Reference document").cached())
+            .user("Question");
+        let body =
+            serde_json::to_value(provider().build_request(&request, false).unwrap()).unwrap();
+
+        let ephemeral = serde_json::json!({ "type": "ephemeral" });
+        assert_eq!(body["system"][0]["text"], "Long instructions");
+        assert_eq!(body["system"][0]["cache_control"], ephemeral);
+        assert_eq!(
+            body["messages"][0]["content"][0]["cache_control"],
+            ephemeral
+        );
+        assert!(
+            body["messages"][0]["content"][1]
+                .get("cache_control")
+                .is_none()
+        );
+
+        let plain = ChatRequest::new().system("Short").user("Hi");
+        let body = serde_json::to_value(provider().build_request(&plain, false).unwrap()).unwrap();
+        assert_eq!(body["system"], "Short");
+    }
+
+    #[test]
+    fn test_tool_extensions_are_sent() {
+        let cached = ToolDefinition::new("search", "Search the docs", serde_json::json!({}))
+            .strict(true)
+            .extension(
+                "anthropic",
+                "cache_control",
+                serde_json::json!({ "type": "ephemeral" }),
+            )
+            .extension("anthropic", "name", "renamed".into())
+            .extension("openai", "ignored", true.into());
+        let request = ChatRequest::new().user("Hi").tools(vec![
+            cached,
+            ToolDefinition::new("plain", "", serde_json::json!({})),
+        ]);
+        let body =
+            serde_json::to_value(provider().build_request(&request, false).unwrap()).unwrap();
+
+        assert_eq!(
+            body["tools"][0],
+            serde_json::json!({
+                "name": "search",
+                "description": "Search the docs",
+                "input_schema": { "type": "object" },
+                "cache_control": { "type": "ephemeral" }
+            })
+        );
+        assert!(body["tools"][1].get("cache_control").is_none());
+    }
+
+    #[test]
+    fn test_conversations_are_normalized_into_alternating_turns() {
+        let calls = Message::Assistant {
+            content: ["call_1", "call_2"]
+                .map(|id| AssistantContent::ToolCall {
+                    tool_call: ToolCall {
+                        id: id.to_string(),
+                        name: "lookup".to_string(),
+                        arguments: serde_json::json!({}),
+                    },
+                })
+                .to_vec(),
+            metadata: None,
+        };
+        let result = |id: &str| {
+            Message::tool(ToolResult {
+                tool_call_id: id.to_string(),
+                result: serde_json::json!("ok"),
+                is_error: false,
+                content: Vec::new(),
+            })
+        };
+        let request = ChatRequest::new()
+            .user("Look both up")
+            .message(calls)
+            .user("Quickly")
+            .message(result("call_2"))
+            .message(result("call_1"));
+        let body =
+            serde_json::to_value(provider().build_request(&request, false).unwrap()).unwrap();
+
+        let roles: Vec<_> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "assistant", "user"]);
+        let turn = &body["messages"][2]["content"];
+        assert_eq!(turn[0]["tool_use_id"], "call_1");
+        assert_eq!(turn[1]["tool_use_id"], "call_2");
+        assert_eq!(turn[2]["text"], "Quickly");
+
+        let dangling = ChatRequest::new().user("Hi").message(result("call_9"));
+        assert!(provider().build_request(&dangling, false).is_err());
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_providers_share_a_configured_client() {
+        let options = HttpClientOptions::new()
+            .pool_max_idle_per_host(32)
+            .pool_idle_timeout(Duration::from_secs(30))
+            .tcp_keepalive(Duration::from_secs(60))
+            .tcp_nodelay(true);
+        let config = AnthropicConfig::new("test-key", DEFAULT_MODEL).with_http_options(options);
+        assert_eq!(config.http.pool_max_idle_per_host, Some(32));
+        assert!(AnthropicProvider::new(config.clone()).is_ok());
+
+        let client = ai_core::http::reqwest::Client::new();
+        let first = AnthropicProvider::with_client(config.clone(), client.clone());
+        let second = AnthropicProvider::with_client(config, client);
+        assert_eq!(first.model(), second.model());
+    }
+
+    #[test]
+    fn test_tool_result_with_image_content() {
+        let messages = vec![Message::tool(ToolResult {
+            tool_call_id: "call_1".to_string(),
+            result: serde_json::json!("Rendered chart"),
+            is_error: false,
+            content: vec![ToolResultContent::Image {
+                image: ImageContent {
+                    url: None,
+                    base64: Some("aGVsbG8=".to_string()),
+                    mime_type: Some("image/png".to_string()),
+                },
+            }],
+        })];
+
+        let (_, converted) = provider().convert_messages(&messages).unwrap();
+        let json = serde_json::to_value(&converted).unwrap();
+        assert_eq!(
+            json[0]["content"][0]["content"],
+            serde_json::json!([
+                {"type": "text", "text": "Rendered chart"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "aGVsbG8="}}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_plain_tool_result_is_sent_as_text() {
+        let messages = vec![Message::tool(ToolResult {
+            tool_call_id: "call_1".to_string(),
+            result: serde_json::json!("42"),
+            is_error: false,
+            content: Vec::new(),
+        })];
+
+        let (_, converted) = provider().convert_messages(&messages).unwrap();
+        let json = serde_json::to_value(&converted).unwrap();
+        assert_eq!(json[0]["content"][0]["content"], "42");
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(64))]
+
+        #[test]
+        fn test_any_request_converts_without_panicking(
+            request in proptest::arbitrary::any::<ChatRequest>(),
+        ) {
+            if let Ok(converted) = provider().build_request(&request, false) {
+                serde_json::to_value(&converted).unwrap();
+            }
+        }
+    }
+
+    /// Transport answering every request with the next canned response
+    #[derive(Debug, Default)]
+    struct CannedTransport {
+        responses: std::sync::Mutex<Vec<HttpResponse>>,
+        requests: std::sync::Mutex<Vec<HttpRequest>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for CannedTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.requests.lock().unwrap().push(request);
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_the_transport() {
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![
+            HttpResponse::new(429, "slow down").header("retry-after", "7"),
+            HttpResponse::new(
+                200,
+                serde_json::json!({
+                    "id": "msg_1",
+                    "model": "claude-3-5-haiku-20241022",
+                    "content": [{"type": "text", "text": "Hello"}],
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 3, "output_tokens": 1},
+                })
+                .to_string(),
+            )
+            .header("request-id", "req_1")
+            .header("anthropic-ratelimit-requests-remaining", "49")
+            .header("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:30Z"),
+        ];
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022"),
+            transport.clone(),
+        );
+        let request = ChatRequest::new().user("Hi");
+
+        match provider.generate(request.clone()).await {
+            Err(AiError::Provider(ProviderError::RateLimit { retry_after, .. })) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(7)))
+            }
+            other => panic!("expected a rate limit error, got {:?}", other),
+        }
+        let response = provider.generate(request).await.unwrap();
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.model_used(), Some("claude-3-5-haiku-20241022"));
+        assert_eq!(response.request_id(), Some("req_1"));
+        assert!(response.latency().is_some());
+        assert_eq!(
+            response.rate_limits(),
+            Some(RateLimits {
+                requests_remaining: Some(49),
+                tokens_reset: Some("2025-01-01T00:00:30Z".to_string()),
+                ..RateLimits::default()
+            })
+        );
+        assert_eq!(response.usage.unwrap().total_tokens, 4);
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests[1].url, "https://api.anthropic.com/v1/messages");
+        assert_eq!(requests[1].header_value("x-api-key"), Some("test-key"));
+    }
+
+    #[tokio::test]
+    async fn test_refusals_finish_with_content_filter() {
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![HttpResponse::new(
+            200,
+            serde_json::json!({
+                "id": "msg_1",
+                "content": [{"type": "text", "text": "I can't"}],
+                "stop_reason": "refusal",
+            })
+            .to_string(),
+        )];
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", DEFAULT_MODEL),
+            transport,
+        );
+
+        let response = provider
+            .generate(ChatRequest::new().user("Hi"))
+            .await
+            .unwrap();
+        assert_eq!(response.finish_reason, FinishReason::ContentFilter);
+        assert_eq!(
+            response.refusal(),
+            Some(
+                Refusal::new(RefusalKind::Filtered)
+                    .message("I can't")
+                    .provider_reason("refusal")
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gateway_and_request_headers() {
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![HttpResponse::new(
+            200,
+            serde_json::json!({"id": "msg_1", "content": [], "stop_reason": "end_turn"})
+                .to_string(),
+        )];
+        let gateway = Gateway::helicone("sk-helicone");
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022").with_gateway(&gateway),
+            transport.clone(),
+        );
+        let request = ChatRequest::new()
+            .user("Hi")
+            .headers(gateway.tag_headers(&GatewayTags::new().user_id("u1")));
+        provider.generate(request).await.unwrap();
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests[0].url, "https://anthropic.helicone.ai/v1/messages");
+        assert_eq!(requests[0].header_value("x-api-key"), Some("test-key"));
+        assert_eq!(
+            requests[0].header_value("helicone-auth"),
+            Some("Bearer sk-helicone")
+        );
+        assert_eq!(requests[0].header_value("helicone-user-id"), Some("u1"));
+    }
+
+    #[test]
+    fn test_betas_merge_into_one_header() {
+        let provider = with_config(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022")
+                .with_beta(AnthropicBeta::PromptCaching)
+                .with_beta(AnthropicBeta::PromptCaching),
+        );
+        let request = ChatRequest::new()
+            .user("Hi")
+            .header("x-trace", "1")
+            .headers([AnthropicBeta::request_header(&[
+                AnthropicBeta::TokenCounting,
+                AnthropicBeta::PromptCaching,
+            ])]);
+        let converted = provider.build_request(&request, false).unwrap();
+        assert_eq!(
+            converted.headers,
+            vec![
+                ("x-trace".to_string(), "1".to_string()),
+                (
+                    "anthropic-beta".to_string(),
+                    "prompt-caching-2024-07-31,token-counting-2024-11-01".to_string()
+                ),
+            ]
+        );
+
+        let request = ChatRequest::new()
+            .user("Hi")
+            .header("anthropic-beta", "made-up-2099-01-01");
+        assert!(matches!(
+            provider.build_request(&request, false),
+            Err(AiError::Validation(ValidationError::InvalidValue { .. }))
+        ));
+    }
+
+    /// SSE body streaming `texts`, optionally breaking off with a connection error
+    fn sse_response(texts: &[&str], complete: bool) -> HttpResponse {
+        let mut chunks: Vec<Result<Vec<u8>>> = texts
+            .iter()
+            .map(|text| {
+                let data = serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": {"type": "text_delta", "text": text},
+                });
+                Ok(format!("event: content_block_delta\ndata: {}\n\n", data).into_bytes())
+            })
+            .collect();
+        if complete {
+            chunks.push(Ok(
+                b"event: message_stop\ndata: {\"type\": \"message_stop\"}\n\n".to_vec(),
+            ));
+        } else {
+            chunks.push(Err(AiError::Network(NetworkError::ConnectionFailed {
+                message: "connection reset".to_string(),
+            })));
+        }
+        HttpResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: Box::pin(futures::stream::iter(chunks)),
+        }
+    }
+
+    async fn stream_text(provider: &AnthropicProvider) -> (String, Option<AiError>) {
+        let mut stream = provider
+            .generate_stream(ChatRequest::new().user("Hi"))
+            .await
+            .unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(ChatStreamChunk {
+                    delta:
+                        MessageDelta::Assistant {
+                            content: Some(AssistantContent::Text { text: delta }),
+                        },
+                    ..
+                }) => text.push_str(&delta),
+                Ok(_) => {}
+                Err(error) => return (text, Some(error)),
+            }
+        }
+        (text, None)
+    }
+
+    #[tokio::test]
+    async fn test_broken_streams_resume_or_return_partial_text() {
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![
+            sse_response(&["Hello, ", "wor"], false),
+            sse_response(&["Hello, w", "orld", "!"], true),
+            sse_response(&["Hello, ", "wor"], false),
+        ];
+        let config = AnthropicConfig::new("test-key", DEFAULT_MODEL);
+        let provider = AnthropicProvider::with_transport(
+            config.clone().with_stream_reconnects(1),
+            transport.clone(),
+        );
+        assert_eq!(
+            stream_text(&provider).await,
+            ("Hello, world!".to_string(), None)
+        );
+
+        let provider = AnthropicProvider::with_transport(config, transport);
+        let (text, error) = stream_text(&provider).await;
+        assert_eq!(text, "Hello, wor");
+        match error {
+            Some(AiError::Provider(ProviderError::StreamInterrupted { partial_text, .. })) => {
+                assert_eq!(partial_text, "Hello, wor")
+            }
+            other => panic!("expected an interrupted stream, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_usage_is_cumulative() {
+        let events = [
+            r#"{"type": "message_start", "message": {"id": "msg_1", "type": "message", "role": "assistant", "model": "claude", "content": [], "stop_reason": null, "usage": {"input_tokens": 12, "output_tokens": 1}}}"#,
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}"#,
+            r#"{"type": "message_delta", "delta": {"stop_reason": "end_turn", "stop_sequence": null}, "usage": {"output_tokens": 5}}"#,
+        ];
+        let body: String = events
+            .iter()
+            .map(|data| format!("event: message\ndata: {}\n\n", data))
+            .collect();
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![HttpResponse::new(200, body)];
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", DEFAULT_MODEL),
+            transport,
+        );
+
+        let chunks: Vec<ChatStreamChunk> = provider
+            .generate_stream(ChatRequest::new().user("Hi"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let usage: Vec<Option<Usage>> = chunks.iter().map(|chunk| chunk.usage.clone()).collect();
+        assert_eq!(
+            usage,
+            vec![Some(Usage::new(12, 1)), None, Some(Usage::new(12, 5))]
+        );
+        assert_eq!(chunks[2].finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[tokio::test]
+    async fn test_thinking_is_streamed_as_reasoning() {
+        let events = [
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "The user greets me."}}"#,
+            r#"{"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Hi"}}"#,
+        ];
+        let body: String = events
+            .iter()
+            .map(|data| format!("event: content_block_delta\ndata: {}\n\n", data))
+            .collect();
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![HttpResponse::new(200, body)];
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", DEFAULT_MODEL),
+            transport,
+        );
+
+        let deltas: Vec<MessageDelta> = provider
+            .generate_stream(ChatRequest::new().user("Hi"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap().delta)
+            .collect()
+            .await;
+        assert_eq!(
+            deltas,
+            vec![
+                MessageDelta::Reasoning {
+                    text: "The user greets me.".to_string()
+                },
+                MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text {
+                        text: "Hi".to_string()
+                    })
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streams_broken_during_thinking_resume_without_repeats() {
+        let start = r#"{"type": "message_start", "message": {"id": "msg_1", "type": "message", "role": "assistant", "model": "claude", "content": [], "stop_reason": null, "usage": {"input_tokens": 12, "output_tokens": 1}}}"#;
+        let thinking = |text: &str| {
+            serde_json::json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": {"type": "thinking_delta", "thinking": text},
+            })
+            .to_string()
+        };
+        let frames = |events: &[String]| -> Vec<Result<Vec<u8>>> {
+            events
+                .iter()
+                .map(|data| Ok(format!("event: message\ndata: {}\n\n", data).into_bytes()))
+                .collect()
+        };
+        let mut broken = frames(&[start.to_string(), thinking("The user ")]);
+        broken.push(Err(AiError::Network(NetworkError::ConnectionFailed {
+            message: "connection reset".to_string(),
+        })));
+        let resumed = frames(&[
+            start.to_string(),
+            thinking("The user gre"),
+            thinking("ets me."),
+            r#"{"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Hi"}}"#.to_string(),
+            r#"{"type": "message_delta", "delta": {"stop_reason": "end_turn", "stop_sequence": null}, "usage": {"output_tokens": 5}}"#.to_string(),
+        ]);
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = [broken, resumed]
+            .into_iter()
+            .map(|chunks| HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: Box::pin(futures::stream::iter(chunks)),
+            })
+            .collect();
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", DEFAULT_MODEL).with_stream_reconnects(1),
+            transport,
+        );
+
+        let chunks: Vec<ChatStreamChunk> = provider
+            .generate_stream(ChatRequest::new().user("Hi"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let deltas: Vec<&MessageDelta> = chunks.iter().map(|chunk| &chunk.delta).collect();
+        assert_eq!(
+            deltas,
+            [
+                &MessageDelta::Assistant { content: None },
+                &MessageDelta::Reasoning {
+                    text: "The user ".to_string()
+                },
+                &MessageDelta::Reasoning {
+                    text: "gre".to_string()
+                },
+                &MessageDelta::Reasoning {
+                    text: "ets me.".to_string()
+                },
+                &MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text {
+                        text: "Hi".to_string()
+                    })
+                },
+                &MessageDelta::Assistant { content: None },
+            ]
+        );
+        assert_eq!(chunks.iter().filter(|chunk| chunk.id == "msg_1").count(), 1);
+        assert_eq!(chunks.last().unwrap().usage, Some(Usage::new(12, 5)));
+    }
+
+    #[tokio::test]
+    async fn test_raw_sse_frames_are_captured() {
+        let mut response = sse_response(&["Hi"], true);
+        let body = std::mem::replace(&mut response.body, Box::pin(futures::stream::empty()));
+        let malformed = futures::stream::iter([
+            Ok(b"event: content_block_delta\ndata: {\"type\": \"content_block_del".to_vec()),
+            Ok(b"\n\n".to_vec()),
+        ]);
+        response.body = Box::pin(malformed.chain(body));
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![response];
+
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let capture = SseCapture::new({
+            let frames = frames.clone();
+            move |frame| frames.lock().unwrap().push(frame.clone())
+        });
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", DEFAULT_MODEL).with_sse_capture(capture),
+            transport,
+        );
+        assert_eq!(stream_text(&provider).await, ("Hi".to_string(), None));
+
+        let frames = frames.lock().unwrap();
+        let events: Vec<&str> = frames.iter().map(|frame| frame.event.as_str()).collect();
+        assert_eq!(
+            events,
+            ["content_block_delta", "content_block_delta", "message_stop"]
+        );
+        assert!(frames[0].parse_error.is_some());
+        assert_eq!(frames[0].data, "{\"type\": \"content_block_del");
+        assert!(frames[1].parse_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_map_to_provider_errors() {
+        let error_body = |error_type: &str| {
+            serde_json::json!({
+                "type": "error",
+                "error": {"type": error_type, "message": "details"},
+                "request_id": "req_body",
+            })
+            .to_string()
+        };
+        let transport = Arc::new(CannedTransport::default());
+        *transport.responses.lock().unwrap() = vec![
+            HttpResponse::new(529, error_body("overloaded_error")),
+            HttpResponse::new(400, error_body("invalid_request_error")),
+            HttpResponse::new(502, "<html>Bad gateway</html>").header("request-id", "req_header"),
+        ];
+        let provider = AnthropicProvider::with_transport(
+            AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022").with_debug_errors(true),
+            transport,
+        );
+        let request = ChatRequest::new().user("Hi");
+
+        let error = provider.generate(request.clone()).await.unwrap_err();
+        assert!(error.is_retryable());
+        assert_eq!(
+            error,
+            AiError::Provider(ProviderError::Overloaded {
+                provider: "anthropic".to_string(),
+                message: "details".to_string(),
+                request_id: Some("req_body".to_string()),
+            })
+        );
+
+        let error = provider.generate(request.clone()).await.unwrap_err();
+        assert!(!error.is_retryable());
+        assert!(matches!(
+            error,
+            AiError::Provider(ProviderError::InvalidRequest { .. })
+        ));
+
+        match provider.generate(request).await.unwrap_err() {
+            AiError::Provider(error @ ProviderError::ApiError { status: 502, .. }) => {
+                assert_eq!(error.request_id(), Some("req_header"));
+                assert!(error.to_string().contains("<html>Bad gateway</html>"));
+                let ProviderError::ApiError { diagnostics, .. } = error else {
+                    unreachable!()
+                };
+                let diagnostics = diagnostics.unwrap();
+                assert_eq!(
+                    diagnostics.headers,
+                    vec![("request-id".to_string(), "req_header".to_string())]
+                );
+                assert_eq!(diagnostics.body, "<html>Bad gateway</html>");
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+}
@@ -32,8 +32,11 @@ fn create_simple_request(content: &str) -> ChatRequest {
             presence_penalty: None,
             stop_sequences: None,
             seed: None,
+            logit_bias: None,
+            constraint: None,
         },
         tools: None,
+        headers: Vec::new(),
     }
 }
 
@@ -66,7 +69,7 @@ async fn test_basic_conversation() {
             for item in &content {
                 if let AssistantContent::Text { text } = item {
                     println!("Response text: {}", text);
-                    assert!(text.len() > 0, "Text content should not be empty");
+                    assert!(!text.is_empty(), "Text content should not be empty");
                 }
             }
         }
@@ -117,14 +120,12 @@ async fn test_streaming_conversation() {
         chunks.push(chunk.clone());
 
         // Accumulate text content
-        match chunk.delta {
-            MessageDelta::Assistant {
-                content: Some(AssistantContent::Text { text }),
-            } => {
-                accumulated_text.push_str(&text);
-                println!("Streaming text: {}", text);
-            }
-            _ => {}
+        if let MessageDelta::Assistant {
+            content: Some(AssistantContent::Text { text }),
+        } = chunk.delta
+        {
+            accumulated_text.push_str(&text);
+            println!("Streaming text: {}", text);
         }
 
         // Capture final usage and finish reason
@@ -194,8 +195,11 @@ async fn test_conversation_with_system_message() {
             presence_penalty: None,
             stop_sequences: None,
             seed: None,
+            logit_bias: None,
+            constraint: None,
         },
         tools: None,
+        headers: Vec::new(),
     };
 
     let response = provider
@@ -227,10 +231,10 @@ async fn test_tool_use_conversation() {
     let provider = setup();
 
     // Define a simple calculator tool
-    let calculator_tool = ToolDefinition {
-        name: "calculator".to_string(),
-        description: "Perform basic mathematical operations".to_string(),
-        parameters: serde_json::json!({
+    let calculator_tool = ToolDefinition::new(
+        "calculator",
+        "Perform basic mathematical operations",
+        serde_json::json!({
             "type": "object",
             "properties": {
                 "operation": {
@@ -249,7 +253,7 @@ async fn test_tool_use_conversation() {
             },
             "required": ["operation", "a", "b"]
         }),
-    };
+    );
 
     let request = ChatRequest {
         messages: vec![Message::User {
@@ -267,8 +271,11 @@ async fn test_tool_use_conversation() {
             presence_penalty: None,
             stop_sequences: None,
             seed: None,
+            logit_bias: None,
+            constraint: None,
         },
         tools: Some(vec![calculator_tool]),
+        headers: Vec::new(),
     };
 
     let response = provider
@@ -363,8 +370,11 @@ async fn test_multi_turn_conversation() {
             presence_penalty: None,
             stop_sequences: None,
             seed: None,
+            logit_bias: None,
+            constraint: None,
         },
         tools: None,
+        headers: Vec::new(),
     };
 
     let response2 = provider
@@ -471,6 +481,7 @@ async fn test_image_conversation() {
             ..Default::default()
         },
         tools: None,
+        headers: Vec::new(),
     };
 
     let response = provider
@@ -0,0 +1,164 @@
+use ai_anthropic::{AnthropicConfig, AnthropicProvider};
+use ai_core::provider::ChatTextGeneration;
+use ai_core::types::*;
+use ai_core::{AiError, ProviderError};
+use ai_test_utils::{MockApiServer, fixtures::anthropic};
+use futures::StreamExt;
+
+fn provider(server: &MockApiServer) -> AnthropicProvider {
+    let config =
+        AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022").with_base_url(server.uri());
+    AnthropicProvider::new(config).expect("Failed to create provider")
+}
+
+fn text_of(message: &Message) -> String {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Text and finish reason of a stream, or the error it ended with
+async fn collect_stream(
+    provider: &AnthropicProvider,
+    request: ChatRequest,
+) -> (String, Option<FinishReason>, Option<AiError>) {
+    let mut stream = provider.generate_stream(request).await.unwrap();
+    let (mut text, mut finish_reason) = (String::new(), None);
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => {
+                if let MessageDelta::Assistant {
+                    content: Some(AssistantContent::Text { text: delta }),
+                } = &chunk.delta
+                {
+                    text.push_str(delta);
+                }
+                finish_reason = finish_reason.or(chunk.finish_reason);
+            }
+            Err(e) => return (text, finish_reason, Some(e)),
+        }
+    }
+    (text, finish_reason, None)
+}
+
+#[tokio::test]
+async fn test_text_response() {
+    let server = MockApiServer::start().await;
+    server.anthropic_message(anthropic::TEXT_RESPONSE).await;
+
+    let response = provider(&server)
+        .generate(ChatRequest::new().system("Be terse.").user("What's 2+2?"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.id, "msg_01XFDUDYJgAACzvnptvVoYEL");
+    assert_eq!(text_of(&response.message), "2 + 2 = 4");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage.unwrap().total_tokens, 23);
+
+    let requests = server.requests().await;
+    assert_eq!(requests[0]["model"], "claude-3-5-haiku-20241022");
+    assert_eq!(requests[0]["system"], "Be terse.");
+    assert_eq!(requests[0]["messages"][0]["role"], "user");
+}
+
+#[tokio::test]
+async fn test_tool_use_response() {
+    let server = MockApiServer::start().await;
+    server.anthropic_message(anthropic::TOOL_USE_RESPONSE).await;
+
+    let response = provider(&server)
+        .generate(ChatRequest::new().user("Weather in Paris?"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    let Message::Assistant { content, .. } = &response.message else {
+        panic!("expected an assistant message");
+    };
+    let AssistantContent::ToolCall { tool_call } = &content[1] else {
+        panic!("expected a tool call, got {:?}", content[1]);
+    };
+    assert_eq!(tool_call.id, "toolu_01A09q90qw90lq917835lq9");
+    assert_eq!(tool_call.name, "get_weather");
+    assert_eq!(tool_call.arguments["location"], "Paris, France");
+}
+
+#[tokio::test]
+async fn test_error_responses_are_typed() {
+    let server = MockApiServer::start().await;
+    server
+        .anthropic_error(529, anthropic::OVERLOADED_ERROR)
+        .await;
+    server
+        .anthropic_error(400, anthropic::INVALID_REQUEST_ERROR)
+        .await;
+    let provider = provider(&server);
+
+    match provider.generate(ChatRequest::new().user("Hi")).await {
+        Err(AiError::Provider(ProviderError::Overloaded { request_id, .. })) => {
+            assert_eq!(request_id.as_deref(), Some("req_011CSHoEeqs5C35K2UUqR7Fy"))
+        }
+        other => panic!("expected an overloaded error, got {:?}", other),
+    }
+    match provider.generate(ChatRequest::new().user("Hi")).await {
+        Err(AiError::Provider(ProviderError::InvalidRequest { message, .. })) => {
+            assert!(message.contains("roles must alternate"))
+        }
+        other => panic!("expected an invalid request error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_text_stream() {
+    let server = MockApiServer::start().await;
+    server.anthropic_stream(anthropic::TEXT_STREAM).await;
+
+    let (text, finish_reason, error) =
+        collect_stream(&provider(&server), ChatRequest::new().user("What's 2+2?")).await;
+
+    assert!(error.is_none(), "{:?}", error);
+    assert_eq!(text, "2 + 2 = 4");
+    assert_eq!(finish_reason, Some(FinishReason::Stop));
+    assert_eq!(server.requests().await[0]["stream"], true);
+}
+
+#[tokio::test]
+async fn test_tool_use_stream() {
+    let server = MockApiServer::start().await;
+    server.anthropic_stream(anthropic::TOOL_USE_STREAM).await;
+
+    let (text, finish_reason, error) = collect_stream(
+        &provider(&server),
+        ChatRequest::new().user("Weather in Paris?"),
+    )
+    .await;
+
+    assert!(error.is_none(), "{:?}", error);
+    assert_eq!(text, "Let me check the weather in Paris.");
+    assert_eq!(finish_reason, Some(FinishReason::ToolCalls));
+}
+
+#[tokio::test]
+async fn test_error_stream_keeps_the_partial_text() {
+    let server = MockApiServer::start().await;
+    server.anthropic_stream(anthropic::ERROR_STREAM).await;
+
+    let (text, _, error) =
+        collect_stream(&provider(&server), ChatRequest::new().user("What's 2+2?")).await;
+
+    assert_eq!(text, "2 + 2");
+    match error {
+        Some(AiError::Provider(ProviderError::StreamInterrupted { partial_text, .. })) => {
+            assert_eq!(partial_text, "2 + 2")
+        }
+        other => panic!("expected an interrupted stream, got {:?}", other),
+    }
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+
+use crate::response_metadata::{Refusal, RefusalKind};
+use crate::types::Message;
 use std::time::Duration;
 
 /// Core error type for the AI SDK
@@ -22,19 +25,41 @@ pub enum AiError {
 
     /// Configuration and validation errors
     Validation(ValidationError),
+
+    /// Conversation storage errors
+    Storage(StorageError),
 }
 
 /// Provider-specific errors
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ProviderError {
     /// Authentication failed for a provider
-    Authentication { provider: String, message: String },
+    Authentication {
+        provider: String,
+        message: String,
+        request_id: Option<String>,
+    },
 
     /// Rate limit exceeded
     RateLimit {
         provider: String,
         retry_after: Option<Duration>,
         message: String,
+        request_id: Option<String>,
+    },
+
+    /// Provider is temporarily overloaded
+    Overloaded {
+        provider: String,
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// Provider rejected the request as malformed or too large
+    InvalidRequest {
+        provider: String,
+        message: String,
+        request_id: Option<String>,
     },
 
     /// Model not found or not available
@@ -43,14 +68,83 @@ pub enum ProviderError {
     /// Feature not supported by provider
     UnsupportedFeature { provider: String, feature: String },
 
+    /// A streaming response broke off after some text had been sent
+    StreamInterrupted {
+        provider: String,
+        message: String,
+        /// Text streamed before the interruption
+        partial_text: String,
+    },
+
     /// Generic API error from provider
     ApiError {
         provider: String,
         status: u16,
         message: String,
+        request_id: Option<String>,
+        /// Only captured when the provider is configured to collect diagnostics
+        diagnostics: Option<Box<HttpDiagnostics>>,
     },
 }
 
+/// Snapshot of a failed HTTP response, for bug reports and provider support tickets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpDiagnostics {
+    /// Request id, rate limit, retry and tracing headers; other headers are dropped
+    pub headers: Vec<(String, String)>,
+    /// Start of the response body, at most `MAX_BODY` bytes
+    pub body: String,
+    /// Whether `body` was cut short
+    pub truncated: bool,
+}
+
+impl HttpDiagnostics {
+    /// Longest body snapshot kept, in bytes
+    pub const MAX_BODY: usize = 2048;
+
+    /// Capture the relevant headers and the start of `body`
+    pub fn capture(headers: &[(String, String)], body: &str) -> Self {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| {
+                let name = name.to_ascii_lowercase();
+                name.contains("request-id")
+                    || name.contains("ratelimit")
+                    || matches!(
+                        name.as_str(),
+                        "retry-after" | "content-type" | "date" | "server" | "cf-ray" | "via"
+                    )
+            })
+            .cloned()
+            .collect();
+        let mut end = body.len().min(Self::MAX_BODY);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        Self {
+            headers,
+            body: body[..end].to_string(),
+            truncated: end < body.len(),
+        }
+    }
+}
+
+impl ProviderError {
+    /// Id the provider assigned to the failed request, for support tickets
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            ProviderError::Authentication { request_id, .. }
+            | ProviderError::RateLimit { request_id, .. }
+            | ProviderError::Overloaded { request_id, .. }
+            | ProviderError::InvalidRequest { request_id, .. }
+            | ProviderError::ApiError { request_id, .. } => request_id.as_deref(),
+            ProviderError::ModelNotFound { .. }
+            | ProviderError::UnsupportedFeature { .. }
+            | ProviderError::StreamInterrupted { .. } => None,
+        }
+    }
+}
+
 /// Tool execution errors
 #[derive(Debug, Clone, PartialEq)]
 pub enum ToolError {
@@ -91,6 +185,42 @@ pub enum AgentError {
 
     /// Agent state error
     StateError { message: String },
+
+    /// A guardrail blocked the input or output
+    GuardrailBlocked { guardrail: String, reason: String },
+
+    /// The final answer did not match the requested output schema
+    InvalidOutput { message: String },
+
+    /// A streaming step failed after producing part of its answer
+    PartialResponse {
+        step: u32,
+        /// Assistant message streamed before the failure
+        partial: Box<Message>,
+        message: String,
+    },
+
+    /// The provider refused or filtered a response and the refusal policy gave up
+    Refused { step: u32, refusal: Refusal },
+
+    /// A job or run did not finish within its time limit
+    Timeout {
+        operation: String,
+        duration: Duration,
+    },
+}
+
+/// Conversation storage errors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StorageError {
+    /// Reading or writing the underlying storage failed
+    Io { message: String },
+
+    /// A concurrent writer modified the conversation first
+    Conflict { conversation_id: String },
+
+    /// The storage backend reported an error
+    Backend { backend: String, message: String },
 }
 
 /// Network and transport errors
@@ -120,6 +250,9 @@ pub enum SerializationError {
 
     /// Type mismatch
     TypeMismatch { expected: String, found: String },
+
+    /// Data written with a newer schema version than this build reads
+    UnsupportedVersion { found: u32, supported: u32 },
 }
 
 /// Configuration and validation errors
@@ -133,6 +266,13 @@ pub enum ValidationError {
 
     /// Configuration error
     ConfigError { message: String },
+
+    /// The prompt plus the requested output does not fit the model's context window
+    PromptTooLarge {
+        prompt_tokens: u32,
+        max_output_tokens: u32,
+        context_window: u32,
+    },
 }
 
 /// Tool-specific execution error
@@ -169,6 +309,7 @@ impl Display for AiError {
             AiError::Network(e) => write!(f, "Network error: {}", e),
             AiError::Serialization(e) => write!(f, "Serialization error: {}", e),
             AiError::Validation(e) => write!(f, "Validation error: {}", e),
+            AiError::Storage(e) => write!(f, "Storage error: {}", e),
         }
     }
 }
@@ -176,46 +317,77 @@ impl Display for AiError {
 impl Display for ProviderError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            ProviderError::Authentication { provider, message } => {
-                write!(f, "Authentication failed for {}: {}", provider, message)
+            ProviderError::Authentication {
+                provider, message, ..
+            } => {
+                write!(f, "Authentication failed for {}: {}", provider, message)?;
             }
             ProviderError::RateLimit {
                 provider,
                 retry_after,
                 message,
+                ..
             } => {
                 if let Some(duration) = retry_after {
                     write!(
                         f,
                         "Rate limit exceeded for {} (retry after {:?}): {}",
                         provider, duration, message
-                    )
+                    )?;
                 } else {
-                    write!(f, "Rate limit exceeded for {}: {}", provider, message)
+                    write!(f, "Rate limit exceeded for {}: {}", provider, message)?;
                 }
             }
+            ProviderError::Overloaded {
+                provider, message, ..
+            } => {
+                write!(f, "{} is overloaded: {}", provider, message)?;
+            }
+            ProviderError::InvalidRequest {
+                provider, message, ..
+            } => {
+                write!(f, "Invalid request to {}: {}", provider, message)?;
+            }
+            ProviderError::StreamInterrupted {
+                provider,
+                message,
+                partial_text,
+            } => {
+                write!(
+                    f,
+                    "Stream from {} interrupted after {} bytes of text: {}",
+                    provider,
+                    partial_text.len(),
+                    message
+                )?;
+            }
             ProviderError::ModelNotFound { provider, model } => {
-                write!(f, "Model '{}' not found for provider {}", model, provider)
+                write!(f, "Model '{}' not found for provider {}", model, provider)?;
             }
             ProviderError::UnsupportedFeature { provider, feature } => {
                 write!(
                     f,
                     "Feature '{}' not supported by provider {}",
                     feature, provider
-                )
+                )?;
             }
             ProviderError::ApiError {
                 provider,
                 status,
                 message,
+                ..
             } => {
                 write!(
                     f,
                     "API error from {} (HTTP {}): {}",
                     provider, status, message
-                )
+                )?;
             }
         }
+        if let Some(request_id) = self.request_id() {
+            write!(f, " (request id: {})", request_id)?;
+        }
+        Ok(())
     }
 }
 
@@ -275,6 +447,50 @@ impl Display for AgentError {
             AgentError::StateError { message } => {
                 write!(f, "Agent state error: {}", message)
             }
+            AgentError::GuardrailBlocked { guardrail, reason } => {
+                write!(f, "Blocked by guardrail '{}': {}", guardrail, reason)
+            }
+            AgentError::InvalidOutput { message } => {
+                write!(f, "Invalid structured output: {}", message)
+            }
+            AgentError::PartialResponse { step, message, .. } => {
+                write!(f, "Step {} failed after partial output: {}", step, message)
+            }
+            AgentError::Timeout {
+                operation,
+                duration,
+            } => {
+                write!(f, "{} timed out after {:?}", operation, duration)
+            }
+            AgentError::Refused { step, refusal } => {
+                let kind = match refusal.kind {
+                    RefusalKind::Declined => "declined by the model",
+                    RefusalKind::Filtered => "blocked by a content filter",
+                };
+                write!(f, "Step {} was {}", step, kind)?;
+                match &refusal.message {
+                    Some(message) => write!(f, ": {}", message),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::Io { message } => write!(f, "I/O error: {}", message),
+            StorageError::Conflict { conversation_id } => {
+                write!(
+                    f,
+                    "Conversation '{}' was modified concurrently",
+                    conversation_id
+                )
+            }
+            StorageError::Backend { backend, message } => {
+                write!(f, "{} error: {}", backend, message)
+            }
         }
     }
 }
@@ -310,6 +526,11 @@ impl Display for SerializationError {
             SerializationError::TypeMismatch { expected, found } => {
                 write!(f, "Type mismatch: expected {}, found {}", expected, found)
             }
+            SerializationError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Unsupported schema version {}: this build reads up to version {}",
+                found, supported
+            ),
         }
     }
 }
@@ -326,6 +547,17 @@ impl Display for ValidationError {
             ValidationError::ConfigError { message } => {
                 write!(f, "Configuration error: {}", message)
             }
+            ValidationError::PromptTooLarge {
+                prompt_tokens,
+                max_output_tokens,
+                context_window,
+            } => {
+                write!(
+                    f,
+                    "Prompt of about {} tokens plus {} output tokens exceeds the {}-token context window",
+                    prompt_tokens, max_output_tokens, context_window
+                )
+            }
         }
     }
 }
@@ -349,6 +581,7 @@ impl std::error::Error for AiError {}
 impl std::error::Error for ProviderError {}
 impl std::error::Error for ToolError {}
 impl std::error::Error for AgentError {}
+impl std::error::Error for StorageError {}
 impl std::error::Error for NetworkError {}
 impl std::error::Error for SerializationError {}
 impl std::error::Error for ValidationError {}
@@ -394,5 +627,186 @@ impl AiError {
     }
 }
 
+impl AiError {
+    /// Whether sending the same request again later may succeed
+    ///
+    /// True for rate limits, overload, 408, 429 and 5xx responses, interrupted streams,
+    /// connection failures and timeouts. Authentication, invalid requests, validation and tool errors fail the
+    /// same way every time, so they are not retryable. Retry wrappers and user code should
+    /// use this instead of matching on variants, so they all follow the same policy.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AiError::Provider(
+                ProviderError::RateLimit { .. }
+                | ProviderError::Overloaded { .. }
+                | ProviderError::StreamInterrupted { .. },
+            ) => true,
+            AiError::Provider(ProviderError::ApiError { status, .. })
+            | AiError::Network(NetworkError::HttpError { status, .. }) => {
+                matches!(status, 408 | 429 | 500..=599)
+            }
+            AiError::Network(
+                NetworkError::ConnectionFailed { .. } | NetworkError::Timeout { .. },
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// How long the provider asked to wait before retrying, if it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AiError::Provider(ProviderError::RateLimit { retry_after, .. }) => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Stable snake_case identifier for the error category
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            AiError::Provider(_) => "provider_error",
+            AiError::Tool(_) => "tool_error",
+            AiError::Agent(_) => "agent_error",
+            AiError::Network(_) => "network_error",
+            AiError::Serialization(_) => "serialization_error",
+            AiError::Validation(_) => "validation_error",
+            AiError::Storage(_) => "storage_error",
+        }
+    }
+}
+
+impl ToolExecutionError {
+    /// Stable snake_case identifier for the error category
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            ToolExecutionError::InvalidInput(_) => "invalid_input",
+            ToolExecutionError::StateError(_) => "state_error",
+            ToolExecutionError::ExecutionError(_) => "execution_error",
+            ToolExecutionError::ExternalServiceError { .. } => "external_service_error",
+            ToolExecutionError::Unauthorized(_) => "unauthorized",
+            ToolExecutionError::NotFound(_) => "not_found",
+        }
+    }
+
+    /// Whether calling the tool again (possibly with corrected arguments) may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ToolExecutionError::InvalidInput(_) | ToolExecutionError::ExternalServiceError { .. }
+        )
+    }
+
+    /// Hint for the model on how to recover from the error
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            ToolExecutionError::InvalidInput(_) => {
+                "Check the arguments against the tool's input schema and call the tool again."
+            }
+            ToolExecutionError::StateError(_) => {
+                "The tool is in an inconsistent state; do not retry this call."
+            }
+            ToolExecutionError::ExecutionError(_) => {
+                "The tool failed while running; try different arguments or another approach."
+            }
+            ToolExecutionError::ExternalServiceError { .. } => {
+                "An external service failed; retrying the same call may succeed."
+            }
+            ToolExecutionError::Unauthorized(_) => {
+                "This action is not permitted; do not retry it and inform the user instead."
+            }
+            ToolExecutionError::NotFound(_) => {
+                "The requested tool or resource does not exist; verify the name or identifier."
+            }
+        }
+    }
+
+    /// Structured error payload sent back to the model as a tool result
+    pub fn to_result_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.to_string(),
+            "error_type": self.error_type(),
+            "retryable": self.is_retryable(),
+            "suggestion": self.suggestion(),
+        })
+    }
+}
+
 /// Result type for AI operations
 pub type Result<T> = std::result::Result<T, AiError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_error(status: u16) -> AiError {
+        AiError::Provider(ProviderError::ApiError {
+            provider: "test".to_string(),
+            status,
+            message: "error".to_string(),
+            request_id: None,
+            diagnostics: None,
+        })
+    }
+
+    #[test]
+    fn test_diagnostics_keep_relevant_headers_and_truncate_body() {
+        let headers = vec![
+            ("Request-Id".to_string(), "req_1".to_string()),
+            (
+                "anthropic-ratelimit-requests-remaining".to_string(),
+                "0".to_string(),
+            ),
+            ("set-cookie".to_string(), "secret".to_string()),
+        ];
+        let body = "é".repeat(HttpDiagnostics::MAX_BODY);
+        let diagnostics = HttpDiagnostics::capture(&headers, &body);
+        assert_eq!(diagnostics.headers, headers[..2]);
+        assert!(diagnostics.truncated);
+        assert_eq!(diagnostics.body.len(), HttpDiagnostics::MAX_BODY);
+
+        let diagnostics = HttpDiagnostics::capture(&[], "short");
+        assert_eq!(diagnostics.body, "short");
+        assert!(!diagnostics.truncated);
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        for status in [408, 429, 500, 503, 529] {
+            assert!(provider_error(status).is_retryable(), "HTTP {}", status);
+        }
+        for status in [400, 401, 403, 404, 422] {
+            assert!(!provider_error(status).is_retryable(), "HTTP {}", status);
+        }
+
+        let rate_limited = AiError::Provider(ProviderError::RateLimit {
+            provider: "test".to_string(),
+            retry_after: Some(Duration::from_secs(5)),
+            message: "Slow down".to_string(),
+            request_id: None,
+        });
+        assert!(rate_limited.is_retryable());
+        assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(5)));
+
+        let reset = AiError::Network(NetworkError::ConnectionFailed {
+            message: "connection reset by peer".to_string(),
+        });
+        assert!(reset.is_retryable());
+        assert_eq!(reset.retry_after(), None);
+        assert!(
+            AiError::Network(NetworkError::Timeout {
+                duration: Duration::from_secs(60),
+            })
+            .is_retryable()
+        );
+
+        let unauthorized = AiError::Provider(ProviderError::Authentication {
+            provider: "test".to_string(),
+            message: "Bad key".to_string(),
+            request_id: None,
+        });
+        assert!(!unauthorized.is_retryable());
+        let invalid = AiError::Validation(ValidationError::MissingField {
+            field: "model".to_string(),
+        });
+        assert!(!invalid.is_retryable());
+    }
+}
@@ -0,0 +1,306 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::types::*;
+
+/// One piece of a message as it appears in a transcript
+enum Block<'a> {
+    Text(&'a str),
+    Image(&'a ImageContent),
+    Json(&'a serde_json::Value),
+    Call(&'a ToolCall),
+    Result(&'a ToolResult),
+}
+
+fn heading(message: &Message) -> &'static str {
+    match message {
+        Message::System { .. } => "System",
+        Message::User { .. } => "User",
+        Message::Assistant { .. } => "Assistant",
+        Message::Tool { .. } => "Tool",
+    }
+}
+
+fn blocks(message: &Message) -> Vec<Block<'_>> {
+    match message {
+        Message::System { content, .. } => content
+            .iter()
+            .map(|part| match part {
+                SystemContent::Text { text } => Block::Text(text),
+            })
+            .collect(),
+        Message::User { content, .. } => content
+            .iter()
+            .map(|part| match part {
+                UserContent::Text { text } => Block::Text(text),
+                UserContent::Image { image } => Block::Image(image),
+            })
+            .collect(),
+        Message::Assistant { content, .. } => content
+            .iter()
+            .map(|part| match part {
+                AssistantContent::Text { text } => Block::Text(text),
+                AssistantContent::ToolCall { tool_call } => Block::Call(tool_call),
+            })
+            .collect(),
+        Message::Tool { tool_results, .. } => tool_results
+            .iter()
+            .flat_map(|result| {
+                std::iter::once(Block::Result(result)).chain(result.content.iter().map(|part| {
+                    match part {
+                        ToolResultContent::Text { text } => Block::Text(text),
+                        ToolResultContent::Image { image } => Block::Image(image),
+                        ToolResultContent::Json { value } => Block::Json(value),
+                    }
+                }))
+            })
+            .collect(),
+    }
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Text of a tool result: strings as they are, anything else as JSON
+fn result_text(result: &ToolResult) -> (String, &'static str) {
+    match &result.result {
+        serde_json::Value::String(text) => (text.clone(), ""),
+        value => (pretty(value), "json"),
+    }
+}
+
+/// A fenced code block whose fence is longer than any backtick run inside
+fn fence(code: &str, language: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{code}\n{fence}")
+}
+
+/// Render a conversation as Markdown, one section per message
+pub fn messages_to_markdown(messages: &[Message]) -> String {
+    let mut sections = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut parts = vec![format!("### {}", heading(message))];
+        for block in blocks(message) {
+            parts.push(match block {
+                Block::Text(text) => text.to_string(),
+                Block::Image(image) => match &image.url {
+                    Some(url) => format!("![image]({})", url),
+                    None => "*[image]*".to_string(),
+                },
+                Block::Json(value) => fence(&pretty(value), "json"),
+                Block::Call(call) => format!(
+                    "**Tool call** `{}` (`{}`)\n\n{}",
+                    call.name,
+                    call.id,
+                    fence(&pretty(&call.arguments), "json")
+                ),
+                Block::Result(result) => {
+                    let (text, language) = result_text(result);
+                    let label = if result.is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    };
+                    format!(
+                        "**{}** for `{}`\n\n{}",
+                        label,
+                        result.tool_call_id,
+                        fence(&text, language)
+                    )
+                }
+            });
+        }
+        sections.push(parts.join("\n\n"));
+    }
+    sections.join("\n\n") + "\n"
+}
+
+/// One line per block: `role: text`, `assistant -> name({args}) [id]` for tool calls
+/// and `tool <- id: result` for results; continuation lines are indented
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let role = self.role();
+        for (i, block) in blocks(self).into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let line = match block {
+                Block::Text(text) => format!("{}: {}", role, text),
+                Block::Image(image) => match &image.url {
+                    Some(url) => format!("{}: [image {}]", role, url),
+                    None => format!("{}: [image]", role),
+                },
+                Block::Json(value) => format!("{}: {}", role, value),
+                Block::Call(call) => {
+                    format!(
+                        "{} -> {}({}) [{}]",
+                        role, call.name, call.arguments, call.id
+                    )
+                }
+                Block::Result(result) => {
+                    let text = match &result.result {
+                        serde_json::Value::String(text) => text.clone(),
+                        value => value.to_string(),
+                    };
+                    let error = if result.is_error { " (error)" } else { "" };
+                    format!("{} <- {}{}: {}", role, result.tool_call_id, error, text)
+                }
+            };
+            write!(f, "{}", line.replace('\n', "\n    "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Render a conversation compactly, one line per text, tool call or tool result
+pub fn fmt_transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(Message::to_string)
+        .filter(|message| !message.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:48rem;margin:2rem auto}\
+.message{border-left:4px solid #ccc;margin:1rem 0;padding:0 1rem}\
+.user{border-color:#2563eb}.assistant{border-color:#16a34a}.tool{border-color:#d97706}\
+.text{white-space:pre-wrap}pre{background:#f4f4f5;padding:.5rem;overflow-x:auto}\
+.error{color:#dc2626}img{max-width:100%}";
+
+/// Render a conversation as a standalone HTML page
+pub fn messages_to_html(messages: &[Message]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Transcript</title>\n<style>{}</style>\n</head>\n<body>\n",
+        HTML_STYLE
+    );
+    for message in messages {
+        html.push_str(&format!(
+            "<section class=\"message {}\">\n<h3>{}</h3>\n",
+            message.role(),
+            heading(message)
+        ));
+        for block in blocks(message) {
+            let rendered = match block {
+                Block::Text(text) => format!("<div class=\"text\">{}</div>", escape_html(text)),
+                Block::Image(image) => match &image.url {
+                    Some(url) => format!("<img src=\"{}\" alt=\"image\">", escape_html(url)),
+                    None => "<p><em>[image]</em></p>".to_string(),
+                },
+                Block::Json(value) => {
+                    format!("<pre><code>{}</code></pre>", escape_html(&pretty(value)))
+                }
+                Block::Call(call) => format!(
+                    "<p><strong>Tool call</strong> <code>{}</code> (<code>{}</code>)</p>\n<pre><code>{}</code></pre>",
+                    escape_html(&call.name),
+                    escape_html(&call.id),
+                    escape_html(&pretty(&call.arguments))
+                ),
+                Block::Result(result) => {
+                    let (text, _) = result_text(result);
+                    let (class, label) = if result.is_error {
+                        (" class=\"error\"", "Tool error")
+                    } else {
+                        ("", "Tool result")
+                    };
+                    format!(
+                        "<p{}><strong>{}</strong> for <code>{}</code></p>\n<pre><code>{}</code></pre>",
+                        class,
+                        label,
+                        escape_html(&result.tool_call_id),
+                        escape_html(&text)
+                    )
+                }
+            };
+            html.push_str(&rendered);
+            html.push('\n');
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::user("Is 7 < 9?"),
+            Message::Assistant {
+                content: vec![AssistantContent::ToolCall {
+                    tool_call: ToolCall {
+                        id: "call_1".to_string(),
+                        name: "compare".to_string(),
+                        arguments: json!({ "a": 7, "b": 9 }),
+                    },
+                }],
+                metadata: None,
+            },
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: json!({ "less": true }),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant(AssistantContent::Text {
+                text: "Yes, it is.".to_string(),
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_markdown_transcript() {
+        let markdown = messages_to_markdown(&conversation());
+        assert!(markdown.starts_with("### User\n\nIs 7 < 9?\n\n### Assistant"));
+        assert!(markdown.contains("**Tool call** `compare` (`call_1`)\n\n```json\n{\n  \"a\": 7"));
+        assert!(markdown.contains("**Tool result** for `call_1`"));
+        assert!(markdown.ends_with("### Assistant\n\nYes, it is.\n"));
+
+        assert_eq!(fence("a ``` b", ""), "````\na ``` b\n````");
+    }
+
+    #[test]
+    fn test_plain_transcript() {
+        let mut messages = conversation();
+        messages.push(Message::user("Thanks!\nBye."));
+        assert_eq!(
+            fmt_transcript(&messages),
+            "user: Is 7 < 9?\n\
+             assistant -> compare({\"a\":7,\"b\":9}) [call_1]\n\
+             tool <- call_1: {\"less\":true}\n\
+             assistant: Yes, it is.\n\
+             user: Thanks!\n    Bye."
+        );
+        let error = Message::tool(ToolResult::error("call_2", json!("timed out")));
+        assert_eq!(error.to_string(), "tool <- call_2 (error): timed out");
+    }
+
+    #[test]
+    fn test_html_transcript_escapes_content() {
+        let html = messages_to_html(&conversation());
+        assert!(html.contains("<section class=\"message user\">"));
+        assert!(html.contains("<div class=\"text\">Is 7 &lt; 9?</div>"));
+        assert!(html.contains("<code>compare</code>"));
+        assert!(html.contains("&quot;less&quot;: true"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
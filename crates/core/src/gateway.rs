@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+/// A gateway to send provider requests through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gateway {
+    /// Helicone, authenticated with a Helicone API key
+    Helicone { api_key: String },
+    /// Cloudflare AI Gateway; `token` is needed when the gateway is authenticated
+    Cloudflare {
+        account_id: String,
+        gateway_id: String,
+        token: Option<String>,
+    },
+    /// Portkey, authenticated with a Portkey API key
+    Portkey { api_key: String },
+}
+
+impl Gateway {
+    pub fn helicone(api_key: impl Into<String>) -> Self {
+        Self::Helicone {
+            api_key: api_key.into(),
+        }
+    }
+
+    pub fn cloudflare(account_id: impl Into<String>, gateway_id: impl Into<String>) -> Self {
+        Self::Cloudflare {
+            account_id: account_id.into(),
+            gateway_id: gateway_id.into(),
+            token: None,
+        }
+    }
+
+    pub fn portkey(api_key: impl Into<String>) -> Self {
+        Self::Portkey {
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Authenticate with an authenticated Cloudflare gateway; ignored by other gateways
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        if let Self::Cloudflare { token: slot, .. } = &mut self {
+            *slot = Some(token.into());
+        }
+        self
+    }
+
+    /// Base URL and headers for requests to `provider`, whose own base URL is `upstream`
+    ///
+    /// The base URL replaces the provider's and keeps its API paths, e.g.
+    /// `{base_url}/v1/messages` for Anthropic. The headers go on every request.
+    pub fn route(&self, provider: &str, upstream: &str) -> (String, Vec<(String, String)>) {
+        match self {
+            Self::Helicone { api_key } => {
+                let mut headers =
+                    vec![("Helicone-Auth".to_string(), format!("Bearer {}", api_key))];
+                let base_url = match provider {
+                    "anthropic" => "https://anthropic.helicone.ai".to_string(),
+                    "openai" => "https://oai.helicone.ai".to_string(),
+                    _ => {
+                        headers.push(("Helicone-Target-Url".to_string(), upstream.to_string()));
+                        "https://gateway.helicone.ai".to_string()
+                    }
+                };
+                (base_url, headers)
+            }
+            Self::Cloudflare {
+                account_id,
+                gateway_id,
+                token,
+            } => {
+                let base_url = format!(
+                    "https://gateway.ai.cloudflare.com/v1/{}/{}/{}",
+                    account_id, gateway_id, provider
+                );
+                let headers = token
+                    .iter()
+                    .map(|token| {
+                        (
+                            "cf-aig-authorization".to_string(),
+                            format!("Bearer {}", token),
+                        )
+                    })
+                    .collect();
+                (base_url, headers)
+            }
+            Self::Portkey { api_key } => (
+                "https://api.portkey.ai".to_string(),
+                vec![
+                    ("x-portkey-api-key".to_string(), api_key.clone()),
+                    ("x-portkey-provider".to_string(), provider.to_string()),
+                ],
+            ),
+        }
+    }
+
+    /// Headers tagging one request with `tags`, in this gateway's conventions
+    pub fn tag_headers(&self, tags: &GatewayTags) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        match self {
+            Self::Helicone { .. } => {
+                if let Some(user_id) = &tags.user_id {
+                    headers.push(("Helicone-User-Id".to_string(), user_id.clone()));
+                }
+                if let Some(session_id) = &tags.session_id {
+                    headers.push(("Helicone-Session-Id".to_string(), session_id.clone()));
+                }
+                if let Some(cache) = tags.cache {
+                    headers.push(("Helicone-Cache-Enabled".to_string(), cache.to_string()));
+                }
+                for (name, value) in &tags.properties {
+                    headers.push((format!("Helicone-Property-{}", name), value.clone()));
+                }
+            }
+            Self::Cloudflare { .. } => {
+                // Caching is configured on the gateway, so only skipping it is per request
+                if tags.cache == Some(false) {
+                    headers.push(("cf-aig-skip-cache".to_string(), "true".to_string()));
+                }
+                let mut metadata = tags.properties.clone();
+                if let Some(user_id) = &tags.user_id {
+                    metadata.insert("user_id".to_string(), user_id.clone());
+                }
+                if let Some(session_id) = &tags.session_id {
+                    metadata.insert("session_id".to_string(), session_id.clone());
+                }
+                push_metadata(&mut headers, "cf-aig-metadata", metadata);
+            }
+            Self::Portkey { .. } => {
+                if let Some(session_id) = &tags.session_id {
+                    headers.push(("x-portkey-trace-id".to_string(), session_id.clone()));
+                }
+                if tags.cache == Some(false) {
+                    headers.push((
+                        "x-portkey-cache-force-refresh".to_string(),
+                        "true".to_string(),
+                    ));
+                }
+                let mut metadata = tags.properties.clone();
+                if let Some(user_id) = &tags.user_id {
+                    metadata.insert("_user".to_string(), user_id.clone());
+                }
+                push_metadata(&mut headers, "x-portkey-metadata", metadata);
+            }
+        }
+        headers
+    }
+}
+
+fn push_metadata(
+    headers: &mut Vec<(String, String)>,
+    name: &str,
+    metadata: BTreeMap<String, String>,
+) {
+    if !metadata.is_empty() {
+        let metadata = serde_json::to_string(&metadata).expect("string map serializes");
+        headers.push((name.to_string(), metadata));
+    }
+}
+
+/// Analytics and caching tags for one request, see `Gateway::tag_headers`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GatewayTags {
+    pub user_id: Option<String>,
+    pub session_id: Option<String>,
+    /// Use the gateway's response cache (`Some(true)`) or bypass it (`Some(false)`)
+    pub cache: Option<bool>,
+    /// Custom properties to filter and group requests by
+    pub properties: BTreeMap<String, String>,
+}
+
+impl GatewayTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.insert(name.into(), value.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_routes() {
+        let (url, headers) = Gateway::helicone("sk-helicone").route("anthropic", "");
+        assert_eq!(url, "https://anthropic.helicone.ai");
+        assert_eq!(
+            header(&headers, "Helicone-Auth"),
+            Some("Bearer sk-helicone")
+        );
+
+        let (url, headers) = Gateway::helicone("key").route("mistral", "https://api.mistral.ai");
+        assert_eq!(url, "https://gateway.helicone.ai");
+        assert_eq!(
+            header(&headers, "Helicone-Target-Url"),
+            Some("https://api.mistral.ai")
+        );
+
+        let gateway = Gateway::cloudflare("acct", "gw");
+        let (url, headers) = gateway.route("anthropic", "");
+        assert_eq!(
+            url,
+            "https://gateway.ai.cloudflare.com/v1/acct/gw/anthropic"
+        );
+        assert!(headers.is_empty());
+        let (_, headers) = gateway.with_token("cf").route("anthropic", "");
+        assert_eq!(header(&headers, "cf-aig-authorization"), Some("Bearer cf"));
+
+        let (url, headers) = Gateway::portkey("pk").route("anthropic", "");
+        assert_eq!(url, "https://api.portkey.ai");
+        assert_eq!(header(&headers, "x-portkey-provider"), Some("anthropic"));
+    }
+
+    #[test]
+    fn test_tag_headers() {
+        let tags = GatewayTags::new()
+            .user_id("u1")
+            .session_id("s1")
+            .cache(false)
+            .property("Feature", "search");
+
+        let headers = Gateway::helicone("key").tag_headers(&tags);
+        assert_eq!(header(&headers, "Helicone-User-Id"), Some("u1"));
+        assert_eq!(header(&headers, "Helicone-Session-Id"), Some("s1"));
+        assert_eq!(header(&headers, "Helicone-Cache-Enabled"), Some("false"));
+        assert_eq!(
+            header(&headers, "Helicone-Property-Feature"),
+            Some("search")
+        );
+
+        let headers = Gateway::cloudflare("acct", "gw").tag_headers(&tags);
+        assert_eq!(header(&headers, "cf-aig-skip-cache"), Some("true"));
+        assert_eq!(
+            header(&headers, "cf-aig-metadata"),
+            Some(r#"{"Feature":"search","session_id":"s1","user_id":"u1"}"#)
+        );
+
+        let headers = Gateway::portkey("pk").tag_headers(&tags);
+        assert_eq!(header(&headers, "x-portkey-trace-id"), Some("s1"));
+        assert_eq!(
+            header(&headers, "x-portkey-metadata"),
+            Some(r#"{"Feature":"search","_user":"u1"}"#)
+        );
+        assert!(
+            Gateway::portkey("pk")
+                .tag_headers(&GatewayTags::new())
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    fmt::Debug,
+    io::Write,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::{
+    errors::{AiError, Result, SerializationError, StorageError},
+    platform::{MaybeSend, MaybeSync},
+};
+
+#[cfg(feature = "reqwest")]
+use crate::errors::NetworkError;
+
+/// The reqwest version `ReqwestTransport` uses, for building a client to share
+#[cfg(feature = "reqwest")]
+pub use reqwest;
+
+/// Response body, delivered in chunks as they arrive; `Send` except on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>;
+/// Response body, delivered in chunks as they arrive; `Send` except on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>>>>>;
+
+/// Request a provider wants sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new("POST", url)
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Serialize `body` as the JSON request body and set the content type
+    pub fn json<T: Serialize>(self, body: &T) -> Result<Self> {
+        let body = serde_json::to_vec(body).map_err(|e| {
+            AiError::Serialization(SerializationError::JsonError {
+                message: format!("Failed to serialize request: {}", e),
+            })
+        })?;
+        let mut request = self.header("content-type", "application/json");
+        request.body = body;
+        Ok(request)
+    }
+
+    /// First value of header `name`, compared case-insensitively
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// Response returned by a transport once the status and headers are in
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteStream,
+}
+
+impl Debug for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+impl HttpResponse {
+    /// Response with a body that is already complete
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        let body = body.into();
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Box::pin(futures::stream::once(async move { Ok(body) })),
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// First value of header `name`, compared case-insensitively
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Read the whole body
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            body.extend(chunk?);
+        }
+        Ok(body)
+    }
+
+    /// Read the whole body as UTF-8, replacing invalid sequences
+    pub async fn text(self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Connection settings for the HTTP client a provider creates
+///
+/// Unset values keep the client's defaults. Browsers and workers manage connections
+/// themselves, so these are ignored on `wasm32`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpClientOptions {
+    /// Idle connections kept open per host
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle pooled connection is kept
+    pub pool_idle_timeout: Option<Duration>,
+    /// Speak HTTP/2 without negotiating it first
+    pub http2_only: bool,
+    /// Interval of HTTP/2 keep-alive pings
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// TCP keep-alive interval
+    pub tcp_keepalive: Option<Duration>,
+    /// Disable Nagle's algorithm
+    pub tcp_nodelay: Option<bool>,
+}
+
+impl HttpClientOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pool_max_idle_per_host(mut self, connections: usize) -> Self {
+        self.pool_max_idle_per_host = Some(connections);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn http2_only(mut self) -> Self {
+        self.http2_only = true;
+        self
+    }
+
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.tcp_nodelay = Some(nodelay);
+        self
+    }
+}
+
+/// A server-sent event as it came off the wire, before the provider parsed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SseFrame {
+    pub provider: String,
+    /// The `event:` field, empty when the server sent none
+    pub event: String,
+    pub data: String,
+    /// Why the provider could not parse the frame and dropped it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
+}
+
+/// Receives the raw SSE frames of a provider's streams, for debugging
+///
+/// Every frame is passed on alongside the parsed chunks, including frames the provider
+/// ignores or fails to parse. Clones share the destination.
+#[derive(Clone)]
+pub struct SseCapture {
+    sink: Arc<dyn Fn(&SseFrame) + Send + Sync>,
+}
+
+impl Debug for SseCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseCapture").finish_non_exhaustive()
+    }
+}
+
+impl SseCapture {
+    pub fn new(sink: impl Fn(&SseFrame) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Append frames to `path` as JSON lines
+    pub fn file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                AiError::Storage(StorageError::Io {
+                    message: e.to_string(),
+                })
+            })?;
+        let file = Mutex::new(file);
+        Ok(Self::new(move |frame| {
+            if let Ok(line) = serde_json::to_string(frame) {
+                let _ = writeln!(file.lock().unwrap(), "{}", line);
+            }
+        }))
+    }
+
+    pub fn record(&self, frame: &SseFrame) {
+        (self.sink)(frame)
+    }
+}
+
+/// Sends provider requests over HTTP
+///
+/// Non-success statuses are returned as responses, not errors; providers turn them into
+/// their own errors. Errors are for requests that produced no response at all.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait HttpTransport: MaybeSend + MaybeSync + Debug {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+/// Shared transports can be used anywhere an owned transport is expected
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        (**self).send(request).await
+    }
+}
+
+/// Transport allowing at most `limit` requests in flight through `inner`
+///
+/// A request holds its slot until its response body has been read or dropped, so
+/// streaming responses count for as long as they are open. Clones share the limit.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit<T> {
+    inner: T,
+    permits: Arc<Semaphore>,
+}
+
+impl<T> ConcurrencyLimit<T> {
+    pub fn new(inner: T, limit: usize) -> Self {
+        Self {
+            inner,
+            permits: Arc::new(Semaphore::new(limit.max(1))),
+        }
+    }
+
+    /// Requests that could start right now without waiting
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: HttpTransport> HttpTransport for ConcurrencyLimit<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("concurrency limit semaphore is never closed");
+        let mut response = self.inner.send(request).await?;
+        let body = response.body;
+        response.body = Box::pin(body.map(move |chunk| {
+            let _permit = &permit;
+            chunk
+        }));
+        Ok(response)
+    }
+}
+
+/// Transport backed by a `reqwest::Client`
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    timeout: Option<Duration>,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    /// Transport sending through `client`; clones of one client share its connection pool
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            timeout: None,
+        }
+    }
+
+    /// Client with a request timeout; browsers and workers apply their own instead
+    pub fn with_timeout(timeout: Duration) -> Result<Self> {
+        Self::with_options(timeout, &HttpClientOptions::default())
+    }
+
+    /// Client with a request timeout and connection settings
+    pub fn with_options(timeout: Duration, options: &HttpClientOptions) -> Result<Self> {
+        let builder = reqwest::Client::builder();
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = {
+            let mut builder = builder.timeout(timeout);
+            if let Some(connections) = options.pool_max_idle_per_host {
+                builder = builder.pool_max_idle_per_host(connections);
+            }
+            if let Some(idle) = options.pool_idle_timeout {
+                builder = builder.pool_idle_timeout(idle);
+            }
+            if options.http2_only {
+                builder = builder.http2_prior_knowledge();
+            }
+            if let Some(interval) = options.http2_keep_alive_interval {
+                builder = builder.http2_keep_alive_interval(interval);
+            }
+            if let Some(interval) = options.tcp_keepalive {
+                builder = builder.tcp_keepalive(interval);
+            }
+            if let Some(nodelay) = options.tcp_nodelay {
+                builder = builder.tcp_nodelay(nodelay);
+            }
+            builder
+        };
+        #[cfg(target_arch = "wasm32")]
+        let _ = (timeout, options);
+        let client = builder.build().map_err(|e| {
+            AiError::Network(NetworkError::ConnectionFailed {
+                message: format!("Failed to create HTTP client: {}", e),
+            })
+        })?;
+        Ok(Self {
+            client,
+            timeout: Some(timeout),
+        })
+    }
+
+    /// Timeouts become `NetworkError::Timeout` so they are classified as retryable
+    fn request_error(&self, error: reqwest::Error, context: &str) -> AiError {
+        match self.timeout {
+            Some(duration) if error.is_timeout() => {
+                AiError::Network(NetworkError::Timeout { duration })
+            }
+            _ => AiError::Network(NetworkError::ConnectionFailed {
+                message: format!("{}: {}", context, error),
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| {
+            AiError::Network(NetworkError::ConnectionFailed {
+                message: format!("Invalid HTTP method '{}': {}", request.method, e),
+            })
+        })?;
+        let mut builder = self.client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .body(request.body)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e, "Request failed"))?;
+
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let status = response.status().as_u16();
+        let transport = self.clone();
+        let body = response.bytes_stream().map(move |chunk| {
+            chunk
+                .map(|bytes| bytes.to_vec())
+                .map_err(|e| transport.request_error(e, "Stream error"))
+        });
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: Box::pin(body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_and_response_helpers() {
+        let request = HttpRequest::post("https://example.com/v1")
+            .header("X-Api-Key", "secret")
+            .json(&serde_json::json!({"a": 1}))
+            .unwrap();
+        assert_eq!(request.header_value("x-api-key"), Some("secret"));
+        assert_eq!(
+            request.header_value("Content-Type"),
+            Some("application/json")
+        );
+        assert_eq!(request.body, br#"{"a":1}"#);
+
+        let chunks = vec![Ok(b"hel".to_vec()), Ok(b"lo".to_vec())];
+        let response = HttpResponse {
+            status: 200,
+            headers: vec![("Retry-After".to_string(), "3".to_string())],
+            body: Box::pin(futures::stream::iter(chunks)),
+        };
+        assert!(response.is_success());
+        assert_eq!(response.header_value("retry-after"), Some("3"));
+        assert_eq!(response.text().await.unwrap(), "hello");
+    }
+
+    #[derive(Debug, Clone)]
+    struct Echo;
+
+    #[async_trait]
+    impl HttpTransport for Echo {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            Ok(HttpResponse::new(200, request.body))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_holds_slots_until_body_is_done() {
+        let transport = ConcurrencyLimit::new(Echo, 2);
+        let first = transport.send(HttpRequest::post("/")).await.unwrap();
+        let second = transport
+            .clone()
+            .send(HttpRequest::post("/"))
+            .await
+            .unwrap();
+        assert_eq!(transport.available(), 0);
+
+        let third = tokio::spawn({
+            let transport = transport.clone();
+            async move { transport.send(HttpRequest::post("/")).await.unwrap() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!third.is_finished());
+
+        first.bytes().await.unwrap();
+        drop(second);
+        third.await.unwrap();
+        assert_eq!(transport.available(), 2);
+    }
+}
@@ -0,0 +1,330 @@
+use serde::Deserialize;
+
+use crate::{
+    Result,
+    errors::{AiError, SerializationError},
+    types::*,
+};
+
+fn unsupported(message: String) -> AiError {
+    AiError::Serialization(SerializationError::SchemaValidation { message })
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OpenAiLog {
+    Messages(Vec<OpenAiMessage>),
+    Request { messages: Vec<OpenAiMessage> },
+}
+
+#[derive(Deserialize)]
+struct OpenAiMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<OpenAiContent>,
+    #[serde(default)]
+    tool_calls: Option<Vec<OpenAiToolCall>>,
+    #[serde(default)]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OpenAiContent {
+    Text(String),
+    Parts(Vec<OpenAiPart>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAiPart {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: OpenAiImageUrl,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct OpenAiImageUrl {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAiToolCall {
+    id: String,
+    function: OpenAiFunction,
+}
+
+#[derive(Deserialize)]
+struct OpenAiFunction {
+    name: String,
+    arguments: String,
+}
+
+/// Image content from a URL, unpacking `data:` URLs into base64 and MIME type
+fn image(url: String) -> ImageContent {
+    if let Some((mime_type, data)) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+    {
+        return ImageContent {
+            url: None,
+            base64: Some(data.to_string()),
+            mime_type: Some(mime_type.to_string()),
+        };
+    }
+    ImageContent {
+        url: Some(url),
+        base64: None,
+        mime_type: None,
+    }
+}
+
+/// Tool arguments are JSON encoded as a string; keep the raw string if they do not parse
+fn arguments(raw: String) -> serde_json::Value {
+    serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw))
+}
+
+fn openai_message(message: OpenAiMessage) -> Result<Message> {
+    let parts = match message.content {
+        None => Vec::new(),
+        Some(OpenAiContent::Text(text)) => vec![OpenAiPart::Text { text }],
+        Some(OpenAiContent::Parts(parts)) => parts,
+    };
+    let text = || {
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                OpenAiPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(match message.role.as_str() {
+        "system" | "developer" => Message::system(text()),
+        "user" => Message::User {
+            content: parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    OpenAiPart::Text { text } => Some(UserContent::Text { text }),
+                    OpenAiPart::ImageUrl { image_url } => Some(UserContent::Image {
+                        image: image(image_url.url),
+                    }),
+                    OpenAiPart::Other => None,
+                })
+                .collect(),
+            metadata: None,
+        },
+        "assistant" => {
+            let text = text();
+            let mut content = Vec::new();
+            if !text.is_empty() {
+                content.push(AssistantContent::Text { text });
+            }
+            for call in message.tool_calls.unwrap_or_default() {
+                content.push(AssistantContent::ToolCall {
+                    tool_call: ToolCall {
+                        id: call.id,
+                        name: call.function.name,
+                        arguments: arguments(call.function.arguments),
+                    },
+                });
+            }
+            Message::Assistant {
+                content,
+                metadata: None,
+            }
+        }
+        "tool" => {
+            let tool_call_id = message
+                .tool_call_id
+                .ok_or_else(|| unsupported("tool message without tool_call_id".to_string()))?;
+            Message::tool(ToolResult {
+                tool_call_id,
+                result: serde_json::Value::String(text()),
+                is_error: false,
+                content: Vec::new(),
+            })
+        }
+        role => return Err(unsupported(format!("unknown role '{}'", role))),
+    })
+}
+
+/// Messages from an OpenAI chat `messages` array, or a request object holding one
+pub fn messages_from_openai(json: &str) -> Result<Vec<Message>> {
+    let messages = match serde_json::from_str(json)? {
+        OpenAiLog::Messages(messages) | OpenAiLog::Request { messages } => messages,
+    };
+    messages.into_iter().map(openai_message).collect()
+}
+
+/// Messages from JSON Lines holding one `{"role": ..., "content": ...}` object per line
+///
+/// Lines follow the OpenAI message shape, so tool calls and tool results are understood
+/// too. Blank lines are skipped.
+pub fn messages_from_jsonl(text: &str) -> Result<Vec<Message>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| openai_message(serde_json::from_str(line)?))
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShareGptLog {
+    Turns(Vec<ShareGptTurn>),
+    Conversation { conversations: Vec<ShareGptTurn> },
+}
+
+#[derive(Deserialize)]
+struct ShareGptTurn {
+    from: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ShareGptCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+/// Messages from a ShareGPT conversation, either the `conversations` object or its array
+///
+/// `function_call` turns (a JSON `name`/`arguments` object) become tool calls with
+/// generated ids, and `observation` turns become the results of the latest call.
+pub fn messages_from_sharegpt(json: &str) -> Result<Vec<Message>> {
+    let turns = match serde_json::from_str(json)? {
+        ShareGptLog::Turns(turns)
+        | ShareGptLog::Conversation {
+            conversations: turns,
+        } => turns,
+    };
+
+    let mut messages = Vec::with_capacity(turns.len());
+    let mut last_call: Option<String> = None;
+    for (index, turn) in turns.into_iter().enumerate() {
+        messages.push(match turn.from.as_str() {
+            "system" => Message::system(turn.value),
+            "human" | "user" => Message::user(turn.value),
+            "gpt" | "assistant" | "chatgpt" | "bing" | "bard" => Message::assistant(turn.value),
+            "function_call" => {
+                let call: ShareGptCall = serde_json::from_str(&turn.value)?;
+                let id = format!("call_{}", index);
+                last_call = Some(id.clone());
+                Message::Assistant {
+                    content: vec![AssistantContent::ToolCall {
+                        tool_call: ToolCall {
+                            id,
+                            name: call.name,
+                            arguments: call.arguments,
+                        },
+                    }],
+                    metadata: None,
+                }
+            }
+            "observation" => {
+                let tool_call_id = last_call.take().ok_or_else(|| {
+                    unsupported(format!(
+                        "observation at turn {} follows no function call",
+                        index
+                    ))
+                })?;
+                Message::tool(ToolResult {
+                    tool_call_id,
+                    result: serde_json::Value::String(turn.value),
+                    is_error: false,
+                    content: Vec::new(),
+                })
+            }
+            from => return Err(unsupported(format!("unknown speaker '{}'", from))),
+        });
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_openai_messages() {
+        let log = json!({
+            "model": "gpt-4o",
+            "messages": [
+                { "role": "developer", "content": "Be brief" },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "What is this?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }
+                ]},
+                { "role": "assistant", "content": null, "tool_calls": [
+                    { "id": "call_a", "type": "function",
+                      "function": { "name": "classify", "arguments": "{\"kind\":\"cat\"}" } }
+                ]},
+                { "role": "tool", "tool_call_id": "call_a", "content": "cat" },
+                { "role": "assistant", "content": "A cat." }
+            ]
+        });
+        let messages = messages_from_openai(&log.to_string()).unwrap();
+
+        assert_eq!(messages[0], Message::system("Be brief"));
+        let Message::User { content, .. } = &messages[1] else {
+            panic!("expected a user message");
+        };
+        assert_eq!(
+            content[1],
+            UserContent::Image {
+                image: ImageContent {
+                    url: None,
+                    base64: Some("AAAA".to_string()),
+                    mime_type: Some("image/png".to_string()),
+                },
+            }
+        );
+        let Message::Assistant { content, .. } = &messages[2] else {
+            panic!("expected an assistant message");
+        };
+        let AssistantContent::ToolCall { tool_call } = &content[0] else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(tool_call.arguments, json!({ "kind": "cat" }));
+        assert_eq!(messages[3].role(), "tool");
+        assert_eq!(messages[4], Message::assistant("A cat."));
+
+        let bare = json!([{ "role": "user", "content": "Hi" }]).to_string();
+        assert_eq!(messages_from_openai(&bare).unwrap(), [Message::user("Hi")]);
+    }
+
+    #[test]
+    fn test_jsonl_and_sharegpt() {
+        let jsonl = "{\"role\":\"user\",\"content\":\"Hi\"}\n\n{\"role\":\"assistant\",\"content\":\"Hello\"}\n";
+        assert_eq!(
+            messages_from_jsonl(jsonl).unwrap(),
+            [Message::user("Hi"), Message::assistant("Hello")]
+        );
+        assert!(messages_from_jsonl("{\"role\":\"narrator\",\"content\":\"x\"}").is_err());
+
+        let sharegpt = json!({ "conversations": [
+            { "from": "human", "value": "Weather?" },
+            { "from": "function_call", "value": "{\"name\":\"weather\",\"arguments\":{\"city\":\"Oslo\"}}" },
+            { "from": "observation", "value": "Snow" },
+            { "from": "gpt", "value": "Snowing." }
+        ]});
+        let messages = messages_from_sharegpt(&sharegpt.to_string()).unwrap();
+        assert_eq!(
+            messages.iter().map(Message::role).collect::<Vec<_>>(),
+            ["user", "assistant", "tool", "assistant"]
+        );
+        let Message::Tool { tool_results, .. } = &messages[2] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(tool_results[0].tool_call_id, "call_1");
+    }
+}
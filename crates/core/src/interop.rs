@@ -0,0 +1,372 @@
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestAssistantMessageContent, ChatCompletionRequestAssistantMessageContentPart,
+    ChatCompletionRequestDeveloperMessageContent, ChatCompletionRequestMessage,
+    ChatCompletionRequestMessageContentPartImage, ChatCompletionRequestMessageContentPartText,
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestSystemMessageContent,
+    ChatCompletionRequestSystemMessageContentPart, ChatCompletionRequestToolMessage,
+    ChatCompletionRequestToolMessageContent, ChatCompletionRequestToolMessageContentPart,
+    ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart, ChatCompletionResponseMessage, ChatCompletionTool,
+    ChatCompletionToolType, FunctionCall, FunctionObject, ImageUrl,
+};
+
+use crate::types::*;
+
+impl From<ToolDefinition> for ChatCompletionTool {
+    fn from(tool: ToolDefinition) -> Self {
+        ChatCompletionTool {
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionObject {
+                name: tool.name,
+                description: Some(tool.description),
+                parameters: Some(tool.parameters),
+                strict: tool.strict.then_some(true),
+            },
+        }
+    }
+}
+
+impl From<ChatCompletionTool> for ToolDefinition {
+    fn from(tool: ChatCompletionTool) -> Self {
+        ToolDefinition::new(
+            tool.function.name,
+            tool.function.description.unwrap_or_default(),
+            tool.function
+                .parameters
+                .unwrap_or_else(|| serde_json::json!({ "type": "object", "properties": {} })),
+        )
+        .strict(tool.function.strict.unwrap_or(false))
+    }
+}
+
+impl From<ToolCall> for ChatCompletionMessageToolCall {
+    fn from(call: ToolCall) -> Self {
+        ChatCompletionMessageToolCall {
+            id: call.id,
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionCall {
+                name: call.name,
+                arguments: call.arguments.to_string(),
+            },
+        }
+    }
+}
+
+impl From<ChatCompletionMessageToolCall> for ToolCall {
+    fn from(call: ChatCompletionMessageToolCall) -> Self {
+        let arguments = serde_json::from_str(&call.function.arguments)
+            .unwrap_or(serde_json::Value::String(call.function.arguments));
+        ToolCall {
+            id: call.id,
+            name: call.function.name,
+            arguments,
+        }
+    }
+}
+
+fn image_url(image: ImageContent) -> ImageUrl {
+    let url = match (image.url, image.base64) {
+        (Some(url), _) => url,
+        (None, Some(data)) => format!(
+            "data:{};base64,{}",
+            image.mime_type.as_deref().unwrap_or("image/jpeg"),
+            data
+        ),
+        (None, None) => String::new(),
+    };
+    ImageUrl { url, detail: None }
+}
+
+fn image_content(url: String) -> ImageContent {
+    if let Some((mime_type, data)) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+    {
+        return ImageContent {
+            url: None,
+            base64: Some(data.to_string()),
+            mime_type: Some(mime_type.to_string()),
+        };
+    }
+    ImageContent {
+        url: Some(url),
+        base64: None,
+        mime_type: None,
+    }
+}
+
+fn result_text(result: &ToolResult) -> String {
+    match &result.result {
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl From<Message> for Vec<ChatCompletionRequestMessage> {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::System { content, .. } => {
+                let text = content
+                    .into_iter()
+                    .map(|part| match part {
+                        SystemContent::Text { text } => text,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                vec![ChatCompletionRequestMessage::System(
+                    ChatCompletionRequestSystemMessage {
+                        content: ChatCompletionRequestSystemMessageContent::Text(text),
+                        name: None,
+                    },
+                )]
+            }
+            Message::User { content, .. } => {
+                let content = match content.as_slice() {
+                    [UserContent::Text { text }] => {
+                        ChatCompletionRequestUserMessageContent::Text(text.clone())
+                    }
+                    _ => ChatCompletionRequestUserMessageContent::Array(
+                        content
+                            .into_iter()
+                            .map(|part| match part {
+                                UserContent::Text { text } => {
+                                    ChatCompletionRequestUserMessageContentPart::Text(
+                                        ChatCompletionRequestMessageContentPartText { text },
+                                    )
+                                }
+                                UserContent::Image { image } => {
+                                    ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                                        ChatCompletionRequestMessageContentPartImage {
+                                            image_url: image_url(image),
+                                        },
+                                    )
+                                }
+                            })
+                            .collect(),
+                    ),
+                };
+                vec![ChatCompletionRequestMessage::User(
+                    ChatCompletionRequestUserMessage {
+                        content,
+                        name: None,
+                    },
+                )]
+            }
+            Message::Assistant { content, .. } => {
+                let mut text = Vec::new();
+                let mut tool_calls = Vec::new();
+                for part in content {
+                    match part {
+                        AssistantContent::Text { text: t } => text.push(t),
+                        AssistantContent::ToolCall { tool_call } => {
+                            tool_calls.push(tool_call.into())
+                        }
+                    }
+                }
+                vec![ChatCompletionRequestMessage::Assistant(
+                    ChatCompletionRequestAssistantMessage {
+                        content: (!text.is_empty()).then(|| {
+                            ChatCompletionRequestAssistantMessageContent::Text(text.join("\n"))
+                        }),
+                        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                        ..Default::default()
+                    },
+                )]
+            }
+            Message::Tool { tool_results, .. } => tool_results
+                .into_iter()
+                .map(|result| {
+                    ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+                        content: ChatCompletionRequestToolMessageContent::Text(result_text(
+                            &result,
+                        )),
+                        tool_call_id: result.tool_call_id,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Convert a conversation to `async-openai` request messages
+pub fn to_openai_messages(messages: Vec<Message>) -> Vec<ChatCompletionRequestMessage> {
+    messages.into_iter().flat_map(Vec::from).collect()
+}
+
+impl From<ChatCompletionRequestMessage> for Message {
+    fn from(message: ChatCompletionRequestMessage) -> Self {
+        match message {
+            ChatCompletionRequestMessage::Developer(message) => {
+                Message::system(match message.content {
+                    ChatCompletionRequestDeveloperMessageContent::Text(text) => text,
+                    ChatCompletionRequestDeveloperMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .map(|part| part.text)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                })
+            }
+            ChatCompletionRequestMessage::System(message) => {
+                Message::system(match message.content {
+                    ChatCompletionRequestSystemMessageContent::Text(text) => text,
+                    ChatCompletionRequestSystemMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .map(|part| match part {
+                            ChatCompletionRequestSystemMessageContentPart::Text(part) => part.text,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                })
+            }
+            ChatCompletionRequestMessage::User(message) => Message::User {
+                content: match message.content {
+                    ChatCompletionRequestUserMessageContent::Text(text) => {
+                        vec![UserContent::Text { text }]
+                    }
+                    ChatCompletionRequestUserMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .filter_map(|part| match part {
+                            ChatCompletionRequestUserMessageContentPart::Text(part) => {
+                                Some(UserContent::Text { text: part.text })
+                            }
+                            ChatCompletionRequestUserMessageContentPart::ImageUrl(part) => {
+                                Some(UserContent::Image {
+                                    image: image_content(part.image_url.url),
+                                })
+                            }
+                            // Audio has no counterpart in `UserContent`
+                            ChatCompletionRequestUserMessageContentPart::InputAudio(_) => None,
+                        })
+                        .collect(),
+                },
+                metadata: None,
+            },
+            ChatCompletionRequestMessage::Assistant(message) => {
+                let mut content = Vec::new();
+                match message.content {
+                    Some(ChatCompletionRequestAssistantMessageContent::Text(text)) => {
+                        content.push(AssistantContent::Text { text });
+                    }
+                    Some(ChatCompletionRequestAssistantMessageContent::Array(parts)) => {
+                        content.extend(parts.into_iter().map(|part| match part {
+                            ChatCompletionRequestAssistantMessageContentPart::Text(part) => {
+                                AssistantContent::Text { text: part.text }
+                            }
+                            ChatCompletionRequestAssistantMessageContentPart::Refusal(part) => {
+                                AssistantContent::Text { text: part.refusal }
+                            }
+                        }));
+                    }
+                    None => {}
+                }
+                content.extend(
+                    message
+                        .tool_calls
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|call| AssistantContent::ToolCall {
+                            tool_call: call.into(),
+                        }),
+                );
+                Message::Assistant {
+                    content,
+                    metadata: None,
+                }
+            }
+            ChatCompletionRequestMessage::Tool(message) => {
+                let text = match message.content {
+                    ChatCompletionRequestToolMessageContent::Text(text) => text,
+                    ChatCompletionRequestToolMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .map(|part| match part {
+                            ChatCompletionRequestToolMessageContentPart::Text(part) => part.text,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                Message::tool(ToolResult {
+                    tool_call_id: message.tool_call_id,
+                    result: serde_json::Value::String(text),
+                    is_error: false,
+                    content: Vec::new(),
+                })
+            }
+            // Legacy function results carry no call id; the function name stands in for it
+            ChatCompletionRequestMessage::Function(message) => Message::tool(ToolResult {
+                tool_call_id: message.name,
+                result: serde_json::Value::String(message.content.unwrap_or_default()),
+                is_error: false,
+                content: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl From<ChatCompletionResponseMessage> for Message {
+    fn from(message: ChatCompletionResponseMessage) -> Self {
+        let mut content = Vec::new();
+        if let Some(text) = message.content {
+            content.push(AssistantContent::Text { text });
+        }
+        content.extend(
+            message
+                .tool_calls
+                .unwrap_or_default()
+                .into_iter()
+                .map(|call| AssistantContent::ToolCall {
+                    tool_call: call.into(),
+                }),
+        );
+        Message::Assistant {
+            content,
+            metadata: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_messages_round_trip() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "add".to_string(),
+            arguments: json!({ "a": 1, "b": 2 }),
+        };
+        let conversation = vec![
+            Message::system("Add numbers"),
+            Message::user("1 + 2?"),
+            Message::Assistant {
+                content: vec![AssistantContent::ToolCall {
+                    tool_call: call.clone(),
+                }],
+                metadata: None,
+            },
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: json!("3"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant("3"),
+        ];
+
+        let openai = to_openai_messages(conversation.clone());
+        assert_eq!(
+            serde_json::to_value(&openai[2]).unwrap()["tool_calls"][0]["function"]["arguments"],
+            "{\"a\":1,\"b\":2}"
+        );
+        let back: Vec<Message> = openai.into_iter().map(Message::from).collect();
+        assert_eq!(back, conversation);
+
+        let tool =
+            ToolDefinition::new("add", "Add two numbers", json!({ "type": "object" })).strict(true);
+        let openai_tool = ChatCompletionTool::from(tool.clone());
+        assert_eq!(openai_tool.function.strict, Some(true));
+        assert_eq!(ToolDefinition::from(openai_tool), tool);
+    }
+}
@@ -1,12 +1,55 @@
+// Lets the derive macros refer to `::ai_core` from inside this crate
+extern crate self as ai_core;
+
 pub mod errors;
+pub mod export;
+pub mod gateway;
+pub mod http;
+pub mod import;
+#[cfg(feature = "async-openai")]
+pub mod interop;
+pub mod models;
+pub mod normalize;
+pub mod platform;
+pub mod prompt;
 pub mod provider;
+pub mod realtime;
+pub mod response_metadata;
+pub mod schema;
+pub mod scratchpad;
+#[cfg(feature = "proptest")]
+pub mod testing;
+pub mod tokenizer;
 pub mod tools;
 pub mod types;
+pub mod versioning;
 
 pub use errors::{
-    AgentError, AiError, NetworkError, ProviderError, Result, SerializationError, ToolError,
-    ToolExecutionError, ToolResult, ValidationError,
+    AgentError, AiError, HttpDiagnostics, NetworkError, ProviderError, Result, SerializationError,
+    StorageError, ToolError, ToolExecutionError, ToolResult, ValidationError,
 };
+pub use export::*;
+pub use gateway::*;
+pub use http::*;
+pub use import::*;
+#[cfg(feature = "async-openai")]
+pub use interop::to_openai_messages;
+pub use models::*;
+pub use normalize::*;
+pub use platform::{MaybeSend, MaybeSync};
+pub use prompt::{PromptTemplate, PromptVars};
 pub use provider::*;
+pub use realtime::*;
+pub use response_metadata::*;
+pub use schema::*;
+pub use scratchpad::*;
+pub use tokenizer::*;
 pub use tools::*;
 pub use types::*;
+pub use versioning::*;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+    pub use serde_json;
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{AiError, Result, SerializationError, StorageError},
+    types::Usage,
+};
+
+/// What a model accepts and what it costs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub provider: String,
+    /// Model id, or the id without its date suffix to cover every snapshot
+    pub model: String,
+    /// Tokens accepted per request, prompt and output together
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    #[serde(default)]
+    pub supports_tools: bool,
+    #[serde(default)]
+    pub supports_vision: bool,
+    #[serde(default)]
+    pub supports_caching: bool,
+    /// USD per million prompt tokens
+    #[serde(default)]
+    pub input_price: Option<f64>,
+    /// USD per million completion tokens
+    #[serde(default)]
+    pub output_price: Option<f64>,
+}
+
+impl ModelInfo {
+    /// Price of `usage` in USD, when the model's pricing is known
+    pub fn cost(&self, usage: &Usage) -> Option<f64> {
+        let input = self.input_price? * usage.prompt_tokens as f64;
+        let output = self.output_price? * usage.completion_tokens as f64;
+        Some((input + output) / 1_000_000.0)
+    }
+}
+
+/// Layout of `models.json` and override files
+#[derive(Debug, Default, Deserialize)]
+struct ModelTable {
+    /// Provider name to alias to model id
+    #[serde(default)]
+    aliases: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+/// Models and model aliases known for each provider
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelRegistry {
+    models: Vec<ModelInfo>,
+    aliases: BTreeMap<(String, String), String>,
+}
+
+impl ModelRegistry {
+    /// Registry without any models
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the models shipped in `models.json`
+    pub fn builtin() -> Self {
+        Self::new()
+            .extend_from_json(include_str!("models.json"))
+            .expect("built-in model table is valid")
+    }
+
+    /// Add a model, replacing any entry for the same provider and model
+    pub fn register(mut self, info: ModelInfo) -> Self {
+        self.models
+            .retain(|known| known.provider != info.provider || known.model != info.model);
+        self.models.push(info);
+        self
+    }
+
+    /// Make `alias` name `model` for `provider`, replacing an earlier target
+    pub fn alias(
+        mut self,
+        provider: impl Into<String>,
+        alias: impl Into<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        self.aliases
+            .insert((provider.into(), alias.into()), model.into());
+        self
+    }
+
+    /// Add the models and aliases of a table in the `models.json` layout
+    ///
+    /// `{"aliases": {"<provider>": {"<alias>": "<model id>"}}, "models": [ModelInfo, ...]}`;
+    /// both keys are optional and entries override what is already registered.
+    pub fn extend_from_json(self, json: &str) -> Result<Self> {
+        let table: ModelTable = serde_json::from_str(json).map_err(|e| {
+            AiError::Serialization(SerializationError::JsonError {
+                message: format!("Invalid model table: {}", e),
+            })
+        })?;
+        let registry = table.models.into_iter().fold(self, Self::register);
+        Ok(table
+            .aliases
+            .into_iter()
+            .flat_map(|(provider, aliases)| {
+                aliases
+                    .into_iter()
+                    .map(move |(alias, model)| (provider.clone(), alias, model))
+            })
+            .fold(registry, |registry, (provider, alias, model)| {
+                registry.alias(provider, alias, model)
+            }))
+    }
+
+    /// Add the models and aliases of a JSON override file
+    pub fn extend_from_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            AiError::Storage(StorageError::Io {
+                message: format!("Failed to read {}: {}", path.display(), e),
+            })
+        })?;
+        self.extend_from_json(&json)
+    }
+
+    /// Concrete model id for `model`, following aliases; other names are returned as is
+    pub fn resolve(&self, provider: &str, model: &str) -> String {
+        let mut resolved = model;
+        // Aliases may point at other aliases; the bound stops cycles
+        for _ in 0..8 {
+            match self
+                .aliases
+                .get(&(provider.to_string(), resolved.to_string()))
+            {
+                Some(target) => resolved = target,
+                None => break,
+            }
+        }
+        resolved.to_string()
+    }
+
+    /// Look up a model or alias by exact id, or by the longest registered id it starts with
+    pub fn get(&self, provider: &str, model: &str) -> Option<&ModelInfo> {
+        let model = self.resolve(provider, model);
+        self.models
+            .iter()
+            .filter(|info| info.provider == provider && model.starts_with(&info.model))
+            .max_by_key(|info| info.model.len())
+    }
+
+    pub fn models(&self) -> &[ModelInfo] {
+        &self.models
+    }
+}
+
+/// Shared built-in registry, loaded on first use
+pub fn builtin_models() -> Arc<ModelRegistry> {
+    static BUILTIN: OnceLock<Arc<ModelRegistry>> = OnceLock::new();
+    BUILTIN
+        .get_or_init(|| Arc::new(ModelRegistry::builtin()))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_matches_snapshots_and_overrides() {
+        let registry = ModelRegistry::builtin();
+        let info = registry
+            .get("anthropic", "claude-opus-4-1-20250805")
+            .unwrap();
+        assert_eq!(info.model, "claude-opus-4-1");
+        assert_eq!(info.max_output_tokens, 32000);
+        assert!(registry.get("anthropic", "gpt-4o").is_none());
+        assert!(registry.get("openai", "claude-3-haiku-20240307").is_none());
+
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            total_tokens: 1_100_000,
+        };
+        let haiku = registry
+            .get("anthropic", "claude-3-haiku-20240307")
+            .unwrap()
+            .clone();
+        assert_eq!(haiku.cost(&usage), Some(0.375));
+
+        let registry = registry.register(ModelInfo {
+            input_price: None,
+            ..haiku
+        });
+        let haiku = registry
+            .get("anthropic", "claude-3-haiku-20240307")
+            .unwrap();
+        assert_eq!(haiku.cost(&usage), None);
+    }
+}
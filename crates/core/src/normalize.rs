@@ -0,0 +1,562 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Result,
+    errors::{AiError, ValidationError},
+    types::*,
+};
+
+type Metadata = Option<HashMap<String, serde_json::Value>>;
+
+/// Drop empty content, move tool results next to their calls and merge consecutive
+/// messages with the same role (see `merge_consecutive`)
+pub fn normalize_messages(messages: Vec<Message>) -> Vec<Message> {
+    merge_consecutive(reorder_tool_results(drop_empty_content(messages)))
+}
+
+/// Remove text parts that are empty or whitespace, and messages left without content
+pub fn drop_empty_content(messages: Vec<Message>) -> Vec<Message> {
+    fn blank(text: &str) -> bool {
+        text.trim().is_empty()
+    }
+
+    messages
+        .into_iter()
+        .filter_map(|mut message| {
+            let empty = match &mut message {
+                Message::System { content, .. } => {
+                    content.retain(|part| match part {
+                        SystemContent::Text { text } => !blank(text),
+                    });
+                    content.is_empty()
+                }
+                Message::User { content, .. } => {
+                    content
+                        .retain(|part| !matches!(part, UserContent::Text { text } if blank(text)));
+                    content.is_empty()
+                }
+                Message::Assistant { content, .. } => {
+                    content.retain(
+                        |part| !matches!(part, AssistantContent::Text { text } if blank(text)),
+                    );
+                    content.is_empty()
+                }
+                Message::Tool { tool_results, .. } => tool_results.is_empty(),
+            };
+            (!empty).then_some(message)
+        })
+        .collect()
+}
+
+/// Merge runs of user, assistant or tool messages into one message
+///
+/// Metadata entries are combined, later ones winning. System messages are left as they
+/// are, since providers place them apart from the conversation. Few-shot examples are
+/// never merged with messages that are not examples, and nothing is merged into a
+/// message marked `cached`, so the end of the cached prefix stays put.
+pub fn merge_consecutive(messages: Vec<Message>) -> Vec<Message> {
+    let mut merged: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in messages {
+        let rest = match merged.last_mut() {
+            Some(last) => absorb(last, message),
+            None => Some(message),
+        };
+        merged.extend(rest);
+    }
+    merged
+}
+
+/// Append `message` to `target` when they share a role, otherwise hand it back
+fn absorb(target: &mut Message, message: Message) -> Option<Message> {
+    if target.is_cached() || target.is_example() != message.is_example() {
+        return Some(message);
+    }
+    match (target, message) {
+        (
+            Message::User { content, metadata },
+            Message::User {
+                content: more,
+                metadata: extra,
+            },
+        ) => {
+            content.extend(more);
+            merge_metadata(metadata, extra);
+        }
+        (
+            Message::Assistant { content, metadata },
+            Message::Assistant {
+                content: more,
+                metadata: extra,
+            },
+        ) => {
+            content.extend(more);
+            merge_metadata(metadata, extra);
+        }
+        (
+            Message::Tool {
+                tool_results,
+                metadata,
+            },
+            Message::Tool {
+                tool_results: more,
+                metadata: extra,
+            },
+        ) => {
+            tool_results.extend(more);
+            merge_metadata(metadata, extra);
+        }
+        (_, message) => return Some(message),
+    }
+    None
+}
+
+fn merge_metadata(metadata: &mut Metadata, extra: Metadata) {
+    if let Some(extra) = extra {
+        metadata.get_or_insert_with(HashMap::new).extend(extra);
+    }
+}
+
+fn tool_call_ids(message: &Message) -> Vec<&str> {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::ToolCall { tool_call } => Some(tool_call.id.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Place every tool result in one tool message directly after the assistant message
+/// that made the call
+///
+/// Results follow the order of the calls. Results answering no call in the
+/// conversation stay where they are.
+pub fn reorder_tool_results(messages: Vec<Message>) -> Vec<Message> {
+    let calls: HashSet<String> = messages
+        .iter()
+        .flat_map(tool_call_ids)
+        .map(str::to_string)
+        .collect();
+
+    // Pull answered results out of their messages, keeping the first result per call
+    let mut answered: HashMap<String, (ToolResult, Metadata)> = HashMap::new();
+    let mut remaining = Vec::with_capacity(messages.len());
+    for message in messages {
+        let Message::Tool {
+            tool_results,
+            metadata,
+        } = message
+        else {
+            remaining.push(message);
+            continue;
+        };
+        let mut stray = Vec::new();
+        for result in tool_results {
+            if calls.contains(&result.tool_call_id) && !answered.contains_key(&result.tool_call_id)
+            {
+                answered.insert(result.tool_call_id.clone(), (result, metadata.clone()));
+            } else {
+                stray.push(result);
+            }
+        }
+        if !stray.is_empty() {
+            remaining.push(Message::Tool {
+                tool_results: stray,
+                metadata,
+            });
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(remaining.len());
+    for message in remaining {
+        let mut tool_results = Vec::new();
+        let mut metadata = None;
+        for id in tool_call_ids(&message) {
+            if let Some((result, extra)) = answered.remove(id) {
+                tool_results.push(result);
+                merge_metadata(&mut metadata, extra);
+            }
+        }
+        ordered.push(message);
+        if !tool_results.is_empty() {
+            ordered.push(Message::Tool {
+                tool_results,
+                metadata,
+            });
+        }
+    }
+    ordered
+}
+
+/// Drop orphaned tool results, collapse repeated tool outputs, strip reasoning blocks
+/// and remove the assistant messages left empty
+pub fn prune_messages(messages: Vec<Message>) -> Vec<Message> {
+    drop_empty_assistant_messages(strip_reasoning(collapse_repeated_tool_results(
+        drop_orphaned_tool_results(messages),
+    )))
+}
+
+/// Remove tool results answering no tool call in the conversation
+pub fn drop_orphaned_tool_results(messages: Vec<Message>) -> Vec<Message> {
+    let calls: HashSet<String> = messages
+        .iter()
+        .flat_map(tool_call_ids)
+        .map(str::to_string)
+        .collect();
+    messages
+        .into_iter()
+        .filter_map(|mut message| {
+            if let Message::Tool { tool_results, .. } = &mut message {
+                tool_results.retain(|result| calls.contains(&result.tool_call_id));
+                if tool_results.is_empty() {
+                    return None;
+                }
+            }
+            Some(message)
+        })
+        .collect()
+}
+
+/// Replace tool results identical to an earlier one with a reference to that call
+///
+/// Only results longer than the reference are replaced. Messages marked `cached` are
+/// left as they are.
+pub fn collapse_repeated_tool_results(mut messages: Vec<Message>) -> Vec<Message> {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for message in messages.iter_mut().filter(|message| !message.is_cached()) {
+        let Message::Tool { tool_results, .. } = message else {
+            continue;
+        };
+        for result in tool_results {
+            let key = result.result.to_string();
+            match seen.get(&key) {
+                Some(first) => {
+                    let reference = format!("[same result as tool call {}]", first);
+                    if reference.len() < key.len() {
+                        result.result = serde_json::Value::String(reference);
+                    }
+                }
+                None => {
+                    seen.insert(key, result.tool_call_id.clone());
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Remove assistant messages with neither tool calls nor any non-blank text
+pub fn drop_empty_assistant_messages(messages: Vec<Message>) -> Vec<Message> {
+    messages
+        .into_iter()
+        .filter(|message| match message {
+            Message::Assistant { content, .. } => content.iter().any(|part| match part {
+                AssistantContent::Text { text } => !text.trim().is_empty(),
+                AssistantContent::ToolCall { .. } => true,
+            }),
+            _ => true,
+        })
+        .collect()
+}
+
+const REASONING_TAGS: [&str; 3] = ["think", "thinking", "reasoning"];
+
+/// Remove `<think>`, `<thinking>` and `<reasoning>` blocks from assistant text
+///
+/// Reasoning models write these inline; they help the model once and only cost tokens
+/// when sent back. Unclosed blocks are left alone.
+pub fn strip_reasoning(mut messages: Vec<Message>) -> Vec<Message> {
+    for message in &mut messages {
+        let Message::Assistant { content, .. } = message else {
+            continue;
+        };
+        for part in content {
+            if let AssistantContent::Text { text } = part {
+                *text = strip_reasoning_text(text);
+            }
+        }
+    }
+    messages
+}
+
+/// Remove closed reasoning blocks along with the whitespace they leave at their edges
+///
+/// Whitespace after a block goes with it; a block ending the text also takes the
+/// whitespace before it. Text without a closed block is returned unchanged.
+fn strip_reasoning_text(text: &str) -> String {
+    let mut text = text.to_string();
+    for tag in REASONING_TAGS {
+        let open = format!("<{}>", tag);
+        let close = format!("</{}>", tag);
+        while let Some(start) = text.find(&open) {
+            let Some(end) = text[start..].find(&close) else {
+                break;
+            };
+            let block_end = start + end + close.len();
+            let after = text[block_end..].trim_start();
+            let end = text.len() - after.len();
+            let start = if after.is_empty() {
+                text[..start].trim_end().len()
+            } else {
+                start
+            };
+            text.replace_range(start..end, "");
+        }
+    }
+    text
+}
+
+fn out_of_order(message: String) -> AiError {
+    AiError::Validation(ValidationError::InvalidValue {
+        field: "messages".to_string(),
+        message,
+    })
+}
+
+/// Check the turn order required by providers with strict alternation
+///
+/// Ignoring system messages, the conversation must open with a user message, assistant
+/// messages may not follow each other, every tool call must be answered before the
+/// next user or assistant message, and every tool result must answer a call of the
+/// assistant message before it. Consecutive user and tool messages are accepted, since
+/// providers send them as one turn.
+pub fn validate_alternation(messages: &[Message]) -> Result<()> {
+    let mut previous: Option<&Message> = None;
+    let mut answerable: HashSet<&str> = HashSet::new();
+    let mut pending: Vec<&str> = Vec::new();
+
+    let unanswered =
+        |pending: &[&str]| out_of_order(format!("tool call {} has no result", pending.join(", ")));
+
+    for message in messages {
+        match message {
+            Message::System { .. } => continue,
+            Message::User { .. } | Message::Assistant { .. } if !pending.is_empty() => {
+                return Err(unanswered(&pending));
+            }
+            Message::Assistant { .. } if previous.is_none() => {
+                return Err(out_of_order(
+                    "the conversation must start with a user message".to_string(),
+                ));
+            }
+            Message::Assistant { .. } if matches!(previous, Some(Message::Assistant { .. })) => {
+                return Err(out_of_order(
+                    "two assistant messages follow each other".to_string(),
+                ));
+            }
+            Message::Assistant { .. } => {
+                answerable = tool_call_ids(message).into_iter().collect();
+                pending = tool_call_ids(message);
+            }
+            Message::Tool { tool_results, .. } => {
+                for result in tool_results {
+                    let id = result.tool_call_id.as_str();
+                    if !answerable.contains(id) {
+                        return Err(out_of_order(format!(
+                            "tool result {} does not answer a call of the preceding assistant message",
+                            id
+                        )));
+                    }
+                    pending.retain(|call| *call != id);
+                }
+            }
+            Message::User { .. } => {}
+        }
+        previous = Some(message);
+    }
+
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(unanswered(&pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(id: &str) -> Message {
+        Message::Assistant {
+            content: vec![AssistantContent::ToolCall {
+                tool_call: ToolCall {
+                    id: id.to_string(),
+                    name: "lookup".to_string(),
+                    arguments: json!({}),
+                },
+            }],
+            metadata: None,
+        }
+    }
+
+    fn result(id: &str) -> Message {
+        Message::tool(ToolResult {
+            tool_call_id: id.to_string(),
+            result: json!("ok"),
+            is_error: false,
+            content: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_normalize_repairs_agent_conversations() {
+        let messages = vec![
+            Message::system("Be brief"),
+            Message::user("Look it up"),
+            Message::user(""),
+            Message::user("please"),
+            call("call_1"),
+            Message::user("Also, hurry").cached(),
+            result("call_1"),
+            Message::assistant(AssistantContent::Text {
+                text: " ".to_string(),
+            }),
+            Message::assistant(AssistantContent::Text {
+                text: "Found it".to_string(),
+            }),
+        ];
+        assert!(validate_alternation(&messages).is_err());
+
+        let normalized = normalize_messages(messages);
+        assert_eq!(
+            normalized.iter().map(Message::role).collect::<Vec<_>>(),
+            ["system", "user", "assistant", "tool", "user", "assistant"]
+        );
+        let Message::User { content, .. } = &normalized[1] else {
+            panic!("expected a user message");
+        };
+        assert_eq!(content.len(), 2);
+        assert!(normalized[4].is_cached());
+        assert!(validate_alternation(&normalized).is_ok());
+    }
+
+    #[test]
+    fn test_results_follow_the_order_of_their_calls() {
+        let mut both = call("call_1");
+        if let (Message::Assistant { content, .. }, Message::Assistant { content: more, .. }) =
+            (&mut both, call("call_2"))
+        {
+            content.extend(more);
+        }
+        let messages = vec![
+            Message::user("Go"),
+            both,
+            result("call_2"),
+            result("call_1"),
+            result("call_9"),
+        ];
+
+        let reordered = reorder_tool_results(messages);
+        let Message::Tool { tool_results, .. } = &reordered[2] else {
+            panic!("expected the tool results after the calls");
+        };
+        let ids: Vec<_> = tool_results
+            .iter()
+            .map(|r| r.tool_call_id.as_str())
+            .collect();
+        assert_eq!(ids, ["call_1", "call_2"]);
+        assert_eq!(reordered.len(), 4);
+        assert!(validate_alternation(&reordered).is_err());
+    }
+
+    #[test]
+    fn test_alternation_errors() {
+        let starts_with_assistant = [Message::system("Hi"), call("call_1"), result("call_1")];
+        let unanswered = [Message::user("Go"), call("call_1")];
+        let doubled = [
+            Message::user("Go"),
+            Message::assistant(AssistantContent::Text {
+                text: "One".to_string(),
+            }),
+            Message::assistant(AssistantContent::Text {
+                text: "Two".to_string(),
+            }),
+        ];
+        for messages in [&starts_with_assistant[..], &unanswered, &doubled] {
+            match validate_alternation(messages) {
+                Err(AiError::Validation(ValidationError::InvalidValue { field, .. })) => {
+                    assert_eq!(field, "messages")
+                }
+                other => panic!("expected an ordering error, got {:?}", other),
+            }
+        }
+        assert!(
+            validate_alternation(&[Message::user("Go"), call("call_1"), result("call_1")]).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_prune_stale_history() {
+        let messages = vec![
+            Message::user("Look it up twice"),
+            call("call_1"),
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: json!("Found 3 matching records in the archive index"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant("<think>Again, to be sure.</think>"),
+            call("call_2"),
+            Message::tool(ToolResult {
+                tool_call_id: "call_2".to_string(),
+                result: json!("Found 3 matching records in the archive index"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            result("call_trimmed"),
+            Message::assistant("<thinking>Both agree.</thinking>\nIt is there."),
+        ];
+        let pruned = prune_messages(messages);
+
+        assert_eq!(
+            pruned.iter().map(Message::role).collect::<Vec<_>>(),
+            [
+                "user",
+                "assistant",
+                "tool",
+                "assistant",
+                "tool",
+                "assistant"
+            ]
+        );
+        let Message::Tool { tool_results, .. } = &pruned[4] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(
+            tool_results[0].result,
+            json!("[same result as tool call call_1]")
+        );
+        assert_eq!(pruned[5], Message::assistant("It is there."));
+        assert_eq!(
+            strip_reasoning_text("<think>unfinished"),
+            "<think>unfinished"
+        );
+    }
+
+    #[test]
+    fn test_strip_reasoning_trims_only_around_blocks() {
+        assert_eq!(
+            strip_reasoning_text("<think>plan</think>\n\nThe answer.\n"),
+            "The answer.\n"
+        );
+        assert_eq!(
+            strip_reasoning_text("Intro <thinking>x</thinking> rest"),
+            "Intro rest"
+        );
+        assert_eq!(
+            strip_reasoning_text("  Answer first.\n<reasoning>why</reasoning>\n"),
+            "  Answer first."
+        );
+        // Nothing to strip: leading indentation and trailing newlines survive
+        assert_eq!(strip_reasoning_text("  indented\n\n"), "  indented\n\n");
+        assert_eq!(
+            strip_reasoning_text("<think>unfinished \n"),
+            "<think>unfinished \n"
+        );
+    }
+}
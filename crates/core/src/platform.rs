@@ -0,0 +1,63 @@
+use std::{future::Future, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod bounds {
+    /// `Send` on native targets, nothing on `wasm32`
+    pub trait MaybeSend: Send {}
+    impl<T: Send + ?Sized> MaybeSend for T {}
+
+    /// `Sync` on native targets, nothing on `wasm32`
+    pub trait MaybeSync: Sync {}
+    impl<T: Sync + ?Sized> MaybeSync for T {}
+}
+
+#[cfg(target_arch = "wasm32")]
+mod bounds {
+    /// `Send` on native targets, nothing on `wasm32`
+    pub trait MaybeSend {}
+    impl<T: ?Sized> MaybeSend for T {}
+
+    /// `Sync` on native targets, nothing on `wasm32`
+    pub trait MaybeSync {}
+    impl<T: ?Sized> MaybeSync for T {}
+}
+
+pub use bounds::{MaybeSend, MaybeSync};
+
+/// Monotonic clock reading, backed by `performance.now()` on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+/// Monotonic clock reading, backed by `performance.now()` on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
+
+/// Wait for `duration`
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Wait for `duration`
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    futures_timer::Delay::new(duration).await
+}
+
+/// Run `work` for at most `duration`, returning `None` if it did not finish in time
+pub async fn timeout<F: Future>(duration: Duration, work: F) -> Option<F::Output> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        tokio::time::timeout(duration, work).await.ok()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        use futures::future::{Either, select};
+
+        let work = std::pin::pin!(work);
+        let timer = std::pin::pin!(sleep(duration));
+        match select(work, timer).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
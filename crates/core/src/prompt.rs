@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::errors::{AiError, Result, ValidationError};
+
+/// Maximum nesting of partials, which also stops partials that include each other
+const MAX_PARTIAL_DEPTH: usize = 16;
+
+/// A prompt with `{variable}` placeholders, conditional sections and partials
+///
+/// Syntax:
+/// - `{name}` inserts a variable; strings are inserted as is, other values as JSON
+/// - `{#if name}...{#else}...{/if}` keeps the first branch when the variable is set and
+///   not `null`, `false`, `""` or `[]`; `{#else}` is optional
+/// - `{>name}` inserts a partial registered with [`PromptTemplate::partial`]
+/// - `{{` and `}}` are literal braces
+///
+/// Use the [`prompt!`](crate::prompt!) macro to have variables checked at compile time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    source: String,
+    nodes: Vec<Node>,
+    partials: HashMap<String, PromptTemplate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var(String),
+    If {
+        name: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Partial(String),
+}
+
+/// Values for the variables of a template
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptVars {
+    values: HashMap<String, JsonValue>,
+}
+
+impl PromptVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        self.values.get(name)
+    }
+}
+
+impl<K: Into<String>, V: Into<JsonValue>> FromIterator<(K, V)> for PromptVars {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            values: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl PromptTemplate {
+    /// Parse a template, failing on unbalanced braces or sections
+    pub fn new(source: impl Into<String>) -> Result<Self> {
+        let source = source.into();
+        let nodes = parse(&source).map_err(|(message, position)| {
+            AiError::Validation(ValidationError::InvalidValue {
+                field: "template".to_string(),
+                message: format!("{} at byte {}", message, position),
+            })
+        })?;
+        Ok(Self {
+            source,
+            nodes,
+            partials: HashMap::new(),
+        })
+    }
+
+    /// Register a template inserted wherever `{>name}` appears
+    pub fn partial(mut self, name: impl Into<String>, template: PromptTemplate) -> Self {
+        self.partials.insert(name.into(), template);
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Variables used by this template, excluding partials, in order of first use
+    pub fn variables(&self) -> Vec<&str> {
+        fn collect<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+            for node in nodes {
+                match node {
+                    Node::Var(name) => {
+                        if !names.contains(&name.as_str()) {
+                            names.push(name);
+                        }
+                    }
+                    Node::If {
+                        name,
+                        then,
+                        otherwise,
+                    } => {
+                        if !names.contains(&name.as_str()) {
+                            names.push(name);
+                        }
+                        collect(then, names);
+                        collect(otherwise, names);
+                    }
+                    Node::Text(_) | Node::Partial(_) => {}
+                }
+            }
+        }
+        let mut names = Vec::new();
+        collect(&self.nodes, &mut names);
+        names
+    }
+
+    /// Render the template; every interpolated variable must be set
+    pub fn render(&self, vars: &PromptVars) -> Result<String> {
+        let mut output = String::with_capacity(self.source.len());
+        self.render_nodes(&self.nodes, vars, &self.partials, 0, &mut output)?;
+        Ok(output)
+    }
+
+    fn render_nodes(
+        &self,
+        nodes: &[Node],
+        vars: &PromptVars,
+        partials: &HashMap<String, PromptTemplate>,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Var(name) => match vars.get(name) {
+                    Some(JsonValue::String(value)) => output.push_str(value),
+                    Some(JsonValue::Null) => {}
+                    Some(value) => output.push_str(&value.to_string()),
+                    None => {
+                        return Err(AiError::Validation(ValidationError::MissingField {
+                            field: name.clone(),
+                        }));
+                    }
+                },
+                Node::If {
+                    name,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if vars.get(name).is_some_and(is_truthy) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render_nodes(branch, vars, partials, depth, output)?;
+                }
+                Node::Partial(name) => {
+                    let partial = self
+                        .partials
+                        .get(name)
+                        .or_else(|| partials.get(name))
+                        .ok_or_else(|| {
+                            AiError::Validation(ValidationError::InvalidValue {
+                                field: "partial".to_string(),
+                                message: format!("no partial named '{}'", name),
+                            })
+                        })?;
+                    if depth >= MAX_PARTIAL_DEPTH {
+                        return Err(AiError::Validation(ValidationError::InvalidValue {
+                            field: "partial".to_string(),
+                            message: format!("partials nested too deeply at '{}'", name),
+                        }));
+                    }
+                    partial.render_nodes(&partial.nodes, vars, partials, depth + 1, output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null | JsonValue::Bool(false) => false,
+        JsonValue::String(value) => !value.is_empty(),
+        JsonValue::Array(values) => !values.is_empty(),
+        _ => true,
+    }
+}
+
+/// A piece of template source; ranges are byte offsets into the template
+#[derive(Clone, Copy)]
+enum Token {
+    Text(usize, usize),
+    Var(usize, usize),
+    If(usize, usize),
+    Else,
+    EndIf,
+    Partial(usize, usize),
+}
+
+const fn is_name(s: &[u8], start: usize, end: usize) -> bool {
+    if start >= end || !(s[start].is_ascii_alphabetic() || s[start] == b'_') {
+        return false;
+    }
+    let mut i = start + 1;
+    while i < end {
+        if !(s[i].is_ascii_alphanumeric() || s[i] == b'_') {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn trim(s: &[u8], mut start: usize, mut end: usize) -> (usize, usize) {
+    while start < end && s[start] == b' ' {
+        start += 1;
+    }
+    while end > start && s[end - 1] == b' ' {
+        end -= 1;
+    }
+    (start, end)
+}
+
+const fn slice_eq(s: &[u8], start: usize, end: usize, expected: &[u8]) -> bool {
+    if end - start != expected.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < expected.len() {
+        if s[start + i] != expected[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Read the token at `start`, returning it with the offset of the next token
+const fn next_token(s: &[u8], start: usize) -> std::result::Result<(Token, usize), &'static str> {
+    let brace = s[start];
+    if brace != b'{' && brace != b'}' {
+        let mut end = start;
+        while end < s.len() && s[end] != b'{' && s[end] != b'}' {
+            end += 1;
+        }
+        return Ok((Token::Text(start, end), end));
+    }
+    if start + 1 < s.len() && s[start + 1] == brace {
+        return Ok((Token::Text(start, start + 1), start + 2));
+    }
+    if brace == b'}' {
+        return Err("unmatched `}`; write `}}` for a literal brace");
+    }
+
+    let mut close = start + 1;
+    while close < s.len() && s[close] != b'}' {
+        if s[close] == b'{' {
+            return Err("unclosed `{`; write `{{` for a literal brace");
+        }
+        close += 1;
+    }
+    if close == s.len() {
+        return Err("unclosed `{`; write `{{` for a literal brace");
+    }
+    let next = close + 1;
+    let (tag_start, tag_end) = trim(s, start + 1, close);
+
+    if slice_eq(s, tag_start, tag_end, b"#else") {
+        return Ok((Token::Else, next));
+    }
+    if slice_eq(s, tag_start, tag_end, b"/if") {
+        return Ok((Token::EndIf, next));
+    }
+    if tag_end - tag_start > 4 && slice_eq(s, tag_start, tag_start + 4, b"#if ") {
+        let (name_start, name_end) = trim(s, tag_start + 4, tag_end);
+        if !is_name(s, name_start, name_end) {
+            return Err("invalid variable name in `{#if}`");
+        }
+        return Ok((Token::If(name_start, name_end), next));
+    }
+    if tag_start < tag_end && s[tag_start] == b'>' {
+        let (name_start, name_end) = trim(s, tag_start + 1, tag_end);
+        if !is_name(s, name_start, name_end) {
+            return Err("invalid partial name");
+        }
+        return Ok((Token::Partial(name_start, name_end), next));
+    }
+    if !is_name(s, tag_start, tag_end) {
+        return Err("invalid variable name; names use letters, digits and `_`");
+    }
+    Ok((Token::Var(tag_start, tag_end), next))
+}
+
+fn parse(source: &str) -> std::result::Result<Vec<Node>, (&'static str, usize)> {
+    let s = source.as_bytes();
+    // Each open section keeps its name, the nodes before it and its `then` branch once
+    // `{#else}` is reached
+    let mut stack: Vec<(String, Vec<Node>, Option<Vec<Node>>)> = Vec::new();
+    let mut current: Vec<Node> = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        let (token, next) = next_token(s, i).map_err(|message| (message, i))?;
+        match token {
+            Token::Text(start, end) => match current.last_mut() {
+                Some(Node::Text(text)) => text.push_str(&source[start..end]),
+                _ => current.push(Node::Text(source[start..end].to_string())),
+            },
+            Token::Var(start, end) => current.push(Node::Var(source[start..end].to_string())),
+            Token::Partial(start, end) => {
+                current.push(Node::Partial(source[start..end].to_string()))
+            }
+            Token::If(start, end) => {
+                let outer = std::mem::take(&mut current);
+                stack.push((source[start..end].to_string(), outer, None));
+            }
+            Token::Else => match stack.last_mut() {
+                Some((_, _, then @ None)) => *then = Some(std::mem::take(&mut current)),
+                Some(_) => return Err(("second `{#else}` in one section", i)),
+                None => return Err(("`{#else}` outside of `{#if}`", i)),
+            },
+            Token::EndIf => {
+                let Some((name, outer, then)) = stack.pop() else {
+                    return Err(("`{/if}` without `{#if}`", i));
+                };
+                let branch = std::mem::replace(&mut current, outer);
+                current.push(match then {
+                    Some(then) => Node::If {
+                        name,
+                        then,
+                        otherwise: branch,
+                    },
+                    None => Node::If {
+                        name,
+                        then: branch,
+                        otherwise: Vec::new(),
+                    },
+                });
+            }
+        }
+        i = next;
+    }
+    if !stack.is_empty() {
+        return Err(("`{#if}` without `{/if}`", s.len()));
+    }
+    Ok(current)
+}
+
+/// Check template syntax and that every variable is in `names`, for use in const context
+///
+/// Partials are rejected because they are only known at runtime.
+pub const fn check_template(
+    template: &str,
+    names: &[&str],
+) -> std::result::Result<(), &'static str> {
+    let s = template.as_bytes();
+    let mut depth: u32 = 0;
+    // Bit n is set once the section at depth n + 1 has seen `{#else}`
+    let mut else_seen: u64 = 0;
+    let mut i = 0;
+    while i < s.len() {
+        let (token, next) = match next_token(s, i) {
+            Ok(token) => token,
+            Err(message) => return Err(message),
+        };
+        match token {
+            Token::Text(..) => {}
+            Token::Var(start, end) | Token::If(start, end) => {
+                if !name_listed(s, start, end, names) {
+                    return Err("the template uses a variable that is not passed");
+                }
+                if let Token::If(..) = token {
+                    if depth == 64 {
+                        return Err("`{#if}` sections nested too deeply");
+                    }
+                    depth += 1;
+                    else_seen &= !(1 << (depth - 1));
+                }
+            }
+            Token::Else => {
+                if depth == 0 {
+                    return Err("`{#else}` outside of `{#if}`");
+                }
+                if else_seen & (1 << (depth - 1)) != 0 {
+                    return Err("second `{#else}` in one section");
+                }
+                else_seen |= 1 << (depth - 1);
+            }
+            Token::EndIf => {
+                if depth == 0 {
+                    return Err("`{/if}` without `{#if}`");
+                }
+                depth -= 1;
+            }
+            Token::Partial(..) => {
+                return Err("partials are only supported by `PromptTemplate`");
+            }
+        }
+        i = next;
+    }
+    if depth != 0 {
+        return Err("`{#if}` without `{/if}`");
+    }
+    Ok(())
+}
+
+const fn name_listed(s: &[u8], start: usize, end: usize, names: &[&str]) -> bool {
+    let mut n = 0;
+    while n < names.len() {
+        if slice_eq(s, start, end, names[n].as_bytes()) {
+            return true;
+        }
+        n += 1;
+    }
+    false
+}
+
+/// Panics, failing compilation in const context, when `check_template` rejects the template
+pub const fn assert_template(template: &str, names: &[&str]) {
+    if let Err(message) = check_template(template, names) {
+        panic!("{}", message);
+    }
+}
+
+/// Render a template whose variables are checked at compile time
+///
+/// The template must be a string literal or constant. Every variable it uses must be
+/// passed as `name = value`, where the value converts into a JSON value.
+///
+/// ```
+/// let prompt = ai_core::prompt!(
+///     "You are {role}.{#if tone} Answer in a {tone} tone.{/if}",
+///     role = "a support agent",
+///     tone = Some("friendly"),
+/// );
+/// assert_eq!(prompt, "You are a support agent. Answer in a friendly tone.");
+/// ```
+///
+/// A variable missing from the arguments is a compile error:
+///
+/// ```compile_fail
+/// let prompt = ai_core::prompt!("Hello {name}, you are {role}.", name = "Ada");
+/// ```
+#[macro_export]
+macro_rules! prompt {
+    ($template:expr $(, $name:ident = $value:expr)* $(,)?) => {{
+        const _: () = $crate::prompt::assert_template($template, &[$(stringify!($name)),*]);
+        $crate::prompt::PromptTemplate::new($template)
+            .and_then(|template| {
+                template.render(&$crate::prompt::PromptVars::new()$(.var(stringify!($name), $value))*)
+            })
+            .expect("template checked at compile time")
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables_and_sections() {
+        let template = PromptTemplate::new(
+            "Hello {name}!{#if admin} You are an admin.{#else} You are a guest.{/if} {{literal}}",
+        )
+        .unwrap();
+        assert_eq!(template.variables(), vec!["name", "admin"]);
+
+        let vars = PromptVars::new().var("name", "Ada").var("admin", true);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Hello Ada! You are an admin. {literal}"
+        );
+        let vars = PromptVars::new().var("name", "Bob").var("admin", false);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Hello Bob! You are a guest. {literal}"
+        );
+        assert!(template.render(&PromptVars::new()).is_err());
+    }
+
+    #[test]
+    fn test_partials() {
+        let rules = PromptTemplate::new("Always answer in {language}.").unwrap();
+        let template = PromptTemplate::new("You are helpful. {>rules}")
+            .unwrap()
+            .partial("rules", rules);
+        let vars = PromptVars::new().var("language", "French");
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "You are helpful. Always answer in French."
+        );
+
+        let looping = PromptTemplate::new("{>self}").unwrap();
+        let looping = looping.clone().partial("self", looping);
+        assert!(looping.render(&vars).is_err());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for source in [
+            "{name",
+            "name}",
+            "{#if a}x",
+            "{/if}",
+            "{#if a}{#else}{#else}{/if}",
+            "{}",
+        ] {
+            assert!(PromptTemplate::new(source).is_err(), "{}", source);
+            assert!(
+                check_template(source, &["a", "name"]).is_err(),
+                "{}",
+                source
+            );
+        }
+        assert!(check_template("{a}{#if b}{/if}", &["a"]).is_err());
+        assert!(check_template("{>rules}", &[]).is_err());
+        assert!(check_template("{#if a}{#if a}{#else}{/if}{#else}{/if}", &["a"]).is_ok());
+    }
+
+    #[test]
+    fn test_prompt_macro() {
+        let items = 3;
+        let prompt = crate::prompt!(
+            "{user} has {count} items{#if note}: {note}{/if}",
+            user = "Ada",
+            count = items,
+            note = None::<String>,
+        );
+        assert_eq!(prompt, "Ada has 3 items");
+    }
+}
@@ -1,12 +1,52 @@
-use crate::errors::{AiError, ProviderError, Result};
+use crate::errors::{AiError, ProviderError, Result, ValidationError};
+use crate::models::ModelInfo;
+use crate::platform::{MaybeSend, MaybeSync};
 use crate::types::*;
 use async_trait::async_trait;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
+use std::sync::Arc;
+
+/// Streamed chat response; `Send` except on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatStreamChunk>> + Send>>;
+/// Streamed chat response; `Send` except on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatStreamChunk>>>>;
+
+/// Rewrite the usage on `stream`'s chunks as the running total of the response
+///
+/// Providers that split usage across events, like Anthropic sending input tokens at the
+/// start and output tokens at the end, wrap their streams with this so that every chunk
+/// reporting usage carries the cumulative counts and the chunk with the finish reason
+/// carries the complete usage.
+pub fn cumulative_usage(stream: ChatStream) -> ChatStream {
+    Box::pin(stream.scan(None::<Usage>, |total, item| {
+        let item = item.map(|mut chunk| {
+            if let Some(reported) = &chunk.usage {
+                let previous = total.take().unwrap_or(Usage::new(0, 0));
+                *total = Some(previous.merge_reported(reported));
+            }
+            if chunk.usage.is_some() || chunk.finish_reason.is_some() {
+                chunk.usage = total.clone();
+            }
+            chunk
+        });
+        futures::future::ready(Some(item))
+    }))
+}
+
+/// Streamed image generation progress; `Send` except on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type ImageStream = Pin<Box<dyn Stream<Item = Result<ImageProgress>> + Send>>;
+/// Streamed image generation progress; `Send` except on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type ImageStream = Pin<Box<dyn Stream<Item = Result<ImageProgress>>>>;
 
 /// Trait for chat-based text generation providers
-#[async_trait]
-pub trait ChatTextGeneration: Send + Sync {
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ChatTextGeneration: MaybeSend + MaybeSync {
     /// Get the provider's name/identifier
     fn name(&self) -> &str;
 
@@ -17,10 +57,7 @@ pub trait ChatTextGeneration: Send + Sync {
     async fn generate(&self, request: ChatRequest) -> Result<ChatResponse>;
 
     /// Generate a streaming chat response
-    async fn generate_stream(
-        &self,
-        request: ChatRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<ChatStreamChunk>> + Send>>>;
+    async fn generate_stream(&self, request: ChatRequest) -> Result<ChatStream>;
 
     /// Check if the provider supports tool calling
     fn supports_tools(&self) -> bool {
@@ -37,13 +74,36 @@ pub trait ChatTextGeneration: Send + Sync {
         true
     }
 
+    /// Where this provider works best with few-shot examples
+    fn example_style(&self) -> ExampleStyle {
+        ExampleStyle::Messages
+    }
+
     /// Get maximum token limit for this provider/model
     fn max_tokens(&self) -> Option<u32> {
         Some(4096)
     }
 
+    /// Tokens the model accepts in one request, prompt and output together, if known
+    fn context_window(&self) -> Option<u32> {
+        None
+    }
+
+    /// Capabilities and pricing of the model, if it is in the provider's registry
+    fn model_info(&self) -> Option<ModelInfo> {
+        None
+    }
+
+    /// Generation settings this provider accepts
+    fn settings_rules(&self) -> SettingsRules {
+        SettingsRules::default()
+    }
+
     /// Validate that a request is compatible with this provider
     fn validate_request(&self, request: &ChatRequest) -> Result<()> {
+        self.settings_rules()
+            .validate(self.name(), &request.settings)?;
+
         if request.tools.is_some() && !self.supports_tools() {
             return Err(AiError::Provider(ProviderError::UnsupportedFeature {
                 provider: self.name().to_string(),
@@ -98,9 +158,280 @@ pub trait ChatTextGeneration: Send + Sync {
     }
 }
 
+/// Shared providers can be used anywhere an owned provider is expected
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: ChatTextGeneration + ?Sized> ChatTextGeneration for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn model(&self) -> &str {
+        (**self).model()
+    }
+
+    async fn generate(&self, request: ChatRequest) -> Result<ChatResponse> {
+        (**self).generate(request).await
+    }
+
+    async fn generate_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        (**self).generate_stream(request).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        (**self).supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        (**self).supports_vision()
+    }
+
+    fn supports_system_messages(&self) -> bool {
+        (**self).supports_system_messages()
+    }
+
+    fn example_style(&self) -> ExampleStyle {
+        (**self).example_style()
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        (**self).max_tokens()
+    }
+
+    fn context_window(&self) -> Option<u32> {
+        (**self).context_window()
+    }
+
+    fn model_info(&self) -> Option<ModelInfo> {
+        (**self).model_info()
+    }
+
+    fn settings_rules(&self) -> SettingsRules {
+        (**self).settings_rules()
+    }
+
+    fn validate_request(&self, request: &ChatRequest) -> Result<()> {
+        (**self).validate_request(request)
+    }
+}
+
+/// Ranges a provider accepts for each generation setting
+///
+/// A `None` range means the provider has no such setting. The default accepts every
+/// value, for providers that leave validation to their API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsRules {
+    pub temperature: Option<(f32, f32)>,
+    pub top_p: Option<(f32, f32)>,
+    pub top_k: bool,
+    /// Range of both `frequency_penalty` and `presence_penalty`
+    pub penalties: Option<(f32, f32)>,
+    pub seed: bool,
+    pub max_tokens: Option<u32>,
+    pub max_stop_sequences: Option<usize>,
+    /// Range of each `logit_bias` value
+    pub logit_bias: Option<(f32, f32)>,
+    /// Constraint kinds the provider can decode against
+    pub constraints: Vec<ConstraintKind>,
+}
+
+impl Default for SettingsRules {
+    fn default() -> Self {
+        Self {
+            temperature: Some((0.0, f32::MAX)),
+            top_p: Some((0.0, 1.0)),
+            top_k: true,
+            penalties: Some((f32::MIN, f32::MAX)),
+            seed: true,
+            max_tokens: None,
+            max_stop_sequences: None,
+            logit_bias: Some((f32::MIN, f32::MAX)),
+            constraints: vec![
+                ConstraintKind::Grammar,
+                ConstraintKind::Regex,
+                ConstraintKind::JsonSchema,
+                ConstraintKind::Choice,
+            ],
+        }
+    }
+}
+
+impl SettingsRules {
+    /// Settings in `settings` the provider has no way to honor, by field name
+    pub fn unsupported(&self, settings: &GenerationSettings) -> Vec<&'static str> {
+        let checks = [
+            (
+                "temperature",
+                settings.temperature.is_some() && self.temperature.is_none(),
+            ),
+            ("top_p", settings.top_p.is_some() && self.top_p.is_none()),
+            ("top_k", settings.top_k.is_some() && !self.top_k),
+            (
+                "frequency_penalty",
+                settings.frequency_penalty.is_some() && self.penalties.is_none(),
+            ),
+            (
+                "presence_penalty",
+                settings.presence_penalty.is_some() && self.penalties.is_none(),
+            ),
+            ("seed", settings.seed.is_some() && !self.seed),
+            (
+                "logit_bias",
+                settings.logit_bias.is_some() && self.logit_bias.is_none(),
+            ),
+            (
+                "constraint",
+                settings
+                    .constraint
+                    .as_ref()
+                    .is_some_and(|constraint| !self.constraints.contains(&constraint.kind())),
+            ),
+        ];
+        checks
+            .into_iter()
+            .filter(|(_, unsupported)| *unsupported)
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    /// Fail on the first setting `provider` does not accept
+    ///
+    /// Settings the provider has no equivalent for fail with
+    /// `ProviderError::UnsupportedFeature`, out-of-range values with
+    /// `ValidationError::InvalidValue`.
+    pub fn validate(&self, provider: &str, settings: &GenerationSettings) -> Result<()> {
+        if let Some(field) = self.unsupported(settings).first() {
+            return Err(AiError::Provider(ProviderError::UnsupportedFeature {
+                provider: provider.to_string(),
+                feature: field.to_string(),
+            }));
+        }
+        let invalid = |field: &str, message: String| {
+            Err(AiError::Validation(ValidationError::InvalidValue {
+                field: field.to_string(),
+                message,
+            }))
+        };
+        let ranged = [
+            ("temperature", settings.temperature, self.temperature),
+            ("top_p", settings.top_p, self.top_p),
+            (
+                "frequency_penalty",
+                settings.frequency_penalty,
+                self.penalties,
+            ),
+            (
+                "presence_penalty",
+                settings.presence_penalty,
+                self.penalties,
+            ),
+        ];
+        for (field, value, range) in ranged {
+            if let (Some(value), Some((min, max))) = (value, range)
+                && !(min..=max).contains(&value)
+            {
+                return invalid(
+                    field,
+                    format!(
+                        "{} must be between {} and {} for {}",
+                        value, min, max, provider
+                    ),
+                );
+            }
+        }
+        if let (Some(tokens), Some(max)) = (settings.max_tokens, self.max_tokens)
+            && tokens > max
+        {
+            return invalid(
+                "max_tokens",
+                format!("{} exceeds the limit of {} for {}", tokens, max, provider),
+            );
+        }
+        if let (Some(stops), Some(max)) = (&settings.stop_sequences, self.max_stop_sequences)
+            && stops.len() > max
+        {
+            return invalid(
+                "stop_sequences",
+                format!("at most {} allowed by {}", max, provider),
+            );
+        }
+        if let (Some(biases), Some((min, max))) = (&settings.logit_bias, self.logit_bias)
+            && let Some((token, bias)) =
+                biases.iter().find(|(_, bias)| !(min..=max).contains(*bias))
+        {
+            return invalid(
+                "logit_bias",
+                format!(
+                    "{} for token {} must be between {} and {} for {}",
+                    bias, token, min, max, provider
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Validate `settings`, or clamp them when `clamp` is set
+    ///
+    /// Clamping drops settings the provider cannot honor; with the `tracing` feature
+    /// each dropped setting is logged as a warning.
+    pub fn apply(
+        &self,
+        provider: &str,
+        settings: &GenerationSettings,
+        clamp: bool,
+    ) -> Result<GenerationSettings> {
+        if !clamp {
+            self.validate(provider, settings)?;
+            return Ok(settings.clone());
+        }
+        #[cfg(feature = "tracing")]
+        for field in self.unsupported(settings) {
+            tracing::warn!(provider, setting = field, "dropping unsupported setting");
+        }
+        Ok(self.clamp(settings))
+    }
+
+    /// Bring out-of-range values into range and drop unsupported settings
+    pub fn clamp(&self, settings: &GenerationSettings) -> GenerationSettings {
+        let clamp = |value: Option<f32>, range: Option<(f32, f32)>| {
+            range.and_then(|(min, max)| value.map(|value| value.clamp(min, max)))
+        };
+        let mut stop_sequences = settings.stop_sequences.clone();
+        if let (Some(stops), Some(max)) = (&mut stop_sequences, self.max_stop_sequences) {
+            stops.truncate(max);
+        }
+        GenerationSettings {
+            temperature: clamp(settings.temperature, self.temperature),
+            max_tokens: match self.max_tokens {
+                Some(max) => settings.max_tokens.map(|tokens| tokens.min(max)),
+                None => settings.max_tokens,
+            },
+            top_p: clamp(settings.top_p, self.top_p),
+            top_k: settings.top_k.filter(|_| self.top_k),
+            frequency_penalty: clamp(settings.frequency_penalty, self.penalties),
+            presence_penalty: clamp(settings.presence_penalty, self.penalties),
+            stop_sequences,
+            seed: settings.seed.filter(|_| self.seed),
+            logit_bias: self.logit_bias.and_then(|(min, max)| {
+                settings.logit_bias.as_ref().map(|biases| {
+                    biases
+                        .iter()
+                        .map(|(token, bias)| (token.clone(), bias.clamp(min, max)))
+                        .collect()
+                })
+            }),
+            constraint: settings
+                .constraint
+                .clone()
+                .filter(|constraint| self.constraints.contains(&constraint.kind())),
+        }
+    }
+}
+
 /// Trait for embedding generation providers
-#[async_trait]
-pub trait EmbeddingGeneration: Send + Sync {
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait EmbeddingGeneration: MaybeSend + MaybeSync {
     /// Get the provider's name/identifier
     fn name(&self) -> &str;
 
@@ -112,11 +443,51 @@ pub trait EmbeddingGeneration: Send + Sync {
 
     /// Get the dimension of embeddings produced by this model
     fn embedding_dimension(&self) -> u32;
+
+    /// Whether the provider shortens embeddings to `EmbeddingRequest::dimensions` itself
+    fn supports_dimensions(&self) -> bool {
+        false
+    }
+}
+
+/// Shorten an embedding to its first `dimensions` values and scale it back to unit length
+///
+/// Only meaningful for Matryoshka-trained models, whose leading dimensions carry most of
+/// the information. Vectors already short enough are only renormalized.
+pub fn truncate_embedding(embedding: &mut Vec<f32>, dimensions: usize) {
+    embedding.truncate(dimensions);
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Generate embeddings honouring `request.dimensions` with any provider
+///
+/// Providers without `supports_dimensions` get the request without it, and the full
+/// vectors are cut down with `truncate_embedding`.
+pub async fn embed_with_dimensions(
+    embedder: &dyn EmbeddingGeneration,
+    mut request: EmbeddingRequest,
+) -> Result<EmbeddingResponse> {
+    let truncate_to = if embedder.supports_dimensions() {
+        None
+    } else {
+        request.dimensions.take()
+    };
+    let mut response = embedder.generate_embeddings(request).await?;
+    if let Some(dimensions) = truncate_to {
+        for embedding in &mut response.embeddings {
+            truncate_embedding(embedding, dimensions as usize);
+        }
+    }
+    Ok(response)
 }
 
 /// Trait for image generation providers
-#[async_trait]
-pub trait ImageGeneration: Send + Sync {
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ImageGeneration: MaybeSend + MaybeSync {
     /// Get the provider's name/identifier
     fn name(&self) -> &str;
 
@@ -126,8 +497,114 @@ pub trait ImageGeneration: Send + Sync {
     /// Generate images from text prompts
     async fn generate_image(&self, request: ImageRequest) -> Result<ImageResponse>;
 
+    /// Generate images, reporting intermediate steps for providers that have them
+    ///
+    /// The default waits for `generate_image` and yields only `ImageProgress::Completed`.
+    async fn generate_image_stream(&self, request: ImageRequest) -> Result<ImageStream> {
+        let response = self.generate_image(request).await?;
+        Ok(Box::pin(futures::stream::iter([Ok(
+            ImageProgress::Completed { response },
+        )])))
+    }
+
     /// Check if the provider supports image editing
     fn supports_image_editing(&self) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedEmbedder;
+
+    #[async_trait]
+    impl EmbeddingGeneration for FixedEmbedder {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn model(&self) -> &str {
+            "fixed-4"
+        }
+
+        async fn generate_embeddings(
+            &self,
+            request: EmbeddingRequest,
+        ) -> Result<EmbeddingResponse> {
+            assert_eq!(request.dimensions, None);
+            Ok(EmbeddingResponse {
+                embeddings: vec![vec![3.0, 4.0, 12.0, 84.0]; request.inputs.len()],
+                usage: None,
+                metadata: None,
+            })
+        }
+
+        fn embedding_dimension(&self) -> u32 {
+            4
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_are_truncated_client_side() {
+        let request = EmbeddingRequest {
+            inputs: vec!["a".to_string()],
+            model: None,
+            encoding_format: None,
+            dimensions: Some(2),
+        };
+        let response = embed_with_dimensions(&FixedEmbedder, request)
+            .await
+            .unwrap();
+        assert_eq!(response.embeddings, [vec![0.6, 0.8]]);
+
+        let mut zero = vec![0.0; 4];
+        truncate_embedding(&mut zero, 2);
+        assert_eq!(zero, [0.0, 0.0]);
+    }
+
+    struct FixedPainter;
+
+    #[async_trait]
+    impl ImageGeneration for FixedPainter {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn model(&self) -> &str {
+            "fixed-painter"
+        }
+
+        async fn generate_image(&self, _request: ImageRequest) -> Result<ImageResponse> {
+            Ok(ImageResponse {
+                images: Vec::new(),
+                usage: None,
+                metadata: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_stream_defaults_to_the_finished_response() {
+        use futures::StreamExt;
+
+        let request = ImageRequest {
+            prompt: "a cat".to_string(),
+            size: None,
+            quality: None,
+            n: None,
+            response_format: None,
+        };
+        let events: Vec<_> = FixedPainter
+            .generate_image_stream(request)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(matches!(
+            events.as_slice(),
+            [Ok(ImageProgress::Completed { .. })]
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::errors::Result;
+use crate::platform::{MaybeSend, MaybeSync};
+use crate::tools::BuiltToolRouter;
+use crate::types::{ToolCall, ToolDefinition, ToolResult, Usage};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Output a realtime session should produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RealtimeModality {
+    Text,
+    Audio,
+}
+
+/// Settings sent when a realtime session is opened
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RealtimeConfig {
+    pub model: Option<String>,
+    pub instructions: Option<String>,
+    pub tools: Vec<ToolDefinition>,
+    /// Empty means the provider's default
+    pub modalities: Vec<RealtimeModality>,
+    pub voice: Option<String>,
+    /// Audio encoding for both directions, e.g. `pcm16`
+    pub audio_format: Option<String>,
+}
+
+impl RealtimeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn modality(mut self, modality: RealtimeModality) -> Self {
+        self.modalities.push(modality);
+        self
+    }
+
+    pub fn voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    pub fn audio_format(mut self, format: impl Into<String>) -> Self {
+        self.audio_format = Some(format.into());
+        self
+    }
+}
+
+/// Event sent from the client to a realtime session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RealtimeClientEvent {
+    /// A user text message
+    Text { text: String },
+    /// A chunk of user audio, base64 encoded in the session's audio format
+    Audio { data: String },
+    /// End of the buffered user audio, for sessions without server-side voice detection
+    CommitAudio,
+    /// Ask the model to respond to what it has so far
+    CreateResponse,
+    /// Result of a tool call the server asked for
+    ToolResult { result: ToolResult },
+    /// Cancel the response in progress
+    ///
+    /// `audio_played_ms` is how much of the response audio the user actually heard, so
+    /// the provider can cut the unheard rest from the conversation.
+    Interrupt { audio_played_ms: Option<u64> },
+}
+
+/// Event received from a realtime session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RealtimeServerEvent {
+    /// The model started a new response
+    ResponseStarted,
+    TextDelta {
+        delta: String,
+    },
+    /// A chunk of response audio, base64 encoded in the session's audio format
+    AudioDelta {
+        data: String,
+    },
+    /// Transcript of spoken audio; `role` is `user` for input and `assistant` for output
+    Transcript {
+        role: String,
+        text: String,
+    },
+    /// Voice detection heard the user start speaking
+    SpeechStarted,
+    /// The model wants a tool run; the session waits for a `ToolResult` event
+    ToolCall {
+        tool_call: ToolCall,
+    },
+    ResponseDone {
+        usage: Option<Usage>,
+    },
+    /// The response in progress was cancelled
+    Interrupted,
+    /// An error reported by the provider; the session stays open
+    Error {
+        message: String,
+    },
+}
+
+/// An open realtime connection
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RealtimeSession: MaybeSend + MaybeSync {
+    /// Send an event to the provider
+    async fn send(&mut self, event: RealtimeClientEvent) -> Result<()>;
+
+    /// Wait for the next event; `None` once the connection is closed
+    async fn recv(&mut self) -> Option<Result<RealtimeServerEvent>>;
+
+    /// Close the connection
+    async fn close(&mut self) -> Result<()>;
+}
+
+/// Trait for providers with a realtime API
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RealtimeProvider: MaybeSend + MaybeSync {
+    /// Get the provider's name/identifier
+    fn name(&self) -> &str;
+
+    /// Get the model being used
+    fn model(&self) -> &str;
+
+    /// Open a session configured with `config`
+    async fn connect(&self, config: RealtimeConfig) -> Result<Box<dyn RealtimeSession>>;
+}
+
+/// A realtime session whose tool calls are answered by a `ToolRouter`
+///
+/// Tool call events are still passed on to the caller, after their result has been sent
+/// and a new response requested. Calls to tools without a handler are left to the caller,
+/// which answers them with `send_tool_result`.
+pub struct RealtimeConversation<S: Clone + Send + Sync + 'static> {
+    session: Box<dyn RealtimeSession>,
+    router: BuiltToolRouter<S>,
+    responding: bool,
+    interrupt_on_speech: bool,
+}
+
+impl<S: Clone + Send + Sync + 'static> RealtimeConversation<S> {
+    pub fn new(session: Box<dyn RealtimeSession>, router: BuiltToolRouter<S>) -> Self {
+        Self {
+            session,
+            router,
+            responding: false,
+            interrupt_on_speech: false,
+        }
+    }
+
+    /// Cancel the response in progress as soon as the user starts speaking
+    ///
+    /// For providers that leave barge-in handling to the client.
+    pub fn interrupt_on_speech(mut self, enabled: bool) -> Self {
+        self.interrupt_on_speech = enabled;
+        self
+    }
+
+    /// Whether the model is producing a response
+    pub fn is_responding(&self) -> bool {
+        self.responding
+    }
+
+    pub async fn send_text(&mut self, text: impl Into<String>) -> Result<()> {
+        self.session
+            .send(RealtimeClientEvent::Text { text: text.into() })
+            .await
+    }
+
+    /// Send a chunk of base64 encoded audio
+    pub async fn send_audio(&mut self, data: impl Into<String>) -> Result<()> {
+        self.session
+            .send(RealtimeClientEvent::Audio { data: data.into() })
+            .await
+    }
+
+    pub async fn send(&mut self, event: RealtimeClientEvent) -> Result<()> {
+        self.session.send(event).await
+    }
+
+    /// Answer a tool call and ask the model to continue
+    pub async fn send_tool_result(&mut self, result: ToolResult) -> Result<()> {
+        self.session
+            .send(RealtimeClientEvent::ToolResult { result })
+            .await?;
+        self.session.send(RealtimeClientEvent::CreateResponse).await
+    }
+
+    /// Cancel the response in progress, if there is one
+    pub async fn interrupt(&mut self, audio_played_ms: Option<u64>) -> Result<()> {
+        if !self.responding {
+            return Ok(());
+        }
+        self.responding = false;
+        self.session
+            .send(RealtimeClientEvent::Interrupt { audio_played_ms })
+            .await
+    }
+
+    /// Wait for the next event, running any tool calls it carries
+    pub async fn next_event(&mut self) -> Option<Result<RealtimeServerEvent>> {
+        let event = match self.session.recv().await? {
+            Ok(event) => event,
+            Err(e) => return Some(Err(e)),
+        };
+        let handled = match &event {
+            RealtimeServerEvent::ResponseStarted => {
+                self.responding = true;
+                Ok(())
+            }
+            RealtimeServerEvent::ResponseDone { .. } | RealtimeServerEvent::Interrupted => {
+                self.responding = false;
+                Ok(())
+            }
+            RealtimeServerEvent::SpeechStarted if self.interrupt_on_speech => {
+                self.interrupt(None).await
+            }
+            RealtimeServerEvent::ToolCall { tool_call } => self.run_tool(tool_call.clone()).await,
+            _ => Ok(()),
+        };
+        Some(handled.map(|()| event))
+    }
+
+    async fn run_tool(&mut self, tool_call: ToolCall) -> Result<()> {
+        let result = match self.router.execute_call(&tool_call).await {
+            Some(Ok(output)) => output.into_tool_result(tool_call.id),
+            Some(Err(e)) => ToolResult {
+                tool_call_id: tool_call.id,
+                result: e.to_result_json(),
+                is_error: true,
+                content: Vec::new(),
+            },
+            // No handler: the caller answers this one
+            None => return Ok(()),
+        };
+        self.send_tool_result(result).await
+    }
+
+    /// Close the underlying session
+    pub async fn close(mut self) -> Result<()> {
+        self.session.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolRouter;
+    use schemars::JsonSchema;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// Replays scripted server events and records what the client sent
+    struct ScriptedSession {
+        events: VecDeque<RealtimeServerEvent>,
+        sent: Arc<Mutex<Vec<RealtimeClientEvent>>>,
+    }
+
+    #[async_trait]
+    impl RealtimeSession for ScriptedSession {
+        async fn send(&mut self, event: RealtimeClientEvent) -> Result<()> {
+            self.sent.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn recv(&mut self) -> Option<Result<RealtimeServerEvent>> {
+            self.events.pop_front().map(Ok)
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct AddInput {
+        a: i64,
+        b: i64,
+    }
+
+    async fn add(input: AddInput) -> String {
+        (input.a + input.b).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_and_interruption() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let session = ScriptedSession {
+            events: VecDeque::from([
+                RealtimeServerEvent::ResponseStarted,
+                RealtimeServerEvent::ToolCall {
+                    tool_call: ToolCall {
+                        id: "call_1".to_string(),
+                        name: "add".to_string(),
+                        arguments: json!({ "a": 2, "b": 3 }),
+                    },
+                },
+                RealtimeServerEvent::ResponseStarted,
+                RealtimeServerEvent::AudioDelta {
+                    data: "AAAA".to_string(),
+                },
+                RealtimeServerEvent::SpeechStarted,
+            ]),
+            sent: sent.clone(),
+        };
+        let router = ToolRouter::new()
+            .register_infallible("add", None, add)
+            .with_state(());
+        let mut conversation =
+            RealtimeConversation::new(Box::new(session), router).interrupt_on_speech(true);
+
+        let mut events = Vec::new();
+        while let Some(event) = conversation.next_event().await {
+            events.push(event.unwrap());
+        }
+        assert_eq!(events.len(), 5);
+        assert!(!conversation.is_responding());
+
+        let sent = sent.lock().unwrap();
+        let RealtimeClientEvent::ToolResult { result } = &sent[0] else {
+            panic!("expected a tool result");
+        };
+        assert_eq!(result.tool_call_id, "call_1");
+        assert_eq!(result.result, json!("5"));
+        assert_eq!(sent[1], RealtimeClientEvent::CreateResponse);
+        assert_eq!(
+            sent[2],
+            RealtimeClientEvent::Interrupt {
+                audio_played_ms: None
+            }
+        );
+        assert_eq!(sent.len(), 3);
+    }
+}
@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{ChatResponse, FinishReason};
+
+/// Metadata key of the model that generated a response
+pub const MODEL_METADATA_KEY: &str = "model";
+
+/// Metadata key of the provider's request id
+pub const REQUEST_ID_METADATA_KEY: &str = "request_id";
+
+/// Metadata key of the request latency in milliseconds
+pub const LATENCY_MS_METADATA_KEY: &str = "latency_ms";
+
+/// Metadata key of the `RateLimits` reported with a response
+pub const RATE_LIMITS_METADATA_KEY: &str = "rate_limits";
+
+/// Metadata key of the `Refusal` detail of a refused or filtered response
+pub const REFUSAL_METADATA_KEY: &str = "refusal";
+
+/// Rate-limit state reported in a provider's response headers
+///
+/// Reset times are kept as the provider sends them, an RFC 3339 timestamp or a
+/// duration such as `6m0s` depending on the provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_remaining: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_reset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_remaining: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_reset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens_remaining: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_remaining: Option<u64>,
+}
+
+impl RateLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Why a response finished with `FinishReason::ContentFilter`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Refusal {
+    pub kind: RefusalKind,
+    /// The model's explanation or the provider's message, when there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The provider's own reason, e.g. Anthropic's `refusal` stop reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_reason: Option<String>,
+}
+
+impl Refusal {
+    pub fn new(kind: RefusalKind) -> Self {
+        Self {
+            kind,
+            message: None,
+            provider_reason: None,
+        }
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn provider_reason(mut self, reason: impl Into<String>) -> Self {
+        self.provider_reason = Some(reason.into());
+        self
+    }
+}
+
+/// Who stopped the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalKind {
+    /// The model declined to answer
+    Declined,
+    /// A safety filter blocked the request or the output
+    Filtered,
+}
+
+/// Measures request latency; works on `wasm32`, where `std::time::Instant` panics
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch(chrono::DateTime<chrono::Utc>);
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self(chrono::Utc::now())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        (chrono::Utc::now() - self.0).to_std().unwrap_or_default()
+    }
+}
+
+impl ChatResponse {
+    /// Set a metadata entry
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value);
+        self
+    }
+
+    /// Get a metadata entry
+    pub fn metadata(&self, key: &str) -> Option<&serde_json::Value> {
+        self.metadata.as_ref()?.get(key)
+    }
+
+    /// Model that generated the response, which may differ from the one requested
+    pub fn model_used(&self) -> Option<&str> {
+        self.metadata(MODEL_METADATA_KEY)?.as_str()
+    }
+
+    /// The provider's id for the request
+    pub fn request_id(&self) -> Option<&str> {
+        self.metadata(REQUEST_ID_METADATA_KEY)?.as_str()
+    }
+
+    /// Time from sending the request to having the whole response
+    pub fn latency(&self) -> Option<Duration> {
+        self.metadata(LATENCY_MS_METADATA_KEY)?
+            .as_u64()
+            .map(Duration::from_millis)
+    }
+
+    /// Rate-limit state reported with the response
+    pub fn rate_limits(&self) -> Option<RateLimits> {
+        serde_json::from_value(self.metadata(RATE_LIMITS_METADATA_KEY)?.clone()).ok()
+    }
+
+    /// Mark the response as refused, setting `FinishReason::ContentFilter`
+    pub fn with_refusal(mut self, refusal: Refusal) -> Self {
+        self.finish_reason = FinishReason::ContentFilter;
+        let refusal = serde_json::to_value(refusal).expect("refusal serializes");
+        self.with_metadata(REFUSAL_METADATA_KEY, refusal)
+    }
+
+    /// Why the response was refused or filtered; `None` unless it finished with
+    /// `FinishReason::ContentFilter`, and a bare `Filtered` refusal when the provider
+    /// gave no detail
+    pub fn refusal(&self) -> Option<Refusal> {
+        if self.finish_reason != FinishReason::ContentFilter {
+            return None;
+        }
+        self.metadata(REFUSAL_METADATA_KEY)
+            .and_then(|refusal| serde_json::from_value(refusal.clone()).ok())
+            .or(Some(Refusal::new(RefusalKind::Filtered)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Message;
+
+    #[test]
+    fn test_typed_accessors() {
+        let limits = RateLimits {
+            requests_remaining: Some(49),
+            tokens_reset: Some("2025-01-01T00:00:30Z".to_string()),
+            ..RateLimits::default()
+        };
+        let response = ChatResponse {
+            id: "msg_1".to_string(),
+            message: Message::assistant("Hi"),
+            finish_reason: FinishReason::Stop,
+            usage: None,
+            metadata: None,
+        }
+        .with_metadata(MODEL_METADATA_KEY, "claude-3-5-haiku-20241022".into())
+        .with_metadata(REQUEST_ID_METADATA_KEY, "req_1".into())
+        .with_metadata(LATENCY_MS_METADATA_KEY, 1250.into())
+        .with_metadata(
+            RATE_LIMITS_METADATA_KEY,
+            serde_json::to_value(&limits).unwrap(),
+        );
+
+        assert_eq!(response.model_used(), Some("claude-3-5-haiku-20241022"));
+        assert_eq!(response.request_id(), Some("req_1"));
+        assert_eq!(response.latency(), Some(Duration::from_millis(1250)));
+        assert_eq!(response.rate_limits(), Some(limits));
+        assert_eq!(response.refusal(), None);
+
+        let refusal = Refusal::new(RefusalKind::Declined).message("I can't help with that.");
+        let refused = response.clone().with_refusal(refusal.clone());
+        assert_eq!(refused.finish_reason, FinishReason::ContentFilter);
+        assert_eq!(refused.refusal(), Some(refusal));
+        let filtered = ChatResponse {
+            finish_reason: FinishReason::ContentFilter,
+            metadata: None,
+            ..response
+        };
+        assert_eq!(
+            filtered.refusal(),
+            Some(Refusal::new(RefusalKind::Filtered))
+        );
+    }
+}
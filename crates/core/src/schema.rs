@@ -0,0 +1,530 @@
+use schemars::Schema;
+use serde_json::{Map, Value as JsonValue};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// JSON Schema dialect accepted by a provider, which `SchemaOptions` rewrites schemas into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaTarget {
+    /// Any JSON Schema consumer: only the `$schema` declaration is dropped
+    #[default]
+    Generic,
+    /// Anthropic: JSON Schema with an object at the root
+    Anthropic,
+    /// OpenAI function calling: `oneOf` rewritten as `anyOf`
+    OpenAi,
+    /// OpenAI strict mode: subschemas inlined, every property required (optional ones
+    /// made nullable) and no additional properties
+    OpenAiStrict,
+    /// Gemini's OpenAPI subset: no references, `nullable` instead of null types and no
+    /// `additionalProperties`, `default` or `const`
+    Gemini,
+}
+
+impl SchemaTarget {
+    fn requires_inlining(self) -> bool {
+        matches!(self, Self::OpenAiStrict | Self::Gemini)
+    }
+}
+
+/// Post-processing applied to tool parameter schemas before they are sent to a provider
+///
+/// Generated schemas put nested types under `$defs` and reference them with `$ref`,
+/// and carry `format` annotations; some providers reject either. Set on a router with
+/// `ToolRouter::schema_options` or on a provider's config. The `target` dialect is
+/// applied last, so it wins over the other options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaOptions {
+    pub target: SchemaTarget,
+    /// Replace `$ref`s with the subschema they point to and drop `$defs`; recursive
+    /// types keep their references
+    pub inline_subschemas: bool,
+    /// Remove `format` annotations, e.g. `uint32` or `date-time`
+    pub strip_format: bool,
+    /// Set `additionalProperties: false` on object schemas that don't set it
+    pub deny_additional_properties: bool,
+}
+
+impl SchemaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sanitize schemas for `target`
+    pub fn for_target(target: SchemaTarget) -> Self {
+        Self::default().target(target)
+    }
+
+    /// Every transformation enabled, for providers with strict schema support
+    pub fn strict() -> Self {
+        Self {
+            target: SchemaTarget::Generic,
+            inline_subschemas: true,
+            strip_format: true,
+            deny_additional_properties: true,
+        }
+    }
+
+    pub fn target(mut self, target: SchemaTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn inline_subschemas(mut self, inline: bool) -> Self {
+        self.inline_subschemas = inline;
+        self
+    }
+
+    pub fn strip_format(mut self, strip: bool) -> Self {
+        self.strip_format = strip;
+        self
+    }
+
+    pub fn deny_additional_properties(mut self, deny: bool) -> Self {
+        self.deny_additional_properties = deny;
+        self
+    }
+
+    /// Apply the enabled transformations to a JSON schema
+    pub fn apply(&self, schema: &mut JsonValue) {
+        if self.inline_subschemas || self.target.requires_inlining() {
+            inline_subschemas(schema);
+        }
+        if self.strip_format || self.deny_additional_properties {
+            visit_schemas(schema, &mut |object| {
+                if self.strip_format {
+                    object.remove("format");
+                }
+                if self.deny_additional_properties
+                    && is_object_schema(object)
+                    && !object.contains_key("additionalProperties")
+                {
+                    object.insert("additionalProperties".to_string(), JsonValue::Bool(false));
+                }
+            });
+        }
+        sanitize(schema, self.target);
+    }
+}
+
+/// Rewrite `schema` into the dialect of `target`
+fn sanitize(schema: &mut JsonValue, target: SchemaTarget) {
+    let Some(root) = schema.as_object_mut() else {
+        return;
+    };
+    root.remove("$schema");
+    if target != SchemaTarget::Generic {
+        root.entry("type")
+            .or_insert_with(|| JsonValue::String("object".to_string()));
+    }
+
+    visit_schemas(schema, &mut |object| {
+        if target != SchemaTarget::Generic
+            && target != SchemaTarget::Anthropic
+            && !object.contains_key("anyOf")
+            && let Some(one_of) = object.remove("oneOf")
+        {
+            object.insert("anyOf".to_string(), one_of);
+        }
+        match target {
+            SchemaTarget::OpenAiStrict => {
+                require_all_properties(object);
+                if is_object_schema(object) {
+                    object.insert("additionalProperties".to_string(), JsonValue::Bool(false));
+                }
+            }
+            SchemaTarget::Gemini => {
+                use_nullable(object);
+                for key in ["additionalProperties", "default", "examples", "$id"] {
+                    object.remove(key);
+                }
+                if let Some(value) = object.remove("const") {
+                    object.insert("enum".to_string(), JsonValue::Array(vec![value]));
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Whether the schema's type is `object`, possibly nullable
+fn is_object_schema(object: &Map<String, JsonValue>) -> bool {
+    match object.get("type") {
+        Some(JsonValue::String(ty)) => ty == "object",
+        Some(JsonValue::Array(types)) => types.iter().any(|ty| ty == "object"),
+        _ => false,
+    }
+}
+
+fn is_null_schema(schema: &JsonValue) -> bool {
+    schema.get("type").and_then(JsonValue::as_str) == Some("null")
+}
+
+/// Mark every property required, letting the optional ones be null instead
+fn require_all_properties(object: &mut Map<String, JsonValue>) {
+    let required: Vec<JsonValue> = object
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let Some(JsonValue::Object(properties)) = object.get_mut("properties") else {
+        return;
+    };
+    for (name, property) in properties.iter_mut() {
+        if !required.iter().any(|r| r.as_str() == Some(name)) {
+            make_nullable(property);
+        }
+    }
+    let all = properties.keys().cloned().map(JsonValue::String).collect();
+    object.insert("required".to_string(), JsonValue::Array(all));
+}
+
+fn make_nullable(schema: &mut JsonValue) {
+    let null = JsonValue::String("null".to_string());
+    match schema.get_mut("type") {
+        Some(JsonValue::String(ty)) if ty != "null" => {
+            let ty = JsonValue::String(std::mem::take(ty));
+            schema["type"] = JsonValue::Array(vec![ty, null]);
+        }
+        Some(JsonValue::Array(types)) => {
+            if !types.contains(&null) {
+                types.push(null);
+            }
+        }
+        Some(_) => {}
+        None => {
+            let any_of = schema.get_mut("anyOf").and_then(JsonValue::as_array_mut);
+            match any_of {
+                Some(variants) if variants.iter().any(is_null_schema) => {}
+                Some(variants) => variants.push(serde_json::json!({ "type": "null" })),
+                None => {
+                    let inner = schema.take();
+                    *schema = serde_json::json!({ "anyOf": [inner, { "type": "null" }] });
+                }
+            }
+        }
+    }
+}
+
+/// Replace null types and null variants with `nullable: true`
+fn use_nullable(object: &mut Map<String, JsonValue>) {
+    let mut nullable = false;
+    if let Some(JsonValue::Array(types)) = object.get_mut("type") {
+        let count = types.len();
+        types.retain(|ty| ty.as_str() != Some("null"));
+        nullable = types.len() < count;
+        if types.len() == 1 {
+            let ty = types.remove(0);
+            object.insert("type".to_string(), ty);
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        let Some(JsonValue::Array(variants)) = object.get_mut(key) else {
+            continue;
+        };
+        if !variants.iter().any(is_null_schema) {
+            continue;
+        }
+        nullable = true;
+        variants.retain(|variant| !is_null_schema(variant));
+        if variants.len() == 1 {
+            let variant = variants.remove(0);
+            object.remove(key);
+            if let JsonValue::Object(variant) = variant {
+                for (key, value) in variant {
+                    object.entry(key).or_insert(value);
+                }
+            }
+        }
+    }
+    if nullable {
+        object.insert("nullable".to_string(), JsonValue::Bool(true));
+    }
+}
+
+/// Keywords whose value maps names to subschemas
+const SCHEMA_MAPS: [&str; 4] = ["properties", "patternProperties", "$defs", "definitions"];
+
+/// Keywords whose value is data rather than subschemas
+const DATA_KEYWORDS: [&str; 5] = ["enum", "const", "default", "examples", "required"];
+
+/// Call `f` on every schema object in `schema`, the root included
+fn visit_schemas(schema: &mut JsonValue, f: &mut impl FnMut(&mut Map<String, JsonValue>)) {
+    let JsonValue::Object(object) = schema else {
+        return;
+    };
+    f(object);
+    for (key, value) in object.iter_mut() {
+        if SCHEMA_MAPS.contains(&key.as_str()) {
+            if let JsonValue::Object(subschemas) = value {
+                subschemas
+                    .values_mut()
+                    .for_each(|subschema| visit_schemas(subschema, f));
+            }
+        } else if !DATA_KEYWORDS.contains(&key.as_str()) {
+            match value {
+                JsonValue::Array(items) => items.iter_mut().for_each(|item| visit_schemas(item, f)),
+                value => visit_schemas(value, f),
+            }
+        }
+    }
+}
+
+fn inline_subschemas(schema: &mut JsonValue) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    let mut definitions = Map::new();
+    for key in ["$defs", "definitions"] {
+        if let Some(JsonValue::Object(defs)) = object.remove(key) {
+            definitions.extend(
+                defs.into_iter()
+                    .map(|(name, def)| (format!("#/{}/{}", key, name), def)),
+            );
+        }
+    }
+    if definitions.is_empty() {
+        return;
+    }
+
+    let mut recursive = Vec::new();
+    resolve_refs(schema, &definitions, &mut Vec::new(), &mut recursive);
+
+    // Recursive types can't be inlined, so their definitions stay, with the other
+    // references inside them inlined
+    let mut kept = Vec::new();
+    while kept.len() < recursive.len() {
+        let reference = recursive[kept.len()].clone();
+        let mut definition = definitions[&reference].clone();
+        resolve_refs(
+            &mut definition,
+            &definitions,
+            &mut vec![reference.clone()],
+            &mut recursive,
+        );
+        kept.push((reference, definition));
+    }
+    if let Some(object) = schema.as_object_mut() {
+        for (reference, definition) in kept {
+            if let Some((key, name)) = reference
+                .strip_prefix("#/")
+                .and_then(|path| path.split_once('/'))
+            {
+                let defs = object
+                    .entry(key.to_string())
+                    .or_insert_with(|| JsonValue::Object(Map::new()));
+                if let JsonValue::Object(defs) = defs {
+                    defs.insert(name.to_string(), definition);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_refs(
+    schema: &mut JsonValue,
+    definitions: &Map<String, JsonValue>,
+    stack: &mut Vec<String>,
+    recursive: &mut Vec<String>,
+) {
+    let JsonValue::Object(object) = schema else {
+        return;
+    };
+    if let Some(reference) = object.get("$ref").and_then(JsonValue::as_str) {
+        let reference = reference.to_string();
+        if let Some(definition) = definitions.get(&reference) {
+            if stack.contains(&reference) {
+                if !recursive.contains(&reference) {
+                    recursive.push(reference);
+                }
+                return;
+            }
+            let mut inlined = definition.clone();
+            stack.push(reference);
+            resolve_refs(&mut inlined, definitions, stack, recursive);
+            stack.pop();
+            object.remove("$ref");
+            // Keywords next to the `$ref` (e.g. a description) win over the definition's
+            if let JsonValue::Object(inlined) = inlined {
+                for (key, value) in inlined {
+                    object.entry(key).or_insert(value);
+                }
+            }
+            return;
+        }
+    }
+    for (key, value) in object.iter_mut() {
+        if SCHEMA_MAPS.contains(&key.as_str()) {
+            if let JsonValue::Object(subschemas) = value {
+                for subschema in subschemas.values_mut() {
+                    resolve_refs(subschema, definitions, stack, recursive);
+                }
+            }
+        } else if !DATA_KEYWORDS.contains(&key.as_str()) {
+            match value {
+                JsonValue::Array(items) => {
+                    for item in items {
+                        resolve_refs(item, definitions, stack, recursive);
+                    }
+                }
+                value => resolve_refs(value, definitions, stack, recursive),
+            }
+        }
+    }
+}
+
+/// Schema generated for `T`, computed once per type and reused afterwards
+pub(crate) fn cached_schema<T: 'static>(
+    generate: impl FnOnce() -> Option<Schema>,
+) -> Option<Schema> {
+    static CACHE: OnceLock<Mutex<HashMap<TypeId, Option<Schema>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(schema) = cache.lock().unwrap().get(&TypeId::of::<T>()) {
+        return schema.clone();
+    }
+    let schema = generate();
+    cache
+        .lock()
+        .unwrap()
+        .insert(TypeId::of::<T>(), schema.clone());
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn nested() -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "format": { "type": "string" },
+                "count": { "type": "integer", "format": "uint32" },
+                "point": { "$ref": "#/$defs/Point", "description": "Where" },
+                "tree": { "$ref": "#/$defs/Tree" }
+            },
+            "required": ["format"],
+            "$defs": {
+                "Point": {
+                    "type": "object",
+                    "description": "A point",
+                    "properties": { "x": { "type": "number", "format": "double" } }
+                },
+                "Tree": {
+                    "type": "object",
+                    "properties": { "children": { "type": "array", "items": { "$ref": "#/$defs/Tree" } } }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_default_options_leave_schema_unchanged() {
+        let mut schema = nested();
+        SchemaOptions::new().apply(&mut schema);
+        assert_eq!(schema, nested());
+    }
+
+    #[test]
+    fn test_strict_options() {
+        let mut schema = nested();
+        SchemaOptions::strict().apply(&mut schema);
+
+        let properties = &schema["properties"];
+        // A property named `format` is not an annotation
+        assert_eq!(properties["format"], json!({ "type": "string" }));
+        assert!(properties["count"].get("format").is_none());
+        assert_eq!(properties["point"]["description"], "Where");
+        assert_eq!(
+            properties["point"]["properties"]["x"],
+            json!({ "type": "number" })
+        );
+        assert_eq!(properties["point"]["additionalProperties"], false);
+        assert_eq!(schema["additionalProperties"], false);
+
+        // The recursive type is inlined once and keeps its definition
+        assert_eq!(
+            properties["tree"]["properties"]["children"]["items"]["$ref"],
+            "#/$defs/Tree"
+        );
+        assert!(schema["$defs"]["Tree"].is_object());
+        assert!(schema["$defs"].get("Point").is_none());
+    }
+
+    #[allow(dead_code)]
+    #[derive(schemars::JsonSchema)]
+    struct Address {
+        city: String,
+    }
+
+    #[allow(dead_code)]
+    #[derive(schemars::JsonSchema)]
+    #[serde(tag = "kind")]
+    enum Shape {
+        Circle { radius: u32 },
+        Square { side: u32 },
+    }
+
+    #[allow(dead_code)]
+    #[derive(schemars::JsonSchema)]
+    struct Order {
+        id: String,
+        note: Option<String>,
+        address: Option<Address>,
+        shape: Shape,
+    }
+
+    fn order_schema() -> JsonValue {
+        serde_json::to_value(schemars::schema_for!(Order)).unwrap()
+    }
+
+    #[test]
+    fn test_generic_target_drops_schema_declaration() {
+        let mut schema = order_schema();
+        assert!(schema.get("$schema").is_some());
+        SchemaOptions::new().apply(&mut schema);
+        assert!(schema.get("$schema").is_none());
+        assert!(schema["$defs"].is_object());
+    }
+
+    #[test]
+    fn test_openai_strict_target() {
+        let mut schema = order_schema();
+        SchemaOptions::for_target(SchemaTarget::OpenAiStrict).apply(&mut schema);
+
+        assert_eq!(
+            schema["required"],
+            json!(["address", "id", "note", "shape"])
+        );
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["properties"]["note"]["type"],
+            json!(["string", "null"])
+        );
+        let address = &schema["properties"]["address"];
+        assert!(address["anyOf"][1]["type"] == "null" || address["type"][1] == "null");
+        let shape = &schema["properties"]["shape"];
+        assert!(shape.get("oneOf").is_none());
+        assert_eq!(shape["anyOf"][0]["additionalProperties"], false);
+        assert!(schema.get("$defs").is_none());
+    }
+
+    #[test]
+    fn test_gemini_target() {
+        let mut schema = order_schema();
+        SchemaOptions::for_target(SchemaTarget::Gemini).apply(&mut schema);
+
+        let note = &schema["properties"]["note"];
+        assert_eq!(note["type"], "string");
+        assert_eq!(note["nullable"], true);
+        let address = &schema["properties"]["address"];
+        assert_eq!(address["type"], "object");
+        assert_eq!(address["nullable"], true);
+        let circle = &schema["properties"]["shape"]["anyOf"][0];
+        assert_eq!(circle["properties"]["kind"]["enum"], json!(["Circle"]));
+        assert!(schema.to_string().find("$ref").is_none());
+        assert!(schema.to_string().find("additionalProperties").is_none());
+    }
+}
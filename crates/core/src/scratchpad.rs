@@ -0,0 +1,217 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::{
+    errors::{AiError, Result, SerializationError},
+    tools::{FromToolState, ToolState},
+};
+
+tokio::task_local! {
+    static CURRENT: Scratchpad;
+}
+
+/// Key-value working memory shared by the steps, hooks and tools of a run
+///
+/// Values are stored as JSON, so anything `Serialize` fits, including a whole user
+/// struct under one key, and the scratchpad travels with checkpoints. Nothing in it is
+/// sent to the model. Clones share the same storage.
+///
+/// Tools take a `Scratchpad` parameter to read the current run's scratchpad:
+///
+/// ```
+/// # use ai_core::{Scratchpad, ToolRouter};
+/// # #[derive(serde::Deserialize, schemars::JsonSchema)]
+/// # struct Note { text: String }
+/// async fn remember(scratchpad: Scratchpad, note: Note) -> String {
+///     scratchpad.update("notes", |notes: &mut Vec<String>| notes.push(note.text)).ok();
+///     "noted".to_string()
+/// }
+/// let router = ToolRouter::new()
+///     .register_infallible("remember", None, remember)
+///     .with_state(());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Scratchpad {
+    values: Arc<Mutex<Map<String, JsonValue>>>,
+}
+
+impl Scratchpad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value` under `key`, replacing any previous value
+    pub fn set<T: Serialize>(&self, key: impl Into<String>, value: &T) -> Result<()> {
+        let value = serde_json::to_value(value).map_err(serialization_error)?;
+        self.values.lock().unwrap().insert(key.into(), value);
+        Ok(())
+    }
+
+    /// Value under `key`; `None` when absent or not a `T`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.values.lock().unwrap().get(key).cloned()?;
+        serde_json::from_value(value).ok()
+    }
+
+    /// Modify the value under `key` in place, starting from `T::default()` when absent
+    pub fn update<T, R>(&self, key: &str, update: impl FnOnce(&mut T) -> R) -> Result<R>
+    where
+        T: Serialize + DeserializeOwned + Default,
+    {
+        let mut values = self.values.lock().unwrap();
+        let mut value: T = match values.get(key) {
+            Some(value) => serde_json::from_value(value.clone()).map_err(serialization_error)?,
+            None => T::default(),
+        };
+        let result = update(&mut value);
+        let value = serde_json::to_value(&value).map_err(serialization_error)?;
+        values.insert(key.to_string(), value);
+        Ok(result)
+    }
+
+    pub fn remove(&self, key: &str) -> Option<JsonValue> {
+        self.values.lock().unwrap().remove(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.lock().unwrap().contains_key(key)
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.values.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.lock().unwrap().is_empty()
+    }
+
+    /// Copy of the current contents as a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.values.lock().unwrap().clone())
+    }
+
+    /// Independent scratchpad starting with a copy of this one's contents
+    pub fn snapshot(&self) -> Self {
+        Self::from(self.values.lock().unwrap().clone())
+    }
+
+    /// Copy every entry of `other` into this scratchpad, overwriting existing keys
+    pub fn merge(&self, other: &Scratchpad) {
+        if Arc::ptr_eq(&self.values, &other.values) {
+            return;
+        }
+        let entries = other.values.lock().unwrap().clone();
+        self.values.lock().unwrap().extend(entries);
+    }
+
+    /// Run `future` with this as the scratchpad tools receive
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+
+    /// Scratchpad of the enclosing `scope`, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+}
+
+fn serialization_error(e: serde_json::Error) -> AiError {
+    AiError::Serialization(SerializationError::JsonError {
+        message: e.to_string(),
+    })
+}
+
+impl From<Map<String, JsonValue>> for Scratchpad {
+    fn from(values: Map<String, JsonValue>) -> Self {
+        Self {
+            values: Arc::new(Mutex::new(values)),
+        }
+    }
+}
+
+impl PartialEq for Scratchpad {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_json() == other.to_json()
+    }
+}
+
+impl Serialize for Scratchpad {
+    fn serialize<Ser: Serializer>(
+        &self,
+        serializer: Ser,
+    ) -> std::result::Result<Ser::Ok, Ser::Error> {
+        self.values.lock().unwrap().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scratchpad {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Map::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Tools receive the running agent's scratchpad, or a detached empty one outside a run
+impl<S: Clone + Send + Sync + 'static> FromToolState<S> for Scratchpad {
+    fn from_tool_state(_parts: &mut ToolState<S>) -> Self {
+        Self::current().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolRouter;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Plan {
+        steps: Vec<String>,
+        done: usize,
+    }
+
+    async fn advance(scratchpad: Scratchpad, _input: JsonValue) -> usize {
+        scratchpad
+            .update("plan", |plan: &mut Plan| {
+                plan.done += 1;
+                plan.done
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_typed_values_and_tool_access() {
+        let scratchpad = Scratchpad::new();
+        let plan = Plan {
+            steps: vec!["search".to_string(), "summarize".to_string()],
+            done: 0,
+        };
+        scratchpad.set("plan", &plan).unwrap();
+        assert_eq!(scratchpad.get::<Plan>("plan"), Some(plan));
+        assert_eq!(scratchpad.get::<String>("plan"), None);
+
+        let router = ToolRouter::new()
+            .register_infallible("advance", None, advance)
+            .with_state(());
+        let done = scratchpad
+            .scope(router.execute_tool("advance", serde_json::json!({})))
+            .await;
+        assert_eq!(done.unwrap().unwrap(), 1);
+        assert_eq!(scratchpad.get::<Plan>("plan").unwrap().done, 1);
+
+        // Outside a scope the tool gets a detached scratchpad
+        router
+            .execute_tool("advance", serde_json::json!({}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(scratchpad.get::<Plan>("plan").unwrap().done, 1);
+
+        let restored: Scratchpad =
+            serde_json::from_str(&serde_json::to_string(&scratchpad).unwrap()).unwrap();
+        assert_eq!(restored, scratchpad);
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt::Debug;
+
+use proptest::{
+    collection::{hash_map, vec},
+    option,
+    prelude::*,
+    test_runner::TestCaseError,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value as JsonValue;
+
+use crate::{errors::Result, types::*};
+
+/// Short printable text, including non-ASCII characters
+pub fn text() -> impl Strategy<Value = String> + Clone {
+    "\\PC{0,24}"
+}
+
+/// Lowercase identifier usable as a tool name or JSON key
+pub fn identifier() -> impl Strategy<Value = String> + Clone {
+    "[a-z][a-z0-9_]{0,11}"
+}
+
+/// Nested JSON with integer numbers
+pub fn json_value() -> BoxedStrategy<JsonValue> {
+    let leaf = prop_oneof![
+        Just(JsonValue::Null),
+        any::<bool>().prop_map(JsonValue::Bool),
+        any::<i64>().prop_map(JsonValue::from),
+        text().prop_map(JsonValue::String),
+    ];
+    leaf.prop_recursive(3, 24, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(JsonValue::Array),
+            hash_map(identifier(), inner, 0..4)
+                .prop_map(|map| JsonValue::Object(map.into_iter().collect())),
+        ]
+    })
+    .boxed()
+}
+
+/// A float in `min..=max` on a 0.05 grid
+fn grid_f32(min: f32, max: f32) -> impl Strategy<Value = f32> {
+    ((min * 20.0) as i32..=(max * 20.0) as i32).prop_map(|step| step as f32 / 20.0)
+}
+
+fn metadata() -> impl Strategy<Value = Option<std::collections::HashMap<String, JsonValue>>> {
+    option::of(hash_map(identifier(), json_value(), 0..3))
+}
+
+pub fn image_content() -> impl Strategy<Value = ImageContent> {
+    (
+        option::of("https://example\\.com/[a-z]{1,8}\\.png"),
+        option::of("[A-Za-z0-9+/]{4,16}"),
+        option::of(prop_oneof![Just("image/png"), Just("image/jpeg")]),
+    )
+        .prop_map(|(url, base64, mime_type)| ImageContent {
+            url,
+            base64,
+            mime_type: mime_type.map(str::to_string),
+        })
+}
+
+pub fn tool_call() -> impl Strategy<Value = ToolCall> {
+    ("call_[a-z0-9]{1,8}", identifier(), json_value()).prop_map(|(id, name, arguments)| ToolCall {
+        id,
+        name,
+        arguments,
+    })
+}
+
+pub fn tool_result() -> impl Strategy<Value = ToolResult> {
+    let content = prop_oneof![
+        text().prop_map(|text| ToolResultContent::Text { text }),
+        image_content().prop_map(|image| ToolResultContent::Image { image }),
+        json_value().prop_map(|value| ToolResultContent::Json { value }),
+    ];
+    (
+        "call_[a-z0-9]{1,8}",
+        json_value(),
+        any::<bool>(),
+        vec(content, 0..2),
+    )
+        .prop_map(|(tool_call_id, result, is_error, content)| ToolResult {
+            tool_call_id,
+            result,
+            is_error,
+            content,
+        })
+}
+
+pub fn user_content() -> impl Strategy<Value = UserContent> {
+    prop_oneof![
+        3 => text().prop_map(|text| UserContent::Text { text }),
+        1 => image_content().prop_map(|image| UserContent::Image { image }),
+    ]
+}
+
+pub fn assistant_content() -> impl Strategy<Value = AssistantContent> {
+    prop_oneof![
+        text().prop_map(|text| AssistantContent::Text { text }),
+        tool_call().prop_map(|tool_call| AssistantContent::ToolCall { tool_call }),
+    ]
+}
+
+pub fn message() -> impl Strategy<Value = Message> {
+    prop_oneof![
+        (vec(text(), 1..3), metadata()).prop_map(|(texts, metadata)| Message::System {
+            content: texts
+                .into_iter()
+                .map(|text| SystemContent::Text { text })
+                .collect(),
+            metadata,
+        }),
+        (vec(user_content(), 1..4), metadata())
+            .prop_map(|(content, metadata)| Message::User { content, metadata }),
+        (vec(assistant_content(), 1..4), metadata())
+            .prop_map(|(content, metadata)| Message::Assistant { content, metadata }),
+        (vec(tool_result(), 1..3), metadata()).prop_map(|(tool_results, metadata)| {
+            Message::Tool {
+                tool_results,
+                metadata,
+            }
+        }),
+    ]
+}
+
+pub fn generation_constraint() -> impl Strategy<Value = GenerationConstraint> {
+    prop_oneof![
+        text().prop_map(|grammar| GenerationConstraint::Grammar { grammar }),
+        text().prop_map(|pattern| GenerationConstraint::Regex { pattern }),
+        json_value().prop_map(|schema| GenerationConstraint::JsonSchema { schema }),
+        vec(text(), 1..4).prop_map(|choices| GenerationConstraint::Choice { choices }),
+    ]
+}
+
+pub fn generation_settings() -> impl Strategy<Value = GenerationSettings> {
+    (
+        option::of(grid_f32(0.0, 2.0)),
+        option::of(1u32..8192),
+        option::of(grid_f32(0.0, 1.0)),
+        option::of(1u32..100),
+        option::of(grid_f32(-2.0, 2.0)),
+        option::of(grid_f32(-2.0, 2.0)),
+        option::of(vec(text(), 0..3)),
+        option::of(any::<u64>()),
+        option::of(hash_map("[0-9]{1,5}", grid_f32(-100.0, 100.0), 0..3)),
+        option::of(generation_constraint()),
+    )
+        .prop_map(
+            |(
+                temperature,
+                max_tokens,
+                top_p,
+                top_k,
+                frequency_penalty,
+                presence_penalty,
+                stop_sequences,
+                seed,
+                logit_bias,
+                constraint,
+            )| GenerationSettings {
+                temperature,
+                max_tokens,
+                top_p,
+                top_k,
+                frequency_penalty,
+                presence_penalty,
+                stop_sequences,
+                seed,
+                logit_bias,
+                constraint,
+            },
+        )
+}
+
+pub fn tool_definition() -> impl Strategy<Value = ToolDefinition> {
+    (identifier(), text(), json_value(), any::<bool>()).prop_map(
+        |(name, description, parameters, strict)| {
+            ToolDefinition::new(name, description, parameters).strict(strict)
+        },
+    )
+}
+
+pub fn chat_request() -> impl Strategy<Value = ChatRequest> {
+    (
+        vec(message(), 0..6),
+        generation_settings(),
+        option::of(vec(tool_definition(), 0..3)),
+    )
+        .prop_map(|(messages, settings, tools)| ChatRequest {
+            messages,
+            settings,
+            tools,
+            headers: Vec::new(),
+        })
+}
+
+pub fn finish_reason() -> impl Strategy<Value = FinishReason> {
+    prop_oneof![
+        Just(FinishReason::Stop),
+        Just(FinishReason::Length),
+        Just(FinishReason::ToolCalls),
+        Just(FinishReason::ContentFilter),
+        Just(FinishReason::Error),
+    ]
+}
+
+pub fn usage() -> impl Strategy<Value = Usage> {
+    (0u32..100_000, 0u32..100_000).prop_map(|(prompt_tokens, completion_tokens)| Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+    })
+}
+
+pub fn chat_response() -> impl Strategy<Value = ChatResponse> {
+    (
+        "msg_[a-z0-9]{0,8}",
+        message(),
+        finish_reason(),
+        option::of(usage()),
+        metadata(),
+    )
+        .prop_map(
+            |(id, message, finish_reason, usage, metadata)| ChatResponse {
+                id,
+                message,
+                finish_reason,
+                usage,
+                metadata,
+            },
+        )
+}
+
+pub fn message_delta() -> impl Strategy<Value = MessageDelta> {
+    prop_oneof![
+        option::of(user_content()).prop_map(|content| MessageDelta::System { content }),
+        option::of(user_content()).prop_map(|content| MessageDelta::User { content }),
+        option::of(assistant_content()).prop_map(|content| MessageDelta::Assistant { content }),
+        option::of(tool_result()).prop_map(|tool_result| MessageDelta::Tool { tool_result }),
+        text().prop_map(|text| MessageDelta::Reasoning { text }),
+    ]
+}
+
+pub fn chat_stream_chunk() -> impl Strategy<Value = ChatStreamChunk> {
+    (
+        "msg_[a-z0-9]{0,8}",
+        message_delta(),
+        option::of(finish_reason()),
+        option::of(usage()),
+    )
+        .prop_map(|(id, delta, finish_reason, usage)| ChatStreamChunk {
+            id,
+            delta,
+            finish_reason,
+            usage,
+        })
+}
+
+macro_rules! impl_arbitrary {
+    ($($ty:ty => $strategy:ident),* $(,)?) => {
+        $(
+            impl Arbitrary for $ty {
+                type Parameters = ();
+                type Strategy = BoxedStrategy<Self>;
+
+                fn arbitrary_with(_: ()) -> Self::Strategy {
+                    $strategy().boxed()
+                }
+            }
+        )*
+    };
+}
+
+impl_arbitrary! {
+    ImageContent => image_content,
+    ToolCall => tool_call,
+    ToolResult => tool_result,
+    UserContent => user_content,
+    AssistantContent => assistant_content,
+    Message => message,
+    GenerationConstraint => generation_constraint,
+    GenerationSettings => generation_settings,
+    ToolDefinition => tool_definition,
+    ChatRequest => chat_request,
+    FinishReason => finish_reason,
+    Usage => usage,
+    ChatResponse => chat_response,
+    MessageDelta => message_delta,
+    ChatStreamChunk => chat_stream_chunk,
+}
+
+/// Check that `value` comes back unchanged from a trip through JSON
+pub fn serde_roundtrip<T>(value: &T) -> std::result::Result<(), TestCaseError>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = serde_json::to_string(value)
+        .map_err(|e| TestCaseError::fail(format!("serialization failed: {}", e)))?;
+    let decoded: T = serde_json::from_str(&json)
+        .map_err(|e| TestCaseError::fail(format!("deserializing {} failed: {}", json, e)))?;
+    prop_assert_eq!(&decoded, value);
+    Ok(())
+}
+
+/// Check that converting `value` to a provider's wire type and back gives it back
+///
+/// Conversions that drop information should be given values that are already in the
+/// shape the provider preserves.
+pub fn conversion_roundtrip<T, W>(
+    value: &T,
+    to_wire: impl FnOnce(&T) -> Result<W>,
+    from_wire: impl FnOnce(W) -> Result<T>,
+) -> std::result::Result<(), TestCaseError>
+where
+    T: PartialEq + Debug,
+{
+    let wire =
+        to_wire(value).map_err(|e| TestCaseError::fail(format!("conversion failed: {}", e)))?;
+    let converted = from_wire(wire)
+        .map_err(|e| TestCaseError::fail(format!("conversion back failed: {}", e)))?;
+    prop_assert_eq!(&converted, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_messages_roundtrip(message in any::<Message>()) {
+            serde_roundtrip(&message)?;
+        }
+
+        #[test]
+        fn test_requests_roundtrip(request in any::<ChatRequest>()) {
+            serde_roundtrip(&request)?;
+        }
+
+        #[test]
+        fn test_responses_and_chunks_roundtrip(
+            response in any::<ChatResponse>(),
+            chunk in any::<ChatStreamChunk>(),
+        ) {
+            serde_roundtrip(&response)?;
+            serde_roundtrip(&chunk)?;
+        }
+
+        #[test]
+        fn test_conversion_roundtrip(call in any::<ToolCall>()) {
+            conversion_roundtrip(
+                &call,
+                |call| Ok(serde_json::to_value(call)?),
+                |value| Ok(serde_json::from_value(value)?),
+            )?;
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::types::*;
+
+/// Counts tokens in text for a particular model family
+pub trait Tokenizer: Send + Sync {
+    /// Count the tokens in a piece of text
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Estimate the tokens a message occupies in a prompt, including role overhead
+    fn count_message_tokens(&self, message: &Message) -> usize {
+        const MESSAGE_OVERHEAD: usize = 4;
+        const IMAGE_TOKENS: usize = 1_000;
+
+        let content: usize = match message {
+            Message::System { content, .. } => content
+                .iter()
+                .map(|part| match part {
+                    SystemContent::Text { text } => self.count_tokens(text),
+                })
+                .sum(),
+            Message::User { content, .. } => content
+                .iter()
+                .map(|part| match part {
+                    UserContent::Text { text } => self.count_tokens(text),
+                    UserContent::Image { .. } => IMAGE_TOKENS,
+                })
+                .sum(),
+            Message::Assistant { content, .. } => content
+                .iter()
+                .map(|part| match part {
+                    AssistantContent::Text { text } => self.count_tokens(text),
+                    AssistantContent::ToolCall { tool_call } => {
+                        self.count_tokens(&tool_call.name)
+                            + self.count_tokens(&tool_call.arguments.to_string())
+                    }
+                })
+                .sum(),
+            Message::Tool { tool_results, .. } => tool_results
+                .iter()
+                .map(|result| {
+                    let extra: usize = result
+                        .content
+                        .iter()
+                        .map(|part| match part {
+                            ToolResultContent::Text { text } => self.count_tokens(text),
+                            ToolResultContent::Image { .. } => IMAGE_TOKENS,
+                            ToolResultContent::Json { value } => {
+                                self.count_tokens(&value.to_string())
+                            }
+                        })
+                        .sum();
+                    let result_tokens = match &result.result {
+                        serde_json::Value::String(text) => self.count_tokens(text),
+                        value => self.count_tokens(&value.to_string()),
+                    };
+                    result_tokens + extra
+                })
+                .sum(),
+        };
+
+        content + MESSAGE_OVERHEAD
+    }
+
+    /// Estimate the prompt tokens of a whole request, tool definitions included
+    fn count_request_tokens(&self, request: &ChatRequest) -> usize {
+        let messages: usize = request
+            .messages
+            .iter()
+            .map(|message| self.count_message_tokens(message))
+            .sum();
+        messages + self.count_tool_tokens(request.tools.as_deref().unwrap_or_default())
+    }
+
+    /// Estimate the tokens tool definitions add to a prompt
+    fn count_tool_tokens(&self, tools: &[ToolDefinition]) -> usize {
+        tools
+            .iter()
+            .map(|tool| {
+                self.count_tokens(&tool.name)
+                    + self.count_tokens(&tool.description)
+                    + self.count_tokens(&tool.parameters.to_string())
+            })
+            .sum()
+    }
+}
+
+/// Shared tokenizers can be used anywhere an owned tokenizer is expected
+impl<T: Tokenizer + ?Sized> Tokenizer for std::sync::Arc<T> {
+    fn count_tokens(&self, text: &str) -> usize {
+        (**self).count_tokens(text)
+    }
+
+    fn count_message_tokens(&self, message: &Message) -> usize {
+        (**self).count_message_tokens(message)
+    }
+}
+
+/// Rough tokenizer assuming about four characters per token
+///
+/// Good enough for budgeting when no model-specific tokenizer is available.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EstimatingTokenizer;
+
+impl Tokenizer for EstimatingTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// Token usage of a conversation, for context meters and truncation decisions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenCount {
+    /// Tokens of the whole conversation
+    pub total: usize,
+    /// Tokens of each message, in conversation order
+    pub per_message: Vec<usize>,
+}
+
+impl TokenCount {
+    /// Tokens still free in a context window of `context_window`
+    pub fn remaining(&self, context_window: u32) -> usize {
+        (context_window as usize).saturating_sub(self.total)
+    }
+
+    /// Share of a context window of `context_window` in use
+    pub fn fraction_of(&self, context_window: u32) -> f32 {
+        if context_window == 0 {
+            return 1.0;
+        }
+        self.total as f32 / context_window as f32
+    }
+}
+
+/// Count the tokens of a conversation, message by message
+pub fn count_tokens(messages: &[Message], tokenizer: &dyn Tokenizer) -> TokenCount {
+    let per_message: Vec<usize> = messages
+        .iter()
+        .map(|message| tokenizer.count_message_tokens(message))
+        .collect();
+    TokenCount {
+        total: per_message.iter().sum(),
+        per_message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_token_count() {
+        let messages = [
+            Message::system("Answer in one word"),
+            Message::user("What is the capital of France?"),
+        ];
+        let count = count_tokens(&messages, &EstimatingTokenizer);
+
+        assert_eq!(count.per_message, [5 + 4, 8 + 4]);
+        assert_eq!(count.total, 21);
+        assert_eq!(count.remaining(200), 179);
+        assert_eq!(count.remaining(10), 0);
+        assert!((count.fraction_of(42) - 0.5).abs() < f32::EPSILON);
+    }
+}
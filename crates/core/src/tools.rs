@@ -1,4 +1,7 @@
-use crate::errors::{ToolExecutionError, ToolResult};
+use crate::errors::{AiError, SerializationError, ToolExecutionError, ToolResult, ValidationError};
+use crate::schema::{SchemaOptions, SchemaTarget, cached_schema};
+use crate::types::{ImageContent, ToolCall, ToolResultContent};
+pub use ai_macros::{FromToolRequest, FromToolState};
 use schemars::{JsonSchema, Schema};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -6,7 +9,9 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::future::Future;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// Wrapper for functions that return ToolResult
 pub struct Fallible<F>(pub F);
@@ -19,6 +24,33 @@ pub struct ToolMetadata {
     pub parameters_schema: Option<Schema>,
 }
 
+/// Handler-less tool definition loaded from configuration data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinitionConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON schema of the tool input
+    #[serde(default, alias = "input_schema", alias = "schema")]
+    pub parameters: Option<JsonValue>,
+}
+
+/// Accepted layouts for a tool definition file: a bare list or a `tools` key
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ToolDefinitionFile {
+    List(Vec<ToolDefinitionConfig>),
+    Catalog { tools: Vec<ToolDefinitionConfig> },
+}
+
+impl From<ToolDefinitionFile> for Vec<ToolDefinitionConfig> {
+    fn from(file: ToolDefinitionFile) -> Self {
+        match file {
+            ToolDefinitionFile::List(tools) | ToolDefinitionFile::Catalog { tools } => tools,
+        }
+    }
+}
+
 /// Type-safe state wrapper
 #[derive(Clone)]
 pub struct State<S: Clone + Send + Sync + 'static>(pub S);
@@ -29,12 +61,42 @@ pub type Input = JsonValue;
 /// Request parts containing state for extraction
 pub struct ToolState<S: Clone + Send + Sync + 'static> {
     pub state: State<S>,
+    /// Id of the tool call being handled, `None` when the tool is executed by name
+    pub tool_call_id: Option<String>,
+    /// The unparsed tool input
+    pub input: Input,
 }
 
 /// Full request containing both state and input
 pub struct ToolRequest<S: Clone + Send + Sync + 'static> {
     pub state: State<S>,
     pub input: Input,
+    /// Id of the tool call being handled, `None` when the tool is executed by name
+    pub tool_call_id: Option<String>,
+}
+
+impl<S: Clone + Send + Sync + 'static> ToolRequest<S> {
+    pub fn new(state: S, input: Input) -> Self {
+        Self {
+            state: State(state),
+            input,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn with_tool_call_id(mut self, tool_call_id: impl Into<String>) -> Self {
+        self.tool_call_id = Some(tool_call_id.into());
+        self
+    }
+
+    /// Parts for the state extractors of a handler
+    pub fn parts(&self) -> ToolState<S> {
+        ToolState {
+            state: self.state.clone(),
+            tool_call_id: self.tool_call_id.clone(),
+            input: self.input.clone(),
+        }
+    }
 }
 
 /// Trait for extracting values from request parts (state-only)
@@ -57,6 +119,56 @@ impl<S: Clone + Send + Sync + 'static> FromToolState<S> for State<S> {
     }
 }
 
+/// Id of the tool call being handled
+///
+/// `None` when the tool is executed by name rather than from a model's tool call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallId(pub Option<String>);
+
+impl<S: Clone + Send + Sync + 'static> FromToolState<S> for ToolCallId {
+    fn from_tool_state(parts: &mut ToolState<S>) -> Self {
+        ToolCallId(parts.tool_call_id.clone())
+    }
+}
+
+/// The unparsed tool input, usable next to a typed input
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawInput(pub Input);
+
+impl<S: Clone + Send + Sync + 'static> FromToolState<S> for RawInput {
+    fn from_tool_state(parts: &mut ToolState<S>) -> Self {
+        RawInput(parts.input.clone())
+    }
+}
+
+/// Tool input deserialized into a type without a `JsonSchema` impl
+///
+/// The generated schema accepts any object; register the tool with
+/// `register_with_schema` to give the model a precise one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T> JsonSchema for Json<T> {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Json".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> Schema {
+        schemars::json_schema!({ "type": "object" })
+    }
+}
+
+impl<T, S: Clone + Send + Sync + 'static> FromToolRequest<S> for Json<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    fn from_request(request: &mut ToolRequest<S>) -> ToolResult<Self> {
+        serde_json::from_value(request.input.clone())
+            .map(Json)
+            .map_err(|e| ToolExecutionError::InvalidInput(format!("Failed to parse input: {}", e)))
+    }
+}
+
 /// Blanket implementation for types that implement Deserialize + JsonSchema
 impl<T, S: Clone + Send + Sync + 'static> FromToolRequest<S> for T
 where
@@ -68,159 +180,206 @@ where
     }
 }
 
-/// Handler trait for type-safe async tool functions with serializable outputs
-pub trait ToolHandler<S: Clone + Send + Sync + 'static, T> {
-    type Output: Serialize + Send;
+/// Rich tool output: model-facing text, structured data, or both
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ToolOutput {
+    pub text: Option<String>,
+    pub data: Option<JsonValue>,
+    /// Extra content parts (e.g. images) the model should see alongside the result
+    pub content: Vec<ToolResultContent>,
+}
 
-    fn call(
-        &mut self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>>;
+impl ToolOutput {
+    /// Create a text-only output
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
 
-    /// Generate JSON schema for the input parameters
-    fn schema() -> Option<Schema>;
-}
+    /// Create a structured-data-only output
+    pub fn data(data: JsonValue) -> Self {
+        Self {
+            data: Some(data),
+            ..Self::default()
+        }
+    }
 
-// Implementation for async functions that return Future<R>
-impl<F, S: Clone + Send + Sync + 'static, T1, R, Fut> ToolHandler<S, (T1,)> for F
-where
-    F: Fn(T1) -> Fut + Send + Sync,
-    T1: FromToolRequest<S> + JsonSchema,
-    R: Serialize + Send,
-    Fut: Future<Output = R> + Send,
-{
-    type Output = R;
+    /// Attach text to the output
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
 
-    fn call(
-        &mut self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
-        Box::pin(async move {
-            let parsed_input = T1::from_request(&mut ToolRequest {
-                state: state.clone(),
-                input,
-            })?;
-            let result = self(parsed_input).await;
-            Ok(result)
-        })
+    /// Attach structured data to the output
+    pub fn with_data(mut self, data: JsonValue) -> Self {
+        self.data = Some(data);
+        self
     }
 
-    fn schema() -> Option<Schema> {
-        Some(schemars::schema_for!(T1))
+    /// Attach an image the model should see
+    pub fn with_image(mut self, image: ImageContent) -> Self {
+        self.content.push(ToolResultContent::Image { image });
+        self
     }
-}
 
-/// Implementation for Fallible wrapper - async functions that return Future<ToolResult<R>>
-impl<F, S: Clone + Send + Sync + 'static, T1, R, Fut> ToolHandler<S, (T1,)> for Fallible<F>
-where
-    F: Fn(T1) -> Fut + Send + Sync,
-    T1: FromToolRequest<S> + JsonSchema,
-    R: Serialize + Send,
-    Fut: Future<Output = ToolResult<R>> + Send,
-{
-    type Output = R;
+    /// Attach an additional content part
+    pub fn with_content(mut self, content: ToolResultContent) -> Self {
+        self.content.push(content);
+        self
+    }
 
-    fn call(
-        &mut self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
-        Box::pin(async move {
-            let parsed_input = T1::from_request(&mut ToolRequest {
-                state: state.clone(),
-                input,
-            })?;
-            (self.0)(parsed_input).await
-        })
+    /// Convert the output into a successful tool result message part
+    pub fn into_tool_result(mut self, tool_call_id: impl Into<String>) -> crate::types::ToolResult {
+        let content = std::mem::take(&mut self.content);
+        crate::types::ToolResult {
+            tool_call_id: tool_call_id.into(),
+            result: self.into_json(),
+            is_error: false,
+            content,
+        }
     }
 
-    fn schema() -> Option<Schema> {
-        Some(schemars::schema_for!(T1))
+    /// Collapse the text and data into the JSON value sent back as the tool result
+    ///
+    /// Text-only outputs become a JSON string, data-only outputs are passed through,
+    /// and outputs with both become `{"text": ..., "data": ...}`. Extra content
+    /// parts are dropped; use `into_tool_result` to keep them.
+    pub fn into_json(self) -> JsonValue {
+        match (self.text, self.data) {
+            (Some(text), None) => JsonValue::String(text),
+            (None, Some(data)) => data,
+            (Some(text), Some(data)) => serde_json::json!({ "text": text, "data": data }),
+            (None, None) => JsonValue::Null,
+        }
     }
 }
 
-/// Implementation for async functions with two parameters that return Future<R>
-impl<F, S: Clone + Send + Sync + 'static, T1, T2, R, Fut> ToolHandler<S, (T1, T2)> for F
-where
-    F: Fn(T1, T2) -> Fut + Send + Sync,
-    T1: FromToolState<S>,
-    T2: FromToolRequest<S> + JsonSchema,
-    R: Serialize + Send,
-    Fut: Future<Output = R> + Send,
-{
-    type Output = R;
+/// Conversion of handler return values into a `ToolOutput`
+///
+/// Implemented for `ToolOutput` and every `Serialize` type: strings become text,
+/// everything else becomes structured data.
+pub trait IntoToolOutput {
+    fn into_tool_output(self) -> ToolResult<ToolOutput>;
+}
 
-    fn call(
-        &mut self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
-        Box::pin(async move {
-            let parsed_input = T2::from_request(&mut ToolRequest {
-                state: state.clone(),
-                input,
-            })?;
-            let result = self(
-                T1::from_tool_state(&mut ToolState {
-                    state: state.clone(),
-                }),
-                parsed_input,
-            )
-            .await;
-            Ok(result)
-        })
+impl IntoToolOutput for ToolOutput {
+    fn into_tool_output(self) -> ToolResult<ToolOutput> {
+        Ok(self)
     }
+}
 
-    fn schema() -> Option<Schema> {
-        Some(schemars::schema_for!(T2))
+impl<T: Serialize> IntoToolOutput for T {
+    fn into_tool_output(self) -> ToolResult<ToolOutput> {
+        match serde_json::to_value(self) {
+            Ok(JsonValue::String(text)) => Ok(ToolOutput::text(text)),
+            Ok(data) => Ok(ToolOutput::data(data)),
+            Err(e) => Err(ToolExecutionError::ExecutionError(format!(
+                "Failed to serialize result: {}",
+                e
+            ))),
+        }
     }
 }
 
-/// Implementation for Fallible wrapper - async functions with two parameters that return Future<ToolResult<R>>
-impl<F, S: Clone + Send + Sync + 'static, T1, T2, R, Fut> ToolHandler<S, (T1, T2)> for Fallible<F>
-where
-    F: Fn(T1, T2) -> Fut + Send + Sync,
-    T1: FromToolState<S>,
-    T2: FromToolRequest<S> + JsonSchema,
-    R: Serialize + Send,
-    Fut: Future<Output = ToolResult<R>> + Send,
-{
-    type Output = R;
+/// Handler trait for type-safe async tool functions with serializable outputs
+pub trait ToolHandler<S: Clone + Send + Sync + 'static, T> {
+    type Output: IntoToolOutput + Send;
 
     fn call(
         &mut self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
-        Box::pin(async move {
-            let parsed_input = T2::from_request(&mut ToolRequest {
-                state: state.clone(),
-                input,
-            })?;
-            (self.0)(
-                T1::from_tool_state(&mut ToolState {
-                    state: state.clone(),
-                }),
-                parsed_input,
-            )
-            .await
-        })
-    }
+        request: ToolRequest<S>,
+    ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>>;
 
-    fn schema() -> Option<Schema> {
-        Some(schemars::schema_for!(T2))
-    }
+    /// Generate JSON schema for the input parameters
+    fn schema() -> Option<Schema>;
 }
 
+/// Implement `ToolHandler` for async functions and their `Fallible` wrapper
+///
+/// Every parameter but the last is extracted with `FromToolState`; the last one is
+/// the tool input, extracted with `FromToolRequest` and used for the schema.
+macro_rules! impl_tool_handler {
+    ($($state:ident),*; $input:ident) => {
+        impl<F, S: Clone + Send + Sync + 'static, $($state,)* $input, R, Fut>
+            ToolHandler<S, ($($state,)* $input,)> for F
+        where
+            F: Fn($($state,)* $input) -> Fut + Send + Sync,
+            $($state: FromToolState<S>,)*
+            $input: FromToolRequest<S> + JsonSchema,
+            R: IntoToolOutput + Send,
+            Fut: Future<Output = R> + Send,
+        {
+            type Output = R;
+
+            fn call(
+                &mut self,
+                mut request: ToolRequest<S>,
+            ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
+                Box::pin(async move {
+                    let parsed_input = $input::from_request(&mut request)?;
+                    let result = self(
+                        $($state::from_tool_state(&mut request.parts()),)*
+                        parsed_input,
+                    )
+                    .await;
+                    Ok(result)
+                })
+            }
+
+            fn schema() -> Option<Schema> {
+                Some(schemars::schema_for!($input))
+            }
+        }
+
+        impl<F, S: Clone + Send + Sync + 'static, $($state,)* $input, R, Fut>
+            ToolHandler<S, ($($state,)* $input,)> for Fallible<F>
+        where
+            F: Fn($($state,)* $input) -> Fut + Send + Sync,
+            $($state: FromToolState<S>,)*
+            $input: FromToolRequest<S> + JsonSchema,
+            R: IntoToolOutput + Send,
+            Fut: Future<Output = ToolResult<R>> + Send,
+        {
+            type Output = R;
+
+            fn call(
+                &mut self,
+                mut request: ToolRequest<S>,
+            ) -> Pin<Box<dyn Future<Output = ToolResult<Self::Output>> + Send + '_>> {
+                Box::pin(async move {
+                    let parsed_input = $input::from_request(&mut request)?;
+                    (self.0)(
+                        $($state::from_tool_state(&mut request.parts()),)*
+                        parsed_input,
+                    )
+                    .await
+                })
+            }
+
+            fn schema() -> Option<Schema> {
+                Some(schemars::schema_for!($input))
+            }
+        }
+    };
+}
+
+impl_tool_handler!(; T1);
+impl_tool_handler!(T1; T2);
+impl_tool_handler!(T1, T2; T3);
+impl_tool_handler!(T1, T2, T3; T4);
+impl_tool_handler!(T1, T2, T3, T4; T5);
+impl_tool_handler!(T1, T2, T3, T4, T5; T6);
+impl_tool_handler!(T1, T2, T3, T4, T5, T6; T7);
+impl_tool_handler!(T1, T2, T3, T4, T5, T6, T7; T8);
+
 /// Type-erased async tool function
 pub trait ErasedToolHandler<S: Clone + Send + Sync + 'static>: Send + Sync {
     fn call_erased(
         &self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<JsonValue>> + Send + '_>>;
+        request: ToolRequest<S>,
+    ) -> Pin<Box<dyn Future<Output = ToolResult<ToolOutput>> + Send + '_>>;
 }
 
 /// Wrapper to make handlers type-erased
@@ -243,25 +402,22 @@ impl<S: Clone + Send + Sync + 'static, T: Send + Sync, H: ToolHandler<S, T> + Se
 {
     fn call_erased(
         &self,
-        state: State<S>,
-        input: Input,
-    ) -> Pin<Box<dyn Future<Output = ToolResult<JsonValue>> + Send + '_>> {
+        request: ToolRequest<S>,
+    ) -> Pin<Box<dyn Future<Output = ToolResult<ToolOutput>> + Send + '_>> {
         Box::pin(async move {
             let mut handler = self.handler.lock().await;
 
-            let result = handler.call(state, input).await?;
-            let json_result = serde_json::to_value(result).map_err(|e| {
-                ToolExecutionError::ExecutionError(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
+            let result = handler.call(request).await?;
+            result.into_tool_output()
         })
     }
 }
 
 /// Type-safe tool registry (without state)
 pub struct ToolRouter<S: Clone + Send + Sync + 'static> {
-    tools: HashMap<String, Box<dyn ErasedToolHandler<S>>>,
+    tools: HashMap<String, Arc<dyn ErasedToolHandler<S>>>,
     metadata: HashMap<String, ToolMetadata>,
+    schema_options: SchemaOptions,
 }
 
 impl<S: Clone + Send + Sync + 'static + Debug> Debug for ToolRouter<S> {
@@ -269,14 +425,19 @@ impl<S: Clone + Send + Sync + 'static + Debug> Debug for ToolRouter<S> {
         f.debug_struct("ToolRouter")
             .field("tools", &self.tools.keys().collect::<Vec<_>>())
             .field("metadata", &self.metadata)
+            .field("schema_options", &self.schema_options)
             .finish()
     }
 }
 
 /// Built tool registry with state
+///
+/// Cloning is cheap: handlers are shared and only the state is cloned.
+#[derive(Clone)]
 pub struct BuiltToolRouter<S: Clone + Send + Sync + 'static> {
-    tools: HashMap<String, Box<dyn ErasedToolHandler<S>>>,
+    tools: HashMap<String, Arc<dyn ErasedToolHandler<S>>>,
     metadata: HashMap<String, ToolMetadata>,
+    schema_options: SchemaOptions,
     state: S,
 }
 
@@ -285,6 +446,7 @@ impl<S: Clone + Send + Sync + 'static + Debug> Debug for BuiltToolRouter<S> {
         f.debug_struct("BuiltToolRouter")
             .field("tools", &self.tools.keys().collect::<Vec<_>>())
             .field("metadata", &self.metadata)
+            .field("schema_options", &self.schema_options)
             .field("state", &self.state)
             .finish()
     }
@@ -295,6 +457,7 @@ impl<S: Clone + Send + Sync + 'static> Default for ToolRouter<S> {
         Self {
             tools: HashMap::new(),
             metadata: HashMap::new(),
+            schema_options: SchemaOptions::default(),
         }
     }
 }
@@ -317,7 +480,7 @@ impl<S: Clone + Send + Sync + 'static> ToolRouter<S> {
     ) -> Self {
         let name_str = name.into();
         let wrapper = ToolHandlerWrapper::new(handler);
-        self.tools.insert(name_str.clone(), Box::new(wrapper));
+        self.tools.insert(name_str.clone(), Arc::new(wrapper));
 
         // Add metadata with generated schema from handler
         self.metadata.insert(
@@ -325,7 +488,7 @@ impl<S: Clone + Send + Sync + 'static> ToolRouter<S> {
             ToolMetadata {
                 name: name_str.clone(),
                 description,
-                parameters_schema: H::schema(),
+                parameters_schema: cached_schema::<T>(H::schema),
             },
         );
 
@@ -345,6 +508,26 @@ impl<S: Clone + Send + Sync + 'static> ToolRouter<S> {
         self.register_infallible(name, description, Fallible(handler))
     }
 
+    /// Register a fallible tool with an explicit parameters schema, for handlers whose
+    /// input shape is only known at runtime (e.g. raw `serde_json::Value` inputs)
+    pub fn register_with_schema<T: Send + Sync + 'static, H>(
+        self,
+        name: impl Into<String>,
+        description: Option<String>,
+        parameters_schema: Schema,
+        handler: H,
+    ) -> Self
+    where
+        Fallible<H>: ToolHandler<S, T> + Send + Sync + 'static,
+    {
+        let name_str = name.into();
+        let mut router = self.register(name_str.clone(), description, handler);
+        if let Some(metadata) = router.metadata.get_mut(&name_str) {
+            metadata.parameters_schema = Some(parameters_schema);
+        }
+        router
+    }
+
     /// Register a tool definition without a handler (will be skipped during execution)
     pub fn register_definition(
         mut self,
@@ -367,11 +550,81 @@ impl<S: Clone + Send + Sync + 'static> ToolRouter<S> {
         self
     }
 
+    /// Register handler-less definitions (client-side/HITL tools) from configuration data
+    pub fn register_definitions(
+        self,
+        definitions: impl IntoIterator<Item = ToolDefinitionConfig>,
+    ) -> crate::errors::Result<Self> {
+        let mut router = self;
+        for definition in definitions {
+            let schema = definition
+                .parameters
+                .map(Schema::try_from)
+                .transpose()
+                .map_err(|e| {
+                    AiError::Validation(ValidationError::InvalidValue {
+                        field: format!("{}.parameters", definition.name),
+                        message: e.to_string(),
+                    })
+                })?;
+            router = router.register_definition(definition.name, definition.description, schema);
+        }
+        Ok(router)
+    }
+
+    /// Register handler-less definitions from a JSON list (or `{"tools": [...]}` object)
+    pub fn register_definitions_from_json(self, json: &str) -> crate::errors::Result<Self> {
+        let file: ToolDefinitionFile = serde_json::from_str(json)?;
+        self.register_definitions(Vec::from(file))
+    }
+
+    /// Register handler-less definitions from a YAML list (or `tools:` mapping)
+    #[cfg(feature = "yaml")]
+    pub fn register_definitions_from_yaml(self, yaml: &str) -> crate::errors::Result<Self> {
+        let file: ToolDefinitionFile = serde_yaml::from_str(yaml).map_err(|e| {
+            AiError::Serialization(SerializationError::JsonError {
+                message: format!("Failed to parse tool definitions: {}", e),
+            })
+        })?;
+        self.register_definitions(Vec::from(file))
+    }
+
+    /// Load handler-less definitions from a `.json`, `.yaml` or `.yml` file
+    pub fn load_definitions(self, path: impl AsRef<Path>) -> crate::errors::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            AiError::Validation(ValidationError::ConfigError {
+                message: format!("Failed to read {}: {}", path.display(), e),
+            })
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => self.register_definitions_from_yaml(&contents),
+            Some("json") => self.register_definitions_from_json(&contents),
+            other => Err(AiError::Serialization(SerializationError::TypeMismatch {
+                expected: if cfg!(feature = "yaml") {
+                    "a .json, .yaml or .yml file".to_string()
+                } else {
+                    "a .json file".to_string()
+                },
+                found: other.unwrap_or("no extension").to_string(),
+            })),
+        }
+    }
+
+    /// Post-process the parameter schemas returned by `get_tool_definitions`
+    pub fn schema_options(mut self, options: SchemaOptions) -> Self {
+        self.schema_options = options;
+        self
+    }
+
     /// Set the state for the registry, consuming it and returning a BuiltToolRegistry
     pub fn with_state(self, state: S) -> BuiltToolRouter<S> {
         BuiltToolRouter {
             tools: self.tools,
             metadata: self.metadata,
+            schema_options: self.schema_options,
             state,
         }
     }
@@ -383,9 +636,35 @@ impl<S: Clone + Send + Sync + 'static> BuiltToolRouter<S> {
     /// Returns Some(Err) for execution errors
     /// Returns Some(Ok) for successful execution
     pub async fn execute_tool(&self, name: &str, input: Input) -> Option<ToolResult<JsonValue>> {
+        self.execute_tool_output(name, input)
+            .await
+            .map(|result| result.map(ToolOutput::into_json))
+    }
+
+    /// Execute a single tool by name, keeping the handler's rich `ToolOutput`
+    pub async fn execute_tool_output(
+        &self,
+        name: &str,
+        input: Input,
+    ) -> Option<ToolResult<ToolOutput>> {
+        self.execute_request(name, ToolRequest::new(self.state.clone(), input))
+            .await
+    }
+
+    /// Execute a model's tool call, making its id available to the `ToolCallId` extractor
+    pub async fn execute_call(&self, call: &ToolCall) -> Option<ToolResult<ToolOutput>> {
+        let request = ToolRequest::new(self.state.clone(), call.arguments.clone())
+            .with_tool_call_id(call.id.clone());
+        self.execute_request(&call.name, request).await
+    }
+
+    async fn execute_request(
+        &self,
+        name: &str,
+        request: ToolRequest<S>,
+    ) -> Option<ToolResult<ToolOutput>> {
         if let Some(tool) = self.tools.get(name) {
-            let state = State(self.state.clone());
-            Some(tool.call_erased(state, input).await)
+            Some(tool.call_erased(request).await)
         } else if self.metadata.contains_key(name) {
             // Tool definition exists but no handler - don't execute, return None to end loop
             None
@@ -429,7 +708,14 @@ impl<S: Clone + Send + Sync + 'static> BuiltToolRouter<S> {
                     .parameters_schema
                     .as_ref()
                     .and_then(|schema| serde_json::to_value(schema).ok())
+                    .map(|mut parameters| {
+                        self.schema_options.apply(&mut parameters);
+                        parameters
+                    })
                     .unwrap_or_else(|| serde_json::json!({})),
+                // Schemas rewritten for strict mode can be enforced as sent
+                strict: self.schema_options.target == SchemaTarget::OpenAiStrict,
+                extensions: HashMap::new(),
             })
             .collect()
     }
@@ -585,4 +871,332 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Test error"));
     }
+
+    #[tokio::test]
+    async fn test_register_definitions_from_json() {
+        let registry = ToolRouter::<()>::new()
+            .register_definitions_from_json(
+                r#"{"tools": [
+                    {"name": "approve", "description": "Ask for approval",
+                     "input_schema": {"type": "object", "properties": {"action": {"type": "string"}}}},
+                    {"name": "confirm"}
+                ]}"#,
+            )
+            .unwrap()
+            .with_state(());
+
+        let approve = registry.tool_metadata("approve").unwrap();
+        assert_eq!(approve.description.as_deref(), Some("Ask for approval"));
+        assert!(approve.parameters_schema.is_some());
+        assert!(registry.tool_metadata("confirm").is_some());
+
+        // Definitions have no handler, so execution hands control back to the caller
+        let result = registry
+            .execute_tool("approve", serde_json::json!({}))
+            .await;
+        assert!(result.is_none());
+
+        let invalid = ToolRouter::<()>::new()
+            .register_definitions_from_json(r#"[{"name": "bad", "parameters": 42}]"#);
+        assert!(invalid.is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_load_definitions_from_yaml() {
+        let dir = std::env::temp_dir().join(format!("ai-core-tools-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("tools.json");
+        let yaml = dir.join("tools.yaml");
+        std::fs::write(
+            &json,
+            r#"{"tools": [
+                {"name": "approve", "description": "Ask for approval",
+                 "input_schema": {"type": "object", "properties": {"action": {"type": "string"}}}},
+                {"name": "confirm"}
+            ]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &yaml,
+            "\
+tools:
+  - name: approve
+    description: Ask for approval
+    input_schema:
+      type: object
+      properties:
+        action:
+          type: string
+  - name: confirm
+",
+        )
+        .unwrap();
+
+        let from_json = ToolRouter::<()>::new()
+            .load_definitions(&json)
+            .unwrap()
+            .with_state(());
+        let from_yaml = ToolRouter::<()>::new()
+            .load_definitions(&yaml)
+            .unwrap()
+            .with_state(());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut expected = from_json.get_tool_definitions();
+        let mut loaded = from_yaml.get_tool_definitions();
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        loaded.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded, expected);
+        assert_eq!(
+            from_yaml
+                .tool_metadata("approve")
+                .unwrap()
+                .description
+                .as_deref(),
+            Some("Ask for approval")
+        );
+    }
+
+    #[derive(Clone)]
+    struct Multiplier(u64);
+
+    impl FromToolState<MyState> for Multiplier {
+        fn from_tool_state(parts: &mut ToolState<MyState>) -> Self {
+            Multiplier(parts.state.0.value * 2)
+        }
+    }
+
+    async fn three_param_handler(
+        State(state): State<MyState>,
+        Multiplier(multiplier): Multiplier,
+        input: TestInput,
+    ) -> ToolResult<String> {
+        Ok(format!(
+            "{} x{}: {}",
+            state.value, multiplier, input.message
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_multiple_extractors() {
+        let registry = ToolRouter::default()
+            .register("three", None, three_param_handler)
+            .with_state(MyState { value: 21 });
+
+        let result = registry
+            .execute_tool("three", serde_json::json!({"message": "hi"}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, serde_json::json!("21 x42: hi"));
+
+        let definitions = registry.get_tool_definitions();
+        assert!(definitions[0].parameters["properties"]["message"].is_object());
+    }
+
+    #[derive(FromToolState)]
+    #[tool_state(state = MyState)]
+    struct Value(#[tool_state(field = value)] u64);
+
+    #[derive(FromToolState)]
+    struct Context {
+        value: Value,
+        multiplier: Multiplier,
+    }
+
+    #[derive(JsonSchema, FromToolRequest)]
+    struct Scaled {
+        message: String,
+        times: Option<u64>,
+        #[schemars(skip)]
+        #[tool_request(state)]
+        context: Context,
+    }
+
+    async fn derived_handler(input: Scaled) -> ToolResult<String> {
+        let Context {
+            value: Value(value),
+            multiplier: Multiplier(multiplier),
+        } = input.context;
+        Ok(format!(
+            "{} x{}: {}",
+            value * input.times.unwrap_or(1),
+            multiplier,
+            input.message
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_derived_extractors() {
+        let registry = ToolRouter::default()
+            .register("scaled", None, derived_handler)
+            .with_state(MyState { value: 21 });
+
+        let result = registry
+            .execute_tool("scaled", serde_json::json!({"message": "hi", "times": 2}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, serde_json::json!("42 x42: hi"));
+
+        let parameters = &registry.get_tool_definitions()[0].parameters;
+        assert!(parameters["properties"]["message"].is_object());
+        assert!(parameters["properties"].get("context").is_none());
+
+        let missing = registry
+            .execute_tool("scaled", serde_json::json!({"times": 2}))
+            .await
+            .unwrap();
+        assert!(
+            matches!(missing, Err(ToolExecutionError::InvalidInput(message)) if message.contains("`message`"))
+        );
+    }
+
+    // Deserialize only, so it can't be a handler input without `Json`
+    #[derive(Deserialize)]
+    struct Untyped {
+        message: String,
+    }
+
+    async fn builtin_extractors_handler(
+        ToolCallId(id): ToolCallId,
+        RawInput(raw): RawInput,
+        Json(input): Json<Untyped>,
+    ) -> ToolResult<String> {
+        Ok(format!(
+            "{}: {} ({} keys)",
+            id.unwrap_or_default(),
+            input.message,
+            raw.as_object().map_or(0, |object| object.len())
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_builtin_extractors() {
+        let registry = ToolRouter::default()
+            .register("builtin", None, builtin_extractors_handler)
+            .with_state(());
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "builtin".to_string(),
+            arguments: serde_json::json!({"message": "hi", "extra": true}),
+        };
+
+        let output = registry.execute_call(&call).await.unwrap().unwrap();
+        assert_eq!(output.text.as_deref(), Some("call_1: hi (2 keys)"));
+
+        let by_name = registry
+            .execute_tool("builtin", serde_json::json!({"message": "hi"}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_name, serde_json::json!(": hi (1 keys)"));
+
+        let parameters = &registry.get_tool_definitions()[0].parameters;
+        assert_eq!(parameters["type"], "object");
+    }
+
+    async fn six_param_handler(
+        State(state): State<MyState>,
+        Multiplier(multiplier): Multiplier,
+        Value(value): Value,
+        ToolCallId(id): ToolCallId,
+        RawInput(raw): RawInput,
+        input: TestInput,
+    ) -> String {
+        format!(
+            "{} {} {} {:?} {} {}",
+            state.value, multiplier, value, id, raw["message"], input.message
+        )
+    }
+
+    #[tokio::test]
+    async fn test_many_extractors() {
+        let registry = ToolRouter::default()
+            .register_infallible("six", None, six_param_handler)
+            .with_state(MyState { value: 1 });
+
+        let result = registry
+            .execute_tool("six", serde_json::json!({"message": "hi"}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, serde_json::json!("1 2 1 None \"hi\" hi"));
+    }
+
+    #[test]
+    fn test_schema_options() {
+        let registry = ToolRouter::default()
+            .register_infallible("echo", None, test_handler_input_only)
+            .schema_options(SchemaOptions::new().deny_additional_properties(true))
+            .with_state(MyState { value: 42 });
+
+        let definition = &registry.get_tool_definitions()[0];
+        assert_eq!(definition.parameters["additionalProperties"], false);
+        assert!(!definition.strict);
+        // The stored schema is left as generated
+        let metadata = registry.tool_metadata("echo").unwrap();
+        let stored = metadata.parameters_schema.as_ref().unwrap();
+        assert!(stored.get("additionalProperties").is_none());
+
+        let strict = ToolRouter::default()
+            .register_infallible("echo", None, test_handler_input_only)
+            .schema_options(SchemaOptions::for_target(SchemaTarget::OpenAiStrict))
+            .with_state(MyState { value: 42 });
+        assert!(strict.get_tool_definitions()[0].strict);
+    }
+
+    async fn rich_output_handler(input: TestInput) -> ToolOutput {
+        ToolOutput::text(format!("Echo: {}", input.message))
+            .with_data(serde_json::json!({"length": input.message.len()}))
+    }
+
+    #[tokio::test]
+    async fn test_tool_output_conversion() {
+        let registry = ToolRouter::default()
+            .register_infallible("rich", None, rich_output_handler)
+            .register_infallible("text", None, test_handler_input_only)
+            .with_state(MyState { value: 42 });
+        let input = serde_json::json!({"message": "hey"});
+
+        let output = registry
+            .execute_tool_output("rich", input.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.text.as_deref(), Some("Echo: hey"));
+        assert_eq!(output.data, Some(serde_json::json!({"length": 3})));
+
+        let output = registry
+            .execute_tool_output("text", input)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output, ToolOutput::text("Input: hey"));
+    }
+
+    #[tokio::test]
+    async fn test_error_result_json() {
+        let registry = ToolRouter::default()
+            .register_infallible("tool", None, test_handler_input_only)
+            .with_state(MyState { value: 42 });
+
+        let error = registry
+            .execute_tool("tool", serde_json::json!({"wrong": 1}))
+            .await
+            .unwrap()
+            .unwrap_err();
+        let payload = error.to_result_json();
+        assert_eq!(payload["error_type"], "invalid_input");
+        assert_eq!(payload["retryable"], true);
+        assert!(payload["suggestion"].is_string());
+        assert!(
+            payload["error"]
+                .as_str()
+                .unwrap()
+                .contains("Failed to parse input")
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::errors::{AiError, Result, StorageError, ValidationError};
+
 /// Content parts for system messages
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -40,12 +42,64 @@ pub struct ToolCall {
     pub arguments: serde_json::Value,
 }
 
+impl ToolCall {
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: serde_json::Value,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            arguments,
+        }
+    }
+}
+
+/// Additional content parts returned by a tool alongside its result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ToolResultContent {
+    Text { text: String },
+    Image { image: ImageContent },
+    Json { value: serde_json::Value },
+}
+
 /// Tool execution result
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolResult {
     pub tool_call_id: String,
     pub result: serde_json::Value,
     pub is_error: bool,
+    /// Extra content parts (e.g. images) that providers send alongside `result`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<ToolResultContent>,
+}
+
+impl ToolResult {
+    /// Successful result of the tool call `tool_call_id`
+    pub fn success(tool_call_id: impl Into<String>, result: serde_json::Value) -> Self {
+        Self {
+            tool_call_id: tool_call_id.into(),
+            result,
+            is_error: false,
+            content: Vec::new(),
+        }
+    }
+
+    /// Failed result of the tool call `tool_call_id`
+    pub fn error(tool_call_id: impl Into<String>, result: serde_json::Value) -> Self {
+        Self {
+            is_error: true,
+            ..Self::success(tool_call_id, result)
+        }
+    }
+
+    /// Attach a content part, e.g. an image
+    pub fn with_content(mut self, content: ToolResultContent) -> Self {
+        self.content.push(content);
+        self
+    }
 }
 
 /// Message enum with role-specific content constraints
@@ -163,6 +217,25 @@ impl Message {
         }
     }
 
+    /// Create an assistant message calling tools, without text
+    pub fn assistant_with_tool_calls(tool_calls: impl IntoIterator<Item = ToolCall>) -> Self {
+        Self::Assistant {
+            content: tool_calls
+                .into_iter()
+                .map(|tool_call| AssistantContent::ToolCall { tool_call })
+                .collect(),
+            metadata: None,
+        }
+    }
+
+    /// Create a tool message answering several tool calls at once
+    pub fn tool_results(tool_results: Vec<ToolResult>) -> Self {
+        Self::Tool {
+            tool_results,
+            metadata: None,
+        }
+    }
+
     /// Add text content (only for System and User messages)
     pub fn add_text(self, text: impl Into<String>) -> Self {
         match self {
@@ -212,6 +285,45 @@ impl Message {
         }
     }
 
+    /// Set a metadata entry
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        let (Self::System { metadata, .. }
+        | Self::User { metadata, .. }
+        | Self::Assistant { metadata, .. }
+        | Self::Tool { metadata, .. }) = &mut self;
+        metadata
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value);
+        self
+    }
+
+    /// Get a metadata entry
+    pub fn metadata(&self, key: &str) -> Option<&serde_json::Value> {
+        let (Self::System { metadata, .. }
+        | Self::User { metadata, .. }
+        | Self::Assistant { metadata, .. }
+        | Self::Tool { metadata, .. }) = self;
+        metadata.as_ref()?.get(key)
+    }
+
+    /// Whether this message was added by `ChatRequest::examples`
+    pub fn is_example(&self) -> bool {
+        self.metadata(EXAMPLE_METADATA_KEY) == Some(&serde_json::Value::Bool(true))
+    }
+
+    /// Mark the prompt up to and including this message as a stable prefix
+    ///
+    /// Providers with explicit caching place a cache breakpoint here; providers that
+    /// cache automatically ignore the marker.
+    pub fn cached(self) -> Self {
+        self.with_metadata(CACHE_METADATA_KEY, true.into())
+    }
+
+    /// Whether this message ends a cacheable prefix
+    pub fn is_cached(&self) -> bool {
+        self.metadata(CACHE_METADATA_KEY) == Some(&serde_json::Value::Bool(true))
+    }
+
     /// Get the role as a string for compatibility
     pub fn role(&self) -> &'static str {
         match self {
@@ -234,6 +346,157 @@ pub struct GenerationSettings {
     pub presence_penalty: Option<f32>,
     pub stop_sequences: Option<Vec<String>>,
     pub seed: Option<u64>,
+    /// Bias added to the logits of token ids, keyed by the id as a string
+    ///
+    /// Token ids depend on the model's tokenizer. Large negative values ban a token and
+    /// large positive values force it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<String, f32>>,
+    /// Shape the output must take, for backends that decode against a grammar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<GenerationConstraint>,
+}
+
+/// Kinds of `GenerationConstraint`, used to describe what a provider supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintKind {
+    Grammar,
+    Regex,
+    JsonSchema,
+    Choice,
+}
+
+/// Constrained decoding for local and self-hosted models
+///
+/// Guarantees the shape of the output the way hosted JSON modes do. Providers pass it to
+/// their backend's own parameters; `llama_cpp_params` and `vllm_params` build the two
+/// common ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GenerationConstraint {
+    /// A GBNF grammar for llama.cpp, or an EBNF grammar for vLLM
+    Grammar { grammar: String },
+    /// A regular expression the whole output must match
+    Regex { pattern: String },
+    /// A JSON Schema the output must validate against
+    JsonSchema { schema: serde_json::Value },
+    /// Exactly one of the given strings
+    Choice { choices: Vec<String> },
+}
+
+impl GenerationConstraint {
+    pub fn grammar(grammar: impl Into<String>) -> Self {
+        Self::Grammar {
+            grammar: grammar.into(),
+        }
+    }
+
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self::Regex {
+            pattern: pattern.into(),
+        }
+    }
+
+    pub fn json_schema(schema: serde_json::Value) -> Self {
+        Self::JsonSchema { schema }
+    }
+
+    pub fn choice<I: IntoIterator<Item = impl Into<String>>>(choices: I) -> Self {
+        Self::Choice {
+            choices: choices.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn kind(&self) -> ConstraintKind {
+        match self {
+            Self::Grammar { .. } => ConstraintKind::Grammar,
+            Self::Regex { .. } => ConstraintKind::Regex,
+            Self::JsonSchema { .. } => ConstraintKind::JsonSchema,
+            Self::Choice { .. } => ConstraintKind::Choice,
+        }
+    }
+
+    /// Request fields for the llama.cpp server's `/completion` and chat endpoints
+    ///
+    /// Choices become a GBNF grammar. `None` for regexes, which llama.cpp cannot apply.
+    pub fn llama_cpp_params(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::Grammar { grammar } => Some(serde_json::json!({ "grammar": grammar })),
+            Self::JsonSchema { schema } => Some(serde_json::json!({ "json_schema": schema })),
+            Self::Choice { choices } => {
+                let alternatives: Vec<String> = choices
+                    .iter()
+                    .map(|choice| serde_json::Value::String(choice.clone()).to_string())
+                    .collect();
+                Some(serde_json::json!({
+                    "grammar": format!("root ::= {}", alternatives.join(" | "))
+                }))
+            }
+            Self::Regex { .. } => None,
+        }
+    }
+
+    /// Guided decoding fields for vLLM's OpenAI-compatible server
+    pub fn vllm_params(&self) -> serde_json::Value {
+        match self {
+            Self::Grammar { grammar } => serde_json::json!({ "guided_grammar": grammar }),
+            Self::Regex { pattern } => serde_json::json!({ "guided_regex": pattern }),
+            Self::JsonSchema { schema } => serde_json::json!({ "guided_json": schema }),
+            Self::Choice { choices } => serde_json::json!({ "guided_choice": choices }),
+        }
+    }
+}
+
+/// Metadata key marking few-shot example messages
+pub const EXAMPLE_METADATA_KEY: &str = "example";
+
+/// Metadata key marking the last message of a cacheable prompt prefix
+pub const CACHE_METADATA_KEY: &str = "cache";
+
+/// Where few-shot examples are placed in a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleStyle {
+    /// Alternating user and assistant messages before the conversation
+    #[default]
+    Messages,
+    /// One system message listing the exchanges
+    System,
+}
+
+impl ExampleStyle {
+    /// Messages carrying `examples` in this style, marked as examples
+    pub fn messages(self, examples: &[(String, String)]) -> Vec<Message> {
+        if examples.is_empty() {
+            return Vec::new();
+        }
+        let messages = match self {
+            Self::Messages => examples
+                .iter()
+                .flat_map(|(user, assistant)| {
+                    [
+                        Message::user(user.as_str()),
+                        Message::assistant(assistant.as_str()),
+                    ]
+                })
+                .collect(),
+            Self::System => {
+                let exchanges: Vec<String> = examples
+                    .iter()
+                    .map(|(user, assistant)| format!("User: {}\nAssistant: {}", user, assistant))
+                    .collect();
+                vec![Message::system(format!(
+                    "Examples of how to respond:\n\n{}",
+                    exchanges.join("\n\n")
+                ))]
+            }
+        };
+        messages
+            .into_iter()
+            .map(|message| message.with_metadata(EXAMPLE_METADATA_KEY, true.into()))
+            .collect()
+    }
 }
 
 /// Request for chat-based text generation
@@ -242,6 +505,9 @@ pub struct ChatRequest {
     pub messages: Vec<Message>,
     pub settings: GenerationSettings,
     pub tools: Option<Vec<ToolDefinition>>,
+    /// Extra HTTP headers sent with this request only, e.g. gateway analytics tags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
 }
 
 impl ChatRequest {
@@ -251,6 +517,7 @@ impl ChatRequest {
             messages: Vec::new(),
             settings: GenerationSettings::default(),
             tools: None,
+            headers: Vec::new(),
         }
     }
 
@@ -281,6 +548,62 @@ impl ChatRequest {
         self.message(Message::assistant(text))
     }
 
+    /// Add an assistant message calling tools
+    pub fn assistant_with_tool_calls(self, tool_calls: impl IntoIterator<Item = ToolCall>) -> Self {
+        self.message(Message::assistant_with_tool_calls(tool_calls))
+    }
+
+    /// Add a successful tool result, joining the tool message just before it if any
+    pub fn tool_result(self, tool_call_id: impl Into<String>, result: serde_json::Value) -> Self {
+        self.tool_results(vec![ToolResult::success(tool_call_id, result)])
+    }
+
+    /// Add a failed tool result, joining the tool message just before it if any
+    pub fn tool_error(self, tool_call_id: impl Into<String>, error: serde_json::Value) -> Self {
+        self.tool_results(vec![ToolResult::error(tool_call_id, error)])
+    }
+
+    /// Add tool results, joining the tool message just before them if any, since
+    /// providers expect all the results for one assistant turn in a single message
+    pub fn tool_results(mut self, results: Vec<ToolResult>) -> Self {
+        match self.messages.last_mut() {
+            Some(Message::Tool { tool_results, .. }) => tool_results.extend(results),
+            _ => self.messages.push(Message::tool_results(results)),
+        }
+        self
+    }
+
+    /// Add few-shot `(user, assistant)` exchanges as messages before the conversation
+    pub fn examples<U, A>(self, examples: Vec<(U, A)>) -> Self
+    where
+        U: Into<String>,
+        A: Into<String>,
+    {
+        self.examples_as(examples, ExampleStyle::Messages)
+    }
+
+    /// Add few-shot exchanges in the given style, e.g. the one the provider prefers
+    ///
+    /// Examples go after the leading system messages and any earlier examples, wherever
+    /// the builder is called.
+    pub fn examples_as<U, A>(mut self, examples: Vec<(U, A)>, style: ExampleStyle) -> Self
+    where
+        U: Into<String>,
+        A: Into<String>,
+    {
+        let examples: Vec<(String, String)> = examples
+            .into_iter()
+            .map(|(user, assistant)| (user.into(), assistant.into()))
+            .collect();
+        let at = self
+            .messages
+            .iter()
+            .take_while(|message| matches!(message, Message::System { .. }) || message.is_example())
+            .count();
+        self.messages.splice(at..at, style.messages(&examples));
+        self
+    }
+
     /// Set temperature
     pub fn temperature(mut self, temp: f32) -> Self {
         self.settings.temperature = Some(temp);
@@ -293,11 +616,38 @@ impl ChatRequest {
         self
     }
 
+    /// Bias a token id's logit; repeated calls add more tokens
+    pub fn logit_bias(mut self, token: impl Into<String>, bias: f32) -> Self {
+        self.settings
+            .logit_bias
+            .get_or_insert_with(HashMap::new)
+            .insert(token.into(), bias);
+        self
+    }
+
+    /// Constrain the shape of the output
+    pub fn constraint(mut self, constraint: GenerationConstraint) -> Self {
+        self.settings.constraint = Some(constraint);
+        self
+    }
+
     /// Set tools
     pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
         self.tools = Some(tools);
         self
     }
+
+    /// Send an extra HTTP header with this request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send extra HTTP headers with this request, e.g. `Gateway::tag_headers`
+    pub fn headers(mut self, headers: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
 }
 
 impl Default for ChatRequest {
@@ -312,6 +662,57 @@ pub struct ToolDefinition {
     pub name: String,
     pub description: String,
     pub parameters: serde_json::Value, // JSON Schema
+    /// Ask the provider to enforce `parameters` exactly, e.g. OpenAI strict function
+    /// calling; providers without strict mode ignore it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// Extra fields for one provider's tool object, keyed by provider name, e.g.
+    /// `cache_control` under `anthropic`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extensions: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+}
+
+impl ToolDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+            strict: false,
+            extensions: HashMap::new(),
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Add a field to the tool object sent to `provider`
+    pub fn extension(
+        mut self,
+        provider: impl Into<String>,
+        key: impl Into<String>,
+        value: serde_json::Value,
+    ) -> Self {
+        self.extensions
+            .entry(provider.into())
+            .or_default()
+            .insert(key.into(), value);
+        self
+    }
+
+    /// Extra fields for `provider`'s tool object
+    pub fn extensions_for(
+        &self,
+        provider: &str,
+    ) -> Option<&serde_json::Map<String, serde_json::Value>> {
+        self.extensions.get(provider)
+    }
 }
 
 /// Response from chat-based text generation
@@ -343,14 +744,51 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
+impl Usage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    /// Fold in usage reported by a later chunk of the same streamed response
+    ///
+    /// Providers either report cumulative usage or split it between the first and last
+    /// chunk, so each counter keeps the largest value seen.
+    pub fn merge_reported(&self, reported: &Usage) -> Usage {
+        let prompt_tokens = self.prompt_tokens.max(reported.prompt_tokens);
+        let completion_tokens = self.completion_tokens.max(reported.completion_tokens);
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: reported.total_tokens.max(prompt_tokens + completion_tokens),
+        }
+    }
+}
+
 /// Delta content for streaming chunks
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "role", rename_all = "lowercase")]
 pub enum MessageDelta {
-    System { content: Option<UserContent> },
-    User { content: Option<UserContent> },
-    Assistant { content: Option<AssistantContent> },
-    Tool { tool_result: Option<ToolResult> },
+    System {
+        content: Option<UserContent>,
+    },
+    User {
+        content: Option<UserContent>,
+    },
+    Assistant {
+        content: Option<AssistantContent>,
+    },
+    Tool {
+        tool_result: Option<ToolResult>,
+    },
+    /// Model reasoning, e.g. Anthropic extended thinking, streamed apart from the answer
+    /// and not part of the assistant message
+    Reasoning {
+        text: String,
+    },
 }
 
 /// Streaming chunk for real-time chat generation
@@ -359,6 +797,8 @@ pub struct ChatStreamChunk {
     pub id: String,
     pub delta: MessageDelta,
     pub finish_reason: Option<FinishReason>,
+    /// Usage of the response so far; providers normalize it with `cumulative_usage`, so
+    /// the chunk carrying `finish_reason` has the complete usage
     pub usage: Option<Usage>,
 }
 
@@ -404,3 +844,207 @@ pub struct GeneratedImage {
     pub base64: Option<String>,
     pub revised_prompt: Option<String>,
 }
+
+impl GeneratedImage {
+    /// Decode the base64 image data, with or without a `data:` URL prefix
+    ///
+    /// Images returned only as a URL have no data to decode and must be downloaded.
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        use base64::Engine;
+
+        let data = self.base64.as_deref().ok_or_else(|| {
+            AiError::Validation(ValidationError::MissingField {
+                field: "base64".to_string(),
+            })
+        })?;
+        let data = data.split_once(";base64,").map_or(data, |(_, data)| data);
+        base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|e| {
+                AiError::Validation(ValidationError::InvalidValue {
+                    field: "base64".to_string(),
+                    message: e.to_string(),
+                })
+            })
+    }
+
+    /// MIME type of the decoded image, recognized from its first bytes
+    pub fn mime_type(&self) -> Option<&'static str> {
+        let bytes = self.bytes().ok()?;
+        match bytes.as_slice() {
+            [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+            [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+            [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+            [
+                b'R',
+                b'I',
+                b'F',
+                b'F',
+                _,
+                _,
+                _,
+                _,
+                b'W',
+                b'E',
+                b'B',
+                b'P',
+                ..,
+            ] => Some("image/webp"),
+            _ => None,
+        }
+    }
+
+    /// Write the decoded image to `path`
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        std::fs::write(path, self.bytes()?).map_err(|e| {
+            AiError::Storage(StorageError::Io {
+                message: e.to_string(),
+            })
+        })
+    }
+}
+
+/// Generated images can be sent back to vision models, e.g. to critique or edit them
+impl From<GeneratedImage> for ImageContent {
+    fn from(image: GeneratedImage) -> Self {
+        let mime_type = image.mime_type().map(str::to_string);
+        ImageContent {
+            url: image.url,
+            base64: image.base64,
+            mime_type,
+        }
+    }
+}
+
+/// Progress report from a streaming image generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImageProgress {
+    /// An intermediate step, with a preview when the provider sends one
+    Step {
+        step: u32,
+        total_steps: Option<u32>,
+        preview: Option<GeneratedImage>,
+    },
+    /// The finished images
+    Completed { response: ImageResponse },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_image_helpers() {
+        let image = GeneratedImage {
+            url: None,
+            base64: Some("data:image/png;base64,iVBORw0KGgo=".to_string()),
+            revised_prompt: None,
+        };
+        assert_eq!(image.bytes().unwrap(), b"\x89PNG\r\n\x1a\n");
+        assert_eq!(image.mime_type(), Some("image/png"));
+
+        let path = std::env::temp_dir().join(format!("ai-core-{}.png", uuid::Uuid::new_v4()));
+        image.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap().len(), 8);
+        std::fs::remove_file(path).unwrap();
+
+        let content = ImageContent::from(image);
+        assert_eq!(content.mime_type.as_deref(), Some("image/png"));
+
+        let linked = GeneratedImage {
+            url: Some("https://example.com/cat.png".to_string()),
+            base64: None,
+            revised_prompt: None,
+        };
+        assert!(linked.bytes().is_err());
+        assert_eq!(
+            ImageContent::from(linked).url.as_deref(),
+            Some("https://example.com/cat.png")
+        );
+    }
+
+    #[test]
+    fn test_examples_follow_system_messages_in_order() {
+        let request = ChatRequest::new()
+            .system("Be terse.")
+            .user("What is 3 + 3?")
+            .examples(vec![("1 + 1?", "2")])
+            .examples_as(vec![("2 + 2?", "4")], ExampleStyle::Messages);
+        let roles: Vec<&str> = request.messages.iter().map(Message::role).collect();
+        assert_eq!(
+            roles,
+            vec!["system", "user", "assistant", "user", "assistant", "user"]
+        );
+        assert_eq!(
+            request.messages[1],
+            Message::user("1 + 1?").with_metadata(EXAMPLE_METADATA_KEY, true.into())
+        );
+        assert!(request.messages[4].is_example());
+        assert!(!request.messages[5].is_example());
+
+        let request = ChatRequest::new()
+            .user("What is 3 + 3?")
+            .examples_as(vec![("1 + 1?", "2")], ExampleStyle::System);
+        assert_eq!(
+            request.messages[0],
+            Message::system("Examples of how to respond:\n\nUser: 1 + 1?\nAssistant: 2")
+                .with_metadata(EXAMPLE_METADATA_KEY, true.into())
+        );
+    }
+
+    #[test]
+    fn test_constraint_backend_params() {
+        let choice = GenerationConstraint::choice(["yes", "no"]);
+        assert_eq!(
+            choice.llama_cpp_params().unwrap(),
+            serde_json::json!({ "grammar": "root ::= \"yes\" | \"no\"" })
+        );
+        assert_eq!(
+            choice.vllm_params(),
+            serde_json::json!({ "guided_choice": ["yes", "no"] })
+        );
+
+        let regex = GenerationConstraint::regex("[0-9]+");
+        assert!(regex.llama_cpp_params().is_none());
+        assert_eq!(
+            regex.vllm_params(),
+            serde_json::json!({ "guided_regex": "[0-9]+" })
+        );
+
+        let settings: GenerationSettings = serde_json::from_value(serde_json::json!({
+            "constraint": { "type": "json_schema", "schema": { "type": "object" } }
+        }))
+        .unwrap();
+        assert_eq!(
+            settings.constraint.unwrap().kind(),
+            ConstraintKind::JsonSchema
+        );
+    }
+
+    #[test]
+    fn test_tool_flow_builders() {
+        let request = ChatRequest::new()
+            .user("Weather in Paris and Rome?")
+            .assistant_with_tool_calls([
+                ToolCall::new("call_1", "weather", serde_json::json!({ "city": "Paris" })),
+                ToolCall::new("call_2", "weather", serde_json::json!({ "city": "Rome" })),
+            ])
+            .tool_result("call_1", serde_json::json!("sunny"))
+            .tool_error("call_2", serde_json::json!("unavailable"))
+            .assistant("Sunny in Paris; Rome is unavailable.");
+
+        assert_eq!(request.messages.len(), 4);
+        let Message::Tool { tool_results, .. } = &request.messages[2] else {
+            panic!("expected one tool message, got {:?}", request.messages[2]);
+        };
+        assert_eq!(
+            tool_results,
+            &vec![
+                ToolResult::success("call_1", serde_json::json!("sunny")),
+                ToolResult::error("call_2", serde_json::json!("unavailable")),
+            ]
+        );
+        crate::normalize::validate_alternation(&request.messages).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use serde_json::Value as JsonValue;
+
+use crate::errors::{AiError, Result, SerializationError};
+use crate::types::Message;
+
+/// Version of the message format written by this build
+pub const SCHEMA_VERSION: u32 = 1;
+
+const VERSION_KEY: &str = "schema_version";
+
+/// Upgrades of one message's JSON; `MIGRATIONS[v]` takes version `v` to `v + 1`
+const MIGRATIONS: [fn(&mut JsonValue) -> Result<()>; SCHEMA_VERSION as usize] = [from_unversioned];
+
+/// Unversioned messages have the same shape as version 1
+fn from_unversioned(_message: &mut JsonValue) -> Result<()> {
+    Ok(())
+}
+
+/// Run the migrations from `version` up to `SCHEMA_VERSION` on a message's JSON
+pub fn upgrade_message_json(message: &mut JsonValue, version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        return Err(AiError::Serialization(
+            SerializationError::UnsupportedVersion {
+                found: version,
+                supported: SCHEMA_VERSION,
+            },
+        ));
+    }
+    for migrate in &MIGRATIONS[version as usize..] {
+        migrate(message)?;
+    }
+    Ok(())
+}
+
+/// The `schema_version` stored in `value`, removing it; 0 when absent
+pub fn take_schema_version(value: &mut JsonValue) -> Result<u32> {
+    let Some(version) = value.as_object_mut().and_then(|o| o.remove(VERSION_KEY)) else {
+        return Ok(0);
+    };
+    version
+        .as_u64()
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| {
+            AiError::Serialization(SerializationError::TypeMismatch {
+                expected: "an integer schema_version".to_string(),
+                found: version.to_string(),
+            })
+        })
+}
+
+/// Serialize one message with the current `schema_version`
+pub fn message_to_versioned_json(message: &Message) -> Result<String> {
+    let mut value = serde_json::to_value(message)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert(VERSION_KEY.to_string(), SCHEMA_VERSION.into());
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// Deserialize one message written by any version, upgrading it
+pub fn message_from_versioned_json(json: &str) -> Result<Message> {
+    let mut value: JsonValue = serde_json::from_str(json)?;
+    let version = take_schema_version(&mut value)?;
+    upgrade_message_json(&mut value, version)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Serialize a conversation as `{"schema_version": .., "messages": [..]}`
+pub fn conversation_to_versioned_json(messages: &[Message]) -> Result<String> {
+    Ok(serde_json::to_string(&serde_json::json!({
+        VERSION_KEY: SCHEMA_VERSION,
+        "messages": messages,
+    }))?)
+}
+
+/// Deserialize a conversation envelope, or a bare array of unversioned messages
+pub fn conversation_from_versioned_json(json: &str) -> Result<Vec<Message>> {
+    let mut value: JsonValue = serde_json::from_str(json)?;
+    let version = take_schema_version(&mut value)?;
+    let messages = match value {
+        JsonValue::Array(messages) => messages,
+        JsonValue::Object(mut envelope) => match envelope.remove("messages") {
+            Some(JsonValue::Array(messages)) => messages,
+            _ => {
+                return Err(AiError::Serialization(SerializationError::TypeMismatch {
+                    expected: "a `messages` array".to_string(),
+                    found: "an object without one".to_string(),
+                }));
+            }
+        },
+        other => {
+            return Err(AiError::Serialization(SerializationError::TypeMismatch {
+                expected: "a conversation object or array".to_string(),
+                found: other.to_string(),
+            }));
+        }
+    };
+    upgrade_messages(messages, version)
+}
+
+/// Upgrade and deserialize message JSON written at `version`
+pub fn upgrade_messages(messages: Vec<JsonValue>, version: u32) -> Result<Vec<Message>> {
+    messages
+        .into_iter()
+        .map(|mut message| {
+            // A message saved on its own may carry its own version
+            let version = match take_schema_version(&mut message)? {
+                0 => version,
+                own => own,
+            };
+            upgrade_message_json(&mut message, version)?;
+            Ok(serde_json::from_value(message)?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_roundtrip_and_legacy_data() {
+        let messages = vec![Message::system("Be brief."), Message::user("Hi")];
+
+        let line = message_to_versioned_json(&messages[1]).unwrap();
+        assert!(line.contains("\"schema_version\":1"));
+        assert_eq!(message_from_versioned_json(&line).unwrap(), messages[1]);
+        // Lines written before versioning still load
+        let legacy = serde_json::to_string(&messages[1]).unwrap();
+        assert_eq!(message_from_versioned_json(&legacy).unwrap(), messages[1]);
+
+        let json = conversation_to_versioned_json(&messages).unwrap();
+        assert_eq!(conversation_from_versioned_json(&json).unwrap(), messages);
+        let legacy = serde_json::to_string(&messages).unwrap();
+        assert_eq!(conversation_from_versioned_json(&legacy).unwrap(), messages);
+    }
+
+    #[test]
+    fn test_newer_versions_are_rejected() {
+        let json = r#"{"schema_version": 99, "role": "user", "content": []}"#;
+        assert!(matches!(
+            message_from_versioned_json(json),
+            Err(AiError::Serialization(
+                SerializationError::UnsupportedVersion {
+                    found: 99,
+                    supported: SCHEMA_VERSION,
+                }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+use ai_agent::{parse_json_text, validate_json_schema};
+use ai_core::{AiError, Result, ValidationError};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// Check applied to an agent's final answer
+#[derive(Debug, Clone)]
+pub enum Assertion {
+    Contains(String),
+    NotContains(String),
+    Regex(Regex),
+    /// The answer is JSON, optionally inside a Markdown code fence, matching the schema
+    JsonSchema(JsonValue),
+}
+
+/// Outcome of one assertion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssertionResult {
+    /// Short description, e.g. `contains "Paris"`
+    pub assertion: String,
+    pub passed: bool,
+    /// Why the assertion failed
+    pub message: Option<String>,
+}
+
+impl Assertion {
+    pub fn contains(text: impl Into<String>) -> Self {
+        Self::Contains(text.into())
+    }
+
+    pub fn not_contains(text: impl Into<String>) -> Self {
+        Self::NotContains(text.into())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self> {
+        Regex::new(pattern).map(Self::Regex).map_err(|e| {
+            AiError::Validation(ValidationError::InvalidValue {
+                field: "pattern".to_string(),
+                message: e.to_string(),
+            })
+        })
+    }
+
+    pub fn json_schema(schema: JsonValue) -> Self {
+        Self::JsonSchema(schema)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Assertion::Contains(text) => format!("contains {:?}", text),
+            Assertion::NotContains(text) => format!("does not contain {:?}", text),
+            Assertion::Regex(regex) => format!("matches /{}/", regex.as_str()),
+            Assertion::JsonSchema(_) => "matches JSON schema".to_string(),
+        }
+    }
+
+    pub fn check(&self, output: &str) -> AssertionResult {
+        let failure = match self {
+            Assertion::Contains(text) => {
+                (!output.contains(text.as_str())).then(|| "text not found".to_string())
+            }
+            Assertion::NotContains(text) => output
+                .contains(text.as_str())
+                .then(|| "text found".to_string()),
+            Assertion::Regex(regex) => (!regex.is_match(output)).then(|| "no match".to_string()),
+            Assertion::JsonSchema(schema) => check_json_schema(schema, output),
+        };
+        AssertionResult {
+            assertion: self.describe(),
+            passed: failure.is_none(),
+            message: failure,
+        }
+    }
+}
+
+fn check_json_schema(schema: &JsonValue, output: &str) -> Option<String> {
+    let value = match parse_json_text(output) {
+        Ok(value) => value,
+        Err(e) => return Some(format!("not JSON: {}", e)),
+    };
+    validate_json_schema(schema, &value).err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assertions() {
+        assert!(Assertion::contains("Paris").check("It is Paris.").passed);
+        assert!(
+            !Assertion::not_contains("Paris")
+                .check("It is Paris.")
+                .passed
+        );
+        assert!(
+            Assertion::regex(r"\d+ km")
+                .unwrap()
+                .check("About 340 km")
+                .passed
+        );
+        assert!(Assertion::regex("(").is_err());
+
+        let schema = Assertion::json_schema(serde_json::json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"]
+        }));
+        assert!(schema.check("```json\n{\"city\": \"Paris\"}\n```").passed);
+        let failed = schema.check("{\"city\": 1}");
+        assert!(!failed.passed);
+        assert!(failed.message.unwrap().contains("/city"));
+        assert!(!schema.check("Paris").passed);
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use ai_agent::parse_json_text;
+use ai_core::{
+    AgentError, AiError, ChatTextGeneration, Result, prompt,
+    types::{ChatRequest, GenerationSettings, Message},
+};
+use serde::{Deserialize, Serialize};
+
+const JUDGE_SYSTEM: &str = "You are a strict evaluator grading an AI assistant's answer \
+against a rubric. Reply with only a JSON object of the form \
+{\"score\": <number from 0 to 1>, \"reasoning\": \"<one or two sentences>\"}.";
+
+const JUDGE_PROMPT: &str = "Rubric:\n{criteria}\n\nQuestion:\n{input}\n\nAnswer:\n{output}\
+{#if reference}\n\nReference answer:\n{reference}{/if}";
+
+/// Criteria an LLM judge scores an answer against
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rubric {
+    pub criteria: String,
+    /// Minimum score, from 0 to 1, for the case to pass
+    pub pass_score: f32,
+    /// Known-good answer shown to the judge for comparison
+    pub reference: Option<String>,
+}
+
+impl Rubric {
+    pub fn new(criteria: impl Into<String>) -> Self {
+        Self {
+            criteria: criteria.into(),
+            pass_score: 0.7,
+            reference: None,
+        }
+    }
+
+    pub fn pass_score(mut self, score: f32) -> Self {
+        self.pass_score = score;
+        self
+    }
+
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+}
+
+/// A judge's verdict on one answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Judgement {
+    pub score: f32,
+    pub reasoning: String,
+    pub passed: bool,
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    score: f32,
+    #[serde(default)]
+    reasoning: String,
+}
+
+/// LLM-as-judge grader
+///
+/// Runs at temperature 0 so repeated evaluations of the same answer agree as far as
+/// the provider allows.
+#[derive(Clone)]
+pub struct Judge {
+    provider: Arc<dyn ChatTextGeneration>,
+}
+
+impl std::fmt::Debug for Judge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Judge")
+            .field("provider", &self.provider.name())
+            .field("model", &self.provider.model())
+            .finish()
+    }
+}
+
+impl Judge {
+    pub fn new(provider: impl ChatTextGeneration + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    /// Score `output`, the answer to `input`, against `rubric`
+    pub async fn grade(&self, input: &str, output: &str, rubric: &Rubric) -> Result<Judgement> {
+        let prompt = prompt!(
+            JUDGE_PROMPT,
+            criteria = rubric.criteria.as_str(),
+            input = input,
+            output = output,
+            reference = rubric.reference.as_deref(),
+        );
+        let request = ChatRequest {
+            messages: vec![Message::system(JUDGE_SYSTEM), Message::user(prompt)],
+            settings: GenerationSettings {
+                temperature: Some(0.0),
+                ..GenerationSettings::default()
+            },
+            tools: None,
+            headers: Vec::new(),
+        };
+        let response = self.provider.generate(request).await?;
+        let text = match &response.message {
+            Message::Assistant { content, .. } => content
+                .iter()
+                .filter_map(|part| match part {
+                    ai_core::AssistantContent::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>(),
+            _ => String::new(),
+        };
+        let verdict: Verdict = parse_json_text(&text)
+            .and_then(serde_json::from_value)
+            .map_err(|e| {
+                AiError::Agent(AgentError::InvalidOutput {
+                    message: format!("judge reply is not a verdict ({}): {}", e, text),
+                })
+            })?;
+        let score = verdict.score.clamp(0.0, 1.0);
+        Ok(Judgement {
+            score,
+            reasoning: verdict.reasoning,
+            passed: score >= rubric.pass_score,
+        })
+    }
+}
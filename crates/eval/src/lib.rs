@@ -0,0 +1,9 @@
+pub mod assertion;
+pub mod judge;
+pub mod scenario;
+pub mod suite;
+
+pub use assertion::*;
+pub use judge::*;
+pub use scenario::*;
+pub use suite::*;
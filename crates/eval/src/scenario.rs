@@ -0,0 +1,431 @@
+use std::{
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ai_agent::Agent;
+use ai_core::{
+    AgentError, AiError, ChatTextGeneration, Result,
+    types::{AssistantContent, ChatRequest, Message, ToolCall},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assertion::{Assertion, AssertionResult},
+    judge::{Judge, Judgement, Rubric},
+};
+
+/// Reply a `ModelUser` gives once it has nothing left to say
+pub const SIMULATED_USER_DONE: &str = "[DONE]";
+
+const MODEL_USER_SYSTEM: &str = "You are playing the user in a conversation with an AI \
+assistant, to test it. Stay in character and write only the user's next message, without \
+quotes or commentary. Once your goal is met, or the assistant clearly cannot help, reply \
+with exactly [DONE].";
+
+const MODEL_USER_OPENING: &str = "Write your first message to the assistant.";
+
+/// One exchange of a scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioTurn {
+    pub user: String,
+    pub agent: String,
+    /// Tool calls the agent made while answering
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Plays the user in a scenario
+#[async_trait]
+pub trait SimulatedUser: Send + Sync {
+    /// Next user message given the turns so far; `None` ends the conversation
+    async fn next_message(&self, turns: &[ScenarioTurn]) -> Result<Option<String>>;
+}
+
+/// Scripted responders that react to the agent's answers
+#[async_trait]
+impl<F> SimulatedUser for F
+where
+    F: Fn(&[ScenarioTurn]) -> Option<String> + Send + Sync,
+{
+    async fn next_message(&self, turns: &[ScenarioTurn]) -> Result<Option<String>> {
+        Ok(self(turns))
+    }
+}
+
+/// Sends fixed messages in order, ending the conversation after the last one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptedUser {
+    messages: Vec<String>,
+}
+
+impl ScriptedUser {
+    pub fn new<I, M>(messages: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        Self {
+            messages: messages.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl SimulatedUser for ScriptedUser {
+    async fn next_message(&self, turns: &[ScenarioTurn]) -> Result<Option<String>> {
+        Ok(self.messages.get(turns.len()).cloned())
+    }
+}
+
+/// A second model playing a user with the given persona and goal
+///
+/// The conversation is shown to the model with the roles swapped, so its own messages are
+/// the user's. It ends the conversation by replying `SIMULATED_USER_DONE`.
+#[derive(Clone)]
+pub struct ModelUser {
+    provider: Arc<dyn ChatTextGeneration>,
+    persona: String,
+}
+
+impl std::fmt::Debug for ModelUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelUser")
+            .field("provider", &self.provider.name())
+            .field("model", &self.provider.model())
+            .field("persona", &self.persona)
+            .finish()
+    }
+}
+
+impl ModelUser {
+    /// `persona` describes who the user is and what they want from the assistant
+    pub fn new(provider: impl ChatTextGeneration + 'static, persona: impl Into<String>) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            persona: persona.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl SimulatedUser for ModelUser {
+    async fn next_message(&self, turns: &[ScenarioTurn]) -> Result<Option<String>> {
+        let mut request = ChatRequest::new()
+            .system(format!("{}\n\n{}", MODEL_USER_SYSTEM, self.persona))
+            .user(MODEL_USER_OPENING);
+        for turn in turns {
+            request = request
+                .message(Message::assistant(turn.user.as_str()))
+                .user(turn.agent.as_str());
+        }
+        let response = self.provider.generate(request).await?;
+        let text = message_text(&response.message);
+        let text = text.trim();
+        Ok((!text.is_empty() && !text.contains(SIMULATED_USER_DONE)).then(|| text.to_string()))
+    }
+}
+
+/// A multi-turn conversation between a simulated user and an agent, with the checks it
+/// must pass once the conversation ends
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    /// What the conversation should achieve, shown to the judge
+    pub goal: String,
+    /// Turns after which the conversation is cut off and the scenario fails
+    pub max_turns: usize,
+    /// Tools the agent must call at some point
+    pub expected_tools: Vec<String>,
+    /// Checks on the agent's last answer
+    pub assertions: Vec<Assertion>,
+    /// Criteria the judge scores the whole transcript against
+    pub rubric: Option<Rubric>,
+}
+
+/// Outcome of a scenario
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub turns: Vec<ScenarioTurn>,
+    /// Whether the simulated user ended the conversation within `max_turns`
+    pub finished: bool,
+    /// Set when the agent, the simulated user or the judge failed
+    pub error: Option<String>,
+    pub missing_tools: Vec<String>,
+    pub assertions: Vec<AssertionResult>,
+    pub judgement: Option<Judgement>,
+    pub duration: Duration,
+    pub passed: bool,
+}
+
+impl ScenarioResult {
+    /// The conversation as plain text, one line per message and tool call
+    pub fn transcript(&self) -> String {
+        transcript(&self.turns)
+    }
+}
+
+impl Scenario {
+    pub fn new(name: impl Into<String>, goal: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            goal: goal.into(),
+            max_turns: 10,
+            expected_tools: Vec::new(),
+            assertions: Vec::new(),
+            rubric: None,
+        }
+    }
+
+    pub fn max_turns(mut self, turns: usize) -> Self {
+        self.max_turns = turns;
+        self
+    }
+
+    pub fn expect_tool(mut self, name: impl Into<String>) -> Self {
+        self.expected_tools.push(name.into());
+        self
+    }
+
+    pub fn assert(mut self, assertion: Assertion) -> Self {
+        self.assertions.push(assertion);
+        self
+    }
+
+    /// Have a judge score the transcript, see `run`
+    pub fn rubric(mut self, rubric: Rubric) -> Self {
+        self.rubric = Some(rubric);
+        self
+    }
+
+    /// Play the scenario on a fork of `agent`; failures are recorded in the result
+    ///
+    /// `judge` is required when the scenario has a rubric.
+    pub async fn run<P, S>(
+        &self,
+        agent: &Agent<P, S>,
+        user: &impl SimulatedUser,
+        judge: Option<&Judge>,
+    ) -> ScenarioResult
+    where
+        P: ChatTextGeneration,
+        S: Clone + Send + Sync + 'static,
+    {
+        let started = Instant::now();
+        let mut result = ScenarioResult {
+            name: self.name.clone(),
+            turns: Vec::new(),
+            finished: false,
+            error: None,
+            missing_tools: Vec::new(),
+            assertions: Vec::new(),
+            judgement: None,
+            duration: Duration::ZERO,
+            passed: false,
+        };
+
+        if let Err(e) = self.converse(agent.fork(), user, &mut result).await {
+            result.error = Some(e.to_string());
+        }
+        result.missing_tools = self
+            .expected_tools
+            .iter()
+            .filter(|name| {
+                !result
+                    .turns
+                    .iter()
+                    .flat_map(|turn| &turn.tool_calls)
+                    .any(|call| &call.name == *name)
+            })
+            .cloned()
+            .collect();
+        let last_answer = result
+            .turns
+            .last()
+            .map(|turn| turn.agent.as_str())
+            .unwrap_or_default();
+        result.assertions = self
+            .assertions
+            .iter()
+            .map(|assertion| assertion.check(last_answer))
+            .collect();
+        if let (Some(rubric), None) = (&self.rubric, &result.error) {
+            match self.grade(judge, &result.transcript(), rubric).await {
+                Ok(judgement) => result.judgement = Some(judgement),
+                Err(e) => result.error = Some(e.to_string()),
+            }
+        }
+
+        result.passed = result.finished
+            && result.error.is_none()
+            && result.missing_tools.is_empty()
+            && result.assertions.iter().all(|assertion| assertion.passed)
+            && result.judgement.as_ref().is_none_or(|j| j.passed);
+        result.duration = started.elapsed();
+        result
+    }
+
+    async fn converse<P, S>(
+        &self,
+        agent: Agent<P, S>,
+        user: &impl SimulatedUser,
+        result: &mut ScenarioResult,
+    ) -> Result<()>
+    where
+        P: ChatTextGeneration,
+        S: Clone + Send + Sync + 'static,
+    {
+        while result.turns.len() < self.max_turns {
+            let Some(message) = user.next_message(&result.turns).await? else {
+                result.finished = true;
+                return Ok(());
+            };
+            let response = agent.run(message.as_str()).await?;
+            result.turns.push(ScenarioTurn {
+                user: message,
+                agent: response.text(),
+                tool_calls: response
+                    .steps
+                    .iter()
+                    .flat_map(|step| step.tool_calls.iter().cloned())
+                    .collect(),
+            });
+        }
+        // Out of turns: the scenario still finishes if the user had nothing more to say
+        result.finished = user.next_message(&result.turns).await?.is_none();
+        Ok(())
+    }
+
+    async fn grade(
+        &self,
+        judge: Option<&Judge>,
+        transcript: &str,
+        rubric: &Rubric,
+    ) -> Result<Judgement> {
+        let judge = judge.ok_or_else(|| {
+            AiError::Agent(AgentError::StateError {
+                message: "scenario has a rubric but no judge was given".to_string(),
+            })
+        })?;
+        judge.grade(&self.goal, transcript, rubric).await
+    }
+}
+
+fn transcript(turns: &[ScenarioTurn]) -> String {
+    let mut text = String::new();
+    for turn in turns {
+        let _ = writeln!(text, "User: {}", turn.user);
+        for call in &turn.tool_calls {
+            let _ = writeln!(text, "[tool call {} {}]", call.name, call.arguments);
+        }
+        let _ = writeln!(text, "Assistant: {}", turn.agent);
+    }
+    text
+}
+
+fn message_text(message: &Message) -> String {
+    match message {
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_agent::StopOnReason;
+    use ai_core::ToolRouter;
+    use ai_test_utils::MockProvider;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, schemars::JsonSchema)]
+    struct OrderInput {
+        order_id: String,
+    }
+
+    async fn order_status(input: OrderInput) -> String {
+        format!("order {} shipped yesterday", input.order_id)
+    }
+
+    #[tokio::test]
+    async fn test_scripted_scenario() {
+        let provider = MockProvider::new()
+            .text("Sure, what is your order number?")
+            .tool_call("order_status", json!({ "order_id": "A17" }))
+            .text("Your order A17 shipped yesterday.");
+        let agent = Agent::new(provider)
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("order_status", None, order_status)
+                    .with_state(()),
+            )
+            .run_until(StopOnReason::stop_on_finish());
+        let judge = Judge::new(
+            MockProvider::new().text("{\"score\": 0.9, \"reasoning\": \"Resolved quickly\"}"),
+        );
+        let scenario = Scenario::new("order status", "The user learns where their order is")
+            .expect_tool("order_status")
+            .assert(Assertion::contains("shipped"))
+            .rubric(Rubric::new(
+                "The assistant looks the order up before answering",
+            ));
+
+        let user = ScriptedUser::new(["Where is my order?", "It's A17"]);
+        let result = scenario.run(&agent, &user, Some(&judge)).await;
+
+        assert!(result.passed, "{:?}", result);
+        assert_eq!(result.turns.len(), 2);
+        assert_eq!(result.turns[1].tool_calls[0].name, "order_status");
+        assert!(
+            result
+                .transcript()
+                .contains("[tool call order_status {\"order_id\":\"A17\"}]")
+        );
+
+        // A user that never stops runs out of turns and fails the scenario
+        let insistent = |_: &[ScenarioTurn]| Some("Where is my order?".to_string());
+        let agent = Agent::new(
+            MockProvider::new()
+                .text("Let me check.")
+                .text("Still checking."),
+        )
+        .run_until(StopOnReason::stop_on_finish());
+        let result = Scenario::new("stuck", "The user gets an answer")
+            .max_turns(2)
+            .run(&agent, &insistent, None)
+            .await;
+        assert!(!result.finished);
+        assert!(!result.passed);
+        assert_eq!(result.turns.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_model_user_swaps_roles_and_stops() {
+        let provider = MockProvider::new()
+            .text("I need a refund")
+            .text(SIMULATED_USER_DONE);
+        let user = ModelUser::new(provider.clone(), "A customer who wants a refund");
+
+        let first = user.next_message(&[]).await.unwrap();
+        assert_eq!(first.as_deref(), Some("I need a refund"));
+        let turns = [ScenarioTurn {
+            user: "I need a refund".to_string(),
+            agent: "Refund issued.".to_string(),
+            tool_calls: Vec::new(),
+        }];
+        assert_eq!(user.next_message(&turns).await.unwrap(), None);
+
+        let request = &provider.requests()[1];
+        assert_eq!(request.messages[2], Message::assistant("I need a refund"));
+        assert_eq!(request.messages[3], Message::user("Refund issued."));
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use ai_agent::Agent;
+use ai_core::{AgentError, AiError, ChatTextGeneration, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::{
+    assertion::{Assertion, AssertionResult},
+    judge::{Judge, Judgement, Rubric},
+};
+
+/// Something that answers evaluation inputs, usually an `Agent`
+#[async_trait]
+pub trait EvalTarget: Send + Sync {
+    async fn answer(&self, input: &str) -> Result<String>;
+}
+
+/// Each case runs on a fork of the agent, so cases do not see each other's history
+#[async_trait]
+impl<P, S> EvalTarget for Agent<P, S>
+where
+    P: ChatTextGeneration,
+    S: Clone + Send + Sync + 'static,
+{
+    async fn answer(&self, input: &str) -> Result<String> {
+        Ok(self.fork().run(input).await?.text())
+    }
+}
+
+#[async_trait]
+impl<F, Fut> EvalTarget for F
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String>> + Send,
+{
+    async fn answer(&self, input: &str) -> Result<String> {
+        self(input.to_string()).await
+    }
+}
+
+/// One input with the checks its answer must pass
+#[derive(Debug, Clone)]
+pub struct EvalCase {
+    pub name: String,
+    pub input: String,
+    pub assertions: Vec<Assertion>,
+    pub rubric: Option<Rubric>,
+}
+
+impl EvalCase {
+    pub fn new(name: impl Into<String>, input: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            input: input.into(),
+            assertions: Vec::new(),
+            rubric: None,
+        }
+    }
+
+    pub fn assert(mut self, assertion: Assertion) -> Self {
+        self.assertions.push(assertion);
+        self
+    }
+
+    /// Also have the suite's judge score the answer
+    pub fn rubric(mut self, rubric: Rubric) -> Self {
+        self.rubric = Some(rubric);
+        self
+    }
+}
+
+/// Outcome of one case
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub output: Option<String>,
+    /// Set when the target or the judge failed
+    pub error: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+    pub judgement: Option<Judgement>,
+    pub duration: Duration,
+    pub passed: bool,
+}
+
+/// A named set of cases run against one target
+#[derive(Debug, Clone)]
+pub struct EvalSuite {
+    name: String,
+    cases: Vec<EvalCase>,
+    judge: Option<Judge>,
+    concurrency: usize,
+}
+
+impl EvalSuite {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cases: Vec::new(),
+            judge: None,
+            concurrency: 1,
+        }
+    }
+
+    pub fn case(mut self, case: EvalCase) -> Self {
+        self.cases.push(case);
+        self
+    }
+
+    /// Judge scoring cases that have a rubric
+    pub fn judge(mut self, judge: Judge) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
+    /// Number of cases run at the same time (default 1)
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Run every case; failures are recorded in the report rather than returned
+    pub async fn run(&self, target: &impl EvalTarget) -> EvalReport {
+        let results = futures::stream::iter(&self.cases)
+            .map(|case| self.run_case(target, case))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+        EvalReport {
+            suite: self.name.clone(),
+            results,
+        }
+    }
+
+    async fn run_case(&self, target: &impl EvalTarget, case: &EvalCase) -> CaseResult {
+        let started = Instant::now();
+        let mut result = CaseResult {
+            name: case.name.clone(),
+            output: None,
+            error: None,
+            assertions: Vec::new(),
+            judgement: None,
+            duration: Duration::ZERO,
+            passed: false,
+        };
+
+        match target.answer(&case.input).await {
+            Ok(output) => {
+                result.assertions = case
+                    .assertions
+                    .iter()
+                    .map(|assertion| assertion.check(&output))
+                    .collect();
+                if let Some(rubric) = &case.rubric {
+                    match self.grade(&case.input, &output, rubric).await {
+                        Ok(judgement) => result.judgement = Some(judgement),
+                        Err(e) => result.error = Some(e.to_string()),
+                    }
+                }
+                result.output = Some(output);
+            }
+            Err(e) => result.error = Some(e.to_string()),
+        }
+
+        result.passed = result.error.is_none()
+            && result.assertions.iter().all(|assertion| assertion.passed)
+            && result.judgement.as_ref().is_none_or(|j| j.passed);
+        result.duration = started.elapsed();
+        result
+    }
+
+    async fn grade(&self, input: &str, output: &str, rubric: &Rubric) -> Result<Judgement> {
+        let judge = self.judge.as_ref().ok_or_else(|| {
+            AiError::Agent(AgentError::StateError {
+                message: "case has a rubric but the suite has no judge".to_string(),
+            })
+        })?;
+        judge.grade(input, output, rubric).await
+    }
+}
+
+/// Results of a suite run; `Display` prints a summary with one line per case
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub suite: String,
+    pub results: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed).count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.results.iter().filter(|result| !result.passed)
+    }
+
+    pub fn all_passed(&self) -> bool {
+        self.passed() == self.results.len()
+    }
+
+    /// Fraction of cases that passed, 1.0 for an empty suite
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            1.0
+        } else {
+            self.passed() as f64 / self.results.len() as f64
+        }
+    }
+
+    /// Mean judge score over the cases that were judged
+    pub fn mean_score(&self) -> Option<f32> {
+        let scores: Vec<f32> = self
+            .results
+            .iter()
+            .filter_map(|result| result.judgement.as_ref().map(|j| j.score))
+            .collect();
+        (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32)
+    }
+}
+
+impl fmt::Display for EvalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} passed ({:.1}%)",
+            self.suite,
+            self.passed(),
+            self.results.len(),
+            self.pass_rate() * 100.0
+        )?;
+        if let Some(score) = self.mean_score() {
+            write!(f, ", mean judge score {:.2}", score)?;
+        }
+        writeln!(f)?;
+        for result in &self.results {
+            let status = if result.passed { "PASS" } else { "FAIL" };
+            write!(
+                f,
+                "  {} {} ({:.1}s)",
+                status,
+                result.name,
+                result.duration.as_secs_f64()
+            )?;
+            if let Some(error) = &result.error {
+                write!(f, " - error: {}", error)?;
+            }
+            for assertion in result.assertions.iter().filter(|a| !a.passed) {
+                write!(f, " - {}", assertion.assertion)?;
+                if let Some(message) = &assertion.message {
+                    write!(f, ": {}", message)?;
+                }
+            }
+            if let Some(judgement) = result.judgement.as_ref().filter(|j| !j.passed) {
+                write!(
+                    f,
+                    " - judge score {:.2}: {}",
+                    judgement.score, judgement.reasoning
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_test_utils::MockProvider;
+
+    #[tokio::test]
+    async fn test_suite_report() {
+        let judge = Judge::new(
+            MockProvider::new()
+                .text("```json\n{\"score\": 0.4, \"reasoning\": \"Too vague\"}\n```"),
+        );
+        let suite = EvalSuite::new("geography")
+            .judge(judge)
+            .case(
+                EvalCase::new("capital", "Capital of France?").assert(Assertion::contains("Paris")),
+            )
+            .case(EvalCase::new("json", "Capital of France as JSON?").assert(
+                Assertion::json_schema(serde_json::json!({"type": "object", "required": ["city"]})),
+            ))
+            .case(
+                EvalCase::new("explain", "Why is Paris the capital?")
+                    .rubric(Rubric::new("Mentions history").pass_score(0.5)),
+            );
+
+        let target = |input: String| async move {
+            if input.contains("JSON") {
+                Ok("{\"country\": \"France\"}".to_string())
+            } else {
+                Ok("Paris".to_string())
+            }
+        };
+        let report = suite.run(&target).await;
+
+        assert_eq!(report.passed(), 1);
+        assert!(!report.all_passed());
+        assert_eq!(report.mean_score(), Some(0.4));
+        let failures: Vec<&str> = report.failures().map(|r| r.name.as_str()).collect();
+        assert_eq!(failures, vec!["json", "explain"]);
+
+        let summary = report.to_string();
+        assert!(summary.starts_with("geography: 1/3 passed (33.3%), mean judge score 0.40"));
+        assert!(summary.contains("FAIL json"));
+        assert!(summary.contains("Too vague"));
+    }
+}
@@ -0,0 +1,279 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Fields, GenericParam, Generics, Ident, Result, Type, parse_macro_input,
+    parse_quote,
+};
+
+/// Derive `FromToolState` for a struct built from the tool state
+///
+/// Each field is extracted with its own `FromToolState` impl, so extractors compose.
+/// `#[tool_state(state = AppState)]` on the struct fixes the state type, which lets
+/// fields be cloned straight out of it with `#[tool_state(field = name)]`:
+///
+/// ```ignore
+/// #[derive(FromToolState)]
+/// #[tool_state(state = AppState)]
+/// struct Db(#[tool_state(field = pool)] PgPool);
+/// ```
+#[proc_macro_derive(FromToolState, attributes(tool_state))]
+pub fn derive_from_tool_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_tool_state(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `FromToolRequest` for a tool input that also carries state
+///
+/// Fields are read from the input object by name, except those marked
+/// `#[tool_request(state)]`, which are extracted with `FromToolState`. The type must
+/// implement `JsonSchema` to be used as a handler input; hide the state fields from the
+/// schema with `#[schemars(skip)]`. It must not implement `Deserialize`, which already
+/// makes it a `FromToolRequest`.
+///
+/// ```ignore
+/// #[derive(JsonSchema, FromToolRequest)]
+/// struct Query {
+///     sql: String,
+///     #[schemars(skip)]
+///     #[tool_request(state)]
+///     auth: AuthContext,
+/// }
+/// ```
+#[proc_macro_derive(FromToolRequest, attributes(tool_request))]
+pub fn derive_from_tool_request(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_tool_request(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a field of a derived extractor gets its value
+enum Source {
+    /// The field's own `FromToolState` impl
+    Extract,
+    /// A clone of a field of the state
+    StateField(Ident),
+    /// A key of the tool input
+    Input,
+}
+
+/// The state type named by `#[<attr>(state = Type)]` on the struct
+fn state_type(input: &DeriveInput, attr: &str) -> Result<Option<Type>> {
+    let mut state = None;
+    for attribute in input.attrs.iter().filter(|a| a.path().is_ident(attr)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("state") {
+                state = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("expected `{}(state = Type)`", attr)))
+            }
+        })?;
+    }
+    Ok(state)
+}
+
+fn named_or_tuple_fields<'a>(input: &'a DeriveInput, derive: &str) -> Result<&'a Fields> {
+    match &input.data {
+        Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("`{}` can only be derived for structs", derive),
+        )),
+    }
+}
+
+/// Impl generics with the state parameter added when the state type isn't fixed
+fn with_state_param(generics: &Generics, state: &Option<Type>) -> (Generics, Type) {
+    let mut generics = generics.clone();
+    match state {
+        Some(state) => (generics, state.clone()),
+        None => {
+            let param = Ident::new("__S", Span::call_site());
+            generics.params.push(GenericParam::Type(
+                parse_quote!(#param: ::core::clone::Clone + ::core::marker::Send + ::core::marker::Sync + 'static),
+            ));
+            (generics, parse_quote!(#param))
+        }
+    }
+}
+
+/// Build `Self` from per-field expressions, for named, tuple or unit structs
+fn construct(fields: &Fields, values: Vec<TokenStream2>) -> TokenStream2 {
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    }
+}
+
+fn expand_from_tool_state(input: DeriveInput) -> Result<TokenStream2> {
+    let state = state_type(&input, "tool_state")?;
+    let fields = named_or_tuple_fields(&input, "FromToolState")?;
+
+    let mut sources = Vec::new();
+    for field in fields {
+        let mut source = Source::Extract;
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("tool_state"))
+        {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("field") {
+                    source = Source::StateField(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `tool_state(field = name)`"))
+                }
+            })?;
+        }
+        if matches!(source, Source::StateField(_)) && state.is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`tool_state(field = ..)` needs `#[tool_state(state = Type)]` on the struct",
+            ));
+        }
+        sources.push((field, source));
+    }
+
+    let (impl_generics, state_ty) = with_state_param(&input.generics, &state);
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut predicates: Vec<TokenStream2> = where_clause
+        .map(|clause| clause.predicates.iter().map(|p| quote!(#p)).collect())
+        .unwrap_or_default();
+
+    let values = sources
+        .iter()
+        .map(|(field, source)| {
+            let ty = &field.ty;
+            match source {
+                Source::StateField(name) => quote!(::core::clone::Clone::clone(&parts.state.0.#name)),
+                _ => {
+                    predicates.push(quote!(#ty: ::ai_core::tools::FromToolState<#state_ty>));
+                    quote!(<#ty as ::ai_core::tools::FromToolState<#state_ty>>::from_tool_state(parts))
+                }
+            }
+        })
+        .collect();
+    let body = construct(fields, values);
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::ai_core::tools::FromToolState<#state_ty> for #name #ty_generics
+        where
+            #(#predicates,)*
+        {
+            #[allow(unused_variables)]
+            fn from_tool_state(parts: &mut ::ai_core::tools::ToolState<#state_ty>) -> Self {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_from_tool_request(input: DeriveInput) -> Result<TokenStream2> {
+    let state = state_type(&input, "tool_request")?;
+    let fields = named_or_tuple_fields(&input, "FromToolRequest")?;
+    if !matches!(fields, Fields::Named(_)) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`FromToolRequest` can only be derived for structs with named fields",
+        ));
+    }
+
+    let (impl_generics, state_ty) = with_state_param(&input.generics, &state);
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut predicates: Vec<TokenStream2> = where_clause
+        .map(|clause| clause.predicates.iter().map(|p| quote!(#p)).collect())
+        .unwrap_or_default();
+
+    let mut bindings = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        let mut source = Source::Input;
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("tool_request"))
+        {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("state") {
+                    source = Source::Extract;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `tool_request(state)`"))
+                }
+            })?;
+        }
+
+        let ident = field.ident.as_ref().expect("named field");
+        let binding = format_ident!("__field_{}", ident);
+        let key = ident.to_string();
+        let key = key.strip_prefix("r#").unwrap_or(&key);
+        let ty = &field.ty;
+        bindings.push(match source {
+            Source::Input => {
+                predicates.push(quote!(#ty: ::ai_core::__private::serde::de::DeserializeOwned));
+                quote! {
+                    let #binding: #ty = ::ai_core::__private::serde_json::from_value(
+                        object.remove(#key).unwrap_or(::ai_core::__private::serde_json::Value::Null),
+                    )
+                    .map_err(|e| {
+                        ::ai_core::ToolExecutionError::InvalidInput(
+                            ::std::format!("Failed to parse input field `{}`: {}", #key, e),
+                        )
+                    })?;
+                }
+            }
+            _ => {
+                predicates.push(quote!(#ty: ::ai_core::tools::FromToolState<#state_ty>));
+                quote! {
+                    let #binding = <#ty as ::ai_core::tools::FromToolState<#state_ty>>::from_tool_state(
+                        &mut request.parts(),
+                    );
+                }
+            }
+        });
+        values.push(quote!(#binding));
+    }
+    let body = construct(fields, values);
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::ai_core::tools::FromToolRequest<#state_ty> for #name #ty_generics
+        where
+            #(#predicates,)*
+        {
+            #[allow(unused_mut)]
+            fn from_request(
+                request: &mut ::ai_core::tools::ToolRequest<#state_ty>,
+            ) -> ::ai_core::ToolResult<Self> {
+                let mut object = match &request.input {
+                    ::ai_core::__private::serde_json::Value::Object(object) => object.clone(),
+                    ::ai_core::__private::serde_json::Value::Null => {
+                        ::ai_core::__private::serde_json::Map::new()
+                    }
+                    other => {
+                        return ::core::result::Result::Err(
+                            ::ai_core::ToolExecutionError::InvalidInput(::std::format!(
+                                "Failed to parse input: expected an object, got {}",
+                                other
+                            )),
+                        );
+                    }
+                };
+                #(#bindings)*
+                ::core::result::Result::Ok(#body)
+            }
+        }
+    })
+}
@@ -0,0 +1,262 @@
+use ai_core::{
+    AiError, Result, StorageError, ValidationError, message_from_versioned_json,
+    message_to_versioned_json, types::Message,
+};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use crate::store::MessageStore;
+
+const EXTENSION: &str = "jsonl";
+
+/// Conversation store keeping one JSON Lines file per conversation in a directory
+///
+/// Each line holds one serialized message, so appends never rewrite earlier turns.
+/// Lines carry a `schema_version` and older ones are upgraded as they load.
+#[derive(Debug)]
+pub struct JsonFileStore {
+    dir: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl JsonFileStore {
+    /// Use `dir` for conversation files, creating it on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, conversation_id: &str) -> Result<PathBuf> {
+        let valid = !conversation_id.is_empty()
+            && !conversation_id.starts_with('.')
+            && conversation_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(AiError::Validation(ValidationError::InvalidValue {
+                field: "conversation_id".to_string(),
+                message: format!(
+                    "'{}' must be non-empty and contain only letters, digits, '-', '_' or '.'",
+                    conversation_id
+                ),
+            }));
+        }
+        Ok(self.dir.join(format!("{}.{}", conversation_id, EXTENSION)))
+    }
+
+    /// Overwrite the file with serialized lines; callers hold the write lock
+    async fn write_lines(&self, path: &Path, lines: &str) -> Result<()> {
+        // Write to a temporary file and rename so readers never see a partial file
+        fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let tmp = path.with_extension(format!("{}.tmp", EXTENSION));
+        fs::write(&tmp, lines).await.map_err(io_error)?;
+        fs::rename(&tmp, path).await.map_err(io_error)
+    }
+
+    /// Append serialized lines; callers hold the write lock
+    async fn append_lines(&self, path: &Path, lines: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(io_error)?;
+        file.write_all(lines.as_bytes()).await.map_err(io_error)?;
+        file.flush().await.map_err(io_error)
+    }
+}
+
+/// Serialize messages as versioned JSON Lines
+fn to_lines(messages: &[Message]) -> Result<String> {
+    let mut buffer = String::new();
+    for message in messages {
+        buffer.push_str(&message_to_versioned_json(message)?);
+        buffer.push('\n');
+    }
+    Ok(buffer)
+}
+
+fn io_error(err: std::io::Error) -> AiError {
+    AiError::Storage(StorageError::Io {
+        message: err.to_string(),
+    })
+}
+
+#[async_trait]
+impl MessageStore for JsonFileStore {
+    async fn load(&self, conversation_id: &str) -> Result<Vec<Message>> {
+        let path = self.path(conversation_id)?;
+        let contents = match fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(io_error(err)),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(message_from_versioned_json)
+            .collect()
+    }
+
+    async fn append(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        let path = self.path(conversation_id)?;
+        let lines = to_lines(messages)?;
+
+        let _guard = self.write_lock.lock().await;
+        self.append_lines(&path, &lines).await
+    }
+
+    async fn append_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        let path = self.path(conversation_id)?;
+        let lines = to_lines(messages)?;
+
+        let _guard = self.write_lock.lock().await;
+        if self.load(conversation_id).await?.len() != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        self.append_lines(&path, &lines).await
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(io_error(err)),
+        };
+
+        let mut ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some(EXTENSION)
+                && let Some(stem) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                ids.push(stem.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete(&self, conversation_id: &str) -> Result<()> {
+        let path = self.path(conversation_id)?;
+        let _guard = self.write_lock.lock().await;
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(io_error(err)),
+        }
+    }
+
+    async fn replace(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        let path = self.path(conversation_id)?;
+        let lines = to_lines(messages)?;
+
+        let _guard = self.write_lock.lock().await;
+        self.write_lines(&path, &lines).await
+    }
+
+    async fn replace_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        let path = self.path(conversation_id)?;
+        let lines = to_lines(messages)?;
+
+        let _guard = self.write_lock.lock().await;
+        if self.load(conversation_id).await?.len() != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        self.write_lines(&path, &lines).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonFileStore::new(dir.path().join("conversations"));
+        assert!(store.list().await.unwrap().is_empty());
+
+        store
+            .append(
+                "support-42",
+                &[Message::user("hi"), Message::assistant("hello")],
+            )
+            .await
+            .unwrap();
+        store
+            .append("support-42", &[Message::user("bye")])
+            .await
+            .unwrap();
+
+        // A fresh store over the same directory sees the same conversation
+        let reopened = JsonFileStore::new(dir.path().join("conversations"));
+        assert_eq!(
+            reopened.load("support-42").await.unwrap(),
+            vec![
+                Message::user("hi"),
+                Message::assistant("hello"),
+                Message::user("bye")
+            ]
+        );
+        assert_eq!(reopened.list().await.unwrap(), vec!["support-42"]);
+
+        reopened.delete("support-42").await.unwrap();
+        assert!(reopened.load("support-42").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_loads_unversioned_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = serde_json::to_string(&Message::user("hi")).unwrap();
+        std::fs::write(dir.path().join("old.jsonl"), legacy + "\n").unwrap();
+
+        let store = JsonFileStore::new(dir.path());
+        store
+            .append("old", &[Message::assistant("hello")])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.load("old").await.unwrap(),
+            vec![Message::user("hi"), Message::assistant("hello")]
+        );
+        let contents = std::fs::read_to_string(dir.path().join("old.jsonl")).unwrap();
+        assert!(
+            contents
+                .lines()
+                .nth(1)
+                .unwrap()
+                .contains("\"schema_version\":1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_like_ids() {
+        let store = JsonFileStore::new("unused");
+        assert!(store.load("../etc/passwd").await.is_err());
+        assert!(store.append("", &[]).await.is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use ai_core::{
+    Result,
+    normalize::{normalize_messages, validate_alternation},
+    types::*,
+};
+use serde_json::{Value, json};
+
+use crate::store::MessageStore;
+
+/// Metadata key holding a message's tags, a JSON array of strings
+pub const TAGS_METADATA_KEY: &str = "tags";
+
+/// Metadata key holding a message's quality score, a JSON number
+pub const QUALITY_METADATA_KEY: &str = "quality";
+
+/// Layout of a fine-tuning JSONL file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FineTuneFormat {
+    /// OpenAI chat fine-tuning: `{"messages": [...]}` with `tool_calls` and `tool` messages
+    OpenAi,
+    /// Anthropic fine-tuning: `{"system": ..., "messages": [...]}` with alternating turns
+    /// and tool use as content blocks
+    Anthropic,
+}
+
+/// Turns stored conversations into fine-tuning JSONL
+///
+/// Conversations are normalized first; those without an assistant reply or with turns
+/// out of order are skipped. Image parts are left out.
+#[derive(Debug, Clone)]
+pub struct FineTuneExport {
+    format: FineTuneFormat,
+    tags: Vec<String>,
+    min_quality: Option<f64>,
+}
+
+impl FineTuneExport {
+    pub fn new(format: FineTuneFormat) -> Self {
+        Self {
+            format,
+            tags: Vec::new(),
+            min_quality: None,
+        }
+    }
+
+    /// Only export conversations with a message tagged `tag`; several tags must all be present
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Only export conversations whose lowest quality score is at least `min`
+    ///
+    /// Conversations without any score are left out.
+    pub fn min_quality(mut self, min: f64) -> Self {
+        self.min_quality = Some(min);
+        self
+    }
+
+    /// Whether a conversation passes the tag and quality filters
+    pub fn matches(&self, messages: &[Message]) -> bool {
+        let has_tag = |tag: &String| {
+            messages.iter().any(|message| {
+                message
+                    .metadata(TAGS_METADATA_KEY)
+                    .and_then(Value::as_array)
+                    .is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag.as_str())))
+            })
+        };
+        if !self.tags.iter().all(has_tag) {
+            return false;
+        }
+        let Some(min) = self.min_quality else {
+            return true;
+        };
+        messages
+            .iter()
+            .filter_map(|message| message.metadata(QUALITY_METADATA_KEY)?.as_f64())
+            .reduce(f64::min)
+            .is_some_and(|quality| quality >= min)
+    }
+
+    /// The training record for one conversation, ignoring the filters
+    ///
+    /// `None` when the conversation has no assistant reply or its turns are out of order.
+    pub fn record(&self, messages: &[Message]) -> Option<Value> {
+        let messages = normalize_messages(messages.to_vec());
+        let replied = messages
+            .iter()
+            .any(|message| matches!(message, Message::Assistant { .. }));
+        if !replied || validate_alternation(&messages).is_err() {
+            return None;
+        }
+        Some(match self.format {
+            FineTuneFormat::OpenAi => openai_record(&messages),
+            FineTuneFormat::Anthropic => anthropic_record(&messages),
+        })
+    }
+
+    /// Every matching conversation in `store` as JSON Lines, in the store's listing order
+    pub async fn export<S: MessageStore + ?Sized>(&self, store: &S) -> Result<String> {
+        let mut lines = String::new();
+        for conversation_id in store.list().await? {
+            let messages = store.load(&conversation_id).await?;
+            if !self.matches(&messages) {
+                continue;
+            }
+            if let Some(record) = self.record(&messages) {
+                lines.push_str(&record.to_string());
+                lines.push('\n');
+            }
+        }
+        Ok(lines)
+    }
+}
+
+fn result_text(result: &ToolResult) -> String {
+    match &result.result {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn user_text(content: &[UserContent]) -> String {
+    content
+        .iter()
+        .filter_map(|part| match part {
+            UserContent::Text { text } => Some(text.as_str()),
+            UserContent::Image { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn system_text(content: &[SystemContent]) -> String {
+    content
+        .iter()
+        .map(|part| match part {
+            SystemContent::Text { text } => text.as_str(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn openai_record(messages: &[Message]) -> Value {
+    let mut out = Vec::new();
+    for message in messages {
+        match message {
+            Message::System { content, .. } => {
+                out.push(json!({ "role": "system", "content": system_text(content) }));
+            }
+            Message::User { content, .. } => {
+                out.push(json!({ "role": "user", "content": user_text(content) }));
+            }
+            Message::Assistant { content, .. } => {
+                let mut text = Vec::new();
+                let mut tool_calls = Vec::new();
+                for part in content {
+                    match part {
+                        AssistantContent::Text { text: t } => text.push(t.as_str()),
+                        AssistantContent::ToolCall { tool_call } => tool_calls.push(json!({
+                            "id": tool_call.id,
+                            "type": "function",
+                            "function": {
+                                "name": tool_call.name,
+                                "arguments": tool_call.arguments.to_string(),
+                            },
+                        })),
+                    }
+                }
+                let mut entry = json!({ "role": "assistant", "content": text.join("\n") });
+                if !tool_calls.is_empty() {
+                    entry["tool_calls"] = Value::Array(tool_calls);
+                }
+                out.push(entry);
+            }
+            Message::Tool { tool_results, .. } => {
+                for result in tool_results {
+                    out.push(json!({
+                        "role": "tool",
+                        "tool_call_id": result.tool_call_id,
+                        "content": result_text(result),
+                    }));
+                }
+            }
+        }
+    }
+    json!({ "messages": out })
+}
+
+fn anthropic_record(messages: &[Message]) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = match message {
+            Message::System { content, .. } => {
+                system.push(system_text(content));
+                continue;
+            }
+            Message::User { content, .. } => {
+                let text = user_text(content);
+                ("user", vec![json!({ "type": "text", "text": text })])
+            }
+            Message::Assistant { content, .. } => {
+                let blocks = content
+                    .iter()
+                    .map(|part| match part {
+                        AssistantContent::Text { text } => json!({ "type": "text", "text": text }),
+                        AssistantContent::ToolCall { tool_call } => json!({
+                            "type": "tool_use",
+                            "id": tool_call.id,
+                            "name": tool_call.name,
+                            "input": tool_call.arguments,
+                        }),
+                    })
+                    .collect();
+                ("assistant", blocks)
+            }
+            Message::Tool { tool_results, .. } => {
+                let blocks = tool_results
+                    .iter()
+                    .map(|result| {
+                        json!({
+                            "type": "tool_result",
+                            "tool_use_id": result.tool_call_id,
+                            "content": result_text(result),
+                            "is_error": result.is_error,
+                        })
+                    })
+                    .collect();
+                ("user", blocks)
+            }
+        };
+        // Tool results and the user text after them form one user turn
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => content.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let messages: Vec<Value> = turns
+        .into_iter()
+        .map(|(role, mut blocks)| {
+            let content = match blocks.as_mut_slice() {
+                [block] if block["type"] == "text" => block["text"].take(),
+                _ => Value::Array(blocks),
+            };
+            json!({ "role": role, "content": content })
+        })
+        .collect();
+    let mut record = json!({ "messages": messages });
+    if !system.is_empty() {
+        record["system"] = Value::String(system.join("\n\n"));
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStore;
+
+    fn conversation(quality: f64) -> Vec<Message> {
+        vec![
+            Message::system("Be brief"),
+            Message::user("Weather in Paris?")
+                .with_metadata(TAGS_METADATA_KEY, json!(["approved"])),
+            Message::Assistant {
+                content: vec![AssistantContent::ToolCall {
+                    tool_call: ToolCall {
+                        id: "call_1".to_string(),
+                        name: "weather".to_string(),
+                        arguments: json!({ "city": "Paris" }),
+                    },
+                }],
+                metadata: None,
+            },
+            Message::tool(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: json!("Sunny"),
+                is_error: false,
+                content: Vec::new(),
+            }),
+            Message::assistant("Sunny.").with_metadata(QUALITY_METADATA_KEY, json!(quality)),
+        ]
+    }
+
+    #[test]
+    fn test_records_in_both_formats() {
+        let openai = FineTuneExport::new(FineTuneFormat::OpenAi)
+            .record(&conversation(1.0))
+            .unwrap();
+        let messages = openai["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Paris\"}"
+        );
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["content"], "Sunny");
+
+        let anthropic = FineTuneExport::new(FineTuneFormat::Anthropic)
+            .record(&conversation(1.0))
+            .unwrap();
+        assert_eq!(anthropic["system"], "Be brief");
+        let messages = anthropic["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["content"], "Weather in Paris?");
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(messages[3]["content"], "Sunny.");
+
+        let unanswered = [Message::user("Hello?")];
+        assert!(
+            FineTuneExport::new(FineTuneFormat::OpenAi)
+                .record(&unanswered)
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_filters_by_tag_and_quality() {
+        let store = InMemoryStore::new();
+        store.append("good", &conversation(0.9)).await.unwrap();
+        store.append("poor", &conversation(0.2)).await.unwrap();
+        store
+            .append(
+                "untagged",
+                &[Message::user("Hi"), Message::assistant("Hello")],
+            )
+            .await
+            .unwrap();
+
+        let export = FineTuneExport::new(FineTuneFormat::OpenAi);
+        assert_eq!(export.export(&store).await.unwrap().lines().count(), 3);
+
+        let export = export.tag("approved").min_quality(0.5);
+        let jsonl = export.export(&store).await.unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.contains("Paris"));
+    }
+}
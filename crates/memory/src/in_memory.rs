@@ -0,0 +1,141 @@
+use ai_core::{AiError, Result, StorageError, types::Message};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use crate::store::MessageStore;
+
+/// Process-local conversation store, mainly for tests and single-process apps
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    conversations: RwLock<HashMap<String, Vec<Message>>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MessageStore for InMemoryStore {
+    async fn load(&self, conversation_id: &str) -> Result<Vec<Message>> {
+        Ok(self
+            .conversations
+            .read()
+            .await
+            .get(conversation_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn append(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        self.conversations
+            .write()
+            .await
+            .entry(conversation_id.to_string())
+            .or_default()
+            .extend_from_slice(messages);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = self.conversations.read().await.keys().cloned().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete(&self, conversation_id: &str) -> Result<()> {
+        self.conversations.write().await.remove(conversation_id);
+        Ok(())
+    }
+
+    async fn append_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        let mut conversations = self.conversations.write().await;
+        let conversation = conversations
+            .entry(conversation_id.to_string())
+            .or_default();
+        if conversation.len() != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        conversation.extend_from_slice(messages);
+        Ok(())
+    }
+
+    async fn replace(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        self.conversations
+            .write()
+            .await
+            .insert(conversation_id.to_string(), messages.to_vec());
+        Ok(())
+    }
+
+    async fn replace_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        let mut conversations = self.conversations.write().await;
+        let len = conversations.get(conversation_id).map_or(0, Vec::len);
+        if len != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        conversations.insert(conversation_id.to_string(), messages.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_append_and_load() {
+        let store = InMemoryStore::new();
+        assert!(store.load("chat").await.unwrap().is_empty());
+
+        store.append("chat", &[Message::user("hi")]).await.unwrap();
+        store
+            .append("chat", &[Message::assistant("hello")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.load("chat").await.unwrap(),
+            vec![Message::user("hi"), Message::assistant("hello")]
+        );
+        assert_eq!(store.list().await.unwrap(), vec!["chat".to_string()]);
+
+        // A writer that saw an older version of the conversation is rejected
+        let stale = store
+            .append_if_len("chat", 1, &[Message::user("late")])
+            .await;
+        assert!(matches!(
+            stale,
+            Err(AiError::Storage(StorageError::Conflict { .. }))
+        ));
+
+        // Rewrites are checked the same way
+        let summary = [Message::system("Summary: greeted")];
+        let stale = store.replace_if_len("chat", 1, &summary).await;
+        assert!(matches!(
+            stale,
+            Err(AiError::Storage(StorageError::Conflict { .. }))
+        ));
+        store.replace_if_len("chat", 2, &summary).await.unwrap();
+        assert_eq!(store.load("chat").await.unwrap(), summary);
+
+        store.delete("chat").await.unwrap();
+        assert!(store.list().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file;
+pub mod finetune;
+pub mod in_memory;
+#[cfg(feature = "pgvector")]
+pub mod pgvector;
+#[cfg(feature = "qdrant")]
+pub mod qdrant;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod store;
+pub mod vector;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::*;
+pub use finetune::*;
+pub use in_memory::*;
+#[cfg(feature = "pgvector")]
+pub use pgvector::*;
+#[cfg(feature = "qdrant")]
+pub use qdrant::*;
+#[cfg(feature = "redis")]
+pub use redis::*;
+pub use store::*;
+pub use vector::*;
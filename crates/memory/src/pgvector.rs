@@ -0,0 +1,365 @@
+use ai_core::{AiError, Result, StorageError, ValidationError};
+use async_trait::async_trait;
+use pgvector::Vector;
+use serde_json::Value as JsonValue;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, postgres::PgPoolOptions, types::Json};
+use std::collections::HashMap;
+
+use crate::vector::{MetadataFilter, ScoredRecord, VectorRecord, VectorStore};
+
+/// Rows per upsert statement; each row uses four bind parameters
+const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// HNSW index parameters, see the pgvector documentation for tuning advice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HnswOptions {
+    /// Maximum connections per layer
+    pub m: u32,
+    /// Size of the candidate list while building the index
+    pub ef_construction: u32,
+}
+
+impl Default for HnswOptions {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 64,
+        }
+    }
+}
+
+impl HnswOptions {
+    pub fn m(mut self, m: u32) -> Self {
+        self.m = m;
+        self
+    }
+
+    pub fn ef_construction(mut self, ef_construction: u32) -> Self {
+        self.ef_construction = ef_construction;
+        self
+    }
+}
+
+/// Vector store backed by a PostgreSQL table using the pgvector extension
+///
+/// Each record is a row of `(id TEXT PRIMARY KEY, embedding vector(n), text TEXT,
+/// metadata JSONB)`. Similarity is cosine, and `MetadataFilter`s become `WHERE` clauses
+/// on the `metadata` column.
+#[derive(Debug, Clone)]
+pub struct PgVectorStore {
+    pool: PgPool,
+    table: String,
+    batch_size: usize,
+    ef_search: Option<u32>,
+}
+
+fn pg_error(err: sqlx::Error) -> AiError {
+    AiError::Storage(StorageError::Backend {
+        backend: "postgres".to_string(),
+        message: err.to_string(),
+    })
+}
+
+/// Check a table name, optionally schema-qualified, before it is spliced into SQL
+fn validate_table(table: &str) -> Result<()> {
+    let valid = table.split('.').count() <= 2
+        && table.split('.').all(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(AiError::Validation(ValidationError::InvalidValue {
+            field: "table".to_string(),
+            message: format!("'{}' is not a valid table name", table),
+        }))
+    }
+}
+
+impl PgVectorStore {
+    /// Connect to the database at `url`, e.g. `postgres://localhost/app`
+    pub async fn connect(url: &str, table: impl Into<String>) -> Result<Self> {
+        let pool = PgPoolOptions::new().connect(url).await.map_err(pg_error)?;
+        Self::new(pool, table)
+    }
+
+    pub fn new(pool: PgPool, table: impl Into<String>) -> Result<Self> {
+        let table = table.into();
+        validate_table(&table)?;
+        Ok(Self {
+            pool,
+            table,
+            batch_size: DEFAULT_BATCH_SIZE,
+            ef_search: None,
+        })
+    }
+
+    /// Number of records written per upsert statement
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, u16::MAX as usize / 4);
+        self
+    }
+
+    /// Size of the HNSW candidate list at query time; higher is more accurate but slower
+    pub fn with_ef_search(mut self, ef_search: u32) -> Self {
+        self.ef_search = Some(ef_search);
+        self
+    }
+
+    /// Get the underlying pool
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Create the pgvector extension and the table if they do not exist yet
+    pub async fn ensure_schema(&self, dimensions: u32) -> Result<()> {
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             id TEXT PRIMARY KEY, \
+             embedding vector({}) NOT NULL, \
+             text TEXT NOT NULL, \
+             metadata JSONB NOT NULL DEFAULT '{{}}')",
+            self.table, dimensions
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(pg_error)?;
+        Ok(())
+    }
+
+    /// Create an HNSW index for cosine distance on the embedding column if it does not exist yet
+    pub async fn ensure_hnsw_index(&self, options: HnswOptions) -> Result<()> {
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {} USING hnsw (embedding vector_cosine_ops) \
+             WITH (m = {}, ef_construction = {})",
+            self.index_name(),
+            self.table,
+            options.m,
+            options.ef_construction
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(pg_error)?;
+        Ok(())
+    }
+
+    /// Create a GIN index on the metadata column to speed up filtered queries
+    pub async fn ensure_metadata_index(&self) -> Result<()> {
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {}_metadata_idx ON {} USING gin (metadata)",
+            self.table.replace('.', "_"),
+            self.table
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(pg_error)?;
+        Ok(())
+    }
+
+    fn index_name(&self) -> String {
+        format!("{}_embedding_idx", self.table.replace('.', "_"))
+    }
+}
+
+/// Append `filter` as a boolean SQL expression on the `metadata` column
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &MetadataFilter) {
+    match filter {
+        MetadataFilter::Eq { key, value } => {
+            builder.push("metadata -> ");
+            builder.push_bind(key.clone());
+            builder.push(" = ");
+            builder.push_bind(Json(value.clone()));
+        }
+        MetadataFilter::In { key, values } => {
+            builder.push("metadata -> ");
+            builder.push_bind(key.clone());
+            builder.push(" = ANY(");
+            builder.push_bind(values.iter().cloned().map(Json).collect::<Vec<_>>());
+            builder.push(")");
+        }
+        MetadataFilter::Exists { key } => {
+            builder.push("metadata ? ");
+            builder.push_bind(key.clone());
+        }
+        MetadataFilter::And(filters) => push_group(builder, filters, " AND ", "TRUE"),
+        MetadataFilter::Or(filters) => push_group(builder, filters, " OR ", "FALSE"),
+        MetadataFilter::Not(filter) => {
+            builder.push("NOT (");
+            push_filter(builder, filter);
+            builder.push(")");
+        }
+    }
+}
+
+fn push_group(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filters: &[MetadataFilter],
+    separator: &str,
+    empty: &str,
+) {
+    if filters.is_empty() {
+        builder.push(empty);
+        return;
+    }
+    builder.push("(");
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            builder.push(separator);
+        }
+        builder.push("(");
+        push_filter(builder, filter);
+        builder.push(")");
+    }
+    builder.push(")");
+}
+
+/// Nearest-neighbour query; the vector is bound first so `ORDER BY` can reuse `$1`
+fn search_query<'a>(
+    table: &str,
+    vector: &[f32],
+    top_k: usize,
+    filter: Option<&MetadataFilter>,
+) -> QueryBuilder<'a, Postgres> {
+    let mut builder =
+        QueryBuilder::new("SELECT id, embedding, text, metadata, 1 - (embedding <=> ");
+    builder.push_bind(Vector::from(vector.to_vec()));
+    builder.push(format!(") AS score FROM {}", table));
+    if let Some(filter) = filter {
+        builder.push(" WHERE ");
+        push_filter(&mut builder, filter);
+    }
+    builder.push(" ORDER BY embedding <=> $1 LIMIT ");
+    builder.push_bind(top_k as i64);
+    builder
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await.map_err(pg_error)?;
+        for chunk in records.chunks(self.batch_size) {
+            let mut builder = QueryBuilder::<Postgres>::new(format!(
+                "INSERT INTO {} (id, embedding, text, metadata) ",
+                self.table
+            ));
+            builder.push_values(chunk, |mut row, record| {
+                row.push_bind(record.id.clone())
+                    .push_bind(Vector::from(record.vector.clone()))
+                    .push_bind(record.text.clone())
+                    .push_bind(Json(record.metadata.clone()));
+            });
+            builder.push(
+                " ON CONFLICT (id) DO UPDATE SET embedding = EXCLUDED.embedding, \
+                 text = EXCLUDED.text, metadata = EXCLUDED.metadata",
+            );
+            builder.build().execute(&mut *tx).await.map_err(pg_error)?;
+        }
+        tx.commit().await.map_err(pg_error)
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<ScoredRecord>> {
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+        let mut builder = search_query(&self.table, vector, top_k, filter);
+        let rows = match self.ef_search {
+            Some(ef_search) => {
+                // SET LOCAL only lasts until the end of the transaction
+                let mut tx = self.pool.begin().await.map_err(pg_error)?;
+                sqlx::query(&format!("SET LOCAL hnsw.ef_search = {}", ef_search))
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(pg_error)?;
+                let rows = builder
+                    .build()
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(pg_error)?;
+                tx.commit().await.map_err(pg_error)?;
+                rows
+            }
+            None => builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(pg_error)?,
+        };
+
+        rows.into_iter()
+            .map(|row| {
+                let embedding: Vector = row.try_get("embedding").map_err(pg_error)?;
+                let Json(metadata): Json<HashMap<String, JsonValue>> =
+                    row.try_get("metadata").map_err(pg_error)?;
+                let score: f64 = row.try_get("score").map_err(pg_error)?;
+                Ok(ScoredRecord {
+                    record: VectorRecord {
+                        id: row.try_get("id").map_err(pg_error)?,
+                        vector: embedding.to_vec(),
+                        text: row.try_get("text").map_err(pg_error)?,
+                        metadata,
+                    },
+                    score: score as f32,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query(&format!("DELETE FROM {} WHERE id = ANY($1)", self.table))
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_table() {
+        assert!(validate_table("embeddings").is_ok());
+        assert!(validate_table("rag.documents_v2").is_ok());
+        assert!(validate_table("").is_err());
+        assert!(validate_table("1docs").is_err());
+        assert!(validate_table("docs; DROP TABLE users").is_err());
+        assert!(validate_table("a.b.c").is_err());
+    }
+
+    #[test]
+    fn test_search_query_sql() {
+        let filter = MetadataFilter::eq("user", "alice")
+            .and(MetadataFilter::Not(Box::new(MetadataFilter::exists(
+                "archived",
+            ))))
+            .and(MetadataFilter::Or(Vec::new()));
+        let builder = search_query("docs", &[1.0, 0.0], 3, Some(&filter));
+        assert_eq!(
+            builder.sql(),
+            "SELECT id, embedding, text, metadata, 1 - (embedding <=> $1) AS score FROM docs \
+             WHERE ((metadata -> $2 = $3) AND (NOT (metadata ? $4)) AND (FALSE)) \
+             ORDER BY embedding <=> $1 LIMIT $5"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use ai_core::{AiError, Result, StorageError, ValidationError};
+use async_trait::async_trait;
+use qdrant_client::{
+    Payload, Qdrant, QdrantError,
+    qdrant::{
+        Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, PointId,
+        PointStruct, PointsIdsList, SearchPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+    },
+};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use crate::vector::{MetadataFilter, ScoredRecord, VectorRecord, VectorStore};
+
+/// Payload field holding the record ID
+pub const QDRANT_ID_FIELD: &str = "_ai_id";
+/// Payload field holding the record text
+pub const QDRANT_TEXT_FIELD: &str = "_ai_text";
+
+/// Vector store backed by a Qdrant collection
+///
+/// Record metadata is stored as top-level payload fields, so `MetadataFilter`s translate
+/// to Qdrant payload filters. Qdrant point IDs must be integers or UUIDs, so each record
+/// ID is mapped to a stable UUID and kept in the `_ai_id` field. Query results do not
+/// include vectors.
+pub struct QdrantStore {
+    client: Qdrant,
+    collection: String,
+    batch_size: usize,
+}
+
+impl std::fmt::Debug for QdrantStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QdrantStore")
+            .field("collection", &self.collection)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+fn qdrant_error(err: QdrantError) -> AiError {
+    AiError::Storage(StorageError::Backend {
+        backend: "qdrant".to_string(),
+        message: err.to_string(),
+    })
+}
+
+fn unsupported_filter(message: &str) -> AiError {
+    AiError::Validation(ValidationError::InvalidValue {
+        field: "filter".to_string(),
+        message: format!("qdrant {}", message),
+    })
+}
+
+impl QdrantStore {
+    /// Connect to the Qdrant gRPC endpoint at `url`, e.g. `http://localhost:6334`
+    pub fn connect(url: &str, collection: impl Into<String>) -> Result<Self> {
+        let client = Qdrant::from_url(url).build().map_err(qdrant_error)?;
+        Ok(Self::new(client, collection))
+    }
+
+    pub fn new(client: Qdrant, collection: impl Into<String>) -> Self {
+        Self {
+            client,
+            collection: collection.into(),
+            batch_size: 256,
+        }
+    }
+
+    /// Number of points sent per upsert request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Create the collection with cosine distance if it does not exist yet
+    pub async fn ensure_collection(&self, dimensions: u64) -> Result<()> {
+        let exists = self
+            .client
+            .collection_exists(&self.collection)
+            .await
+            .map_err(qdrant_error)?;
+        if !exists {
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(&self.collection)
+                        .vectors_config(VectorParamsBuilder::new(dimensions, Distance::Cosine)),
+                )
+                .await
+                .map_err(qdrant_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Map a record ID to a stable UUID using 128-bit FNV-1a
+fn point_id(id: &str) -> PointId {
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    for byte in id.bytes() {
+        hash ^= byte as u128;
+        hash = hash.wrapping_mul(0x0000000001000000000000000000013b);
+    }
+    let hex = format!("{:032x}", hash);
+    PointId::from(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+fn to_condition(filter: &MetadataFilter) -> Result<Condition> {
+    Ok(match filter {
+        MetadataFilter::Eq { key, value } => match value {
+            JsonValue::String(value) => Condition::matches(key, value.clone()),
+            JsonValue::Bool(value) => Condition::matches(key, *value),
+            JsonValue::Number(number) => match number.as_i64() {
+                Some(value) => Condition::matches(key, value),
+                None => return Err(unsupported_filter("only matches integer numbers")),
+            },
+            _ => {
+                return Err(unsupported_filter(
+                    "only matches strings, integers and booleans",
+                ));
+            }
+        },
+        MetadataFilter::In { key, values } => {
+            if let Some(strings) = values
+                .iter()
+                .map(|value| value.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+            {
+                Condition::matches(key, strings)
+            } else if let Some(integers) = values
+                .iter()
+                .map(JsonValue::as_i64)
+                .collect::<Option<Vec<_>>>()
+            {
+                Condition::matches(key, integers)
+            } else {
+                return Err(unsupported_filter(
+                    "only matches lists of strings or lists of integers",
+                ));
+            }
+        }
+        MetadataFilter::Exists { key } => Filter::must_not([Condition::is_empty(key)]).into(),
+        MetadataFilter::And(filters) => Filter::must(to_conditions(filters)?).into(),
+        MetadataFilter::Or(filters) => Filter::should(to_conditions(filters)?).into(),
+        MetadataFilter::Not(filter) => Filter::must_not([to_condition(filter)?]).into(),
+    })
+}
+
+fn to_conditions(filters: &[MetadataFilter]) -> Result<Vec<Condition>> {
+    filters.iter().map(to_condition).collect()
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let points: Vec<PointStruct> = records
+            .into_iter()
+            .map(|record| {
+                let mut payload: HashMap<String, JsonValue> = record.metadata;
+                payload.insert(QDRANT_ID_FIELD.to_string(), record.id.clone().into());
+                payload.insert(QDRANT_TEXT_FIELD.to_string(), record.text.into());
+                PointStruct::new(point_id(&record.id), record.vector, Payload::from(payload))
+            })
+            .collect();
+
+        self.client
+            .upsert_points_chunked(
+                UpsertPointsBuilder::new(&self.collection, points).wait(true),
+                self.batch_size,
+            )
+            .await
+            .map_err(qdrant_error)?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<ScoredRecord>> {
+        let mut request = SearchPointsBuilder::new(&self.collection, vector.to_vec(), top_k as u64)
+            .with_payload(true);
+        if let Some(filter) = filter {
+            request = request.filter(Filter::must([to_condition(filter)?]));
+        }
+        let response = self
+            .client
+            .search_points(request)
+            .await
+            .map_err(qdrant_error)?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .map(|point| {
+                let mut metadata: HashMap<String, JsonValue> =
+                    serde_json::Map::from(Payload::from(point.payload))
+                        .into_iter()
+                        .collect();
+                let id = match metadata.remove(QDRANT_ID_FIELD) {
+                    Some(JsonValue::String(id)) => id,
+                    _ => String::new(),
+                };
+                let text = match metadata.remove(QDRANT_TEXT_FIELD) {
+                    Some(JsonValue::String(text)) => text,
+                    _ => String::new(),
+                };
+                ScoredRecord {
+                    record: VectorRecord {
+                        id,
+                        vector: Vec::new(),
+                        text,
+                        metadata,
+                    },
+                    score: point.score,
+                }
+            })
+            .collect())
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let ids = ids.iter().map(|id| point_id(id)).collect();
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.collection)
+                    .points(PointsIdsList { ids })
+                    .wait(true),
+            )
+            .await
+            .map_err(qdrant_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_ids_are_stable_uuids() {
+        assert_eq!(point_id("doc-1"), point_id("doc-1"));
+        assert_ne!(point_id("doc-1"), point_id("doc-2"));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_filters() {
+        assert!(to_condition(&MetadataFilter::eq("user", "alice")).is_ok());
+        assert!(to_condition(&MetadataFilter::eq("score", 0.5)).is_err());
+        assert!(
+            to_condition(&MetadataFilter::is_in(
+                "tag",
+                vec![JsonValue::from("a"), JsonValue::from(1)]
+            ))
+            .is_err()
+        );
+    }
+}
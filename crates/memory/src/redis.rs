@@ -0,0 +1,244 @@
+use ai_core::{
+    AiError, Result, StorageError, message_from_versioned_json, message_to_versioned_json,
+    types::Message,
+};
+use async_trait::async_trait;
+use redis::{AsyncCommands, Client, Script, aio::ConnectionManager};
+use std::time::Duration;
+
+use crate::store::MessageStore;
+
+/// Appends when the list still has the expected length, then refreshes the TTL
+///
+/// KEYS[1] = conversation key; ARGV[1] = expected length (-1 to skip the check),
+/// ARGV[2] = TTL in seconds (0 for none), ARGV[3..] = serialized messages.
+const APPEND_SCRIPT: &str = r#"
+local len = redis.call('LLEN', KEYS[1])
+local expected = tonumber(ARGV[1])
+if expected >= 0 and len ~= expected then
+    return -1
+end
+for i = 3, #ARGV do
+    redis.call('RPUSH', KEYS[1], ARGV[i])
+end
+local ttl = tonumber(ARGV[2])
+if ttl > 0 and #ARGV > 2 then
+    redis.call('EXPIRE', KEYS[1], ttl)
+end
+return len + #ARGV - 2
+"#;
+
+/// Replaces the whole list atomically when it still has the expected length; same
+/// arguments as the append script
+const REPLACE_SCRIPT: &str = r#"
+local expected = tonumber(ARGV[1])
+if expected >= 0 and redis.call('LLEN', KEYS[1]) ~= expected then
+    return -1
+end
+redis.call('DEL', KEYS[1])
+for i = 3, #ARGV do
+    redis.call('RPUSH', KEYS[1], ARGV[i])
+end
+local ttl = tonumber(ARGV[2])
+if ttl > 0 and #ARGV > 2 then
+    redis.call('EXPIRE', KEYS[1], ttl)
+end
+return #ARGV - 2
+"#;
+
+/// Conversation store backed by Redis lists, for sharing conversations between instances
+///
+/// Each conversation is a list of JSON messages under `{prefix}:conversation:{id}`.
+/// Writes are atomic Lua scripts, so `append_if_len` and `replace_if_len` give optimistic
+/// locking across processes, and every write refreshes the conversation's TTL.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    prefix: String,
+    ttl: Option<Duration>,
+}
+
+impl std::fmt::Debug for RedisStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisStore")
+            .field("prefix", &self.prefix)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+fn redis_error(err: redis::RedisError) -> AiError {
+    AiError::Storage(StorageError::Backend {
+        backend: "redis".to_string(),
+        message: err.to_string(),
+    })
+}
+
+impl RedisStore {
+    /// Connect to the Redis server at `url`, e.g. `redis://127.0.0.1/`
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = Client::open(url).map_err(redis_error)?;
+        Self::new(client).await
+    }
+
+    pub async fn new(client: Client) -> Result<Self> {
+        let connection = ConnectionManager::new(client).await.map_err(redis_error)?;
+        Ok(Self {
+            connection,
+            prefix: "ai".to_string(),
+            ttl: None,
+        })
+    }
+
+    /// Namespace for all keys written by this store
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Expire conversations after this long without writes
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set the expiry of a single conversation, overriding the store default until its next write
+    pub async fn expire(&self, conversation_id: &str, ttl: Duration) -> Result<()> {
+        let mut connection = self.connection.clone();
+        let _: bool = connection
+            .expire(self.key(conversation_id), ttl.as_secs().max(1) as i64)
+            .await
+            .map_err(redis_error)?;
+        Ok(())
+    }
+
+    fn key(&self, conversation_id: &str) -> String {
+        format!("{}:conversation:{}", self.prefix, conversation_id)
+    }
+
+    fn ttl_secs(&self) -> u64 {
+        self.ttl.map(|ttl| ttl.as_secs().max(1)).unwrap_or(0)
+    }
+
+    /// Run the append or replace script, which take the same arguments
+    async fn run_write(
+        &self,
+        script: &str,
+        conversation_id: &str,
+        expected_len: Option<usize>,
+        messages: &[Message],
+    ) -> Result<()> {
+        let script = Script::new(script);
+        let mut invocation = script.prepare_invoke();
+        invocation
+            .key(self.key(conversation_id))
+            .arg(expected_len.map(|len| len as i64).unwrap_or(-1))
+            .arg(self.ttl_secs());
+        for message in messages {
+            invocation.arg(message_to_versioned_json(message)?);
+        }
+
+        let mut connection = self.connection.clone();
+        let len: i64 = invocation
+            .invoke_async(&mut connection)
+            .await
+            .map_err(redis_error)?;
+        if len < 0 {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageStore for RedisStore {
+    async fn load(&self, conversation_id: &str) -> Result<Vec<Message>> {
+        let mut connection = self.connection.clone();
+        let items: Vec<String> = connection
+            .lrange(self.key(conversation_id), 0, -1)
+            .await
+            .map_err(redis_error)?;
+        items
+            .iter()
+            .map(|item| message_from_versioned_json(item))
+            .collect()
+    }
+
+    async fn append(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        self.run_write(APPEND_SCRIPT, conversation_id, None, messages)
+            .await
+    }
+
+    async fn append_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        self.run_write(APPEND_SCRIPT, conversation_id, Some(expected_len), messages)
+            .await
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let pattern = format!("{}:conversation:*", self.prefix);
+        let key_prefix = format!("{}:conversation:", self.prefix);
+
+        let mut connection = self.connection.clone();
+        let mut ids = Vec::new();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut connection)
+                .await
+                .map_err(redis_error)?;
+            ids.extend(
+                keys.iter()
+                    .filter_map(|key| key.strip_prefix(&key_prefix))
+                    .map(str::to_string),
+            );
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    async fn delete(&self, conversation_id: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        let _: i64 = connection
+            .del(self.key(conversation_id))
+            .await
+            .map_err(redis_error)?;
+        Ok(())
+    }
+
+    async fn replace(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        self.run_write(REPLACE_SCRIPT, conversation_id, None, messages)
+            .await
+    }
+
+    async fn replace_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        self.run_write(
+            REPLACE_SCRIPT,
+            conversation_id,
+            Some(expected_len),
+            messages,
+        )
+        .await
+    }
+}
@@ -0,0 +1,107 @@
+use ai_core::{AiError, Result, StorageError, types::Message};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Persistent storage for conversations, keyed by conversation ID
+#[async_trait]
+pub trait MessageStore: Send + Sync {
+    /// Load all messages of a conversation, or an empty list if it does not exist
+    async fn load(&self, conversation_id: &str) -> Result<Vec<Message>>;
+
+    /// Append messages to the end of a conversation, creating it if needed
+    async fn append(&self, conversation_id: &str, messages: &[Message]) -> Result<()>;
+
+    /// List the IDs of all stored conversations
+    async fn list(&self) -> Result<Vec<String>>;
+
+    /// Remove a conversation; removing a missing conversation is not an error
+    async fn delete(&self, conversation_id: &str) -> Result<()>;
+
+    /// Append only if the conversation still holds `expected_len` messages
+    ///
+    /// Fails with `StorageError::Conflict` when another writer got there first. Backends
+    /// that can check and append atomically override this; the default checks and then
+    /// appends, which is only safe with a single writer.
+    async fn append_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        if self.load(conversation_id).await?.len() != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        self.append(conversation_id, messages).await
+    }
+
+    /// Overwrite a conversation, e.g. after it was compacted
+    async fn replace(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        self.delete(conversation_id).await?;
+        self.append(conversation_id, messages).await
+    }
+
+    /// Overwrite only if the conversation still holds `expected_len` messages
+    ///
+    /// The rewriting counterpart of `append_if_len`, with the same `StorageError::Conflict`
+    /// and the same single-writer caveat for the default implementation.
+    async fn replace_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        if self.load(conversation_id).await?.len() != expected_len {
+            return Err(AiError::Storage(StorageError::Conflict {
+                conversation_id: conversation_id.to_string(),
+            }));
+        }
+        self.replace(conversation_id, messages).await
+    }
+}
+
+#[async_trait]
+impl<T: MessageStore + ?Sized> MessageStore for Arc<T> {
+    async fn load(&self, conversation_id: &str) -> Result<Vec<Message>> {
+        (**self).load(conversation_id).await
+    }
+
+    async fn append(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        (**self).append(conversation_id, messages).await
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        (**self).list().await
+    }
+
+    async fn delete(&self, conversation_id: &str) -> Result<()> {
+        (**self).delete(conversation_id).await
+    }
+
+    async fn append_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        (**self)
+            .append_if_len(conversation_id, expected_len, messages)
+            .await
+    }
+
+    async fn replace(&self, conversation_id: &str, messages: &[Message]) -> Result<()> {
+        (**self).replace(conversation_id, messages).await
+    }
+
+    async fn replace_if_len(
+        &self,
+        conversation_id: &str,
+        expected_len: usize,
+        messages: &[Message],
+    ) -> Result<()> {
+        (**self)
+            .replace_if_len(conversation_id, expected_len, messages)
+            .await
+    }
+}
@@ -0,0 +1,280 @@
+use ai_core::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+
+/// A piece of text stored with its embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorRecord {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub text: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, JsonValue>,
+}
+
+impl VectorRecord {
+    pub fn new(id: impl Into<String>, vector: Vec<f32>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            text: text.into(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: JsonValue) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+}
+
+/// A record returned by a similarity query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredRecord {
+    pub record: VectorRecord,
+    /// Cosine similarity to the query vector; higher is closer
+    pub score: f32,
+}
+
+/// Condition on record metadata used to narrow a query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataFilter {
+    /// The key is present and equal to the value
+    Eq {
+        key: String,
+        value: JsonValue,
+    },
+    /// The key is present and equal to one of the values
+    In {
+        key: String,
+        values: Vec<JsonValue>,
+    },
+    /// The key is present
+    Exists {
+        key: String,
+    },
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Not(Box<MetadataFilter>),
+}
+
+impl MetadataFilter {
+    pub fn eq(key: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+        Self::Eq {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn is_in(key: impl Into<String>, values: Vec<JsonValue>) -> Self {
+        Self::In {
+            key: key.into(),
+            values,
+        }
+    }
+
+    pub fn exists(key: impl Into<String>) -> Self {
+        Self::Exists { key: key.into() }
+    }
+
+    /// Both this filter and `other` match
+    pub fn and(self, other: MetadataFilter) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
+
+    /// Check a record's metadata against the filter
+    pub fn matches(&self, metadata: &HashMap<String, JsonValue>) -> bool {
+        match self {
+            Self::Eq { key, value } => metadata.get(key) == Some(value),
+            Self::In { key, values } => metadata.get(key).is_some_and(|v| values.contains(v)),
+            Self::Exists { key } => metadata.contains_key(key),
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(metadata)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(metadata)),
+            Self::Not(filter) => !filter.matches(metadata),
+        }
+    }
+}
+
+/// Storage for embeddings searchable by similarity
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Insert records, replacing existing records with the same ID
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()>;
+
+    /// The `top_k` records most similar to `vector` and matching `filter`, best first
+    async fn query(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<ScoredRecord>>;
+
+    /// Remove records by ID; missing IDs are ignored
+    async fn delete(&self, ids: &[String]) -> Result<()>;
+}
+
+#[async_trait]
+impl<T: VectorStore + ?Sized> VectorStore for Arc<T> {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        (**self).upsert(records).await
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<ScoredRecord>> {
+        (**self).query(vector, top_k, filter).await
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        (**self).delete(ids).await
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when either is zero or their lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Process-local vector store doing a linear cosine-similarity scan
+#[derive(Debug, Default)]
+pub struct InMemoryVectorStore {
+    records: RwLock<HashMap<String, VectorRecord>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.records.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.records.read().await.is_empty()
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        let mut stored = self.records.write().await;
+        for record in records {
+            stored.insert(record.id.clone(), record);
+        }
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<ScoredRecord>> {
+        let records = self.records.read().await;
+        let mut scored: Vec<ScoredRecord> = records
+            .values()
+            .filter(|record| filter.is_none_or(|filter| filter.matches(&record.metadata)))
+            .map(|record| ScoredRecord {
+                score: cosine_similarity(vector, &record.vector),
+                record: record.clone(),
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(top_k);
+        Ok(scored)
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        let mut records = self.records.write().await;
+        for id in ids {
+            records.remove(id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_orders_by_similarity() {
+        let store = InMemoryVectorStore::new();
+        store
+            .upsert(vec![
+                VectorRecord::new("a", vec![1.0, 0.0], "east"),
+                VectorRecord::new("b", vec![0.0, 1.0], "north"),
+                VectorRecord::new("c", vec![0.7, 0.7], "north-east"),
+            ])
+            .await
+            .unwrap();
+        store
+            .upsert(vec![VectorRecord::new("b", vec![-1.0, 0.0], "west")])
+            .await
+            .unwrap();
+        assert_eq!(store.len().await, 3);
+
+        let results = store.query(&[1.0, 0.1], 2, None).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.record.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert!(results[0].score > results[1].score);
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_and_delete() {
+        let store = InMemoryVectorStore::new();
+        store
+            .upsert(vec![
+                VectorRecord::new("a", vec![1.0, 0.0], "a").metadata("user", "alice".into()),
+                VectorRecord::new("b", vec![0.9, 0.1], "b").metadata("user", "bob".into()),
+                VectorRecord::new("c", vec![0.0, 1.0], "c")
+                    .metadata("user", "alice".into())
+                    .metadata("archived", true.into()),
+            ])
+            .await
+            .unwrap();
+
+        let filter = MetadataFilter::eq("user", "alice").and(MetadataFilter::Not(Box::new(
+            MetadataFilter::exists("archived"),
+        )));
+        let results = store.query(&[0.0, 1.0], 10, Some(&filter)).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].record.id, "a");
+
+        store
+            .delete(&["a".to_string(), "missing".to_string()])
+            .await
+            .unwrap();
+        assert!(
+            store
+                .query(&[1.0, 0.0], 10, Some(&filter))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(store.len().await, 2);
+    }
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "qdrant")]
+
+use ai_memory::{MetadataFilter, QdrantStore, VectorRecord, VectorStore};
+use std::env;
+
+#[tokio::test]
+#[ignore] // Requires a running Qdrant server
+async fn test_qdrant_upsert_query_delete() {
+    let url = env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6334".to_string());
+    let store = QdrantStore::connect(&url, "ai_test_vectors").expect("Failed to connect to Qdrant");
+    store.ensure_collection(2).await.unwrap();
+
+    store
+        .upsert(vec![
+            VectorRecord::new("a", vec![1.0, 0.0], "east").metadata("user", "alice".into()),
+            VectorRecord::new("b", vec![0.9, 0.1], "east-ish").metadata("user", "bob".into()),
+        ])
+        .await
+        .unwrap();
+
+    let filter = MetadataFilter::eq("user", "alice");
+    let results = store.query(&[1.0, 0.0], 5, Some(&filter)).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].record.id, "a");
+    assert_eq!(results[0].record.text, "east");
+    assert_eq!(results[0].record.metadata["user"], "alice");
+
+    store
+        .delete(&["a".to_string(), "b".to_string()])
+        .await
+        .unwrap();
+    assert!(
+        store
+            .query(&[1.0, 0.0], 5, Some(&filter))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "redis")]
+
+use ai_core::{AiError, StorageError, types::Message};
+use ai_memory::{MessageStore, RedisStore};
+use std::{env, time::Duration};
+
+async fn setup(prefix: &str) -> RedisStore {
+    let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    RedisStore::connect(&url)
+        .await
+        .expect("Failed to connect to Redis")
+        .with_prefix(prefix)
+        .with_ttl(Duration::from_secs(60))
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Redis server
+async fn test_redis_append_and_conflict() {
+    let store = setup("ai-test-append").await;
+    store.delete("chat").await.unwrap();
+
+    store
+        .append_if_len("chat", 0, &[Message::user("hi")])
+        .await
+        .unwrap();
+    store
+        .append_if_len("chat", 1, &[Message::assistant("hello")])
+        .await
+        .unwrap();
+
+    let stale = store
+        .append_if_len("chat", 1, &[Message::assistant("again")])
+        .await;
+    assert!(matches!(
+        stale,
+        Err(AiError::Storage(StorageError::Conflict { .. }))
+    ));
+
+    assert_eq!(
+        store.load("chat").await.unwrap(),
+        vec![Message::user("hi"), Message::assistant("hello")]
+    );
+    assert!(store.list().await.unwrap().contains(&"chat".to_string()));
+
+    store
+        .replace("chat", &[Message::user("fresh")])
+        .await
+        .unwrap();
+    assert_eq!(
+        store.load("chat").await.unwrap(),
+        vec![Message::user("fresh")]
+    );
+
+    let stale = store
+        .replace_if_len("chat", 2, &[Message::user("stale")])
+        .await;
+    assert!(matches!(
+        stale,
+        Err(AiError::Storage(StorageError::Conflict { .. }))
+    ));
+    store
+        .replace_if_len("chat", 1, &[Message::user("compacted")])
+        .await
+        .unwrap();
+    assert_eq!(
+        store.load("chat").await.unwrap(),
+        vec![Message::user("compacted")]
+    );
+
+    store.delete("chat").await.unwrap();
+}
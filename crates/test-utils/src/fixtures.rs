@@ -0,0 +1,48 @@
+/// Anthropic Messages API payloads
+pub mod anthropic {
+    /// Plain text answer
+    pub const TEXT_RESPONSE: &str = include_str!("../fixtures/anthropic/text_response.json");
+    /// Text followed by a `get_weather` tool call, stopped with `tool_use`
+    pub const TOOL_USE_RESPONSE: &str =
+        include_str!("../fixtures/anthropic/tool_use_response.json");
+    /// Error body served with status 529
+    pub const OVERLOADED_ERROR: &str = include_str!("../fixtures/anthropic/overloaded_error.json");
+    /// Error body served with status 400
+    pub const INVALID_REQUEST_ERROR: &str =
+        include_str!("../fixtures/anthropic/invalid_request_error.json");
+    /// Event stream of `TEXT_RESPONSE`, split into two text deltas
+    pub const TEXT_STREAM: &str = include_str!("../fixtures/anthropic/text_stream.sse");
+    /// Event stream of `TOOL_USE_RESPONSE`, with the tool input sent as JSON deltas
+    pub const TOOL_USE_STREAM: &str = include_str!("../fixtures/anthropic/tool_use_stream.sse");
+    /// Event stream that fails with an `overloaded_error` event after some text
+    pub const ERROR_STREAM: &str = include_str!("../fixtures/anthropic/error_stream.sse");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_are_well_formed() {
+        for json in [
+            anthropic::TEXT_RESPONSE,
+            anthropic::TOOL_USE_RESPONSE,
+            anthropic::OVERLOADED_ERROR,
+            anthropic::INVALID_REQUEST_ERROR,
+        ] {
+            serde_json::from_str::<serde_json::Value>(json).unwrap();
+        }
+        for stream in [
+            anthropic::TEXT_STREAM,
+            anthropic::TOOL_USE_STREAM,
+            anthropic::ERROR_STREAM,
+        ] {
+            for data in stream
+                .lines()
+                .filter_map(|line| line.strip_prefix("data: "))
+            {
+                serde_json::from_str::<serde_json::Value>(data).unwrap();
+            }
+        }
+    }
+}
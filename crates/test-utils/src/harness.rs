@@ -0,0 +1,554 @@
+use std::cell::Cell;
+
+use ai_agent::AgentResponse;
+use ai_core::types::{AssistantContent, FinishReason, Message, ToolCall, ToolResult};
+use serde_json::Value as JsonValue;
+
+/// Assertions over the messages and tool executions of a finished run
+///
+/// `expect_tool_call` walks the run's tool calls in order, so consecutive expectations
+/// also check the order the model called tools in. Failed expectations panic with the
+/// calls that were actually made.
+///
+/// ```
+/// # use ai_test_utils::*;
+/// # fn check(response: &ai_agent::AgentResponse) {
+/// assert_run(response)
+///     .expect_tool_call("calculator")
+///     .with_args_matching(|args| args["a"] == 6)
+///     .succeeded()
+///     .expect_no_more_tool_calls()
+///     .expect_text_contains("42");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RunAssertions<'a> {
+    messages: &'a [Message],
+    final_text: String,
+    finish_reason: &'a FinishReason,
+    steps: usize,
+    tool_calls: Vec<&'a ToolCall>,
+    cursor: Cell<usize>,
+}
+
+/// Start asserting on a finished run
+pub fn assert_run<T>(response: &AgentResponse<T>) -> RunAssertions<'_> {
+    let tool_calls = response
+        .messages
+        .iter()
+        .flat_map(|message| match message {
+            Message::Assistant { content, .. } => content.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|part| match part {
+            AssistantContent::ToolCall { tool_call } => Some(tool_call),
+            _ => None,
+        })
+        .collect();
+    RunAssertions {
+        messages: &response.messages,
+        final_text: response.text(),
+        finish_reason: &response.finish_reason,
+        steps: response.steps.len(),
+        tool_calls,
+        cursor: Cell::new(0),
+    }
+}
+
+impl<'a> RunAssertions<'a> {
+    fn called_names(&self) -> Vec<&str> {
+        self.tool_calls
+            .iter()
+            .map(|call| call.name.as_str())
+            .collect()
+    }
+
+    /// The next tool call named `name`, skipping calls to other tools
+    #[track_caller]
+    pub fn expect_tool_call(&self, name: &str) -> ToolCallAssertions<'_, 'a> {
+        let start = self.cursor.get();
+        let Some(offset) = self.tool_calls[start..]
+            .iter()
+            .position(|call| call.name == name)
+        else {
+            panic!(
+                "expected a call to '{}' after {} earlier call(s), but the run called {:?}",
+                name,
+                start,
+                self.called_names()
+            );
+        };
+        self.cursor.set(start + offset + 1);
+        ToolCallAssertions {
+            run: self,
+            call: self.tool_calls[start + offset],
+        }
+    }
+
+    /// The run called exactly these tools, in this order
+    #[track_caller]
+    pub fn expect_tool_calls(&self, names: &[&str]) -> &Self {
+        assert_eq!(self.called_names(), names, "unexpected tool calls");
+        self.cursor.set(self.tool_calls.len());
+        self
+    }
+
+    #[track_caller]
+    pub fn expect_no_tool_calls(&self) -> &Self {
+        self.expect_tool_calls(&[])
+    }
+
+    /// No tool calls follow the ones already matched
+    #[track_caller]
+    pub fn expect_no_more_tool_calls(&self) -> &Self {
+        let rest: Vec<&str> = self.called_names()[self.cursor.get()..].to_vec();
+        assert!(rest.is_empty(), "unexpected further tool calls: {:?}", rest);
+        self
+    }
+
+    #[track_caller]
+    pub fn expect_steps(&self, steps: usize) -> &Self {
+        assert_eq!(self.steps, steps, "unexpected number of steps");
+        self
+    }
+
+    #[track_caller]
+    pub fn expect_finish_reason(&self, finish_reason: FinishReason) -> &Self {
+        assert_eq!(
+            self.finish_reason, &finish_reason,
+            "unexpected finish reason"
+        );
+        self
+    }
+
+    /// Text of the final assistant message equals `text`
+    #[track_caller]
+    pub fn expect_text(&self, text: &str) -> &Self {
+        assert_eq!(self.final_text, text, "unexpected final text");
+        self
+    }
+
+    #[track_caller]
+    pub fn expect_text_contains(&self, needle: &str) -> &Self {
+        assert!(
+            self.final_text.contains(needle),
+            "expected final text to contain {:?}, got {:?}",
+            needle,
+            self.final_text
+        );
+        self
+    }
+
+    /// Roles of the conversation, e.g. `["user", "assistant", "tool", "assistant"]`
+    #[track_caller]
+    pub fn expect_roles(&self, roles: &[&str]) -> &Self {
+        let actual: Vec<&str> = self
+            .messages
+            .iter()
+            .map(|message| match message {
+                Message::System { .. } => "system",
+                Message::User { .. } => "user",
+                Message::Assistant { .. } => "assistant",
+                Message::Tool { .. } => "tool",
+            })
+            .collect();
+        assert_eq!(actual, roles, "unexpected message sequence");
+        self
+    }
+
+    fn result_of(&self, call: &ToolCall) -> Option<&'a ToolResult> {
+        self.messages
+            .iter()
+            .flat_map(|message| match message {
+                Message::Tool { tool_results, .. } => tool_results.as_slice(),
+                _ => &[],
+            })
+            .find(|result| result.tool_call_id == call.id)
+    }
+}
+
+/// Assertions on one tool call, see `RunAssertions::expect_tool_call`
+#[derive(Debug)]
+pub struct ToolCallAssertions<'r, 'a> {
+    run: &'r RunAssertions<'a>,
+    call: &'a ToolCall,
+}
+
+impl<'r, 'a> ToolCallAssertions<'r, 'a> {
+    pub fn tool_call(&self) -> &'a ToolCall {
+        self.call
+    }
+
+    /// Result sent back to the model for this call, if the tool ran
+    pub fn result(&self) -> Option<&'a ToolResult> {
+        self.run.result_of(self.call)
+    }
+
+    #[track_caller]
+    pub fn with_args(self, arguments: JsonValue) -> Self {
+        assert_eq!(
+            self.call.arguments, arguments,
+            "unexpected arguments for '{}'",
+            self.call.name
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn with_args_matching(self, predicate: impl FnOnce(&JsonValue) -> bool) -> Self {
+        assert!(
+            predicate(&self.call.arguments),
+            "arguments for '{}' did not match: {}",
+            self.call.name,
+            self.call.arguments
+        );
+        self
+    }
+
+    #[track_caller]
+    fn expect_result(&self) -> &'a ToolResult {
+        self.result()
+            .unwrap_or_else(|| panic!("'{}' has no tool result", self.call.name))
+    }
+
+    #[track_caller]
+    pub fn with_result_matching(self, predicate: impl FnOnce(&JsonValue) -> bool) -> Self {
+        let result = self.expect_result();
+        assert!(
+            predicate(&result.result),
+            "result of '{}' did not match: {}",
+            self.call.name,
+            result.result
+        );
+        self
+    }
+
+    /// The tool ran and returned a successful result; returns the run for chaining
+    #[track_caller]
+    pub fn succeeded(self) -> &'r RunAssertions<'a> {
+        let result = self.expect_result();
+        assert!(
+            !result.is_error,
+            "'{}' failed: {}",
+            self.call.name, result.result
+        );
+        self.run
+    }
+
+    /// The tool ran, or was vetoed, and returned an error; returns the run for chaining
+    #[track_caller]
+    pub fn failed(self) -> &'r RunAssertions<'a> {
+        let result = self.expect_result();
+        assert!(result.is_error, "'{}' did not fail", self.call.name);
+        self.run
+    }
+
+    /// Back to the run, without checking the result
+    pub fn and(self) -> &'r RunAssertions<'a> {
+        self.run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockProvider;
+    use ai_agent::{
+        AgentCheckpoint, AgentEvent, GenerateConfig, MaxSteps, OutputValidator, RunUntilFirst,
+        StopOnReason, StreamConfig, generate_text, stream_events, stream_events_from_checkpoint,
+        stream_text,
+    };
+    use ai_core::{
+        AgentError, AiError, NetworkError, ToolRouter, errors::ToolExecutionError,
+        types::UserContent,
+    };
+    use futures::StreamExt;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct AddInput {
+        a: i64,
+        b: i64,
+    }
+
+    async fn add(input: AddInput) -> std::result::Result<i64, ToolExecutionError> {
+        if input.a < 0 {
+            return Err(ToolExecutionError::InvalidInput(
+                "a must be positive".into(),
+            ));
+        }
+        Ok(input.a + input.b)
+    }
+
+    fn router() -> ai_core::BuiltToolRouter<()> {
+        ToolRouter::new()
+            .register("add", Some("Add two numbers".to_string()), add)
+            .with_state(())
+    }
+
+    fn until_answer() -> RunUntilFirst<MaxSteps, StopOnReason> {
+        RunUntilFirst::new(MaxSteps::new(5), StopOnReason::stop_on_finish())
+    }
+
+    fn script() -> MockProvider {
+        MockProvider::new()
+            .tool_call("add", serde_json::json!({"a": 40, "b": 2}))
+            .tool_call("add", serde_json::json!({"a": -1, "b": 2}))
+            .text("The answer is 42")
+    }
+
+    #[tokio::test]
+    async fn test_asserts_over_agent_loop() {
+        let provider = script();
+        let config = GenerateConfig::new(provider.clone())
+            .messages(vec![Message::user("What is 40 + 2?")])
+            .tools(router())
+            .run_until(until_answer());
+        let response = generate_text(config).await.unwrap();
+
+        assert_run(&response)
+            .expect_steps(3)
+            .expect_roles(&[
+                "user",
+                "assistant",
+                "tool",
+                "assistant",
+                "tool",
+                "assistant",
+            ])
+            .expect_tool_call("add")
+            .with_args(serde_json::json!({"a": 40, "b": 2}))
+            .with_result_matching(|result| result == 42)
+            .succeeded()
+            .expect_tool_call("add")
+            .with_args_matching(|args| args["a"] == -1)
+            .failed()
+            .expect_no_more_tool_calls()
+            .expect_finish_reason(FinishReason::Stop)
+            .expect_text_contains("42");
+
+        assert_eq!(provider.requests().len(), 3);
+        assert_eq!(provider.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_streams_scripted_responses() {
+        let provider = script();
+        let config = StreamConfig::new(provider)
+            .messages(vec![Message::user("What is 40 + 2?")])
+            .tools(router())
+            .run_until(until_answer());
+        let mut stream = stream_text(config).await.unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            if let ai_core::MessageDelta::Assistant {
+                content: Some(AssistantContent::Text { text: delta }),
+            } = &chunk.chunk.delta
+            {
+                text.push_str(delta);
+            }
+        }
+        assert_eq!(text, "The answer is 42");
+    }
+
+    #[tokio::test]
+    async fn test_stream_failure_keeps_partial_answer() {
+        let provider = MockProvider::new().interrupted(
+            "The answer is",
+            AiError::Network(NetworkError::ConnectionFailed {
+                message: "connection reset".to_string(),
+            }),
+        );
+        let config = StreamConfig::new(provider)
+            .messages(vec![Message::user("What is 40 + 2?")])
+            .run_until(until_answer());
+        let results: Vec<_> = stream_text(config).await.unwrap().collect().await;
+        match results.last() {
+            Some(Err(AiError::Agent(AgentError::PartialResponse { step, partial, .. }))) => {
+                assert_eq!(*step, 0);
+                assert_eq!(
+                    **partial,
+                    Message::assistant(AssistantContent::Text {
+                        text: "The answer is".to_string()
+                    })
+                );
+            }
+            other => panic!("expected a partial response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_pauses_on_pending_tool_calls() {
+        let provider = MockProvider::new()
+            .tool_calls([
+                ("add", serde_json::json!({"a": 1, "b": 2})),
+                ("ask_user", serde_json::json!({"question": "Proceed?"})),
+            ])
+            .text("Done");
+        let config = || {
+            StreamConfig::new(provider.clone())
+                .messages(vec![Message::user("Add and ask")])
+                .tools(
+                    ToolRouter::new()
+                        .register("add", None, add)
+                        .register_definition("ask_user", None, None)
+                        .with_state(()),
+                )
+                .run_until(until_answer())
+        };
+
+        let events: Vec<AgentEvent> = stream_events(config())
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let checkpoint = events
+            .iter()
+            .find_map(|event| match event {
+                AgentEvent::PendingToolCalls {
+                    tool_calls,
+                    checkpoint,
+                    ..
+                } => {
+                    assert_eq!(tool_calls.len(), 1);
+                    assert_eq!(tool_calls[0].name, "ask_user");
+                    Some(checkpoint.clone())
+                }
+                _ => None,
+            })
+            .expect("no PendingToolCalls event");
+        let Some(AgentEvent::RunFinished(paused)) = events.last() else {
+            panic!("run did not finish");
+        };
+        assert_eq!(paused.checkpoint.as_ref(), Some(&checkpoint));
+        assert_eq!(checkpoint.tool_results.len(), 1);
+
+        // The token survives a round trip through the client
+        let token = serde_json::to_string(&checkpoint).unwrap();
+        let checkpoint: AgentCheckpoint = serde_json::from_str(&token).unwrap();
+        let answer = ToolResult {
+            tool_call_id: checkpoint.pending_tool_calls[0].id.clone(),
+            result: serde_json::json!("yes"),
+            is_error: false,
+            content: Vec::new(),
+        };
+        assert!(
+            stream_events_from_checkpoint(config(), checkpoint.clone(), Vec::new())
+                .await
+                .is_err()
+        );
+        let events: Vec<AgentEvent> =
+            stream_events_from_checkpoint(config(), checkpoint, vec![answer])
+                .await
+                .unwrap()
+                .map(|event| event.unwrap())
+                .collect()
+                .await;
+        let Some(AgentEvent::RunFinished(finished)) = events.last() else {
+            panic!("resumed run did not finish");
+        };
+
+        assert!(finished.checkpoint.is_none());
+        assert_run(finished)
+            .expect_roles(&["user", "assistant", "tool", "assistant"])
+            .expect_tool_call("add")
+            .succeeded()
+            .expect_tool_call("ask_user")
+            .with_result_matching(|result| result == "yes")
+            .succeeded()
+            .expect_text("Done");
+    }
+
+    async fn tally(scratchpad: ai_core::Scratchpad, input: AddInput) -> i64 {
+        scratchpad
+            .update("tally", |tally: &mut i64| {
+                *tally += input.a + input.b;
+                *tally
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_is_shared_by_tools_and_hooks() {
+        let provider = MockProvider::new()
+            .tool_call("tally", serde_json::json!({"a": 1, "b": 2}))
+            .tool_call("tally", serde_json::json!({"a": 3, "b": 4}))
+            .text("Done");
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_seen = seen.clone();
+        let config = GenerateConfig::new(provider)
+            .messages(vec![Message::user("Keep a tally")])
+            .tools(
+                ToolRouter::new()
+                    .register_infallible("tally", None, tally)
+                    .with_state(()),
+            )
+            .on_step_finish(move |finish| {
+                hook_seen
+                    .lock()
+                    .unwrap()
+                    .push(finish.scratchpad.get::<i64>("tally"));
+                async { Ok(()) }
+            })
+            .run_until(until_answer());
+        let response = generate_text(config).await.unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![Some(3), Some(10), Some(10)]);
+        assert_eq!(response.scratchpad.get::<i64>("tally"), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_output_validator_repairs_the_answer() {
+        let validator = || {
+            OutputValidator::schema(serde_json::json!({
+                "type": "object",
+                "properties": { "answer": { "type": "integer" } },
+                "required": ["answer"]
+            }))
+        };
+        let provider = MockProvider::new()
+            .text("{\"answer\": \"forty-two\"}")
+            .text("{\"answer\": 42}");
+        let config = GenerateConfig::new(provider.clone())
+            .messages(vec![Message::user("What is 40 + 2? Answer in JSON.")])
+            .run_until(until_answer())
+            .output_validator(validator());
+        let response = generate_text(config).await.unwrap();
+
+        assert_eq!(
+            response.output_value(),
+            Some(serde_json::json!({"answer": 42}))
+        );
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 2);
+        let Some(Message::User { content, .. }) = requests[1].messages.last() else {
+            panic!("expected a repair message");
+        };
+        assert!(matches!(
+            &content[0],
+            UserContent::Text { text } if text.starts_with("Your output failed validation")
+        ));
+
+        let config = GenerateConfig::new(MockProvider::new().text("forty-two"))
+            .messages(vec![Message::user("What is 40 + 2?")])
+            .run_until(until_answer())
+            .output_validator(validator().max_repairs(0));
+        assert!(matches!(
+            generate_text(config).await,
+            Err(AiError::Agent(AgentError::InvalidOutput { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected a call to 'search'")]
+    async fn test_missing_tool_call_panics() {
+        let config = GenerateConfig::new(MockProvider::new().text("Hi"))
+            .messages(vec![Message::user("Hello")])
+            .run_until(until_answer());
+        let response = generate_text(config).await.unwrap();
+        assert_run(&response).expect_tool_call("search");
+    }
+}
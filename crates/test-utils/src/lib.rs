@@ -0,0 +1,10 @@
+pub mod fixtures;
+pub mod harness;
+pub mod mock;
+#[cfg(feature = "wiremock")]
+pub mod mock_server;
+
+pub use harness::*;
+pub use mock::*;
+#[cfg(feature = "wiremock")]
+pub use mock_server::*;
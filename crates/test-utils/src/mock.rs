@@ -0,0 +1,284 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use ai_core::{
+    AgentError, AiError, ChatStream, ChatTextGeneration, Result,
+    types::{
+        AssistantContent, ChatRequest, ChatResponse, ChatStreamChunk, FinishReason, Message,
+        MessageDelta, ToolCall, Usage,
+    },
+};
+use async_trait::async_trait;
+use serde_json::Value as JsonValue;
+
+/// One scripted reply
+#[derive(Debug)]
+enum Scripted {
+    Response(ChatResponse),
+    Error(AiError),
+    /// Streams the response's content, then fails instead of finishing
+    Interrupted(ChatResponse, AiError),
+    /// Streams reasoning before the response's content
+    Reasoned(String, ChatResponse),
+}
+
+/// Provider replaying scripted responses in order
+///
+/// Each call to `generate` or `generate_stream` takes the next response; streams yield
+/// one chunk per content part followed by a final chunk with the finish reason and
+/// usage. Every request is recorded for later inspection. Clones share the script.
+#[derive(Debug, Clone)]
+pub struct MockProvider {
+    name: String,
+    model: String,
+    usage: Option<Usage>,
+    context_window: Option<u32>,
+    responses: Arc<Mutex<VecDeque<Scripted>>>,
+    requests: Arc<Mutex<Vec<ChatRequest>>>,
+    next_call_id: Arc<Mutex<u32>>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self {
+            name: "mock".to_string(),
+            model: "mock-model".to_string(),
+            usage: None,
+            context_window: None,
+            responses: Arc::new(Mutex::new(VecDeque::new())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            next_call_id: Arc::new(Mutex::new(1)),
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Context window reported by `context_window`, unknown by default
+    pub fn with_context_window(mut self, tokens: u32) -> Self {
+        self.context_window = Some(tokens);
+        self
+    }
+
+    /// Usage reported with responses scripted after this call
+    pub fn with_usage(mut self, prompt_tokens: u32, completion_tokens: u32) -> Self {
+        self.usage = Some(Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        });
+        self
+    }
+
+    /// Queue a complete response
+    pub fn respond(self, response: ChatResponse) -> Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Scripted::Response(response));
+        self
+    }
+
+    /// Queue a text answer that stops the run
+    pub fn text(self, text: impl Into<String>) -> Self {
+        let message = Message::assistant(AssistantContent::Text { text: text.into() });
+        let response = self.response(message, FinishReason::Stop);
+        self.respond(response)
+    }
+
+    /// Queue a text answer whose stream starts with `reasoning` as a
+    /// `MessageDelta::Reasoning` chunk; `generate` returns the answer alone
+    pub fn reasoning(self, reasoning: impl Into<String>, text: impl Into<String>) -> Self {
+        let message = Message::assistant(AssistantContent::Text { text: text.into() });
+        let response = self.response(message, FinishReason::Stop);
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Scripted::Reasoned(reasoning.into(), response));
+        self
+    }
+
+    /// Queue a response calling one tool
+    pub fn tool_call(self, name: impl Into<String>, arguments: JsonValue) -> Self {
+        self.tool_calls([(name, arguments)])
+    }
+
+    /// Queue a response calling several tools at once
+    pub fn tool_calls<N: Into<String>>(
+        self,
+        calls: impl IntoIterator<Item = (N, JsonValue)>,
+    ) -> Self {
+        let content = calls
+            .into_iter()
+            .map(|(name, arguments)| AssistantContent::ToolCall {
+                tool_call: ToolCall {
+                    id: self.call_id(),
+                    name: name.into(),
+                    arguments,
+                },
+            })
+            .collect();
+        let message = Message::Assistant {
+            content,
+            metadata: None,
+        };
+        let response = self.response(message, FinishReason::ToolCalls);
+        self.respond(response)
+    }
+
+    /// Queue an error
+    pub fn error(self, error: AiError) -> Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Scripted::Error(error));
+        self
+    }
+
+    /// Queue a stream that sends `text` and then fails with `error`
+    ///
+    /// `generate` returns the error straight away.
+    pub fn interrupted(self, text: impl Into<String>, error: AiError) -> Self {
+        let message = Message::assistant(AssistantContent::Text { text: text.into() });
+        let response = self.response(message, FinishReason::Stop);
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Scripted::Interrupted(response, error));
+        self
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Scripted responses not yet used
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    fn call_id(&self) -> String {
+        let mut next = self.next_call_id.lock().unwrap();
+        let id = format!("call_{}", *next);
+        *next += 1;
+        id
+    }
+
+    fn response(&self, message: Message, finish_reason: FinishReason) -> ChatResponse {
+        ChatResponse {
+            id: format!("mock_{}", self.remaining() + 1),
+            message,
+            finish_reason,
+            usage: self.usage.clone(),
+            metadata: None,
+        }
+    }
+
+    fn next(&self, request: ChatRequest) -> Result<Scripted> {
+        self.requests.lock().unwrap().push(request);
+        self.responses.lock().unwrap().pop_front().ok_or_else(|| {
+            AiError::Agent(AgentError::StateError {
+                message: "mock provider has no scripted response left".to_string(),
+            })
+        })
+    }
+}
+
+/// Split a response into the chunks a streaming provider would send
+fn chunks(response: ChatResponse) -> Vec<ChatStreamChunk> {
+    let content = match response.message {
+        Message::Assistant { content, .. } => content,
+        _ => Vec::new(),
+    };
+    let mut chunks: Vec<ChatStreamChunk> = content
+        .into_iter()
+        .map(|part| ChatStreamChunk {
+            id: response.id.clone(),
+            delta: MessageDelta::Assistant {
+                content: Some(part),
+            },
+            finish_reason: None,
+            usage: None,
+        })
+        .collect();
+    chunks.push(ChatStreamChunk {
+        id: response.id,
+        delta: MessageDelta::Assistant { content: None },
+        finish_reason: Some(response.finish_reason),
+        usage: response.usage,
+    });
+    chunks
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ChatTextGeneration for MockProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn context_window(&self) -> Option<u32> {
+        self.context_window
+    }
+
+    async fn generate(&self, request: ChatRequest) -> Result<ChatResponse> {
+        match self.next(request)? {
+            Scripted::Response(response) | Scripted::Reasoned(_, response) => Ok(response),
+            Scripted::Error(error) | Scripted::Interrupted(_, error) => Err(error),
+        }
+    }
+
+    async fn generate_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        let chunks: Vec<Result<ChatStreamChunk>> = match self.next(request)? {
+            Scripted::Response(response) => chunks(response).into_iter().map(Ok).collect(),
+            Scripted::Error(error) => return Err(error),
+            Scripted::Interrupted(response, error) => {
+                let mut chunks: Vec<_> = chunks(response).into_iter().map(Ok).collect();
+                chunks.pop();
+                chunks.push(Err(error));
+                chunks
+            }
+            Scripted::Reasoned(reasoning, response) => {
+                let reasoning = ChatStreamChunk {
+                    id: response.id.clone(),
+                    delta: MessageDelta::Reasoning { text: reasoning },
+                    finish_reason: None,
+                    usage: None,
+                };
+                std::iter::once(reasoning)
+                    .chain(chunks(response))
+                    .map(Ok)
+                    .collect()
+            }
+        };
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+}
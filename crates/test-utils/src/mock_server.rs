@@ -0,0 +1,99 @@
+use serde_json::Value as JsonValue;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+const ANTHROPIC_MESSAGES: &str = "/v1/messages";
+
+/// HTTP server replaying queued responses, for testing providers end to end
+///
+/// Each queued response answers one POST to its path, in the order they were queued;
+/// point the provider's base URL at `uri()`. Requests are recorded and can be inspected
+/// with `requests`.
+///
+/// ```no_run
+/// # use ai_test_utils::{MockApiServer, fixtures::anthropic};
+/// # async fn example() {
+/// let server = MockApiServer::start().await;
+/// server.anthropic_error(529, anthropic::OVERLOADED_ERROR).await;
+/// server.anthropic_message(anthropic::TEXT_RESPONSE).await;
+/// // AnthropicConfig::new("test-key", "claude-3-5-haiku-20241022").with_base_url(server.uri())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockApiServer {
+    server: MockServer,
+}
+
+impl MockApiServer {
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:38211`
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// The underlying server, for custom matchers and responses
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Queue a JSON response to the next POST to `route`
+    pub async fn respond_json(&self, route: &str, status: u16, body: &str) {
+        self.respond(
+            route,
+            ResponseTemplate::new(status).set_body_raw(body.to_string(), "application/json"),
+        )
+        .await;
+    }
+
+    /// Queue an event stream response to the next POST to `route`
+    pub async fn respond_sse(&self, route: &str, body: &str) {
+        self.respond(
+            route,
+            ResponseTemplate::new(200).set_body_raw(body.to_string(), "text/event-stream"),
+        )
+        .await;
+    }
+
+    /// Queue a custom response to the next POST to `route`
+    pub async fn respond(&self, route: &str, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path(route))
+            .respond_with(response)
+            .up_to_n_times(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Queue an Anthropic message, e.g. `fixtures::anthropic::TOOL_USE_RESPONSE`
+    pub async fn anthropic_message(&self, body: &str) {
+        self.respond_json(ANTHROPIC_MESSAGES, 200, body).await;
+    }
+
+    /// Queue an Anthropic event stream, e.g. `fixtures::anthropic::TEXT_STREAM`
+    pub async fn anthropic_stream(&self, body: &str) {
+        self.respond_sse(ANTHROPIC_MESSAGES, body).await;
+    }
+
+    /// Queue an Anthropic error, e.g. `fixtures::anthropic::OVERLOADED_ERROR` with 529
+    pub async fn anthropic_error(&self, status: u16, body: &str) {
+        self.respond_json(ANTHROPIC_MESSAGES, status, body).await;
+    }
+
+    /// JSON bodies of the requests received so far
+    pub async fn requests(&self) -> Vec<JsonValue> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap_or(JsonValue::Null))
+            .collect()
+    }
+}
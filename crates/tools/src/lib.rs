@@ -0,0 +1,9 @@
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "sql")]
+pub mod sql;
+
+#[cfg(feature = "openapi")]
+pub use openapi::*;
+#[cfg(feature = "sql")]
+pub use sql::*;
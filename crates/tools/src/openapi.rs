@@ -0,0 +1,670 @@
+use ai_core::errors::{
+    AiError, SerializationError, ToolExecutionError, ToolResult, ValidationError,
+};
+use ai_core::tools::ToolRouter;
+use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use schemars::Schema;
+use serde_json::{Map, Value as JsonValue, json};
+use std::sync::Arc;
+
+/// HTTP methods that can appear as operations under an OpenAPI path item
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Maximum number of nested `$ref` expansions, guarding against recursive schemas
+const MAX_REF_DEPTH: usize = 16;
+
+/// Where an operation parameter is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+}
+
+/// A single parameter of an OpenAPI operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiParameter {
+    pub name: String,
+    pub location: ParameterLocation,
+    pub required: bool,
+}
+
+/// An OpenAPI operation mapped onto a tool definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiOperation {
+    /// Tool name, derived from `operationId` or the method and path
+    pub name: String,
+    pub description: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub parameters: Vec<OpenApiParameter>,
+    /// Whether the operation accepts a JSON request body (passed as the `body` argument)
+    pub has_body: bool,
+    /// JSON schema of the tool input, with all `$ref`s inlined
+    pub input_schema: JsonValue,
+}
+
+/// A parsed OpenAPI 3.x document
+#[derive(Debug, Clone)]
+pub struct OpenApiSpec {
+    document: JsonValue,
+}
+
+impl OpenApiSpec {
+    /// Parse an OpenAPI document from JSON
+    pub fn from_json(json: &str) -> ai_core::Result<Self> {
+        Ok(Self {
+            document: serde_json::from_str(json)?,
+        })
+    }
+
+    /// Parse an OpenAPI document from YAML
+    pub fn from_yaml(yaml: &str) -> ai_core::Result<Self> {
+        let document = serde_yaml::from_str(yaml).map_err(|e| {
+            AiError::Serialization(SerializationError::JsonError {
+                message: format!("Failed to parse OpenAPI YAML: {}", e),
+            })
+        })?;
+        Ok(Self { document })
+    }
+
+    /// The first server URL declared by the document, if any
+    pub fn server_url(&self) -> Option<&str> {
+        self.document["servers"][0]["url"].as_str()
+    }
+
+    /// Extract every operation in the document
+    ///
+    /// Fails if two operations map to the same tool name.
+    pub fn operations(&self) -> ai_core::Result<Vec<OpenApiOperation>> {
+        let paths = self.document["paths"].as_object().ok_or_else(|| {
+            AiError::Validation(ValidationError::MissingField {
+                field: "paths".to_string(),
+            })
+        })?;
+
+        let mut operations = Vec::new();
+        for (path, item) in paths {
+            let item = self.resolve(item, 0);
+            let shared_parameters = item["parameters"].as_array().cloned().unwrap_or_default();
+
+            for method in METHODS {
+                let Some(operation) = item.get(*method) else {
+                    continue;
+                };
+                let mut parameters = shared_parameters.clone();
+                if let Some(own) = operation["parameters"].as_array() {
+                    parameters.extend(own.iter().cloned());
+                }
+                let operation = self.build_operation(method, path, operation, &parameters);
+                if let Some(existing) = operations
+                    .iter()
+                    .find(|o: &&OpenApiOperation| o.name == operation.name)
+                {
+                    return Err(AiError::Validation(ValidationError::InvalidValue {
+                        field: "operationId".to_string(),
+                        message: format!(
+                            "'{} {}' and '{} {}' both map to the tool name '{}'",
+                            existing.method,
+                            existing.path,
+                            operation.method,
+                            operation.path,
+                            operation.name
+                        ),
+                    }));
+                }
+                operations.push(operation);
+            }
+        }
+
+        Ok(operations)
+    }
+
+    fn build_operation(
+        &self,
+        method: &str,
+        path: &str,
+        operation: &JsonValue,
+        raw_parameters: &[JsonValue],
+    ) -> OpenApiOperation {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut parameters = Vec::new();
+
+        for raw in raw_parameters {
+            let raw = self.resolve(raw, 0);
+            let Some(name) = raw["name"].as_str() else {
+                continue;
+            };
+            let location = match raw["in"].as_str() {
+                Some("path") => ParameterLocation::Path,
+                Some("query") => ParameterLocation::Query,
+                Some("header") => ParameterLocation::Header,
+                _ => continue,
+            };
+            let is_required =
+                location == ParameterLocation::Path || raw["required"].as_bool().unwrap_or(false);
+
+            // Operation-level parameters override path-level ones with the same name
+            parameters.retain(|p: &OpenApiParameter| p.name != name);
+            required.retain(|r| *r != name);
+            let mut schema = match &raw["schema"] {
+                JsonValue::Null => json!({}),
+                schema => self.inline_refs(schema, 0),
+            };
+            if let Some(description) = raw["description"].as_str()
+                && let Some(object) = schema.as_object_mut()
+            {
+                object.insert("description".to_string(), json!(description));
+            }
+            properties.insert(name.to_string(), schema);
+            if is_required {
+                required.push(json!(name));
+            }
+            parameters.push(OpenApiParameter {
+                name: name.to_string(),
+                location,
+                required: is_required,
+            });
+        }
+
+        let body = self.resolve(&operation["requestBody"], 0);
+        let body_schema = &body["content"]["application/json"]["schema"];
+        let has_body = !body_schema.is_null();
+        if has_body {
+            properties.insert("body".to_string(), self.inline_refs(body_schema, 0));
+            if body["required"].as_bool().unwrap_or(false) {
+                required.push(json!("body"));
+            }
+        }
+
+        let description = operation["description"]
+            .as_str()
+            .or_else(|| operation["summary"].as_str())
+            .map(str::to_string);
+
+        OpenApiOperation {
+            name: tool_name(operation["operationId"].as_str(), method, path),
+            description,
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            parameters,
+            has_body,
+            input_schema: json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            }),
+        }
+    }
+
+    /// Follow a local `$ref` (if any) to the value it points at
+    fn resolve<'a>(&'a self, value: &'a JsonValue, depth: usize) -> &'a JsonValue {
+        match value["$ref"].as_str() {
+            Some(reference) if depth < MAX_REF_DEPTH => reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.document.pointer(pointer))
+                .map(|target| self.resolve(target, depth + 1))
+                .unwrap_or(value),
+            _ => value,
+        }
+    }
+
+    /// Recursively replace local `$ref`s in a schema with their targets
+    ///
+    /// `depth` counts the `$ref`s expanded on the way here, not the JSON nesting, so
+    /// deeply nested inline schemas are kept whole.
+    fn inline_refs(&self, value: &JsonValue, depth: usize) -> JsonValue {
+        match value {
+            JsonValue::Object(object) if object.contains_key("$ref") => {
+                let target = self.resolve(value, 0);
+                if std::ptr::eq(target, value) || depth >= MAX_REF_DEPTH {
+                    json!({})
+                } else {
+                    self.inline_refs(target, depth + 1)
+                }
+            }
+            JsonValue::Object(object) => JsonValue::Object(
+                object
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.inline_refs(v, depth)))
+                    .collect(),
+            ),
+            JsonValue::Array(items) => {
+                JsonValue::Array(items.iter().map(|v| self.inline_refs(v, depth)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+}
+
+/// Builds tools that call the operations of an OpenAPI document over HTTP
+#[derive(Debug, Clone)]
+pub struct OpenApiToolset {
+    operations: Vec<OpenApiOperation>,
+    base_url: String,
+    client: Client,
+    headers: HeaderMap,
+    service: String,
+}
+
+impl OpenApiToolset {
+    /// Create a toolset for every operation in the spec, using its first server URL
+    pub fn new(spec: &OpenApiSpec) -> ai_core::Result<Self> {
+        let service = spec.document["info"]["title"]
+            .as_str()
+            .unwrap_or("openapi")
+            .to_string();
+        Ok(Self {
+            operations: spec.operations()?,
+            base_url: spec.server_url().unwrap_or_default().to_string(),
+            client: Client::new(),
+            headers: HeaderMap::new(),
+            service,
+        })
+    }
+
+    /// Override the base URL operations are sent to
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Use a custom HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Add a header sent with every request (e.g. an API key)
+    pub fn with_header(mut self, name: &str, value: &str) -> ai_core::Result<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+            AiError::Validation(ValidationError::InvalidValue {
+                field: "header name".to_string(),
+                message: e.to_string(),
+            })
+        })?;
+        let mut value = HeaderValue::from_str(value).map_err(|e| {
+            AiError::Validation(ValidationError::InvalidValue {
+                field: "header value".to_string(),
+                message: e.to_string(),
+            })
+        })?;
+        value.set_sensitive(true);
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Authenticate every request with a bearer token
+    pub fn with_bearer_auth(self, token: &str) -> ai_core::Result<Self> {
+        self.with_header("authorization", &format!("Bearer {}", token))
+    }
+
+    /// Keep only the operations matching a predicate
+    pub fn filter(mut self, predicate: impl Fn(&OpenApiOperation) -> bool) -> Self {
+        self.operations.retain(|op| predicate(op));
+        self
+    }
+
+    /// Get the operations that will be registered
+    pub fn operations(&self) -> &[OpenApiOperation] {
+        &self.operations
+    }
+
+    /// Register one tool per operation on a router
+    pub fn register<S: Clone + Send + Sync + 'static>(
+        self,
+        router: ToolRouter<S>,
+    ) -> ToolRouter<S> {
+        let mut router = router;
+        for operation in self.operations {
+            let schema = Schema::try_from(operation.input_schema.clone())
+                .unwrap_or_else(|_| Schema::default());
+            let name = operation.name.clone();
+            let description = operation.description.clone();
+            let call = Arc::new(OperationCall {
+                operation,
+                base_url: self.base_url.clone(),
+                client: self.client.clone(),
+                headers: self.headers.clone(),
+                service: self.service.clone(),
+            });
+            router =
+                router.register_with_schema(name, description, schema, move |input: JsonValue| {
+                    let call = call.clone();
+                    async move { call.execute(input).await }
+                });
+        }
+        router
+    }
+}
+
+/// Everything needed to call a single operation
+struct OperationCall {
+    operation: OpenApiOperation,
+    base_url: String,
+    client: Client,
+    headers: HeaderMap,
+    service: String,
+}
+
+impl OperationCall {
+    async fn execute(&self, input: JsonValue) -> ToolResult<JsonValue> {
+        let url = build_url(&self.base_url, &self.operation, &input)?;
+        let method = reqwest::Method::from_bytes(self.operation.method.as_bytes())
+            .map_err(|e| ToolExecutionError::ExecutionError(e.to_string()))?;
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .headers(self.headers.clone());
+
+        let mut query = Vec::new();
+        for parameter in &self.operation.parameters {
+            let Some(value) = input.get(&parameter.name).filter(|v| !v.is_null()) else {
+                continue;
+            };
+            match parameter.location {
+                ParameterLocation::Query => query.push((parameter.name.clone(), scalar(value))),
+                ParameterLocation::Header => {
+                    request = request.header(parameter.name.as_str(), scalar(value))
+                }
+                ParameterLocation::Path => {}
+            }
+        }
+        if !query.is_empty() {
+            request = request.query(&query);
+        }
+        if self.operation.has_body
+            && let Some(body) = input.get("body")
+        {
+            request = request.json(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| self.service_error(e.to_string()))?;
+        let status = response.status();
+        let is_json = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("json"));
+        let text = response
+            .text()
+            .await
+            .map_err(|e| self.service_error(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(self.service_error(format!("HTTP {}: {}", status.as_u16(), text)));
+        }
+
+        if is_json {
+            serde_json::from_str(&text).map_err(|e| self.service_error(e.to_string()))
+        } else {
+            Ok(JsonValue::String(text))
+        }
+    }
+
+    fn service_error(&self, error: String) -> ToolExecutionError {
+        ToolExecutionError::ExternalServiceError {
+            service: self.service.clone(),
+            error,
+        }
+    }
+}
+
+/// Build the request URL, substituting path parameters
+fn build_url(
+    base_url: &str,
+    operation: &OpenApiOperation,
+    input: &JsonValue,
+) -> ToolResult<String> {
+    let mut path = operation.path.clone();
+    for parameter in &operation.parameters {
+        if parameter.location != ParameterLocation::Path {
+            continue;
+        }
+        let value = input
+            .get(&parameter.name)
+            .filter(|v| !v.is_null())
+            .ok_or_else(|| {
+                ToolExecutionError::InvalidInput(format!(
+                    "Missing path parameter '{}'",
+                    parameter.name
+                ))
+            })?;
+        path = path.replace(
+            &format!("{{{}}}", parameter.name),
+            &percent_encode(&scalar(value)),
+        );
+    }
+    Ok(format!("{}{}", base_url.trim_end_matches('/'), path))
+}
+
+/// Render a JSON value the way it should appear in a URL or header
+fn scalar(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Array(items) => items.iter().map(scalar).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Derive a provider-safe tool name (`[a-zA-Z0-9_-]{1,64}`)
+fn tool_name(operation_id: Option<&str>, method: &str, path: &str) -> String {
+    let raw = match operation_id {
+        Some(id) => id.to_string(),
+        None => format!("{}_{}", method, path),
+    };
+    let mut name = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            name.push(c);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_matches('_');
+    name.chars().take(64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PETSTORE: &str = r##"
+openapi: 3.0.0
+info:
+  title: Petstore
+servers:
+  - url: https://petstore.example.com/v1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      summary: List all pets
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+    post:
+      summary: Create a pet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+  /pets/{petId}:
+    parameters:
+      - $ref: '#/components/parameters/PetId'
+    get:
+      operationId: showPetById
+      description: Info for a specific pet
+components:
+  parameters:
+    PetId:
+      name: petId
+      in: path
+      description: The id of the pet
+      schema:
+        type: string
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name:
+          type: string
+"##;
+
+    #[test]
+    fn test_operations_from_yaml() {
+        let spec = OpenApiSpec::from_yaml(PETSTORE).unwrap();
+        assert_eq!(spec.server_url(), Some("https://petstore.example.com/v1"));
+
+        let operations = spec.operations().unwrap();
+        assert_eq!(operations.len(), 3);
+
+        let list = operations.iter().find(|o| o.name == "listPets").unwrap();
+        assert_eq!(list.method, "GET");
+        assert_eq!(list.description.as_deref(), Some("List all pets"));
+        assert_eq!(list.input_schema["properties"]["limit"]["type"], "integer");
+
+        let create = operations.iter().find(|o| o.name == "post_pets").unwrap();
+        assert!(create.has_body);
+        assert_eq!(create.input_schema["required"], json!(["body"]));
+        assert_eq!(
+            create.input_schema["properties"]["body"]["properties"]["name"]["type"],
+            "string"
+        );
+
+        let show = operations.iter().find(|o| o.name == "showPetById").unwrap();
+        assert_eq!(show.parameters[0].location, ParameterLocation::Path);
+        assert_eq!(
+            show.input_schema["properties"]["petId"]["description"],
+            "The id of the pet"
+        );
+    }
+
+    #[test]
+    fn test_operation_parameters_override_path_parameters() {
+        let spec = OpenApiSpec::from_json(
+            &json!({
+                "paths": {
+                    "/pets/{petId}": {
+                        "parameters": [{"name": "petId", "in": "path"}],
+                        "get": {
+                            "operationId": "showPet",
+                            "parameters": [{"name": "petId", "in": "query"}]
+                        }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let operations = spec.operations().unwrap();
+        assert_eq!(operations[0].parameters.len(), 1);
+        assert_eq!(
+            operations[0].parameters[0].location,
+            ParameterLocation::Query
+        );
+        assert_eq!(operations[0].input_schema["required"], json!([]));
+    }
+
+    #[test]
+    fn test_duplicate_tool_names_are_rejected() {
+        let spec = OpenApiSpec::from_json(
+            &json!({
+                "paths": {
+                    "/pets": {"get": {"operationId": "pets"}},
+                    "/animals": {"get": {"operationId": "pets"}}
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let error = spec.operations().unwrap_err().to_string();
+        assert!(error.contains("'pets'"), "{}", error);
+    }
+
+    #[test]
+    fn test_ref_depth_counts_only_ref_expansions() {
+        // Nesting deeper than MAX_REF_DEPTH without `$ref`s is kept whole
+        let mut deep = json!({"type": "string"});
+        for _ in 0..MAX_REF_DEPTH * 2 {
+            deep = json!({"type": "object", "properties": {"inner": deep}});
+        }
+        let spec = OpenApiSpec::from_json(
+            &json!({
+                "paths": {},
+                "components": {"schemas": {
+                    "Node": {
+                        "type": "object",
+                        "properties": {"next": {"$ref": "#/components/schemas/Node"}}
+                    }
+                }}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert_eq!(spec.inline_refs(&deep, 0), deep);
+
+        // Recursive schemas are cut off after MAX_REF_DEPTH expansions
+        let mut node = spec.inline_refs(&json!({"$ref": "#/components/schemas/Node"}), 0);
+        let mut expansions = 0;
+        while node["type"] == "object" {
+            node = node["properties"]["next"].take();
+            expansions += 1;
+        }
+        assert_eq!(expansions, MAX_REF_DEPTH);
+        assert_eq!(node, json!({}));
+    }
+
+    #[test]
+    fn test_build_url() {
+        let spec = OpenApiSpec::from_yaml(PETSTORE).unwrap();
+        let operations = spec.operations().unwrap();
+        let show = operations.iter().find(|o| o.name == "showPetById").unwrap();
+
+        let url = build_url("https://api.test/", show, &json!({"petId": "a b/c"})).unwrap();
+        assert_eq!(url, "https://api.test/pets/a%20b%2Fc");
+        assert!(build_url("https://api.test", show, &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_register_tools() {
+        let spec = OpenApiSpec::from_yaml(PETSTORE).unwrap();
+        let router = OpenApiToolset::new(&spec)
+            .unwrap()
+            .with_bearer_auth("secret")
+            .unwrap()
+            .filter(|op| op.method == "GET")
+            .register(ToolRouter::new())
+            .with_state(());
+
+        let definitions = router.get_tool_definitions();
+        assert_eq!(definitions.len(), 2);
+        let list = definitions.iter().find(|d| d.name == "listPets").unwrap();
+        assert_eq!(list.parameters["properties"]["limit"]["type"], "integer");
+    }
+}
@@ -0,0 +1,614 @@
+use ai_core::errors::{ToolExecutionError, ToolResult};
+use ai_core::tools::{State, ToolRouter};
+use futures::TryStreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlx::any::{AnyRow, AnyTypeInfoKind};
+use sqlx::{AnyPool, Column, Connection, Row, ValueRef};
+
+/// Default number of rows returned by a single query
+pub const DEFAULT_MAX_ROWS: usize = 100;
+
+/// Statement keywords that are never allowed, even inside a `WITH` query
+///
+/// `INTO` covers `SELECT ... INTO`, which creates a table or writes a file.
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "DROP", "ALTER", "CREATE", "TRUNCATE",
+    "GRANT", "REVOKE", "ATTACH", "DETACH", "VACUUM", "REINDEX", "PRAGMA", "COPY", "CALL", "LOCK",
+    "INTO",
+];
+
+/// Read-only SQL access to a `sqlx` pool, meant to be held in tool state
+///
+/// Queries are checked to be single read-only statements and always run inside a
+/// transaction that is rolled back, opened read-only on PostgreSQL and MySQL. This is a
+/// safety net, not a sandbox: the pool should still connect with read-only database
+/// credentials.
+#[derive(Debug, Clone)]
+pub struct SqlDatabase {
+    pool: AnyPool,
+    max_rows: usize,
+}
+
+impl SqlDatabase {
+    pub fn new(pool: AnyPool) -> Self {
+        Self {
+            pool,
+            max_rows: DEFAULT_MAX_ROWS,
+        }
+    }
+
+    /// Set the maximum number of rows returned per query
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    /// Get the underlying pool
+    pub fn pool(&self) -> &AnyPool {
+        &self.pool
+    }
+
+    /// Get the configured row limit
+    pub fn max_rows(&self) -> usize {
+        self.max_rows
+    }
+
+    /// Run a parameterized read-only query
+    pub async fn query(&self, sql: &str, params: &[JsonValue]) -> ToolResult<SqlQueryOutput> {
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let backend = Backend::from_name(conn.backend_name());
+        ensure_read_only(sql, backend.as_ref())?;
+
+        let mut query = sqlx::query(sql);
+        for param in params {
+            query = match param {
+                JsonValue::Null => query.bind(None::<String>),
+                JsonValue::Bool(b) => query.bind(*b),
+                JsonValue::Number(n) => {
+                    if let Some(i) = n.as_i64() {
+                        query.bind(i)
+                    } else {
+                        query.bind(n.as_f64().unwrap_or_default())
+                    }
+                }
+                JsonValue::String(s) => query.bind(s.clone()),
+                other => query.bind(other.to_string()),
+            };
+        }
+
+        let mut tx = match backend.and_then(|backend| backend.begin_read_only()) {
+            Some(statement) => conn.begin_with(statement).await,
+            None => conn.begin().await,
+        }
+        .map_err(database_error)?;
+        let mut columns = Vec::new();
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        {
+            let mut stream = query.fetch(&mut *tx);
+            while let Some(row) = stream.try_next().await.map_err(database_error)? {
+                if rows.len() == self.max_rows {
+                    truncated = true;
+                    break;
+                }
+                if columns.is_empty() {
+                    columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+                }
+                rows.push(row_to_json(&row)?);
+            }
+        }
+        tx.rollback().await.map_err(database_error)?;
+
+        Ok(SqlQueryOutput {
+            row_count: rows.len(),
+            columns,
+            rows,
+            truncated,
+        })
+    }
+
+    /// List the tables and views visible to the connection
+    pub async fn list_tables(&self) -> ToolResult<Vec<String>> {
+        let sql = match self.backend().await? {
+            Backend::Sqlite => {
+                "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+                 AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            }
+            Backend::Postgres => {
+                "SELECT table_name::text FROM information_schema.tables \
+                 WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+                 ORDER BY table_name"
+            }
+            Backend::MySql => {
+                "SELECT CAST(table_name AS CHAR) FROM information_schema.tables \
+                 WHERE table_schema = DATABASE() ORDER BY table_name"
+            }
+        };
+
+        let rows = sqlx::query(sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.iter()
+            .map(|row| row.try_get::<String, _>(0).map_err(database_error))
+            .collect()
+    }
+
+    /// Describe the columns of a table
+    pub async fn describe_table(&self, table: &str) -> ToolResult<Vec<SqlColumnInfo>> {
+        let sql = match self.backend().await? {
+            Backend::Sqlite => {
+                "SELECT name, type, \"notnull\" = 0 FROM pragma_table_info(?) ORDER BY cid"
+            }
+            Backend::Postgres => {
+                "SELECT column_name::text, data_type::text, is_nullable = 'YES' \
+                 FROM information_schema.columns \
+                 WHERE table_schema = current_schema() AND table_name = $1 \
+                 ORDER BY ordinal_position"
+            }
+            Backend::MySql => {
+                "SELECT CAST(column_name AS CHAR), CAST(column_type AS CHAR), is_nullable = 'YES' \
+                 FROM information_schema.columns \
+                 WHERE table_schema = DATABASE() AND table_name = ? ORDER BY ordinal_position"
+            }
+        };
+
+        let rows = sqlx::query(sql)
+            .bind(table.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        if rows.is_empty() {
+            return Err(ToolExecutionError::NotFound(format!(
+                "Table '{}' does not exist",
+                table
+            )));
+        }
+
+        rows.iter()
+            .map(|row| {
+                Ok(SqlColumnInfo {
+                    name: row.try_get(0).map_err(database_error)?,
+                    data_type: row.try_get(1).map_err(database_error)?,
+                    nullable: decode_flag(row, 2)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn backend(&self) -> ToolResult<Backend> {
+        let conn = self.pool.acquire().await.map_err(database_error)?;
+        Backend::from_name(conn.backend_name()).ok_or_else(|| {
+            ToolExecutionError::ExecutionError(format!(
+                "Schema introspection is not supported for {}",
+                conn.backend_name()
+            ))
+        })
+    }
+}
+
+impl AsRef<SqlDatabase> for SqlDatabase {
+    fn as_ref(&self) -> &SqlDatabase {
+        self
+    }
+}
+
+enum Backend {
+    Sqlite,
+    Postgres,
+    MySql,
+}
+
+impl Backend {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SQLite" => Some(Backend::Sqlite),
+            "PostgreSQL" => Some(Backend::Postgres),
+            "MySQL" => Some(Backend::MySql),
+            _ => None,
+        }
+    }
+
+    /// Statement opening a read-only transaction, on backends that have one
+    fn begin_read_only(&self) -> Option<&'static str> {
+        match self {
+            Backend::Sqlite => None,
+            Backend::Postgres => Some("BEGIN READ ONLY"),
+            Backend::MySql => Some("START TRANSACTION READ ONLY"),
+        }
+    }
+}
+
+/// Input for the `sql_query` tool
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SqlQueryInput {
+    /// A single read-only SQL statement (SELECT, WITH or EXPLAIN)
+    pub sql: String,
+    /// Positional parameters bound to the statement's placeholders
+    #[serde(default)]
+    pub params: Vec<JsonValue>,
+}
+
+/// Result of the `sql_query` tool
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SqlQueryOutput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, JsonValue>>,
+    pub row_count: usize,
+    /// Whether more rows were available than the configured limit
+    pub truncated: bool,
+}
+
+/// Input for the `sql_list_tables` tool
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SqlListTablesInput {}
+
+/// Input for the `sql_describe_table` tool
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SqlDescribeTableInput {
+    /// Name of the table to describe
+    pub table: String,
+}
+
+/// Column information returned by the `sql_describe_table` tool
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SqlColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+/// Tool handler running a read-only query against the state's database
+pub async fn sql_query<S>(
+    State(state): State<S>,
+    input: SqlQueryInput,
+) -> ToolResult<SqlQueryOutput>
+where
+    S: AsRef<SqlDatabase> + Clone + Send + Sync + 'static,
+{
+    state.as_ref().query(&input.sql, &input.params).await
+}
+
+/// Tool handler listing the tables of the state's database
+pub async fn sql_list_tables<S>(
+    State(state): State<S>,
+    _input: SqlListTablesInput,
+) -> ToolResult<Vec<String>>
+where
+    S: AsRef<SqlDatabase> + Clone + Send + Sync + 'static,
+{
+    state.as_ref().list_tables().await
+}
+
+/// Tool handler describing a table of the state's database
+pub async fn sql_describe_table<S>(
+    State(state): State<S>,
+    input: SqlDescribeTableInput,
+) -> ToolResult<Vec<SqlColumnInfo>>
+where
+    S: AsRef<SqlDatabase> + Clone + Send + Sync + 'static,
+{
+    state.as_ref().describe_table(&input.table).await
+}
+
+/// Register `sql_query`, `sql_list_tables` and `sql_describe_table` on a router
+pub fn register_sql_tools<S>(router: ToolRouter<S>) -> ToolRouter<S>
+where
+    S: AsRef<SqlDatabase> + Clone + Send + Sync + 'static,
+{
+    router
+        .register(
+            "sql_query",
+            Some("Run a read-only SQL query against the database".to_string()),
+            sql_query::<S>,
+        )
+        .register(
+            "sql_list_tables",
+            Some("List the tables available in the database".to_string()),
+            sql_list_tables::<S>,
+        )
+        .register(
+            "sql_describe_table",
+            Some("Describe the columns of a database table".to_string()),
+            sql_describe_table::<S>,
+        )
+}
+
+/// Check that a statement is a single read-only query in the backend's dialect
+///
+/// Whether a backslash escapes a quote depends on the backend and its settings, so the
+/// statement is checked under both readings and must pass each. Dollar quoting is only
+/// understood on PostgreSQL.
+fn ensure_read_only(sql: &str, backend: Option<&Backend>) -> ToolResult<()> {
+    let dollar_quotes = matches!(backend, Some(Backend::Postgres));
+    check_keywords(&keywords(sql, false, dollar_quotes)?)?;
+    check_keywords(&keywords(sql, true, dollar_quotes)?)
+}
+
+fn check_keywords(words: &[String]) -> ToolResult<()> {
+    match words.first().map(String::as_str) {
+        Some("SELECT" | "WITH" | "EXPLAIN" | "VALUES") => {}
+        Some(other) => {
+            return Err(ToolExecutionError::Unauthorized(format!(
+                "Only read-only queries are allowed, got {} statement",
+                other
+            )));
+        }
+        None => {
+            return Err(ToolExecutionError::InvalidInput(
+                "SQL statement is empty".to_string(),
+            ));
+        }
+    }
+
+    if let Some(word) = words
+        .iter()
+        .find(|w| FORBIDDEN_KEYWORDS.contains(&w.as_str()))
+    {
+        return Err(ToolExecutionError::Unauthorized(format!(
+            "Only read-only queries are allowed, found {}",
+            word
+        )));
+    }
+
+    Ok(())
+}
+
+/// Extract upper-cased bare words outside of literals, quoted identifiers and comments,
+/// rejecting multiple statements along the way
+///
+/// With `dollar_quotes`, `$tag$ ... $tag$` bodies are skipped like literals. With
+/// `backslash_escapes`, a backslash inside a quoted string escapes the next character.
+fn keywords(sql: &str, backslash_escapes: bool, dollar_quotes: bool) -> ToolResult<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.char_indices().peekable();
+    let mut ended = false;
+
+    while let Some((index, c)) = chars.next() {
+        if c == '$'
+            && dollar_quotes
+            && current.is_empty()
+            && let Some(tag) = dollar_quote_tag(&sql[index..])
+        {
+            // Skip to the closing tag, or to the end if the body is unterminated
+            let body = index + tag.len();
+            let end = sql[body..]
+                .find(tag)
+                .map_or(sql.len(), |at| body + at + tag.len());
+            while chars.next_if(|(next, _)| *next < end).is_some() {}
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            if ended {
+                return Err(ToolExecutionError::InvalidInput(
+                    "Only a single SQL statement is allowed".to_string(),
+                ));
+            }
+            current.push(c.to_ascii_uppercase());
+            continue;
+        }
+        if !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+
+        match c {
+            '\'' | '"' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == c {
+                        break;
+                    }
+                    if next == '\\' && backslash_escapes {
+                        chars.next();
+                    }
+                }
+            }
+            '-' if matches!(chars.peek(), Some((_, '-'))) => {
+                for (_, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                chars.next();
+                let mut prev = '\0';
+                for (_, next) in chars.by_ref() {
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+            }
+            ';' => ended = true,
+            c if ended && !c.is_whitespace() => {
+                return Err(ToolExecutionError::InvalidInput(
+                    "Only a single SQL statement is allowed".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    Ok(words)
+}
+
+/// Opening tag of a dollar-quoted string at the start of `sql`, such as `$$` or `$body$`
+///
+/// Positional parameters like `$1` are not tags, since a tag cannot start with a digit.
+fn dollar_quote_tag(sql: &str) -> Option<&str> {
+    let rest = sql.strip_prefix('$')?;
+    let end = rest.find('$')?;
+    let tag = &rest[..end];
+    let valid = tag.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !tag.starts_with(|c: char| c.is_ascii_digit());
+    valid.then(|| &sql[..end + 2])
+}
+
+fn row_to_json(row: &AnyRow) -> ToolResult<Map<String, JsonValue>> {
+    let mut object = Map::new();
+
+    for (index, column) in row.columns().iter().enumerate() {
+        let raw = row.try_get_raw(index).map_err(database_error)?;
+        let value = if raw.is_null() {
+            JsonValue::Null
+        } else {
+            match raw.type_info().kind() {
+                AnyTypeInfoKind::Bool => {
+                    JsonValue::from(row.try_get::<bool, _>(index).map_err(database_error)?)
+                }
+                AnyTypeInfoKind::SmallInt | AnyTypeInfoKind::Integer | AnyTypeInfoKind::BigInt => {
+                    JsonValue::from(row.try_get::<i64, _>(index).map_err(database_error)?)
+                }
+                AnyTypeInfoKind::Real => {
+                    JsonValue::from(row.try_get::<f32, _>(index).map_err(database_error)?)
+                }
+                AnyTypeInfoKind::Double => {
+                    JsonValue::from(row.try_get::<f64, _>(index).map_err(database_error)?)
+                }
+                AnyTypeInfoKind::Text => {
+                    JsonValue::from(row.try_get::<String, _>(index).map_err(database_error)?)
+                }
+                AnyTypeInfoKind::Blob => {
+                    let bytes = row.try_get::<Vec<u8>, _>(index).map_err(database_error)?;
+                    JsonValue::from(format!("<{} bytes>", bytes.len()))
+                }
+                AnyTypeInfoKind::Null => JsonValue::Null,
+            }
+        };
+        object.insert(column.name().to_string(), value);
+    }
+
+    Ok(object)
+}
+
+/// Decode a boolean that some backends report as an integer
+fn decode_flag(row: &AnyRow, index: usize) -> ToolResult<bool> {
+    match row.try_get::<bool, _>(index) {
+        Ok(flag) => Ok(flag),
+        Err(_) => Ok(row.try_get::<i64, _>(index).map_err(database_error)? != 0),
+    }
+}
+
+fn database_error(err: sqlx::Error) -> ToolExecutionError {
+    ToolExecutionError::ExternalServiceError {
+        service: "database".to_string(),
+        error: err.to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+
+    async fn test_database() -> SqlDatabase {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (name, score) in [("alice", 1.5), ("bob", 2.0), ("carol", 3.5)] {
+            sqlx::query("INSERT INTO users (name, score) VALUES (?, ?)")
+                .bind(name)
+                .bind(score)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        SqlDatabase::new(pool).with_max_rows(2)
+    }
+
+    fn check(sql: &str) -> ToolResult<()> {
+        ensure_read_only(sql, Some(&Backend::Sqlite))
+    }
+
+    #[test]
+    fn test_read_only_check() {
+        assert!(check("SELECT * FROM users").is_ok());
+        assert!(check("  -- comment\n with x as (select 1) select * from x;").is_ok());
+        assert!(check("SELECT 'DELETE' AS word").is_ok());
+        assert!(check("DELETE FROM users").is_err());
+        assert!(check("SELECT 1; DROP TABLE users").is_err());
+        assert!(check("WITH d AS (DELETE FROM users RETURNING *) SELECT * FROM d").is_err());
+        assert!(check("").is_err());
+    }
+
+    #[test]
+    fn test_read_only_check_quoting() {
+        // Dollar-quoted bodies are literals on PostgreSQL, whatever they contain
+        let postgres = |sql| ensure_read_only(sql, Some(&Backend::Postgres));
+        assert!(postgres("SELECT $$DELETE; DROP$$ AS body").is_ok());
+        assert!(postgres("SELECT $fn$ it's; $$ $fn$, $1").is_ok());
+        assert!(postgres("SELECT $$x$$; DELETE FROM users").is_err());
+        assert!(postgres("SELECT $tag$ x $tag$ FROM t WHERE id = $1; DROP TABLE t").is_err());
+        // Elsewhere `$x$` is not a quote and cannot hide a statement
+        for backend in [Some(Backend::Sqlite), Some(Backend::MySql), None] {
+            let sql = "SELECT $x$; DELETE FROM t; SELECT $x$";
+            assert!(ensure_read_only(sql, backend.as_ref()).is_err());
+        }
+        // A backslash-escaped quote does not end the string on backends that honour it
+        assert!(check("SELECT '\\'' ; DROP TABLE users; SELECT ''").is_err());
+        assert!(check("SELECT 'a\\' ; DELETE FROM users; '").is_err());
+        assert!(check("SELECT 'it''s', 'C:\\dir'").is_ok());
+        // SELECT ... INTO writes a table or a file
+        assert!(check("SELECT * INTO backup FROM users").is_err());
+        assert!(check("SELECT * FROM users INTO OUTFILE '/tmp/users'").is_err());
+        assert!(check("SELECT 'INTO' AS word").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_query_with_params_and_row_limit() {
+        let db = test_database().await;
+
+        let output = db
+            .query(
+                "SELECT id, name, score FROM users WHERE score > ? ORDER BY id",
+                &[serde_json::json!(1.0)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.columns, vec!["id", "name", "score"]);
+        assert_eq!(output.row_count, 2);
+        assert!(output.truncated);
+        assert_eq!(output.rows[0]["name"], "alice");
+        assert_eq!(output.rows[1]["score"], 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_schema_introspection_tools() {
+        let router = register_sql_tools(ToolRouter::new()).with_state(test_database().await);
+
+        let tables = router
+            .execute_tool("sql_list_tables", serde_json::json!({}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tables, serde_json::json!(["users"]));
+
+        let columns = router
+            .execute_tool("sql_describe_table", serde_json::json!({"table": "users"}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(columns[1]["name"], "name");
+        assert_eq!(columns[1]["nullable"], false);
+
+        let denied = router
+            .execute_tool("sql_query", serde_json::json!({"sql": "DELETE FROM users"}))
+            .await
+            .unwrap();
+        assert!(matches!(denied, Err(ToolExecutionError::Unauthorized(_))));
+    }
+}
@@ -29,45 +29,8 @@ pub async fn run_basic_examples() -> Result<()> {
         .run_until(MaxSteps::new(3));
     match generate_text(config).await {
         Ok(response) => {
-            println!("Final conversation ({} steps):", response.steps);
-            for (i, msg) in response.messages.iter().enumerate() {
-                match msg {
-                    Message::System { content, .. } => {
-                        let text = content
-                            .iter()
-                            .map(|c| match c {
-                                SystemContent::Text { text } => text.as_str(),
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ");
-                        println!("{}. System: {}", i + 1, text);
-                    }
-                    Message::User { content, .. } => {
-                        let text = content
-                            .iter()
-                            .filter_map(|c| match c {
-                                UserContent::Text { text } => Some(text.as_str()),
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ");
-                        println!("{}. User: {}", i + 1, text);
-                    }
-                    Message::Assistant { content, .. } => {
-                        let text = content
-                            .iter()
-                            .filter_map(|c| match c {
-                                AssistantContent::Text { text } => Some(text.as_str()),
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ");
-                        println!("{}. Assistant: {}", i + 1, text);
-                    }
-                    _ => {}
-                }
-            }
-
+            println!("Final conversation ({} steps):", response.steps.len());
+            println!("{}", fmt_transcript(&response.messages));
             println!("\nFinal reason: {:?}", response.finish_reason);
             if let Some(usage) = response.total_usage {
                 println!("Total tokens: {}", usage.total_tokens);
@@ -110,6 +73,9 @@ pub async fn run_basic_examples() -> Result<()> {
 
                         if agent_chunk.is_final {
                             println!(); // New line after final chunk
+                            if let Some(usage) = &agent_chunk.total_usage {
+                                println!("Tokens used so far: {}", usage.total_tokens);
+                            }
                         }
                     }
                     Err(e) => {
@@ -142,7 +108,8 @@ pub async fn run_basic_examples() -> Result<()> {
         Ok(response) => {
             println!(
                 "Completed {} steps with reason: {:?}",
-                response.steps, response.finish_reason
+                response.steps.len(),
+                response.finish_reason
             );
 
             // Show just the assistant responses
@@ -76,52 +76,19 @@ pub async fn run_tool_calling_example() -> Result<()> {
     match generate_text(config).await {
         Ok(response) => {
             println!("=== Final Conversation ===");
-            for (i, msg) in response.messages.iter().enumerate() {
-                match msg {
-                    Message::User { content, .. } => {
-                        let text = content
-                            .iter()
-                            .filter_map(|c| match c {
-                                UserContent::Text { text } => Some(text.as_str()),
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ");
-                        println!("{}. 👤 User: {}", i + 1, text);
-                    }
-                    Message::Assistant { content, .. } => {
-                        for c in content {
-                            match c {
-                                AssistantContent::Text { text } => {
-                                    println!("{}. 🤖 Assistant: {}", i + 1, text);
-                                }
-                                AssistantContent::ToolCall { tool_call } => {
-                                    println!(
-                                        "{}. 🔧 Tool Call: {} with args: {}",
-                                        i + 1,
-                                        tool_call.name,
-                                        serde_json::to_string(&tool_call.arguments)
-                                            .unwrap_or_default()
-                                    );
-                                }
-                            }
-                        }
-                    }
-                    Message::Tool { tool_results, .. } => {
-                        for result in tool_results {
-                            println!(
-                                "{}. ⚙️  Tool Result: {}",
-                                i + 1,
-                                serde_json::to_string_pretty(&result.result).unwrap_or_default()
-                            );
-                        }
-                    }
-                    _ => {}
-                }
-            }
+            println!("{}", fmt_transcript(&response.messages));
 
             println!("\n=== Summary ===");
-            println!("Completed {} steps", response.steps);
+            println!("Completed {} steps", response.steps.len());
+            for step in &response.steps {
+                println!(
+                    "  Step {}: {} tool call(s), {:?}, {:?}",
+                    step.step + 1,
+                    step.tool_calls.len(),
+                    step.finish_reason,
+                    step.duration
+                );
+            }
             println!("Finish reason: {:?}", response.finish_reason);
             if let Some(usage) = response.total_usage {
                 println!("Total tokens: {}", usage.total_tokens);
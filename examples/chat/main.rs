@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+
+use ai_agent::*;
+use ai_anthropic::*;
+use ai_core::{errors::ToolExecutionError, *};
+use dotenv::dotenv;
+use futures::StreamExt;
+use rustyline::{DefaultEditor, error::ReadlineError};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::io::Write;
+
+const DEFAULT_MODEL: &str = "claude-3-5-sonnet-20241022";
+const PROVIDERS: &[&str] = &["anthropic"];
+const TOOLS: &[&str] = &["calculator", "get_time"];
+
+const HELP: &str = "\
+/model <name>        switch model, keeping the conversation
+/provider <name>     switch provider (available: anthropic)
+/tools               list tools and whether they are enabled
+/enable <tool>       let the model call a tool
+/disable <tool>      stop offering a tool
+/save <path>         write the conversation to a JSON file
+/load <path>         continue a conversation saved with /save
+/clear               start a new conversation
+/quit                exit";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Calculation {
+    a: f64,
+    /// One of `+`, `-`, `*`, `/`
+    op: String,
+    b: f64,
+}
+
+async fn calculator(input: Calculation) -> std::result::Result<f64, ToolExecutionError> {
+    match input.op.as_str() {
+        "+" => Ok(input.a + input.b),
+        "-" => Ok(input.a - input.b),
+        "*" => Ok(input.a * input.b),
+        "/" if input.b == 0.0 => Err(ToolExecutionError::InvalidInput(
+            "division by zero".to_string(),
+        )),
+        "/" => Ok(input.a / input.b),
+        op => Err(ToolExecutionError::InvalidInput(format!(
+            "unknown operator '{}'",
+            op
+        ))),
+    }
+}
+
+/// Settings the slash commands change; the agent is rebuilt from them for every turn
+struct Session {
+    provider: String,
+    model: String,
+    tools: BTreeSet<String>,
+    history: Vec<Message>,
+}
+
+impl Session {
+    fn agent(&self) -> Result<Agent<AnthropicProvider>> {
+        let provider = match self.provider.as_str() {
+            "anthropic" => {
+                let api_key = std::env::var("ANTHROPIC_API_KEY")
+                    .expect("ANTHROPIC_API_KEY environment variable must be set");
+                AnthropicProvider::new(AnthropicConfig::new(api_key, &self.model))?
+            }
+            other => unreachable!("provider '{}' is rejected by /provider", other),
+        };
+
+        let mut router = ToolRouter::new();
+        if self.tools.contains("calculator") {
+            router = router.register(
+                "calculator",
+                Some("Apply +, -, * or / to two numbers".to_string()),
+                calculator,
+            );
+        }
+        if self.tools.contains("get_time") {
+            router = router.register_infallible(
+                "get_time",
+                Some("Get the current UTC time".to_string()),
+                |_input: serde_json::Value| async {
+                    serde_json::json!({ "time": chrono::Utc::now().to_rfc3339() })
+                },
+            );
+        }
+
+        let agent = Agent::new(provider)
+            .system_prompt(
+                "You are a helpful assistant chatting in a terminal. Keep answers short.",
+            )
+            .run_until(MaxSteps::new(10));
+        let agent = if self.tools.is_empty() {
+            agent
+        } else {
+            agent.tools(router.with_state(()))
+        };
+        Ok(agent.with_history(self.history.clone()))
+    }
+
+    /// Handle a slash command; returns false to quit
+    fn command(&mut self, line: &str) -> bool {
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match (command, argument) {
+            ("/quit" | "/exit", _) => return false,
+            ("/help", _) => println!("{}", HELP),
+            ("/model", "") => println!("model: {}", self.model),
+            ("/model", model) => {
+                self.model = model.to_string();
+                println!("switched to {}", self.model);
+            }
+            ("/provider", "") => println!("provider: {}", self.provider),
+            ("/provider", provider) if PROVIDERS.contains(&provider) => {
+                self.provider = provider.to_string();
+                println!("switched to {}", self.provider);
+            }
+            ("/provider", provider) => println!(
+                "unknown provider '{}', available: {}",
+                provider,
+                PROVIDERS.join(", ")
+            ),
+            ("/tools", _) => {
+                for tool in TOOLS {
+                    let state = if self.tools.contains(*tool) {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    println!("{:<12} {}", tool, state);
+                }
+            }
+            ("/enable", tool) if TOOLS.contains(&tool) => {
+                self.tools.insert(tool.to_string());
+            }
+            ("/disable", tool) if TOOLS.contains(&tool) => {
+                self.tools.remove(tool);
+            }
+            ("/enable" | "/disable", tool) => {
+                println!("unknown tool '{}', see /tools", tool)
+            }
+            ("/save", "") | ("/load", "") => println!("usage: {} <path>", command),
+            ("/save", path) => match serde_json::to_string_pretty(&self.history) {
+                Ok(json) => match std::fs::write(path, json) {
+                    Ok(()) => println!("saved {} messages to {}", self.history.len(), path),
+                    Err(e) => println!("could not write {}: {}", path, e),
+                },
+                Err(e) => println!("could not serialize the conversation: {}", e),
+            },
+            ("/load", path) => match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(history) => {
+                    self.history = history;
+                    println!("loaded {} messages from {}", self.history.len(), path);
+                }
+                Err(e) => println!("could not load {}: {}", path, e),
+            },
+            ("/clear", _) => {
+                self.history.clear();
+                println!("conversation cleared");
+            }
+            _ => println!("unknown command '{}', see /help", command),
+        }
+        true
+    }
+
+    /// Stream one reply to stdout and keep the resulting conversation
+    async fn turn(&mut self, input: &str) -> Result<()> {
+        let agent = self.agent()?;
+        let mut events = agent.run_events(input).await?;
+        let mut stdout = std::io::stdout();
+        while let Some(event) = events.next().await {
+            match event? {
+                AgentEvent::TextDelta { text, .. } => {
+                    print!("{}", text);
+                    stdout.flush().ok();
+                }
+                AgentEvent::ToolCallStarted { tool_call, .. } => {
+                    println!("\n[{} {}]", tool_call.name, tool_call.arguments)
+                }
+                AgentEvent::ToolCallCompleted { result, .. } => {
+                    println!("[-> {}]", result.result)
+                }
+                _ => {}
+            }
+        }
+        println!();
+        self.history = agent.history();
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    let mut session = Session {
+        provider: "anthropic".to_string(),
+        model: std::env::args()
+            .nth(1)
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        tools: TOOLS.iter().map(|tool| tool.to_string()).collect(),
+        history: Vec::new(),
+    };
+    let mut editor = DefaultEditor::new().expect("terminal input is available");
+    println!(
+        "Chatting with {} ({}), /help for commands",
+        session.model, session.provider
+    );
+
+    loop {
+        let line = match editor.readline("you> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("input error: {}", e);
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line).ok();
+
+        if line.starts_with('/') {
+            if !session.command(line) {
+                break;
+            }
+            continue;
+        }
+        if let Err(e) = session.turn(line).await {
+            eprintln!("\nerror: {}", e);
+        }
+    }
+    Ok(())
+}
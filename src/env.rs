@@ -0,0 +1,59 @@
+//! Provider selection from environment variables
+
+use std::sync::Arc;
+
+use ai_core::{AiError, ChatTextGeneration, Result, ValidationError};
+
+/// Providers `provider_from_env` can build, with the variable holding each API key
+const PROVIDERS: &[(&str, &str)] = &[
+    #[cfg(feature = "anthropic")]
+    ("anthropic", "ANTHROPIC_API_KEY"),
+];
+
+/// Provider configured entirely from environment variables
+///
+/// `AI_PROVIDER` names the provider to use; without it, the first enabled provider whose
+/// API key variable is set is chosen. Each provider then reads its own variables, e.g.
+/// `ANTHROPIC_API_KEY`, `ANTHROPIC_BASE_URL` and `ANTHROPIC_MODEL`.
+pub fn provider_from_env() -> Result<Arc<dyn ChatTextGeneration>> {
+    let name = match std::env::var("AI_PROVIDER") {
+        Ok(name) => name.to_lowercase(),
+        Err(_) => select(|name| std::env::var(name).is_ok())?.to_string(),
+    };
+    match name.as_str() {
+        #[cfg(feature = "anthropic")]
+        "anthropic" => Ok(Arc::new(ai_anthropic::AnthropicProvider::from_env()?)),
+        _ => Err(AiError::Validation(ValidationError::InvalidValue {
+            field: "AI_PROVIDER".to_string(),
+            message: format!("unknown or disabled provider '{}'", name),
+        })),
+    }
+}
+
+/// First provider whose API key variable is set
+fn select(is_set: impl Fn(&str) -> bool) -> Result<&'static str> {
+    PROVIDERS
+        .iter()
+        .find(|(_, key)| is_set(key))
+        .map(|(name, _)| *name)
+        .ok_or_else(|| {
+            AiError::Validation(ValidationError::ConfigError {
+                message: "no provider API key found in the environment; set AI_PROVIDER or a provider API key".to_string(),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_requires_an_api_key() {
+        assert!(select(|_| false).is_err());
+        #[cfg(feature = "anthropic")]
+        assert_eq!(
+            select(|name| name == "ANTHROPIC_API_KEY").unwrap(),
+            "anthropic"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! The whole SDK behind one dependency
+//!
+//! This crate re-exports the workspace crates and defines no types of its own, so there
+//! is a single implementation of every type. Its only addition is `provider_from_env`,
+//! which picks among the providers that are enabled. `ai_core` is always included and
+//! re-exported at the root. Providers and components are modules behind cargo
+//! features, so nothing is compiled for the ones left out:
+//!
+//! | Feature | Module | Crate |
+//! |---|---|---|
+//! | `anthropic` (default) | `anthropic` | `ai-anthropic` |
+//! | `agent` (default) | `agent` | `ai-agent` |
+//! | `memory` | `memory` | `ai-memory` |
+//! | `eval` | `eval` | `ai-eval` |
+//! | `openapi` | `openapi` | `ai-tools` |
+//! | `sql` | `sql` | `ai-tools` |
+//!
+//! `full` enables every provider and component. Integrations (`ai_http`, `tracing`,
+//! `async-openai`) and storage backends (`redis`, `qdrant`, `pgvector`) enable the
+//! matching feature of the crate that implements them.
+//!
+//! ```toml
+//! ai-rs = { version = "0.1", default-features = false, features = ["anthropic"] }
+//! ```
+
+mod env;
+
+pub use ai_core::*;
+pub use env::provider_from_env;
+
+#[cfg(feature = "agent")]
+pub use ai_agent as agent;
+#[cfg(feature = "anthropic")]
+pub use ai_anthropic as anthropic;
+#[cfg(feature = "eval")]
+pub use ai_eval as eval;
+#[cfg(feature = "memory")]
+pub use ai_memory as memory;
+#[cfg(feature = "openapi")]
+pub use ai_tools::openapi;
+#[cfg(feature = "sql")]
+pub use ai_tools::sql;
+
+#[cfg(test)]
+mod tests {
+    /// Items reached through this crate are the workspace crates' own items
+    #[test]
+    fn test_reexports_are_the_workspace_items() {
+        let message: ai_core::Message = crate::Message::user("Hi");
+        assert_eq!(message.role(), "user");
+
+        #[cfg(feature = "anthropic")]
+        {
+            let config: ai_anthropic::AnthropicConfig =
+                crate::anthropic::AnthropicConfig::new("key", "model");
+            assert_eq!(config.model, "model");
+        }
+        #[cfg(feature = "agent")]
+        {
+            let steps: ai_agent::MaxSteps = crate::agent::MaxSteps::new(3);
+            assert_eq!(steps.max, 3);
+        }
+    }
+}